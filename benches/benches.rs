@@ -1,5 +1,7 @@
-use criterion::{criterion_group, criterion_main, Criterion};
-use pcap_file::pcap::{PcapParser, PcapReader};
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use std::time::Duration;
+
+use pcap_file::pcap::{Keep, PcapHeader, PcapPacket, PcapPacketHeader, PcapParser, PcapReader, PcapWriter, TransformData};
 use pcap_file::pcapng::{PcapNgParser, PcapNgReader};
 use pcap_file::PcapError;
 
@@ -113,5 +115,104 @@ pub fn pcapng(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, pcap, pcapng);
+/// Bench the Pcap code paths in isolation over synthetic data
+pub fn pcap_synthetic(c: &mut Criterion) {
+    // 10_000 packets with sizes between 64 and 1500 bytes
+    let payload = vec![0xAA_u8; 1500];
+    let packets: Vec<_> = (0..10_000_u32)
+        .map(|i| {
+            let len = 64 + (i as usize * 7919) % (1500 - 64);
+            PcapPacket::new(Duration::new(i as u64, i * 1000), len as u32, &payload[..len])
+        })
+        .collect();
+
+    let mut writer = PcapWriter::new(Vec::new()).unwrap();
+    for packet in &packets {
+        writer.write_packet(packet).unwrap();
+    }
+    let pcap = writer.into_writer();
+
+    let mut group = c.benchmark_group("PcapSynthetic");
+
+    group.bench_function("Header", |b| b.iter(|| PcapHeader::from_slice(&pcap).unwrap()));
+
+    let (src, header) = PcapHeader::from_slice(&pcap).unwrap();
+    group.bench_function("Packet", |b| b.iter(|| PcapPacket::from_slice(src, &header).unwrap()));
+
+    group.throughput(criterion::Throughput::Bytes(pcap.len() as u64));
+
+    group.bench_function("Reader", |b| {
+        b.iter(|| {
+            let mut reader = PcapReader::new(&pcap[..]).unwrap();
+            while let Some(pkt) = reader.next_packet() {
+                pkt.unwrap();
+            }
+        })
+    });
+
+    // Keeps the data of all the packets, like a pipeline passing them to other tasks
+    group.bench_function("ReaderOwned", |b| {
+        b.iter(|| {
+            let mut reader = PcapReader::new(&pcap[..]).unwrap();
+            let mut packets = Vec::with_capacity(10_000);
+            while let Some(pkt) = reader.next_packet() {
+                packets.push(pkt.unwrap().into_owned());
+            }
+            packets
+        })
+    });
+
+    #[cfg(feature = "bytes")]
+    group.bench_function("ReaderBytes", |b| {
+        b.iter(|| {
+            let mut reader = pcap_file::pcap::PcapBytesReader::new(&pcap[..]).unwrap();
+            let mut packets = Vec::with_capacity(10_000);
+            while let Some(pkt) = reader.next_packet() {
+                packets.push(pkt.unwrap());
+            }
+            packets
+        })
+    });
+
+    group.bench_function("Writer", |b| {
+        b.iter(|| {
+            let mut writer = PcapWriter::new(Vec::with_capacity(pcap.len())).unwrap();
+            for packet in &packets {
+                writer.write_packet(packet).unwrap();
+            }
+        })
+    });
+
+    // A transform modifying the data, fed with borrowed packets (copied) or owned ones (moved)
+    let transform = |_: &mut PcapPacketHeader, data: &mut TransformData| {
+        data.to_mut()[0] = 0;
+        Ok(Keep::Yes)
+    };
+
+    group.bench_function("WriterTransform", |b| {
+        b.iter(|| {
+            let mut writer = PcapWriter::new(Vec::with_capacity(pcap.len())).unwrap();
+            writer.set_transform(transform);
+            for packet in &packets {
+                writer.write_packet(packet).unwrap();
+            }
+        })
+    });
+
+    group.bench_function("WriterTransformOwned", |b| {
+        b.iter_batched(
+            || packets.iter().map(|packet| packet.clone().into_owned()).collect::<Vec<_>>(),
+            |owned| {
+                let mut writer = PcapWriter::new(Vec::with_capacity(pcap.len())).unwrap();
+                writer.set_transform(transform);
+                for packet in owned {
+                    writer.write_packet_owned(packet).unwrap();
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+criterion_group!(benches, pcap, pcapng, pcap_synthetic);
 criterion_main!(benches);
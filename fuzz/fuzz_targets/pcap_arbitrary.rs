@@ -0,0 +1,20 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use pcap_file::pcap::PcapReader;
+use pcap_file::ArbitraryPcap;
+
+fuzz_target!(|pcap: ArbitraryPcap| {
+    let data = pcap.to_bytes();
+
+    let mut pcap_reader = PcapReader::new(&data[..]).unwrap();
+    assert_eq!(pcap_reader.header(), pcap.header);
+
+    let mut nb_packets = 0;
+    while let Some(packet) = pcap_reader.next_packet() {
+        let packet = packet.unwrap();
+        assert_eq!(packet.data, pcap.packets[nb_packets].data);
+        nb_packets += 1;
+    }
+
+    assert_eq!(nb_packets, pcap.packets.len());
+});
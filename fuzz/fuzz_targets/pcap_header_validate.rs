@@ -0,0 +1,28 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use pcap_file::pcap::{PcapHeader, PcapReader, PcapWriter};
+use pcap_file::{DataLink, Endianness, TsResolution};
+
+type Fields = (u16, u16, i32, u32, u32, u32, bool, bool);
+
+fuzz_target!(|fields: Fields| {
+    let (version_major, version_minor, ts_correction, ts_accuracy, snaplen, datalink, nano, big) = fields;
+    let header = PcapHeader {
+        version_major,
+        version_minor,
+        ts_correction,
+        ts_accuracy,
+        snaplen,
+        datalink: DataLink::from(datalink),
+        ts_resolution: if nano { TsResolution::NanoSecond } else { TsResolution::MicroSecond },
+        endianness: if big { Endianness::Big } else { Endianness::Little },
+    };
+
+    // A header passing the validation is accepted by the writer, and the pcap written can be read
+    let written = PcapWriter::with_header(Vec::new(), header);
+    assert_eq!(header.validate().is_ok(), written.is_ok(), "{header:?}");
+    if let Ok(writer) = written {
+        let pcap = writer.into_writer();
+        assert_eq!(PcapReader::new(&pcap[..]).unwrap().header(), header);
+    }
+});
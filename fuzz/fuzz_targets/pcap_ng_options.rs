@@ -0,0 +1,13 @@
+#![no_main]
+use byteorder_slice::LittleEndian;
+use libfuzzer_sys::fuzz_target;
+use pcap_file::pcapng::blocks::interface_description::InterfaceDescriptionBlock;
+use pcap_file::pcapng::PcapNgBlock;
+
+fuzz_target!(|data: &[u8]| {
+    // Ethernet interface with a snaplen of 0xFFFF, followed by the fuzzed options
+    let mut body = vec![1, 0, 0, 0, 0xFF, 0xFF, 0, 0];
+    body.extend_from_slice(data);
+
+    let _ = InterfaceDescriptionBlock::from_slice::<LittleEndian>(&body);
+});
@@ -0,0 +1,226 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::errors::*;
+use crate::DataLink;
+
+
+/// Rewrites the source and destination IP addresses of a packet with `map`, e.g. to anonymize a capture before
+/// publishing it.
+///
+/// The IP header is located with the datalink of the packet: after the Ethernet header and its VLAN tags for
+/// [`DataLink::ETHERNET`], else at the offset given by [`DataLink::header_len`], where it is recognized by its version.
+/// Only the outer IP header is rewritten.
+///
+/// The checksum of an IPv4 header is recomputed. The checksums of the transport layer, which also cover the addresses,
+/// are left as they are.
+///
+/// Returns false, leaving the data unchanged, if the packet isn't an IP packet or its datalink isn't supported.
+///
+/// # Errors
+/// The IP header is cut, e.g. by the snaplen, or invalid, so its addresses can't be rewritten.
+///
+/// `map` returned an address of another IP version.
+///
+/// The data are left unchanged on error.
+pub fn remap_ip_addresses(datalink: DataLink, data: &mut [u8], mut map: impl FnMut(IpAddr) -> IpAddr) -> PcapResult<bool> {
+    let other_version = PcapError::InvalidField("remap_ip_addresses: address mapped to another IP version");
+
+    match locate_ip_header(datalink, data)? {
+        Some(IpHeader::V4 { offset, len }) => {
+            let header = &mut data[offset..offset + len];
+
+            let mut addrs = [[0_u8; 4]; 2];
+            for (addr, field) in addrs.iter_mut().zip(header[12..20].chunks_exact(4)) {
+                // Can unwrap because the chunks are 4 bytes long
+                let ip = Ipv4Addr::from(<[u8; 4]>::try_from(field).unwrap());
+                *addr = match map(IpAddr::V4(ip)) {
+                    IpAddr::V4(mapped) => mapped.octets(),
+                    IpAddr::V6(_) => return Err(other_version),
+                };
+            }
+
+            header[12..16].copy_from_slice(&addrs[0]);
+            header[16..20].copy_from_slice(&addrs[1]);
+            header[10..12].fill(0);
+            let checksum = internet_checksum(header);
+            header[10..12].copy_from_slice(&checksum.to_be_bytes());
+
+            Ok(true)
+        },
+        Some(IpHeader::V6 { offset }) => {
+            let header = &mut data[offset..offset + 40];
+
+            let mut addrs = [[0_u8; 16]; 2];
+            for (addr, field) in addrs.iter_mut().zip(header[8..40].chunks_exact(16)) {
+                // Can unwrap because the chunks are 16 bytes long
+                let ip = Ipv6Addr::from(<[u8; 16]>::try_from(field).unwrap());
+                *addr = match map(IpAddr::V6(ip)) {
+                    IpAddr::V6(mapped) => mapped.octets(),
+                    IpAddr::V4(_) => return Err(other_version),
+                };
+            }
+
+            header[8..24].copy_from_slice(&addrs[0]);
+            header[24..40].copy_from_slice(&addrs[1]);
+
+            Ok(true)
+        },
+        None => Ok(false),
+    }
+}
+
+/// Location of the IP header in a packet.
+pub(crate) enum IpHeader {
+    V4 { offset: usize, len: usize },
+    V6 { offset: usize },
+}
+
+/// Locates the whole IP header of a packet, returns [`None`] if the packet isn't an IP packet.
+///
+/// # Errors
+/// The IP header is cut or its length is invalid.
+pub(crate) fn locate_ip_header(datalink: DataLink, data: &[u8]) -> PcapResult<Option<IpHeader>> {
+    let cut = PcapError::InvalidField("remap_ip_addresses: IP header cut");
+
+    let offset = if datalink == DataLink::ETHERNET {
+        let mut offset = 12;
+        loop {
+            let ethertype = match data.get(offset..offset + 2) {
+                Some(ethertype) => u16::from_be_bytes([ethertype[0], ethertype[1]]),
+                None => return Ok(None),
+            };
+
+            match ethertype {
+                // 802.1Q and 802.1ad VLAN tags
+                0x8100 | 0x88A8 => offset += 4,
+                0x0800 | 0x86DD => break offset + 2,
+                _ => return Ok(None),
+            }
+        }
+    }
+    else {
+        match datalink.header_len() {
+            Some(len) => len,
+            None => return Ok(None),
+        }
+    };
+
+    let version = match data.get(offset) {
+        Some(first) => first >> 4,
+        None if datalink == DataLink::ETHERNET => return Err(cut),
+        None => return Ok(None),
+    };
+
+    match version {
+        4 => {
+            let len = usize::from(data[offset] & 0x0F) * 4;
+            if len < 20 {
+                return Err(PcapError::InvalidField("remap_ip_addresses: IPv4 header length < 20"));
+            }
+            if data.len() < offset + len {
+                return Err(cut);
+            }
+
+            Ok(Some(IpHeader::V4 { offset, len }))
+        },
+        6 if data.len() < offset + 40 => Err(cut),
+        6 => Ok(Some(IpHeader::V6 { offset })),
+        _ => Ok(None),
+    }
+}
+
+/// Computes the internet checksum (RFC 1071) of a header whose checksum field is zeroed.
+fn internet_checksum(header: &[u8]) -> u16 {
+    let mut sum: u32 = header.chunks(2).map(|word| u32::from(word[0]) << 8 | u32::from(*word.get(1).unwrap_or(&0))).sum();
+    while sum > 0xFFFF {
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+
+    !(sum as u16)
+}
+
+
+/// Prefix-preserving IP anonymizer, in the style of Crypto-PAn, whose pseudo-random function is a keyed SHA-256.
+///
+/// Two addresses sharing a prefix of `n` bits are mapped to two addresses sharing a prefix of `n` bits, so the
+/// subnets are kept, and the same key always gives the same mapping. Each bit of the address is flipped or not
+/// depending on the key and the bits preceding it.
+///
+/// The mapping can't be reverted without the key, which must be kept secret and be random, e.g. 32 bytes from
+/// a CSPRNG.
+///
+/// # Example
+/// ```rust
+/// use std::net::{IpAddr, Ipv4Addr};
+///
+/// use pcap_file::PrefixPreservingAnonymizer;
+///
+/// let anonymizer = PrefixPreservingAnonymizer::new([42; 32]);
+/// let a = anonymizer.anonymize(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1))).to_string();
+/// let b = anonymizer.anonymize(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 2))).to_string();
+/// assert_eq!(a.rsplit_once('.').unwrap().0, b.rsplit_once('.').unwrap().0);
+/// ```
+#[cfg(feature = "sha2")]
+#[derive(Clone)]
+pub struct PrefixPreservingAnonymizer {
+    key: [u8; 32],
+}
+
+#[cfg(feature = "sha2")]
+impl PrefixPreservingAnonymizer {
+    /// Creates a new [`PrefixPreservingAnonymizer`] from its secret key.
+    pub fn new(key: [u8; 32]) -> Self {
+        PrefixPreservingAnonymizer { key }
+    }
+
+    /// Returns the anonymized address, of the same IP version.
+    pub fn anonymize(&self, ip: IpAddr) -> IpAddr {
+        match ip {
+            IpAddr::V4(ip) => IpAddr::V4(self.anonymize_ipv4(ip)),
+            IpAddr::V6(ip) => IpAddr::V6(self.anonymize_ipv6(ip)),
+        }
+    }
+
+    /// Returns the anonymized IPv4 address.
+    pub fn anonymize_ipv4(&self, ip: Ipv4Addr) -> Ipv4Addr {
+        Ipv4Addr::from(self.anonymize_bits(u32::from(ip).into(), 32) as u32)
+    }
+
+    /// Returns the anonymized IPv6 address.
+    pub fn anonymize_ipv6(&self, ip: Ipv6Addr) -> Ipv6Addr {
+        Ipv6Addr::from(self.anonymize_bits(u128::from(ip), 128))
+    }
+
+    /// Anonymizes the `nb_bits` low bits of `addr`, from the most significant one.
+    fn anonymize_bits(&self, addr: u128, nb_bits: u32) -> u128 {
+        use sha2::{Digest, Sha256};
+
+        let mut keyed = Sha256::new();
+        keyed.update(self.key);
+        keyed.update((nb_bits as u8).to_be_bytes());
+
+        let mut anonymized = 0;
+        for i in 0..nb_bits {
+            // The i first bits of the address, the bit i itself excluded
+            let prefix = addr.checked_shr(nb_bits - i).unwrap_or(0);
+
+            let mut hasher = keyed.clone();
+            hasher.update((i as u8).to_be_bytes());
+            hasher.update(prefix.to_be_bytes());
+            let flip = u128::from(hasher.finalize()[0] & 1);
+
+            let bit = (addr >> (nb_bits - 1 - i)) & 1;
+            anonymized = anonymized << 1 | (bit ^ flip);
+        }
+
+        anonymized
+    }
+}
+
+#[cfg(feature = "sha2")]
+impl std::fmt::Debug for PrefixPreservingAnonymizer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The key is secret
+        f.debug_struct("PrefixPreservingAnonymizer").finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,112 @@
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::errors::*;
+
+
+/// File written to a temporary path, then renamed into place by [`AtomicFile::finish`], so that the consumers of the
+/// final path never see a half-written file, see [`PcapWriter::create_atomic`](crate::pcap::PcapWriter::create_atomic).
+///
+/// The temporary file is `<path>.tmp-<pid>`, in the same directory as the final file so that the rename is atomic.
+/// It is removed if the [`AtomicFile`] is dropped without being finished, e.g. after an error or during a panic, but
+/// kept if only its rename fails.
+///
+/// The file is wrapped in a [`BufWriter`].
+pub struct AtomicFile {
+    /// Temporary file, only taken when the [`AtomicFile`] is finished or dropped
+    file: Option<BufWriter<File>>,
+    tmp_path: PathBuf,
+    path: PathBuf,
+    finished: bool,
+}
+
+impl AtomicFile {
+    /// Creates the temporary file of `path`, truncating it if it exists.
+    ///
+    /// # Errors
+    /// The temporary file can't be created.
+    /// The error is a [`PcapError::WithPath`] containing the path of the temporary file.
+    pub fn create(path: impl AsRef<Path>) -> PcapResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut tmp_path = path.clone().into_os_string();
+        tmp_path.push(format!(".tmp-{}", std::process::id()));
+        let tmp_path = PathBuf::from(tmp_path);
+
+        let file = File::create(&tmp_path).map_err(|e| PcapError::IoError(e).with_path(&tmp_path))?;
+
+        Ok(AtomicFile { file: Some(BufWriter::new(file)), tmp_path, path, finished: false })
+    }
+
+    /// Returns the final path of the file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the path of the temporary file.
+    pub fn tmp_path(&self) -> &Path {
+        &self.tmp_path
+    }
+
+    /// Flushes the temporary file, syncs it to the disk, then renames it to the final path, replacing the file
+    /// there if any.
+    ///
+    /// # Errors
+    /// The temporary file can't be flushed or synced, the error is a [`PcapError::WithPath`] containing its path.
+    ///
+    /// The temporary file can't be renamed ([`PcapError::RenameFailed`]).
+    ///
+    /// The temporary file is removed if it can't be flushed or synced. It is kept if it can't be renamed, as it is
+    /// complete: its data can be recovered from the `from` path of the error.
+    pub fn finish(mut self) -> PcapResult<()> {
+        let file = self.file_mut().flush().and_then(|_| self.file_mut().get_ref().sync_all());
+        file.map_err(|e| PcapError::IoError(e).with_path(&self.tmp_path))?;
+
+        // Closes the file before renaming it, the drop no longer removes it
+        self.file = None;
+        self.finished = true;
+        std::fs::rename(&self.tmp_path, &self.path).map_err(|source| PcapError::RenameFailed {
+            from: self.tmp_path.clone().into_boxed_path(),
+            to: self.path.clone().into_boxed_path(),
+            source,
+        })
+    }
+
+    fn file_mut(&mut self) -> &mut BufWriter<File> {
+        // Can unwrap because the file is only taken by finish and drop, which consume self
+        self.file.as_mut().unwrap()
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.file_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file_mut().flush()
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if !self.finished {
+            // The buffered data are discarded instead of being flushed to a file that is removed
+            if let Some(file) = self.file.take() {
+                drop(file.into_parts());
+            }
+            let _ = std::fs::remove_file(&self.tmp_path);
+        }
+    }
+}
+
+impl Debug for AtomicFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AtomicFile")
+            .field("tmp_path", &self.tmp_path)
+            .field("path", &self.path)
+            .field("finished", &self.finished)
+            .finish_non_exhaustive()
+    }
+}
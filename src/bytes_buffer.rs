@@ -0,0 +1,129 @@
+use std::fmt::Debug;
+use std::io::{Error, ErrorKind, Read};
+
+use bytes::{Bytes, BytesMut};
+
+use crate::PcapError;
+
+
+/// Internal structure that bufferize its input in a [`BytesMut`] and splits the parsed elements off it.
+///
+/// The elements are [`Bytes`] sharing the allocation of the buffer, so they are returned without copy. A new
+/// allocation is only made when the buffer is refilled while elements borrowing the previous one are still alive.
+pub(crate) struct BytesBuffer<R: Read> {
+    /// Reader from which we read the data from
+    reader: R,
+    /// Data read but not split off yet, followed by an initialised region in which the next data are read
+    buffer: BytesMut,
+    /// Length of the data at the start of `buffer`
+    filled: usize,
+    /// Size of the allocations, which is also the maximum size of an element
+    capacity: usize,
+}
+
+impl<R: Read> BytesBuffer<R> {
+    /// Creates a new BytesBuffer with capacity of 1MiB
+    pub fn new(reader: R) -> Self {
+        Self::with_capacity(reader, 1 << 20)
+    }
+
+    /// Creates a new BytesBuffer with the given capacity
+    pub fn with_capacity(reader: R, capacity: usize) -> Self {
+        Self { reader, buffer: BytesMut::new(), filled: 0, capacity }
+    }
+
+    /// Splits the next element off the buffer.
+    ///
+    /// The `probe` is called on the buffered data until it doesn't return [`PcapError::IncompleteBuffer`],
+    /// filling the buffer in between. It returns the length of the element at the start of the data.
+    pub fn next_with<P>(&mut self, probe: P) -> Result<Bytes, PcapError>
+    where
+        P: FnMut(&[u8]) -> Result<usize, PcapError>,
+    {
+        self.next_with_cut(false, probe).map(|(element, _)| element)
+    }
+
+    /// Splits the next element off the buffer like [`BytesBuffer::next_with`], but if `cut_allowed` is true and the
+    /// reader ends before the `probe` succeeds, the rest of the data is split off instead of failing.
+    ///
+    /// Returns the element and true if it is cut by the end of the data.
+    pub fn next_with_cut<P>(&mut self, cut_allowed: bool, mut probe: P) -> Result<(Bytes, bool), PcapError>
+    where
+        P: FnMut(&[u8]) -> Result<usize, PcapError>,
+    {
+        loop {
+            match probe(&self.buffer[..self.filled]) {
+                Ok(len) => {
+                    self.filled -= len;
+                    return Ok((self.buffer.split_to(len).freeze(), false));
+                },
+
+                Err(PcapError::IncompleteBuffer) => {
+                    // The element len should never be more than the buffer capacity
+                    if self.filled >= self.capacity {
+                        return Err(PcapError::IoError(Error::from(ErrorKind::UnexpectedEof)));
+                    }
+
+                    let nb_read = self.fill_buf().map_err(PcapError::IoError)?;
+                    if nb_read == 0 && cut_allowed {
+                        let len = std::mem::take(&mut self.filled);
+                        return Ok((self.buffer.split_to(len).freeze(), true));
+                    }
+                    if nb_read == 0 {
+                        return Err(PcapError::TruncatedPacket);
+                    }
+                },
+
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Fill the buffer up to its capacity.
+    /// The initialised region after the data is reused if there is any left, otherwise the remaining data are moved
+    /// to a new allocation, or to the start of the current one, which is zeroed once.
+    fn fill_buf(&mut self) -> Result<usize, std::io::Error> {
+        if self.filled == self.buffer.len() {
+            self.buffer.reserve(self.capacity - self.filled);
+            self.buffer.resize(self.buffer.capacity(), 0);
+        }
+
+        let nb_read = self.reader.read(&mut self.buffer[self.filled..])?;
+        self.filled += nb_read;
+
+        Ok(nb_read)
+    }
+
+    /// Return true there are some data that can be read
+    pub fn has_data_left(&mut self) -> Result<bool, std::io::Error> {
+        if self.filled == 0 {
+            let nb_read = self.fill_buf()?;
+            if nb_read == 0 {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Return the inner reader
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Return a reference over the inner reader
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+}
+
+impl<R: Read + Debug> Debug for BytesBuffer<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The reader can hold the whole capture, e.g. a slice, only its type is shown
+        f.debug_struct("BytesBuffer")
+            .field("reader", &std::any::type_name::<R>())
+            .field("capacity", &self.capacity)
+            .field("buffered", &self.filled)
+            .finish()
+    }
+}
@@ -2,6 +2,8 @@ use byteorder_slice::{BigEndian, ByteOrder, LittleEndian};
 
 /// Timestamp resolution of the pcap
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum TsResolution {
     /// Microsecond resolution
     MicroSecond,
@@ -11,6 +13,8 @@ pub enum TsResolution {
 
 /// Endianness of the pcap
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum Endianness {
     /// Big endian
     Big,
@@ -35,6 +39,14 @@ impl Endianness {
         }
     }
 
+    /// Return the opposite endianness
+    pub fn opposite(self) -> Self {
+        match self {
+            Endianness::Big => Endianness::Little,
+            Endianness::Little => Endianness::Big,
+        }
+    }
+
     /// Return the endianness of the given ByteOrder
     pub fn from_byteorder<B: ByteOrder>() -> Self {
         if B::read_u32(&[0, 0, 0, 1]) == 1 {
@@ -71,15 +83,122 @@ impl RuntimeByteorder for LittleEndian {
     }
 }
 
+/// Small seedable pseudo random number generator (SplitMix64), not suitable for cryptography.
+#[derive(Clone, Debug)]
+pub(crate) struct SplitMix64(u64);
+
+impl SplitMix64 {
+    pub(crate) fn new(seed: u64) -> Self {
+        SplitMix64(seed)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in `0..n`.
+    pub(crate) fn below(&mut self, n: u64) -> u64 {
+        ((self.next_u64() as u128 * n as u128) >> 64) as u64
+    }
+}
+
+/// True if the crate is built with the `redact` feature, under which the packet data are never formatted.
+/// It can be checked at startup by the applications which must never log packet data.
+///
+/// The [`Debug`](std::fmt::Debug) representations of the packets and the blocks then only show the length of their data.
+/// The errors and the reports never contain packet data, with or without the feature.
+pub const PAYLOAD_REDACTED: bool = cfg!(feature = "redact");
+
+/// Debug representation of packet data, showing at most its first [`DebugBytes::MAX_LEN`] bytes and its length.
+///
+/// Only the length is shown with the `redact` feature, see [`PAYLOAD_REDACTED`].
+pub(crate) struct DebugBytes<'a>(pub(crate) &'a [u8]);
+
+impl DebugBytes<'_> {
+    /// Maximum number of bytes shown
+    pub(crate) const MAX_LEN: usize = 32;
+}
+
+impl std::fmt::Debug for DebugBytes<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if PAYLOAD_REDACTED {
+            return write!(f, "<redacted> ({} bytes)", self.0.len());
+        }
+
+        if self.0.len() <= Self::MAX_LEN {
+            return write!(f, "{:?}", self.0);
+        }
+
+        f.debug_list().entries(&self.0[..Self::MAX_LEN]).finish_non_exhaustive()?;
+        write!(f, " ({} bytes)", self.0.len())
+    }
+}
+
+/// Retry policy of the readers for the reads returning no data in the middle of a record, e.g. the transient
+/// short reads of a network filesystem, see [`PcapReader::with_zero_read_retry`](crate::pcap::PcapReader::with_zero_read_retry).
+///
+/// A read returning no data at a record boundary is the end of the capture, it is never retried.
+/// When the reads still return no data after the retries, the record is cut and the reader returns
+/// [`PcapError::UnexpectedEofMidPacket`](crate::PcapError::UnexpectedEofMidPacket).
+pub struct ZeroReadRetry {
+    max_retries: u32,
+    backoff: Option<Box<dyn FnMut(u32) + Send + Sync>>,
+}
+
+impl ZeroReadRetry {
+    /// Creates a new [`ZeroReadRetry`] retrying a read returning no data at most `max_retries` times, without waiting.
+    pub fn new(max_retries: u32) -> Self {
+        ZeroReadRetry { max_retries, backoff: None }
+    }
+
+    /// Sets a hook called before each retry with the number of the retry, starting at 1, e.g. to sleep
+    /// for an increasing duration.
+    pub fn with_backoff<F: FnMut(u32) + Send + Sync + 'static>(mut self, backoff: F) -> Self {
+        self.backoff = Some(Box::new(backoff));
+        self
+    }
+
+    /// Returns the maximum number of retries of a read returning no data.
+    pub fn max_retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    /// Calls the backoff hook before the retry number `retry`.
+    pub(crate) fn backoff(&mut self, retry: u32) {
+        if let Some(backoff) = &mut self.backoff {
+            backoff(retry);
+        }
+    }
+}
+
+impl std::fmt::Debug for ZeroReadRetry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ZeroReadRetry")
+            .field("max_retries", &self.max_retries)
+            .field("backoff", &self.backoff.is_some())
+            .finish()
+    }
+}
+
 
 /// Data link type
 ///
 /// The link-layer header type specifies the first protocol of the packet.
 ///
 /// See [http://www.tcpdump.org/linktypes.html](http://www.tcpdump.org/linktypes.html)
+///
+/// [`DataLink::from`] and the readers never build a [`DataLink::Unknown`] with a known code. One built by hand, e.g.
+/// `DataLink::Unknown(199)`, isn't equal to the variant of this code: [`DataLink::normalize`] converts it, and the
+/// writers normalize the data links they are given, so that they are equal to the ones read back.
 #[allow(non_camel_case_types)]
 #[allow(missing_docs)]
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(into = "u32"))]
 pub enum DataLink {
     NULL,
     ETHERNET,
@@ -734,3 +853,283 @@ impl From<DataLink> for u32 {
         }
     }
 }
+
+impl DataLink {
+    /// Returns the data link built from the numeric code, i.e. the variant of the code for a [`DataLink::Unknown`]
+    /// with a known code, e.g. [`DataLink::IPMB_KONTRON`] for `DataLink::Unknown(199)`.
+    pub fn normalize(self) -> DataLink {
+        DataLink::from(u32::from(self))
+    }
+
+    /// Returns the canonical libpcap name of the data link, as accepted by `tcpdump -y`, e.g. `EN10MB` for
+    /// [`DataLink::ETHERNET`].
+    ///
+    /// Returns [`None`] for [`DataLink::Unknown`].
+    pub fn name(self) -> Option<&'static str> {
+        // Goes through the numeric code so that the aliases of the same code have the same name
+        let link = DataLink::from(u32::from(self));
+        DATALINK_NAMES.iter().find(|(known, _)| *known == link).map(|(_, name)| *name)
+    }
+
+    /// Parses a data link from its libpcap name (see [`DataLink::name`]), its LINKTYPE name or its numeric code.
+    ///
+    /// The names are case-insensitive and can have the `DLT_` or `LINKTYPE_` prefix, e.g. `EN10MB`, `ethernet`,
+    /// `DLT_EN10MB` and `1` are all [`DataLink::ETHERNET`].
+    pub fn from_name(name: &str) -> Option<DataLink> {
+        if let Ok(code) = name.parse::<u32>() {
+            return Some(code.into());
+        }
+
+        let upper = name.to_ascii_uppercase();
+        let name = upper.strip_prefix("DLT_").or_else(|| upper.strip_prefix("LINKTYPE_")).unwrap_or(&upper);
+
+        DATALINK_NAMES
+            .iter()
+            .chain(DATALINK_ALIASES)
+            .find(|(_, link_name)| *link_name == name)
+            .map(|(link, _)| *link)
+    }
+
+    /// Returns the length of the link-layer header of the packets, i.e. the offset of the network layer, for the data
+    /// links whose header has a fixed length, e.g. 14 for [`DataLink::ETHERNET`] and 0 for [`DataLink::RAW`].
+    ///
+    /// The Ethernet header is counted without VLAN tags. Returns [`None`] for the data links whose header length
+    /// varies or isn't known.
+    pub fn header_len(self) -> Option<usize> {
+        match self {
+            DataLink::RAW | DataLink::IPV4 | DataLink::IPV6 => Some(0),
+            DataLink::NULL | DataLink::LOOP => Some(4),
+            DataLink::ETHERNET => Some(14),
+            DataLink::LINUX_SLL => Some(16),
+            DataLink::LINUX_SLL2 => Some(20),
+            _ => None,
+        }
+    }
+}
+
+/// Libpcap names of the data links.
+const DATALINK_NAMES: &[(DataLink, &str)] = &[
+    (DataLink::NULL, "NULL"),
+    (DataLink::ETHERNET, "EN10MB"),
+    (DataLink::EXP_ETHERNET, "EN3MB"),
+    (DataLink::AX25, "AX25"),
+    (DataLink::PRONET, "PRONET"),
+    (DataLink::CHAOS, "CHAOS"),
+    (DataLink::IEEE802_5, "IEEE802"),
+    (DataLink::ARCNET_BSD, "ARCNET"),
+    (DataLink::SLIP, "SLIP"),
+    (DataLink::PPP, "PPP"),
+    (DataLink::FDDI, "FDDI"),
+    (DataLink::PPP_HDLC, "PPP_SERIAL"),
+    (DataLink::PPP_ETHER, "PPP_ETHER"),
+    (DataLink::SYMANTEC_FIREWALL, "SYMANTEC_FIREWALL"),
+    (DataLink::ATM_RFC1483, "ATM_RFC1483"),
+    (DataLink::RAW, "RAW"),
+    (DataLink::SLIP_BSDOS, "SLIP_BSDOS"),
+    (DataLink::PPP_BSDOS, "PPP_BSDOS"),
+    (DataLink::C_HDLC, "C_HDLC"),
+    (DataLink::IEEE802_11, "IEEE802_11"),
+    (DataLink::ATM_CLIP, "ATM_CLIP"),
+    (DataLink::FRELAY, "FRELAY"),
+    (DataLink::LOOP, "LOOP"),
+    (DataLink::ENC, "ENC"),
+    (DataLink::LANE8023, "LANE8023"),
+    (DataLink::HIPPI, "HIPPI"),
+    (DataLink::NETBSD_HDLC, "NETBSD_HDLC"),
+    (DataLink::LINUX_SLL, "LINUX_SLL"),
+    (DataLink::LTALK, "LTALK"),
+    (DataLink::ECONET, "ECONET"),
+    (DataLink::IPFILTER, "IPFILTER"),
+    (DataLink::PFLOG, "PFLOG"),
+    (DataLink::CISCO_IOS, "CISCO_IOS"),
+    (DataLink::IEEE802_11_PRISM, "PRISM_HEADER"),
+    (DataLink::IEEE802_11_AIRONET, "AIRONET_HEADER"),
+    (DataLink::HHDLC, "HHDLC"),
+    (DataLink::IP_OVER_FC, "IP_OVER_FC"),
+    (DataLink::SUNATM, "SUNATM"),
+    (DataLink::RIO, "RIO"),
+    (DataLink::PCI_EXP, "PCI_EXP"),
+    (DataLink::AURORA, "AURORA"),
+    (DataLink::IEEE802_11_RADIOTAP, "IEEE802_11_RADIO"),
+    (DataLink::TZSP, "TZSP"),
+    (DataLink::ARCNET_LINUX, "ARCNET_LINUX"),
+    (DataLink::JUNIPER_MLPPP, "JUNIPER_MLPPP"),
+    (DataLink::JUNIPER_MLFR, "JUNIPER_MLFR"),
+    (DataLink::JUNIPER_ES, "JUNIPER_ES"),
+    (DataLink::JUNIPER_GGSN, "JUNIPER_GGSN"),
+    (DataLink::JUNIPER_MFR, "JUNIPER_MFR"),
+    (DataLink::JUNIPER_ATM2, "JUNIPER_ATM2"),
+    (DataLink::JUNIPER_SERVICES, "JUNIPER_SERVICES"),
+    (DataLink::JUNIPER_ATM1, "JUNIPER_ATM1"),
+    (DataLink::APPLE_IP_OVER_IEEE1394, "APPLE_IP_OVER_IEEE1394"),
+    (DataLink::MTP2_WITH_PHDR, "MTP2_WITH_PHDR"),
+    (DataLink::MTP2, "MTP2"),
+    (DataLink::MTP3, "MTP3"),
+    (DataLink::SCCP, "SCCP"),
+    (DataLink::DOCSIS, "DOCSIS"),
+    (DataLink::LINUX_IRDA, "LINUX_IRDA"),
+    (DataLink::IBM_SP, "IBM_SP"),
+    (DataLink::IBM_SN, "IBM_SN"),
+    (DataLink::USER0, "USER0"),
+    (DataLink::USER1, "USER1"),
+    (DataLink::USER2, "USER2"),
+    (DataLink::USER3, "USER3"),
+    (DataLink::USER4, "USER4"),
+    (DataLink::USER5, "USER5"),
+    (DataLink::USER6, "USER6"),
+    (DataLink::USER7, "USER7"),
+    (DataLink::USER8, "USER8"),
+    (DataLink::USER9, "USER9"),
+    (DataLink::USER10, "USER10"),
+    (DataLink::USER11, "USER11"),
+    (DataLink::USER12, "USER12"),
+    (DataLink::USER13, "USER13"),
+    (DataLink::USER14, "USER14"),
+    (DataLink::USER15, "USER15"),
+    (DataLink::IEEE802_11_AVS, "IEEE802_11_RADIO_AVS"),
+    (DataLink::JUNIPER_MONITOR, "JUNIPER_MONITOR"),
+    (DataLink::BACNET_MS_TP, "BACNET_MS_TP"),
+    (DataLink::PPP_PPPD, "PPP_PPPD"),
+    (DataLink::JUNIPER_PPPOE, "JUNIPER_PPPOE"),
+    (DataLink::JUNIPER_PPPOE_ATM, "JUNIPER_PPPOE_ATM"),
+    (DataLink::GPRS_LLC, "GPRS_LLC"),
+    (DataLink::GPF_T, "GPF_T"),
+    (DataLink::GPF_F, "GPF_F"),
+    (DataLink::GCOM_T1E1, "GCOM_T1E1"),
+    (DataLink::GCOM_SERIAL, "GCOM_SERIAL"),
+    (DataLink::JUNIPER_PIC_PEER, "JUNIPER_PIC_PEER"),
+    (DataLink::ERF_ETH, "ERF_ETH"),
+    (DataLink::ERF_POS, "ERF_POS"),
+    (DataLink::LINUX_LAPD, "LINUX_LAPD"),
+    (DataLink::JUNIPER_ETHER, "JUNIPER_ETHER"),
+    (DataLink::JUNIPER_PPP, "JUNIPER_PPP"),
+    (DataLink::JUNIPER_FRELAY, "JUNIPER_FRELAY"),
+    (DataLink::JUNIPER_CHDLC, "JUNIPER_CHDLC"),
+    (DataLink::MFR, "MFR"),
+    (DataLink::JUNIPER_VP, "JUNIPER_VP"),
+    (DataLink::A429, "A429"),
+    (DataLink::A653_ICM, "A653_ICM"),
+    (DataLink::USB_FREEBSD, "USB_FREEBSD"),
+    (DataLink::BLUETOOTH_HCI_H4, "BLUETOOTH_HCI_H4"),
+    (DataLink::IEEE802_16_MAC_CPS, "IEEE802_16_MAC_CPS"),
+    (DataLink::USB_LINUX, "USB_LINUX"),
+    (DataLink::CAN20B, "CAN20B"),
+    (DataLink::IEEE802_15_4_LINUX, "IEEE802_15_4_LINUX"),
+    (DataLink::PPI, "PPI"),
+    (DataLink::IEEE802_16_MAC_CPS_RADIO, "IEEE802_16_MAC_CPS_RADIO"),
+    (DataLink::JUNIPER_ISM, "JUNIPER_ISM"),
+    (DataLink::IEEE802_15_4, "IEEE802_15_4"),
+    (DataLink::SITA, "SITA"),
+    (DataLink::ERF, "ERF"),
+    (DataLink::RAIF1, "RAIF1"),
+    (DataLink::IPMB_KONTRON, "IPMB_KONTRON"),
+    (DataLink::JUNIPER_ST, "JUNIPER_ST"),
+    (DataLink::BLUETOOTH_HCI_H4_WITH_PHDR, "BLUETOOTH_HCI_H4_WITH_PHDR"),
+    (DataLink::AX25_KISS, "AX25_KISS"),
+    (DataLink::LAPD, "LAPD"),
+    (DataLink::PPP_WITH_DIR, "PPP_WITH_DIR"),
+    (DataLink::C_HDLC_WITH_DIR, "C_HDLC_WITH_DIR"),
+    (DataLink::FRELAY_WITH_DIR, "FRELAY_WITH_DIR"),
+    (DataLink::LAPB_WITH_DIR, "LAPB_WITH_DIR"),
+    (DataLink::IPMB_LINUX, "IPMB_LINUX"),
+    (DataLink::FLEXRAY, "FLEXRAY"),
+    (DataLink::MOST, "MOST"),
+    (DataLink::LIN, "LIN"),
+    (DataLink::X2E_SERIAL, "X2E_SERIAL"),
+    (DataLink::X2E_XORAYA, "X2E_XORAYA"),
+    (DataLink::IEEE802_15_4_NONASK_PHY, "IEEE802_15_4_NONASK_PHY"),
+    (DataLink::LINUX_EVDEV, "LINUX_EVDEV"),
+    (DataLink::GSMTAP_UM, "GSMTAP_UM"),
+    (DataLink::GSMTAP_ABIS, "GSMTAP_ABIS"),
+    (DataLink::MPLS, "MPLS"),
+    (DataLink::USB_LINUX_MMAPPED, "USB_LINUX_MMAPPED"),
+    (DataLink::DECT, "DECT"),
+    (DataLink::AOS, "AOS"),
+    (DataLink::WIHART, "WIHART"),
+    (DataLink::FC_2, "FC_2"),
+    (DataLink::FC_2_WITH_FRAME_DELIMS, "FC_2_WITH_FRAME_DELIMS"),
+    (DataLink::IPNET, "IPNET"),
+    (DataLink::CAN_SOCKETCAN, "CAN_SOCKETCAN"),
+    (DataLink::IPV4, "IPV4"),
+    (DataLink::IPV6, "IPV6"),
+    (DataLink::IEEE802_15_4_NOFCS, "IEEE802_15_4_NOFCS"),
+    (DataLink::DBUS, "DBUS"),
+    (DataLink::JUNIPER_VS, "JUNIPER_VS"),
+    (DataLink::JUNIPER_SRX_E2E, "JUNIPER_SRX_E2E"),
+    (DataLink::JUNIPER_FIBRECHANNEL, "JUNIPER_FIBRECHANNEL"),
+    (DataLink::DVB_CI, "DVB_CI"),
+    (DataLink::MUX27010, "MUX27010"),
+    (DataLink::STANAG_5066_D_PDU, "STANAG_5066_D_PDU"),
+    (DataLink::JUNIPER_ATM_CEMIC, "JUNIPER_ATM_CEMIC"),
+    (DataLink::NFLOG, "NFLOG"),
+    (DataLink::NETANALYZER, "NETANALYZER"),
+    (DataLink::NETANALYZER_TRANSPARENT, "NETANALYZER_TRANSPARENT"),
+    (DataLink::IPOIB, "IPOIB"),
+    (DataLink::MPEG_2_TS, "MPEG_2_TS"),
+    (DataLink::NG40, "NG40"),
+    (DataLink::NFC_LLCP, "NFC_LLCP"),
+    (DataLink::PFSYNC, "PFSYNC"),
+    (DataLink::INFINIBAND, "INFINIBAND"),
+    (DataLink::SCTP, "SCTP"),
+    (DataLink::USBPCAP, "USBPCAP"),
+    (DataLink::RTAC_SERIAL, "RTAC_SERIAL"),
+    (DataLink::BLUETOOTH_LE_LL, "BLUETOOTH_LE_LL"),
+    (DataLink::WIRESHARK_UPPER_PDU, "WIRESHARK_UPPER_PDU"),
+    (DataLink::NETLINK, "NETLINK"),
+    (DataLink::BLUETOOTH_LINUX_MONITOR, "BLUETOOTH_LINUX_MONITOR"),
+    (DataLink::BLUETOOTH_BREDR_BB, "BLUETOOTH_BREDR_BB"),
+    (DataLink::BLUETOOTH_LE_LL_WITH_PHDR, "BLUETOOTH_LE_LL_WITH_PHDR"),
+    (DataLink::PROFIBUS_DL, "PROFIBUS_DL"),
+    (DataLink::PKTAP, "PKTAP"),
+    (DataLink::EPON, "EPON"),
+    (DataLink::IPMI_HPM_2, "IPMI_HPM_2"),
+    (DataLink::ZWAVE_R1_R2, "ZWAVE_R1_R2"),
+    (DataLink::ZWAVE_R3, "ZWAVE_R3"),
+    (DataLink::WATTSTOPPER_DLM, "WATTSTOPPER_DLM"),
+    (DataLink::ISO_14443, "ISO_14443"),
+    (DataLink::RDS, "RDS"),
+    (DataLink::USB_DARWIN, "USB_DARWIN"),
+    (DataLink::OPENFLOW, "OPENFLOW"),
+    (DataLink::SDLC, "SDLC"),
+    (DataLink::TI_LLN_SNIFFER, "TI_LLN_SNIFFER"),
+    (DataLink::LORATAP, "LORATAP"),
+    (DataLink::VSOCK, "VSOCK"),
+    (DataLink::NORDIC_BLE, "NORDIC_BLE"),
+    (DataLink::DOCSIS31_XRA31, "DOCSIS31_XRA31"),
+    (DataLink::ETHERNET_MPACKET, "ETHERNET_MPACKET"),
+    (DataLink::DISPLAYPORT_AUX, "DISPLAYPORT_AUX"),
+    (DataLink::LINUX_SLL2, "LINUX_SLL2"),
+    (DataLink::SERCOS_MONITOR, "SERCOS_MONITOR"),
+    (DataLink::OPENVIZSLA, "OPENVIZSLA"),
+    (DataLink::EBHSCR, "EBHSCR"),
+    (DataLink::VPP_DISPATCH, "VPP_DISPATCH"),
+    (DataLink::DSA_TAG_BRCM, "DSA_TAG_BRCM"),
+    (DataLink::DSA_TAG_BRCM_PREPEND, "DSA_TAG_BRCM_PREPEND"),
+    (DataLink::IEEE802_15_4_TAP, "IEEE802_15_4_TAP"),
+    (DataLink::DSA_TAG_DSA, "DSA_TAG_DSA"),
+    (DataLink::DSA_TAG_EDSA, "DSA_TAG_EDSA"),
+    (DataLink::ELEE, "ELEE"),
+    (DataLink::Z_WAVE_SERIAL, "Z_WAVE_SERIAL"),
+    (DataLink::USB_2_0, "USB_2_0"),
+    (DataLink::ATSC_ALP, "ATSC_ALP"),
+    (DataLink::ETW, "ETW"),
+    (DataLink::NETANALYZER_NG, "NETANALYZER_NG"),
+    (DataLink::ZBOSS_NCP, "ZBOSS_NCP"),
+    (DataLink::USB_2_0_LOW_SPEED, "USB_2_0_LOW_SPEED"),
+    (DataLink::USB_2_0_FULL_SPEED, "USB_2_0_FULL_SPEED"),
+    (DataLink::USB_2_0_HIGH_SPEED, "USB_2_0_HIGH_SPEED"),
+    (DataLink::AUERSWALD_LOG, "AUERSWALD_LOG"),
+];
+
+/// LINKTYPE names of the data links whose libpcap name is different.
+const DATALINK_ALIASES: &[(DataLink, &str)] = &[
+    (DataLink::ETHERNET, "ETHERNET"),
+    (DataLink::EXP_ETHERNET, "EXP_ETHERNET"),
+    (DataLink::IEEE802_5, "IEEE802_5"),
+    (DataLink::ARCNET_BSD, "ARCNET_BSD"),
+    (DataLink::PPP_HDLC, "PPP_HDLC"),
+    (DataLink::IEEE802_11_PRISM, "IEEE802_11_PRISM"),
+    (DataLink::IEEE802_11_AIRONET, "IEEE802_11_AIRONET"),
+    (DataLink::IEEE802_11_RADIOTAP, "IEEE802_11_RADIOTAP"),
+    (DataLink::IEEE802_11_AVS, "IEEE802_11_AVS"),
+];
@@ -0,0 +1,140 @@
+//! Simple functions to read and write whole pcap files, without lifetimes.
+//!
+//! These functions are built on [`PcapReader`] and [`PcapWriter`] and trade flexibility for simplicity:
+//! the packets are always owned and the files are always accessed by path.
+//! Use the [`pcap`](crate::pcap) module directly for anything more involved.
+//!
+//! The data of the packets read is limited to [`MAX_PACKET_DATA`] bytes, even if the snaplen of the file is bigger:
+//! reading a longer packet is an error.
+//!
+//! ```rust
+//! use std::ops::ControlFlow;
+//! use std::time::Duration;
+//!
+//! use pcap_file::easy::{self, FileInfo, PacketOwned};
+//!
+//! let path = std::env::temp_dir().join(format!("pcap_file_easy_doc_{}.pcap", std::process::id()));
+//!
+//! let packets = vec![PacketOwned::new_owned(Duration::from_secs(1), 4, vec![1, 2, 3, 4])];
+//! easy::write_all(&path, &FileInfo::default(), &packets).unwrap();
+//!
+//! let (info, read) = easy::read_all(&path).unwrap();
+//! assert_eq!(info, FileInfo::default());
+//! assert_eq!(read[0].data, packets[0].data);
+//!
+//! let mut nb_packets = 0;
+//! easy::for_each_packet(&path, |_packet| {
+//!     nb_packets += 1;
+//!     ControlFlow::Continue(())
+//! })
+//! .unwrap();
+//! assert_eq!(nb_packets, 1);
+//!
+//! # std::fs::remove_file(&path).unwrap();
+//! ```
+
+use std::io::Write;
+use std::ops::ControlFlow;
+use std::path::Path;
+
+use crate::errors::*;
+use crate::pcap::{PcapHeader, PcapPacket, PcapReader, PcapWriter};
+use crate::DataLink;
+
+/// Information about a pcap file, i.e. its global header.
+pub type FileInfo = PcapHeader;
+
+/// Packet owning its data.
+pub type PacketOwned = PcapPacket<'static>;
+
+/// Maximum number of bytes of data of each packet read, reading a longer packet is an error.
+pub const MAX_PACKET_DATA: usize = 262144;
+
+/// Reads all the packets of the pcap file at the given path.
+///
+/// # Errors
+/// The file can't be opened or isn't a valid pcap, or a packet has more than [`MAX_PACKET_DATA`] bytes of data.
+/// The error is a [`PcapError::WithPath`] containing the path of the file.
+pub fn read_all(path: impl AsRef<Path>) -> PcapResult<(FileInfo, Vec<PacketOwned>)> {
+    let mut packets = vec![];
+    let info = read_packets(path.as_ref(), |packet| {
+        packets.push(packet);
+        ControlFlow::Continue(())
+    })?;
+
+    Ok((info, packets))
+}
+
+/// Calls `f` on each packet of the pcap file at the given path, until `f` returns [`ControlFlow::Break`].
+///
+/// # Errors
+/// The file can't be opened or isn't a valid pcap, or a packet has more than [`MAX_PACKET_DATA`] bytes of data.
+/// The error is a [`PcapError::WithPath`] containing the path of the file.
+pub fn for_each_packet(path: impl AsRef<Path>, f: impl FnMut(PacketOwned) -> ControlFlow<()>) -> PcapResult<()> {
+    read_packets(path.as_ref(), f).map(drop)
+}
+
+/// Writes the packets to a new pcap file at the given path, truncating it if it exists.
+///
+/// # Errors
+/// The file can't be written to or a packet is invalid (e.g. its data is longer than the snaplen).
+/// The error is a [`PcapError::WithPath`] containing the path of the file.
+pub fn write_all(path: impl AsRef<Path>, info: &FileInfo, packets: &[PacketOwned]) -> PcapResult<()> {
+    let path = path.as_ref();
+    let mut writer = PcapWriter::create(path, *info)?;
+
+    for packet in packets {
+        writer.write_packet(packet).map_err(|e| e.with_path(path))?;
+    }
+
+    writer.flush().map_err(|e| e.with_path(path))
+}
+
+/// Writes raw frames, e.g. generated by a fuzzer, as a pcap with the given datalink.
+///
+/// The frames get increasing timestamps, one microsecond apart starting at 0, and are written whole:
+/// the snaplen is the default one unless a frame is longer.
+///
+/// # Example
+/// ```rust
+/// use pcap_file::easy::frames_to_pcap;
+/// use pcap_file::DataLink;
+///
+/// let frames = vec![vec![0_u8; 60], vec![1_u8; 1514]];
+/// let mut pcap = Vec::new();
+/// frames_to_pcap(&frames, DataLink::ETHERNET, &mut pcap).unwrap();
+/// ```
+///
+/// # Errors
+/// The writer can't be written to.
+pub fn frames_to_pcap<W: Write>(frames: &[impl AsRef<[u8]>], datalink: DataLink, writer: W) -> PcapResult<()> {
+    let default_header = PcapHeader::default();
+    let max_len = frames.iter().map(|frame| frame.as_ref().len()).max().unwrap_or(0);
+    let snaplen = default_header.snaplen.max(max_len.try_into().unwrap_or(u32::MAX));
+
+    let header = PcapHeader { datalink, snaplen, ..default_header };
+    let mut writer = PcapWriter::with_header(writer, header)?;
+    for frame in frames {
+        writer.write_auto(frame.as_ref())?;
+    }
+
+    writer.flush()
+}
+
+/// Reads the packets of the file until `f` returns [`ControlFlow::Break`], returns the header of the file.
+fn read_packets(path: &Path, mut f: impl FnMut(PacketOwned) -> ControlFlow<()>) -> PcapResult<FileInfo> {
+    let mut reader = PcapReader::from_path(path)?.with_data_limit(MAX_PACKET_DATA);
+
+    while let Some(packet) = reader.next_packet() {
+        let packet = packet.map_err(|e| e.with_path(path))?.into_owned();
+        if reader.is_data_truncated() {
+            return Err(PcapError::InvalidField("PcapPacket: incl_len > MAX_PACKET_DATA").with_path(path));
+        }
+
+        if f(packet).is_break() {
+            break;
+        }
+    }
+
+    Ok(reader.header())
+}
@@ -1,9 +1,13 @@
+use std::path::{Path, PathBuf};
+
 use thiserror::Error;
 
 /// Result type for the pcap/pcapng parsing
 pub type PcapResult<T> = Result<T, PcapError>;
 
 /// Error type for the pcap/pcapng parsing
+///
+/// The errors never hold packet data or buffers, so they are small and cheap to move.
 #[derive(Error, Debug)]
 pub enum PcapError {
     /// Buffer too small
@@ -14,10 +18,36 @@ pub enum PcapError {
     #[error("Error reading/writing bytes")]
     IoError(#[source] std::io::Error),
 
+    /// The data ended in the middle of a record, even after retrying the reads returning no data,
+    /// see [`ZeroReadRetry`](crate::ZeroReadRetry)
+    ///
+    /// Without retry policy, the data ending in the middle of a record is a [`PcapError::TruncatedPacket`].
+    #[error("The data ended in the middle of a record after {0} retries")]
+    UnexpectedEofMidPacket(u32),
+
+    /// The data ended in the middle of a packet record or of a block, i.e. the capture is truncated
+    ///
+    /// See [`TruncationPolicy`](crate::pcap::TruncationPolicy) to read the pcaps whose last record is truncated.
+    #[error("The data ended in the middle of a record")]
+    TruncatedPacket,
+
+    /// The magic number of the global header, read in big endian, isn't the one of a pcap (only for Pcap)
+    ///
+    /// The magic numbers of the known variants which aren't supported are a [`PcapError::UnsupportedVariant`].
+    /// The magic number itself isn't kept: the data aren't a pcap and may be anything.
+    #[error("Invalid magic number")]
+    BadMagic,
+
     /// Invalid field
     #[error("Invalid field value: {0}")]
     InvalidField(&'static str),
 
+    /// Invalid configuration given by the caller, e.g. an invalid header given to a writer
+    ///
+    /// Nothing was written when it is returned.
+    #[error("Invalid configuration: {0}")]
+    InvalidConfiguration(&'static str),
+
     /// UTF8 conversion error
     #[error("UTF8 error")]
     Utf8Error(#[source] std::str::Utf8Error),
@@ -26,19 +56,168 @@ pub enum PcapError {
     #[error("UTF8 error")]
     FromUtf8Error(#[source] std::string::FromUtf8Error),
 
+    /// The magic number is the one of a known format that is not supported (only for Pcap)
+    #[error("The file looks like a {0}, which is not supported")]
+    UnsupportedVariant(&'static str),
+
+    /// The global header looks like it has a known defect (only for Pcap)
+    #[error("The global header looks like it has the quirk {0:?}")]
+    SuspectedQuirk(crate::pcap::PcapQuirk),
+
+    /// The records don't seem to have the endianness declared by the global header (only for Pcap)
+    #[error("The packet headers look like they have the opposite endianness of the global header")]
+    SuspiciousEndianness,
+
+    /// The index wasn't built from the capture read (only for Pcap)
+    #[error("The index doesn't match the capture")]
+    IndexMismatch,
+
+    /// A global header was found where a packet header was expected, e.g. in pcaps concatenated with `cat` (only for Pcap)
+    ///
+    /// See [`PcapReader::with_concatenated`](crate::pcap::PcapReader::with_concatenated) to read them.
+    #[error("Embedded global header found, this looks like concatenated pcap files")]
+    EmbeddedGlobalHeader,
+
+    /// The offset isn't the start of a packet (only for Pcap)
+    #[error("The offset {0} isn't the start of a packet")]
+    NotARecordBoundary(u64),
+
+    /// The `incl_len` of a packet to write isn't the length of its data (only for Pcap)
+    #[error("The incl_len ({incl_len}) of the packet isn't the length of its data ({data_len})")]
+    LengthMismatch {
+        /// `incl_len` of the packet
+        incl_len: u32,
+        /// Length of the data of the packet
+        data_len: usize,
+    },
+
     /// Invalid interface ID (only for Pcap NG)
     #[error("The interface id ({0}) of the current block doesn't exists")]
     InvalidInterfaceId(u32),
 
+    /// A block referencing an interface is before the first interface of its section (only for Pcap NG)
+    ///
+    /// See [`PcapNgReader::with_early_block_policy`](crate::pcapng::PcapNgReader::with_early_block_policy) to read them.
+    #[error("Block of type {block_type:#010X} at offset {offset} before the first interface of its section")]
+    BlockBeforeInterface {
+        /// Type of the block
+        block_type: u32,
+        /// Offset of the block from the start of the pcapng
+        offset: u64,
+    },
+
     /// Invalid timestamp resolution (only for Pcap NG)
     #[error("Invalid timestamp resolution: {0} is not in [0-9]")]
     InvalidTsResolution(u8),
 
+    /// Invalid option (only for Pcap NG)
+    ///
+    /// The offset is relative to the start of the options of the block.
+    #[error("Invalid option (code: {code}, offset: {offset}): {reason}")]
+    InvalidOption {
+        /// Code of the option
+        code: u16,
+        /// Offset of the option from the start of the options of the block
+        offset: usize,
+        /// Reason of the error
+        reason: &'static str,
+    },
+
+    /// Error while opening or creating a file, with the path of the file
+    #[error("{}: {source}", path.display())]
+    WithPath {
+        /// Path of the file
+        path: PathBuf,
+        /// Error that occurred
+        #[source]
+        source: Box<PcapError>,
+    },
+
+    /// A file can't be renamed, e.g. the temporary file of an [`AtomicFile`](crate::AtomicFile)
+    ///
+    /// The paths are boxed to keep the error small.
+    #[error("Renaming {} to {}: {source}", from.display(), to.display())]
+    RenameFailed {
+        /// Path of the file renamed
+        from: Box<Path>,
+        /// Path it was renamed to
+        to: Box<Path>,
+        /// Error that occurred
+        #[source]
+        source: std::io::Error,
+    },
+
     /// The packet's timestamp is too big (only for Pcap NG)
     #[error("Packet's timestamp too big, please choose a bigger timestamp resolution")]
     TimestampTooBig,
 }
 
+impl PcapError {
+    /// Returns a stable code identifying the kind of the error, e.g. `"PCAP_SUSPICIOUS_ENDIANNESS"`.
+    ///
+    /// Unlike the messages displayed, the codes are part of the API: they are never changed nor reused
+    /// in a minor version, so they can be matched on or returned to clients.
+    /// Each variant has its own code, except [`PcapError::WithPath`], which returns the code of its source.
+    pub fn code(&self) -> &'static str {
+        match self {
+            PcapError::IncompleteBuffer => "PCAP_INCOMPLETE_BUFFER",
+            PcapError::IoError(_) => "PCAP_IO_ERROR",
+            PcapError::UnexpectedEofMidPacket(_) => "PCAP_UNEXPECTED_EOF_MID_PACKET",
+            PcapError::TruncatedPacket => "PCAP_TRUNCATED_PACKET",
+            PcapError::BadMagic => "PCAP_BAD_MAGIC",
+            PcapError::InvalidField(_) => "PCAP_INVALID_FIELD",
+            PcapError::InvalidConfiguration(_) => "PCAP_INVALID_CONFIGURATION",
+            PcapError::Utf8Error(_) => "PCAP_UTF8_ERROR",
+            PcapError::FromUtf8Error(_) => "PCAP_FROM_UTF8_ERROR",
+            PcapError::UnsupportedVariant(_) => "PCAP_UNSUPPORTED_VARIANT",
+            PcapError::SuspectedQuirk(_) => "PCAP_SUSPECTED_QUIRK",
+            PcapError::SuspiciousEndianness => "PCAP_SUSPICIOUS_ENDIANNESS",
+            PcapError::IndexMismatch => "PCAP_INDEX_MISMATCH",
+            PcapError::EmbeddedGlobalHeader => "PCAP_EMBEDDED_GLOBAL_HEADER",
+            PcapError::NotARecordBoundary(_) => "PCAP_NOT_A_RECORD_BOUNDARY",
+            PcapError::LengthMismatch { .. } => "PCAP_LENGTH_MISMATCH",
+            PcapError::InvalidInterfaceId(_) => "PCAPNG_INVALID_INTERFACE_ID",
+            PcapError::BlockBeforeInterface { .. } => "PCAPNG_BLOCK_BEFORE_INTERFACE",
+            PcapError::InvalidTsResolution(_) => "PCAPNG_INVALID_TS_RESOLUTION",
+            PcapError::InvalidOption { .. } => "PCAPNG_INVALID_OPTION",
+            PcapError::WithPath { source, .. } => source.code(),
+            PcapError::RenameFailed { .. } => "PCAP_RENAME_FAILED",
+            PcapError::TimestampTooBig => "PCAPNG_TIMESTAMP_TOO_BIG",
+        }
+    }
+
+    /// Returns true if the error doesn't come from invalid data, so the same operation can succeed later:
+    /// more data is needed ([`PcapError::IncompleteBuffer`]) or the IO error is transient (interrupted, would block, timed out).
+    pub fn is_recoverable(&self) -> bool {
+        match self {
+            PcapError::IncompleteBuffer => true,
+            PcapError::IoError(e) => {
+                matches!(e.kind(), std::io::ErrorKind::Interrupted | std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut)
+            },
+            PcapError::WithPath { source, .. } => source.is_recoverable(),
+            _ => false,
+        }
+    }
+
+    /// Returns true if the error means that the data ended in the middle of a header, a packet or a block,
+    /// i.e. the capture is truncated or not fully received yet.
+    pub fn is_eof_like(&self) -> bool {
+        match self {
+            PcapError::IncompleteBuffer => true,
+            PcapError::IoError(e) => e.kind() == std::io::ErrorKind::UnexpectedEof,
+            PcapError::UnexpectedEofMidPacket(_) => true,
+            PcapError::TruncatedPacket => true,
+            PcapError::WithPath { source, .. } => source.is_eof_like(),
+            _ => false,
+        }
+    }
+
+    /// Adds the path of the file to the error.
+    pub(crate) fn with_path(self, path: &Path) -> Self {
+        PcapError::WithPath { path: path.to_path_buf(), source: Box::new(self) }
+    }
+}
+
 impl From<std::str::Utf8Error> for PcapError {
     fn from(err: std::str::Utf8Error) -> Self {
         PcapError::Utf8Error(err)
@@ -0,0 +1,107 @@
+//! [`Arbitrary`] implementations used for structured fuzzing.
+
+use std::borrow::Cow;
+use std::time::Duration;
+
+use ::arbitrary::{Arbitrary, Result, Unstructured};
+
+use crate::pcap::{is_swapped_datalink, PcapHeader, PcapPacket, PcapWriter};
+use crate::{DataLink, Endianness, TsResolution};
+
+
+impl<'a> Arbitrary<'a> for TsResolution {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(if u.arbitrary()? { TsResolution::MicroSecond } else { TsResolution::NanoSecond })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Endianness {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(if u.arbitrary()? { Endianness::Big } else { Endianness::Little })
+    }
+}
+
+/// Mostly generates known datalinks, sometimes an `Unknown` one, but never one which looks like a known datalink byte
+/// swapped, as the readers take it for [`PcapQuirk::SwappedSnaplenDatalink`](crate::pcap::PcapQuirk::SwappedSnaplenDatalink).
+impl<'a> Arbitrary<'a> for DataLink {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let value = if u.ratio(1, 8)? { u.arbitrary::<u32>()? } else { u.int_in_range(0..=300)? };
+        let datalink = DataLink::from(value);
+
+        match is_swapped_datalink(datalink) {
+            true => Ok(DataLink::from(value.swap_bytes())),
+            false => Ok(datalink),
+        }
+    }
+}
+
+/// Generates a header accepted by [`PcapHeader::validate`], whose version is always 2.4 and snaplen never 0.
+impl<'a> Arbitrary<'a> for PcapHeader {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(PcapHeader {
+            version_major: 2,
+            version_minor: 4,
+            ts_correction: u.arbitrary()?,
+            ts_accuracy: u.arbitrary()?,
+            snaplen: u.int_in_range(1..=u32::MAX)?,
+            datalink: u.arbitrary()?,
+            ts_resolution: u.arbitrary()?,
+            endianness: u.arbitrary()?,
+        })
+    }
+}
+
+/// Generates a packet with a timestamp representable in a pcap and with `orig_len >= data.len()`.
+impl<'a> Arbitrary<'a> for PcapPacket<'static> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let timestamp = Duration::new(u.arbitrary::<u32>()? as u64, u.int_in_range(0..=999_999_999)?);
+        let data: Vec<u8> = u.arbitrary()?;
+        let orig_len = (data.len() as u32).saturating_add(u.arbitrary::<u16>()? as u32);
+
+        Ok(PcapPacket { timestamp, orig_len, data: Cow::Owned(data) })
+    }
+}
+
+
+/// Structurally valid pcap capture, used for structured fuzzing.
+///
+/// Every packet fits in the snaplen of the header.
+#[derive(Clone, Debug)]
+pub struct ArbitraryPcap {
+    /// Global header of the capture
+    pub header: PcapHeader,
+    /// Packets of the capture
+    pub packets: Vec<PcapPacket<'static>>,
+}
+
+impl ArbitraryPcap {
+    /// Serializes the capture into a pcap file.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = PcapWriter::with_header(Vec::new(), self.header).unwrap();
+        for packet in &self.packets {
+            writer.write_packet(packet).unwrap();
+        }
+
+        writer.into_writer()
+    }
+}
+
+impl<'a> Arbitrary<'a> for ArbitraryPcap {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let header: PcapHeader = u.arbitrary()?;
+
+        let mut packets = Vec::new();
+        for packet in u.arbitrary_iter::<PcapPacket<'static>>()? {
+            let mut packet = packet?;
+
+            // Truncate the packet to the snaplen
+            if let Cow::Owned(data) = &mut packet.data {
+                data.truncate(header.snaplen as usize);
+            }
+
+            packets.push(packet);
+        }
+
+        Ok(ArbitraryPcap { header, packets })
+    }
+}
@@ -1,5 +1,6 @@
 #![allow(clippy::unreadable_literal)]
 #![warn(missing_docs)]
+#![forbid(unsafe_code)]
 
 //! Provides parsers, readers and writers for Pcap and PcapNg files.
 //!
@@ -8,15 +9,30 @@
 //!
 //! For PcapNg files see the [`pcapng`] module, especially [`PcapNgParser`](pcapng::PcapNgParser),
 //! [`PcapNgReader<R>`](pcapng::PcapNgReader) and [`PcapNgWriter<W>`](pcapng::PcapNgWriter)
+//!
+//! To simply read or write a whole pcap file, see the [`easy`] module.
 
 
+pub use anonymize::*;
+pub use atomic_file::*;
 pub use common::*;
 pub use errors::*;
+#[cfg(feature = "arbitrary")]
+pub use fuzzing::*;
 
+pub(crate) mod anonymize;
+pub(crate) mod atomic_file;
+#[cfg(feature = "bytes")]
+pub(crate) mod bytes_buffer;
 pub(crate) mod common;
 pub(crate) mod errors;
+#[cfg(feature = "arbitrary")]
+pub(crate) mod fuzzing;
 pub(crate) mod read_buffer;
+#[cfg(feature = "serde")]
+pub(crate) mod serialize;
 
+pub mod easy;
 pub mod pcap;
 pub mod pcapng;
 
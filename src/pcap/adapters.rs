@@ -0,0 +1,584 @@
+//! Adapters over a [`PcapReader`].
+//!
+//! Like the reader, they lend the packets they return, so they expose a `next_packet` method
+//! instead of implementing [`Iterator`].
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::io::Read;
+use std::net::IpAddr;
+use std::time::Duration;
+
+use super::{PcapPacket, PcapReader};
+use crate::anonymize::locate_ip_header;
+use crate::errors::*;
+use crate::{remap_ip_addresses, DataLink};
+
+
+/// Calls a callback every N packets read, see [`PcapReader::on_progress`].
+pub struct Progress<R: Read, F: FnMut(usize, u64)> {
+    reader: PcapReader<R>,
+    every: usize,
+    callback: F,
+    nb_packets: usize,
+    nb_bytes: u64,
+}
+
+impl<R: Read, F: FnMut(usize, u64)> Progress<R, F> {
+    pub(crate) fn new(reader: PcapReader<R>, every: usize, callback: F) -> Self {
+        assert!(every > 0, "The progress callback can't be called every 0 packets");
+
+        // The global header is already read
+        let nb_bytes = reader.data_start();
+        Progress { reader, every, callback, nb_packets: 0, nb_bytes }
+    }
+
+    /// Returns the next [`PcapPacket`], calling the callback if needed.
+    pub fn next_packet(&mut self) -> Option<PcapResult<PcapPacket<'_>>> {
+        let (packet, read_len) = self.reader.next_packet_with_read_len()?;
+        self.nb_bytes += read_len;
+        if packet.is_ok() {
+            self.nb_packets += 1;
+
+            if self.nb_packets.is_multiple_of(self.every) {
+                (self.callback)(self.nb_packets, self.nb_bytes);
+            }
+        }
+
+        Some(packet)
+    }
+
+    /// Consumes [`Self`], returning the wrapped [`PcapReader`].
+    pub fn into_inner(self) -> PcapReader<R> {
+        self.reader
+    }
+}
+
+impl<R: Read + Debug, F: FnMut(usize, u64)> Debug for Progress<R, F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Progress")
+            .field("reader", &self.reader)
+            .field("every", &self.every)
+            .field("nb_packets", &self.nb_packets)
+            .field("nb_bytes", &self.nb_bytes)
+            .finish_non_exhaustive()
+    }
+}
+
+
+/// Pairs the packets with their index in the pcap, see [`PcapReader::enumerate_packets`].
+#[derive(Debug)]
+pub struct EnumeratePackets<R: Read> {
+    reader: PcapReader<R>,
+    next_index: usize,
+}
+
+impl<R: Read> EnumeratePackets<R> {
+    pub(crate) fn new(reader: PcapReader<R>) -> Self {
+        EnumeratePackets { reader, next_index: 0 }
+    }
+
+    /// Sets the index of the first packet (0 by default), e.g. 1 to match the packet numbers shown by Wireshark.
+    pub fn starting_at(mut self, first: usize) -> Self {
+        self.next_index = first;
+        self
+    }
+
+    /// Returns the next [`PcapPacket`] with its index.
+    ///
+    /// An error doesn't increment the index: it is paired with the index of the packet that couldn't be read.
+    pub fn next_packet(&mut self) -> Option<(usize, PcapResult<PcapPacket<'_>>)> {
+        let index = self.next_index;
+        let packet = self.reader.next_packet()?;
+        if packet.is_ok() {
+            self.next_index += 1;
+        }
+
+        Some((index, packet))
+    }
+
+    /// Consumes [`Self`], returning the wrapped [`PcapReader`].
+    pub fn into_inner(self) -> PcapReader<R> {
+        self.reader
+    }
+}
+
+
+/// Returns one packet every N packets, see [`PcapReader::sample`].
+#[derive(Debug)]
+pub struct Sample<R: Read> {
+    reader: PcapReader<R>,
+    n: usize,
+    to_skip: usize,
+}
+
+impl<R: Read> Sample<R> {
+    pub(crate) fn new(reader: PcapReader<R>, n: usize) -> Self {
+        assert!(n > 0, "Can't sample one packet every 0 packets");
+        Sample { reader, n, to_skip: 0 }
+    }
+
+    /// Returns the next sampled [`PcapPacket`], skipping the packets in between.
+    pub fn next_packet(&mut self) -> Option<PcapResult<PcapPacket<'_>>> {
+        let to_skip = std::mem::take(&mut self.to_skip) as u64;
+        match self.reader.skip_packets(to_skip) {
+            Ok(skipped) if skipped < to_skip => return None,
+            Ok(_) => {},
+            Err(e) => return Some(Err(e)),
+        }
+
+        self.to_skip = self.n - 1;
+        self.reader.next_packet()
+    }
+
+    /// Consumes [`Self`], returning the wrapped [`PcapReader`].
+    pub fn into_inner(self) -> PcapReader<R> {
+        self.reader
+    }
+}
+
+
+/// Drops the packets whose key was already seen, see [`PcapReader::dedup_by_key`].
+pub struct DedupByKey<R: Read, K, F> {
+    reader: PcapReader<R>,
+    key: F,
+    seen: HashSet<K>,
+}
+
+impl<R: Read, K: Eq + Hash, F: FnMut(&PcapPacket) -> K> DedupByKey<R, K, F> {
+    pub(crate) fn new(reader: PcapReader<R>, key: F) -> Self {
+        DedupByKey { reader, key, seen: HashSet::new() }
+    }
+
+    /// Returns the next [`PcapPacket`] whose key wasn't seen yet, skipping the other ones.
+    pub fn next_packet(&mut self) -> Option<PcapResult<PcapPacket<'_>>> {
+        let (seen, key) = (&mut self.seen, &mut self.key);
+        next_packet_if(&mut self.reader, |packet| seen.insert(key(packet)))
+    }
+
+    /// Returns the number of distinct keys seen.
+    pub fn nb_keys(&self) -> usize {
+        self.seen.len()
+    }
+
+    /// Consumes [`Self`], returning the wrapped [`PcapReader`].
+    pub fn into_inner(self) -> PcapReader<R> {
+        self.reader
+    }
+}
+
+impl<R: Read + Debug, K, F> Debug for DedupByKey<R, K, F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DedupByKey")
+            .field("reader", &self.reader)
+            .field("nb_keys", &self.seen.len())
+            .finish_non_exhaustive()
+    }
+}
+
+
+/// Drops the packets whose key was seen recently, see [`PcapReader::dedup_by_key_bounded`].
+pub struct BoundedDedupByKey<R: Read, K, F> {
+    reader: PcapReader<R>,
+    key: F,
+    seen: LruSet<K>,
+}
+
+impl<R: Read, K: Eq + Hash + Clone, F: FnMut(&PcapPacket) -> K> BoundedDedupByKey<R, K, F> {
+    pub(crate) fn new(reader: PcapReader<R>, key: F, capacity: usize) -> Self {
+        assert!(capacity > 0, "Can't deduplicate with a capacity of 0 keys");
+        BoundedDedupByKey { reader, key, seen: LruSet::new(capacity) }
+    }
+
+    /// Returns the next [`PcapPacket`] whose key isn't one of the recently seen keys, skipping the other ones.
+    pub fn next_packet(&mut self) -> Option<PcapResult<PcapPacket<'_>>> {
+        let (seen, key) = (&mut self.seen, &mut self.key);
+        next_packet_if(&mut self.reader, |packet| seen.insert(key(packet)))
+    }
+
+    /// Returns the number of keys currently remembered, at most the capacity.
+    pub fn nb_keys(&self) -> usize {
+        self.seen.keys.len()
+    }
+
+    /// Consumes [`Self`], returning the wrapped [`PcapReader`].
+    pub fn into_inner(self) -> PcapReader<R> {
+        self.reader
+    }
+}
+
+impl<R: Read + Debug, K, F> Debug for BoundedDedupByKey<R, K, F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BoundedDedupByKey")
+            .field("reader", &self.reader)
+            .field("capacity", &self.seen.capacity)
+            .field("nb_keys", &self.seen.keys.len())
+            .finish_non_exhaustive()
+    }
+}
+
+
+/// Groups the consecutive packets having the same timestamp, see [`PcapReader::group_by_timestamp`].
+pub struct GroupByTimestamp<R: Read> {
+    reader: PcapReader<R>,
+    /// Packets of the group being built, which all have the same timestamp
+    group: Vec<PcapPacket<'static>>,
+}
+
+impl<R: Read> GroupByTimestamp<R> {
+    pub(crate) fn new(reader: PcapReader<R>) -> Self {
+        GroupByTimestamp { reader, group: vec![] }
+    }
+
+    /// Returns the next timestamp and the consecutive packets having it.
+    ///
+    /// The packets already grouped are kept when an error is returned, so the group is completed by the next call.
+    pub fn next_group(&mut self) -> Option<PcapResult<(Duration, Vec<PcapPacket<'static>>)>> {
+        loop {
+            let packet = match self.reader.next_packet() {
+                Some(Ok(packet)) => packet.into_owned(),
+                Some(Err(e)) => return Some(Err(e)),
+                None if self.group.is_empty() => return None,
+                None => return Some(Ok((self.group[0].timestamp, std::mem::take(&mut self.group)))),
+            };
+
+            match self.group.first() {
+                Some(first) if first.timestamp != packet.timestamp => {
+                    let group = std::mem::replace(&mut self.group, vec![packet]);
+                    return Some(Ok((group[0].timestamp, group)));
+                },
+                _ => self.group.push(packet),
+            }
+        }
+    }
+
+    /// Consumes [`Self`], returning the wrapped [`PcapReader`].
+    ///
+    /// The packets of the group being built are lost.
+    pub fn into_inner(self) -> PcapReader<R> {
+        self.reader
+    }
+}
+
+impl<R: Read + Debug> Debug for GroupByTimestamp<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GroupByTimestamp").field("reader", &self.reader).field("group_len", &self.group.len()).finish()
+    }
+}
+
+
+/// Splits the packets into groups separated by silences, see [`PcapReader::split_on_gap`].
+pub struct SplitOnGap<R: Read> {
+    reader: PcapReader<R>,
+    threshold: Duration,
+    absolute: bool,
+    /// Timestamp of the last packet read
+    prev_ts: Option<Duration>,
+    /// Packets read but not returned yet, the first one of the next group after a gap
+    group: VecDeque<PcapPacket<'static>>,
+    /// True if the first packet of `group` starts a group
+    group_start: bool,
+}
+
+impl<R: Read> SplitOnGap<R> {
+    pub(crate) fn new(reader: PcapReader<R>, threshold: Duration) -> Self {
+        SplitOnGap { reader, threshold, absolute: false, prev_ts: None, group: VecDeque::new(), group_start: false }
+    }
+
+    /// Also splits when a timestamp is more than the threshold *before* the previous one (false by default).
+    ///
+    /// By default, a packet older than the previous one never starts a new group.
+    pub fn absolute_difference(mut self, absolute: bool) -> Self {
+        self.absolute = absolute;
+        self
+    }
+
+    /// Returns the next group of packets, the last group being returned at the end of the pcap.
+    ///
+    /// The packets already grouped are kept when an error is returned, so the group is completed by the next call.
+    pub fn next_group(&mut self) -> Option<PcapResult<Vec<PcapPacket<'static>>>> {
+        loop {
+            let packet = match self.reader.next_packet() {
+                Some(Ok(packet)) => packet.into_owned(),
+                Some(Err(e)) => return Some(Err(e)),
+                None if self.group.is_empty() => return None,
+                None => return Some(Ok(std::mem::take(&mut self.group).into())),
+            };
+
+            let gap = is_gap(self.prev_ts, packet.timestamp, self.threshold, self.absolute);
+            self.prev_ts = Some(packet.timestamp);
+
+            if gap && !self.group.is_empty() {
+                let group = std::mem::replace(&mut self.group, VecDeque::from([packet]));
+                self.group_start = true;
+                return Some(Ok(group.into()));
+            }
+            self.group.push_back(packet);
+        }
+    }
+
+    /// Returns the next packet, with true if it starts a new group, i.e. the streaming variant of
+    /// [`SplitOnGap::next_group`] which doesn't keep the packets in memory.
+    ///
+    /// The first packet of the pcap starts a group.
+    pub fn next_packet(&mut self) -> Option<PcapResult<(bool, PcapPacket<'_>)>> {
+        if let Some(packet) = self.group.pop_front() {
+            return Some(Ok((std::mem::take(&mut self.group_start), packet)));
+        }
+
+        let packet = match self.reader.next_packet()? {
+            Ok(packet) => packet,
+            Err(e) => return Some(Err(e)),
+        };
+
+        let starts_group = self.prev_ts.is_none() || is_gap(self.prev_ts, packet.timestamp, self.threshold, self.absolute);
+        self.prev_ts = Some(packet.timestamp);
+
+        Some(Ok((starts_group, packet)))
+    }
+
+    /// Consumes [`Self`], returning the wrapped [`PcapReader`].
+    ///
+    /// The packets read but not returned yet are lost.
+    pub fn into_inner(self) -> PcapReader<R> {
+        self.reader
+    }
+}
+
+impl<R: Read + Debug> Debug for SplitOnGap<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SplitOnGap")
+            .field("reader", &self.reader)
+            .field("threshold", &self.threshold)
+            .field("absolute", &self.absolute)
+            .field("prev_ts", &self.prev_ts)
+            .field("group_len", &self.group.len())
+            .field("group_start", &self.group_start)
+            .finish()
+    }
+}
+
+/// Returns true if there is a silence of more than `threshold` between the previous timestamp and `ts`.
+fn is_gap(prev_ts: Option<Duration>, ts: Duration, threshold: Duration, absolute: bool) -> bool {
+    let gap = match prev_ts {
+        Some(prev_ts) if ts >= prev_ts => ts - prev_ts,
+        Some(prev_ts) if absolute => prev_ts - ts,
+        _ => Duration::ZERO,
+    };
+
+    gap > threshold
+}
+
+/// Converts the errors of a [`PcapReader`] into another error type, see [`PcapReader::map_err`].
+pub struct MapErr<R: Read, F> {
+    reader: PcapReader<R>,
+    f: F,
+}
+
+impl<R: Read, E, F: FnMut(PcapError) -> E> MapErr<R, F> {
+    pub(crate) fn new(reader: PcapReader<R>, f: F) -> Self {
+        MapErr { reader, f }
+    }
+
+    /// Returns the next [`PcapPacket`], or the converted error.
+    pub fn next_packet(&mut self) -> Option<Result<PcapPacket<'_>, E>> {
+        Some(self.reader.next_packet()?.map_err(&mut self.f))
+    }
+
+    /// Consumes [`Self`], returning the wrapped [`PcapReader`].
+    pub fn into_inner(self) -> PcapReader<R> {
+        self.reader
+    }
+}
+
+impl<R: Read + Debug, F> Debug for MapErr<R, F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MapErr").field("reader", &self.reader).finish_non_exhaustive()
+    }
+}
+
+/// Rewrites the MAC addresses of the Ethernet packets, see [`PcapReader::remap_macs`].
+pub struct RemapMacs<R: Read, F> {
+    reader: PcapReader<R>,
+    map: F,
+    nb_skipped: u64,
+}
+
+impl<R: Read, F: FnMut([u8; 6]) -> [u8; 6]> RemapMacs<R, F> {
+    pub(crate) fn new(reader: PcapReader<R>, map: F) -> Self {
+        RemapMacs { reader, map, nb_skipped: 0 }
+    }
+
+    /// Returns the next [`PcapPacket`], with its destination and source MACs rewritten if the datalink is Ethernet.
+    ///
+    /// The Ethernet packets too short to contain a full Ethernet header are skipped.
+    pub fn next_packet(&mut self) -> Option<PcapResult<PcapPacket<'_>>> {
+        if self.reader.header().datalink != DataLink::ETHERNET {
+            return self.reader.next_packet();
+        }
+
+        // Can unwrap because the Ethernet header has a fixed length
+        let header_len = DataLink::ETHERNET.header_len().unwrap();
+        let nb_skipped = &mut self.nb_skipped;
+        let packet = next_packet_if(&mut self.reader, |packet| {
+            let complete = packet.data.len() >= header_len;
+            *nb_skipped += u64::from(!complete);
+            complete
+        })?;
+
+        let mut packet = match packet {
+            Ok(packet) => packet,
+            Err(e) => return Some(Err(e)),
+        };
+
+        for mac in packet.data.to_mut()[..12].chunks_exact_mut(6) {
+            // Can unwrap because the chunks are 6 bytes long
+            let mapped = (self.map)(mac.try_into().unwrap());
+            mac.copy_from_slice(&mapped);
+        }
+
+        Some(Ok(packet))
+    }
+
+    /// Returns the number of Ethernet packets skipped because they were too short.
+    pub fn nb_skipped(&self) -> u64 {
+        self.nb_skipped
+    }
+
+    /// Consumes [`Self`], returning the wrapped [`PcapReader`].
+    pub fn into_inner(self) -> PcapReader<R> {
+        self.reader
+    }
+}
+
+impl<R: Read + Debug, F> Debug for RemapMacs<R, F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RemapMacs")
+            .field("reader", &self.reader)
+            .field("nb_skipped", &self.nb_skipped)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Rewrites the IP addresses of the packets, see [`PcapReader::remap_ips`].
+pub struct RemapIps<R: Read, F> {
+    reader: PcapReader<R>,
+    map: F,
+    nb_skipped: u64,
+}
+
+impl<R: Read, F: FnMut(IpAddr) -> IpAddr> RemapIps<R, F> {
+    pub(crate) fn new(reader: PcapReader<R>, map: F) -> Self {
+        RemapIps { reader, map, nb_skipped: 0 }
+    }
+
+    /// Returns the next [`PcapPacket`], with its source and destination IP addresses rewritten if it is an IP packet.
+    ///
+    /// The IP packets whose IP header is cut or invalid are skipped.
+    ///
+    /// # Errors
+    /// The mapping returned an address of another IP version.
+    pub fn next_packet(&mut self) -> Option<PcapResult<PcapPacket<'_>>> {
+        let datalink = self.reader.header().datalink;
+        let nb_skipped = &mut self.nb_skipped;
+        let packet = next_packet_if(&mut self.reader, |packet| {
+            let rewritable = locate_ip_header(datalink, &packet.data).is_ok();
+            *nb_skipped += u64::from(!rewritable);
+            rewritable
+        })?;
+
+        let mut packet = match packet {
+            Ok(packet) => packet,
+            Err(e) => return Some(Err(e)),
+        };
+
+        // The data of the packets which aren't IP packets aren't copied
+        if matches!(locate_ip_header(datalink, &packet.data), Ok(Some(_))) {
+            if let Err(e) = remap_ip_addresses(datalink, packet.data.to_mut(), &mut self.map) {
+                return Some(Err(e));
+            }
+        }
+
+        Some(Ok(packet))
+    }
+
+    /// Returns the number of IP packets skipped because their IP header was cut or invalid.
+    pub fn nb_skipped(&self) -> u64 {
+        self.nb_skipped
+    }
+
+    /// Consumes [`Self`], returning the wrapped [`PcapReader`].
+    pub fn into_inner(self) -> PcapReader<R> {
+        self.reader
+    }
+}
+
+impl<R: Read + Debug, F> Debug for RemapIps<R, F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RemapIps")
+            .field("reader", &self.reader)
+            .field("nb_skipped", &self.nb_skipped)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Set of the `capacity` most recently used keys.
+struct LruSet<K> {
+    capacity: usize,
+    /// Key => last use
+    keys: HashMap<K, u64>,
+    /// Last use => key, the first entry is the least recently used key
+    uses: BTreeMap<u64, K>,
+    next_use: u64,
+}
+
+impl<K: Eq + Hash + Clone> LruSet<K> {
+    fn new(capacity: usize) -> Self {
+        LruSet { capacity, keys: HashMap::new(), uses: BTreeMap::new(), next_use: 0 }
+    }
+
+    /// Marks the key as the most recently used, returns true if it wasn't in the set.
+    fn insert(&mut self, key: K) -> bool {
+        let now = self.next_use;
+        self.next_use += 1;
+
+        if let Some(last_use) = self.keys.get_mut(&key) {
+            self.uses.remove(last_use);
+            *last_use = now;
+            self.uses.insert(now, key);
+            return false;
+        }
+
+        if self.keys.len() == self.capacity {
+            if let Some((_, oldest)) = self.uses.pop_first() {
+                self.keys.remove(&oldest);
+            }
+        }
+
+        self.keys.insert(key.clone(), now);
+        self.uses.insert(now, key);
+        true
+    }
+}
+
+
+/// Returns the next packet for which `predicate` returns true, skipping the other ones.
+fn next_packet_if<R: Read>(
+    reader: &mut PcapReader<R>,
+    mut predicate: impl FnMut(&PcapPacket) -> bool,
+) -> Option<PcapResult<PcapPacket<'_>>> {
+    loop {
+        // The predicate is called on a peeked packet because a packet read can't be returned from inside the loop
+        match reader.peek_packet_with(&mut predicate)? {
+            Ok(true) => return reader.next_packet(),
+            Ok(false) => {},
+            // Read the packet again to consume it when possible, e.g. when its timestamp is out of range
+            Err(_) => return reader.next_packet(),
+        }
+
+        if let Err(e) = reader.skip_packets(1) {
+            return Some(Err(e));
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use std::fmt::Debug;
+use std::time::Duration;
+
+use super::PcapPacket;
+use crate::common::DebugBytes;
+
+
+/// Buffer, reused across packets, in which the data of a packet is copied
+/// so that it starts at an aligned address.
+///
+/// The buffer only allocates when a packet is bigger than all the previous ones.
+pub struct AlignedBuffer {
+    storage: Vec<u8>,
+    align: usize,
+}
+
+impl AlignedBuffer {
+    /// Creates a new [`AlignedBuffer`] with the given alignment.
+    ///
+    /// # Panics
+    /// The alignment is not a power of two.
+    pub fn new(align: usize) -> Self {
+        assert!(align.is_power_of_two(), "The alignment must be a power of two");
+        AlignedBuffer { storage: Vec::new(), align }
+    }
+
+    /// Returns the alignment of the buffer.
+    pub fn align(&self) -> usize {
+        self.align
+    }
+
+    /// Copies the packet into the buffer and returns an [`AlignedPacket`] borrowing it.
+    pub fn copy_packet(&mut self, packet: &PcapPacket) -> AlignedPacket<'_> {
+        let data_len = packet.data.len();
+
+        // Ensure that there is enough room for the data and the alignment padding
+        let needed = data_len + self.align - 1;
+        if self.storage.len() < needed {
+            self.storage.resize(needed, 0);
+        }
+
+        let offset = self.storage.as_ptr().align_offset(self.align);
+        let data = &mut self.storage[offset..offset + data_len];
+        data.copy_from_slice(&packet.data);
+
+        AlignedPacket { timestamp: packet.timestamp, orig_len: packet.orig_len, data }
+    }
+}
+
+impl Debug for AlignedBuffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AlignedBuffer").field("capacity", &self.storage.len()).field("align", &self.align).finish()
+    }
+}
+
+
+/// Pcap packet whose data starts at an address aligned to the alignment of the [`AlignedBuffer`] it borrows.
+#[derive(Clone, Eq, PartialEq)]
+pub struct AlignedPacket<'a> {
+    /// Timestamp EPOCH of the packet with a nanosecond resolution
+    pub timestamp: Duration,
+    /// Original length of the packet when captured on the wire
+    pub orig_len: u32,
+    /// Aligned payload of the packet
+    pub data: &'a [u8],
+}
+
+impl Debug for AlignedPacket<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AlignedPacket")
+            .field("timestamp", &self.timestamp)
+            .field("orig_len", &self.orig_len)
+            .field("data", &DebugBytes(self.data))
+            .finish()
+    }
+}
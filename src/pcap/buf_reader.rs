@@ -0,0 +1,194 @@
+use std::fmt::Debug;
+use std::io::{BufRead, Read};
+
+use super::reader::partial_record;
+use super::{PcapParser, TruncationPolicy};
+use crate::errors::*;
+use crate::pcap::{PcapHeader, PcapPacket};
+
+
+/// Reads a pcap from a [`BufRead`], parsing the packets directly from its buffer.
+///
+/// Unlike the [`PcapReader`](super::PcapReader), it doesn't have its own buffer: the packets fully contained
+/// in the buffer of the reader are borrowed from it, without copy. Only the packets spanning the end of the
+/// buffer are copied into an owned [`PcapPacket`].
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::fs::File;
+/// use std::io::BufReader;
+///
+/// use pcap_file::pcap::PcapBufReader;
+///
+/// let file_in = File::open("test.pcap").expect("Error opening file");
+/// let mut pcap_reader = PcapBufReader::new(BufReader::with_capacity(65536, file_in)).unwrap();
+///
+/// while let Some(pkt) = pcap_reader.next_packet() {
+///     let pkt = pkt.unwrap();
+///     println!("{} bytes", pkt.data.len());
+/// }
+/// ```
+pub struct PcapBufReader<R: BufRead> {
+    parser: PcapParser,
+    reader: R,
+    /// Length of the last packet borrowed from the buffer of the reader, which must be consumed
+    to_consume: usize,
+    truncation: TruncationPolicy,
+    /// True if the last packet returned was cut by the end of the pcap
+    partial: bool,
+}
+
+impl<R: BufRead> Debug for PcapBufReader<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The reader can hold the whole capture, only its type is shown
+        f.debug_struct("PcapBufReader")
+            .field("parser", &self.parser)
+            .field("reader", &std::any::type_name::<R>())
+            .field("to_consume", &self.to_consume)
+            .field("truncation", &self.truncation)
+            .field("partial", &self.partial)
+            .finish()
+    }
+}
+
+impl<R: BufRead> PcapBufReader<R> {
+    /// Creates a new [`PcapBufReader`] from an existing reader.
+    ///
+    /// This function reads the global pcap header of the file to verify its integrity.
+    ///
+    /// # Errors
+    /// The data stream is not in a valid pcap file format.
+    ///
+    /// The underlying data are not readable.
+    pub fn new(mut reader: R) -> PcapResult<PcapBufReader<R>> {
+        let mut header = [0_u8; 24];
+        reader.read_exact(&mut header).map_err(PcapError::IoError)?;
+        let (_, parser) = PcapParser::new(&header)?;
+
+        Ok(PcapBufReader { parser, reader, to_consume: 0, truncation: TruncationPolicy::default(), partial: false })
+    }
+
+    /// Sets what the reader does with a last record cut by the end of the pcap ([`TruncationPolicy::Error`] by default),
+    /// see [`PcapReader::with_truncation_policy`](super::PcapReader::with_truncation_policy).
+    pub fn with_truncation_policy(mut self, truncation: TruncationPolicy) -> Self {
+        self.truncation = truncation;
+        self
+    }
+
+    /// Skips the padding records if `ignore` is true (false by default), see [`PcapParser::set_ignore_padding`].
+    pub fn with_ignore_padding(mut self, ignore: bool) -> Self {
+        self.parser.set_ignore_padding(ignore);
+        self
+    }
+
+    /// Returns true if the last packet returned was cut by the end of the pcap, see [`TruncationPolicy::YieldPartial`].
+    pub fn is_partial(&self) -> bool {
+        self.partial
+    }
+
+    /// Returns the next [`PcapPacket`], borrowed from the buffer of the reader if it is fully contained in it.
+    pub fn next_packet(&mut self) -> Option<PcapResult<PcapPacket<'_>>> {
+        self.reader.consume(std::mem::take(&mut self.to_consume));
+
+        let buf = match self.reader.fill_buf() {
+            Ok([]) => return None,
+            Ok(buf) => buf,
+            Err(e) => return Some(Err(PcapError::IoError(e))),
+        };
+
+        // The buffer is checked before being borrowed by the packet, because the borrow can't end
+        // in the branch reading an owned packet
+        match self.parser.next_raw_packet(buf) {
+            Ok(_) => {},
+            Err(PcapError::IncompleteBuffer) => return self.read_owned_packet().transpose(),
+            Err(e) => return Some(Err(e)),
+        }
+
+        // Doesn't read anything because the buffer wasn't consumed
+        let buf = match self.reader.fill_buf() {
+            Ok(buf) => buf,
+            Err(e) => return Some(Err(PcapError::IoError(e))),
+        };
+
+        match self.parser.next_packet(buf) {
+            Ok((rem, packet)) => {
+                self.to_consume = buf.len() - rem.len();
+                self.partial = false;
+                Some(Ok(packet))
+            },
+            Err(e) => Some(Err(e)),
+        }
+    }
+
+    /// Reads the next packet, which spans the end of the buffer of the reader, into an owned packet.
+    ///
+    /// Returns `None` if the packet is cut by the end of the pcap and discarded by the truncation policy, or if only
+    /// ignored padding records are left.
+    fn read_owned_packet(&mut self) -> PcapResult<Option<PcapPacket<'static>>> {
+        let header = self.parser.header();
+
+        let mut record = Vec::with_capacity(header.record_header_len());
+        let packet_header = loop {
+            read_up_to(&mut self.reader, &mut record, header.record_header_len())?;
+            match self.parser.next_record_header(&record) {
+                Ok((_, packet_header)) if self.parser.is_ignored_padding(&packet_header) => {
+                    let mut padding = (&mut self.reader).take(packet_header.incl_len as u64);
+                    std::io::copy(&mut padding, &mut std::io::sink()).map_err(PcapError::IoError)?;
+                    record.clear();
+                },
+                Ok((_, packet_header)) => break Some(packet_header),
+                Err(PcapError::IncompleteBuffer) => break None,
+                Err(e) => return Err(e),
+            }
+        };
+
+        // The pcap ends with padding records
+        if record.is_empty() {
+            return Ok(None);
+        }
+
+        if let Some(packet_header) = packet_header {
+            // Don't allocate an invalid length, which can be anything
+            if packet_header.incl_len > header.snaplen {
+                return Err(PcapError::InvalidField("PacketHeader incl_len > snap_len"));
+            }
+
+            read_up_to(&mut self.reader, &mut record, packet_header.incl_len as usize)?;
+        }
+
+        let (raw_packet, partial) = match self.parser.next_raw_packet(&record) {
+            Ok((_, raw_packet)) => (Some(raw_packet), false),
+            Err(PcapError::IncompleteBuffer) if self.truncation == TruncationPolicy::Error => return Err(PcapError::TruncatedPacket),
+            Err(PcapError::IncompleteBuffer) => (partial_record(&self.parser, self.truncation, &record)?.1, true),
+            Err(e) => return Err(e),
+        };
+
+        let packet = match raw_packet {
+            Some(raw_packet) => raw_packet.try_into_pcap_packet(header.ts_resolution, header.snaplen)?,
+            None => return Ok(None),
+        };
+        self.partial = partial;
+
+        Ok(Some(packet.into_owned()))
+    }
+
+    /// Returns the global header of the pcap.
+    pub fn header(&self) -> PcapHeader {
+        self.parser.header()
+    }
+
+    /// Consumes [`Self`], returning the wrapped reader.
+    ///
+    /// The reader is positioned after the last packet returned.
+    pub fn into_inner(mut self) -> R {
+        self.reader.consume(self.to_consume);
+        self.reader
+    }
+}
+
+/// Appends up to `len` bytes of the reader to `buf`, less if the reader ends before.
+fn read_up_to<R: BufRead>(reader: &mut R, buf: &mut Vec<u8>, len: usize) -> PcapResult<()> {
+    reader.take(len as u64).read_to_end(buf).map_err(PcapError::IoError)?;
+    Ok(())
+}
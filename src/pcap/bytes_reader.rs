@@ -0,0 +1,188 @@
+use std::borrow::Cow;
+use std::fmt::Debug;
+use std::io::Read;
+use std::time::Duration;
+
+use bytes::Bytes;
+
+use super::reader::partial_record;
+use super::{PcapHeader, PcapPacket, PcapParser, TruncationPolicy};
+use crate::bytes_buffer::BytesBuffer;
+use crate::common::DebugBytes;
+use crate::errors::*;
+
+
+/// Pcap packet whose data is a [`Bytes`], see [`PcapBytesReader`].
+#[derive(Clone, Eq, PartialEq)]
+pub struct PcapBytesPacket {
+    /// Timestamp EPOCH of the packet with a nanosecond resolution
+    pub timestamp: Duration,
+    /// Original length of the packet when captured on the wire
+    pub orig_len: u32,
+    /// Payload, without the padding
+    pub data: Bytes,
+}
+
+impl PcapBytesPacket {
+    /// Returns a [`PcapPacket`] borrowing the data of the packet, e.g. to write it.
+    pub fn as_packet(&self) -> PcapPacket<'_> {
+        PcapPacket { timestamp: self.timestamp, orig_len: self.orig_len, data: Cow::Borrowed(&self.data) }
+    }
+}
+
+impl Debug for PcapBytesPacket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PcapBytesPacket")
+            .field("timestamp", &self.timestamp)
+            .field("orig_len", &self.orig_len)
+            .field("data", &DebugBytes(&self.data))
+            .finish()
+    }
+}
+
+
+/// Reads a pcap from a reader, returning the data of the packets as [`Bytes`] without copying them.
+///
+/// The data are read in chunks of a fixed capacity (1MiB by default), and the data of the packets are slices of their
+/// chunk: a packet can be sent to another thread or kept as long as needed, without lifetime, but it keeps its whole
+/// chunk alive. A packet can't be bigger than the capacity.
+///
+/// Only available with the `bytes` feature.
+///
+/// # Example
+/// ```rust,no_run
+/// use std::fs::File;
+///
+/// use pcap_file::pcap::PcapBytesReader;
+///
+/// let file_in = File::open("test.pcap").expect("Error opening file");
+/// let mut pcap_reader = PcapBytesReader::new(file_in).unwrap();
+///
+/// let mut packets = vec![];
+/// while let Some(packet) = pcap_reader.next_packet() {
+///     packets.push(packet.unwrap().data);
+/// }
+/// ```
+pub struct PcapBytesReader<R: Read> {
+    parser: PcapParser,
+    reader: BytesBuffer<R>,
+    truncation: TruncationPolicy,
+    /// True if the last packet returned was cut by the end of the pcap
+    partial: bool,
+}
+
+impl<R: Read> PcapBytesReader<R> {
+    /// Creates a new [`PcapBytesReader`] from an existing reader, reading its global header.
+    ///
+    /// # Errors
+    /// The data stream is not in a valid pcap file format.
+    ///
+    /// The underlying data are not readable.
+    pub fn new(reader: R) -> PcapResult<PcapBytesReader<R>> {
+        Self::from_buffer(BytesBuffer::new(reader))
+    }
+
+    /// Creates a new [`PcapBytesReader`] reading chunks of `capacity` bytes, see [`PcapBytesReader`].
+    ///
+    /// # Errors
+    /// Same as [`PcapBytesReader::new`].
+    pub fn with_capacity(reader: R, capacity: usize) -> PcapResult<PcapBytesReader<R>> {
+        Self::from_buffer(BytesBuffer::with_capacity(reader, capacity))
+    }
+
+    /// Creates a new [`PcapBytesReader`] reading the global header from the buffer.
+    fn from_buffer(mut reader: BytesBuffer<R>) -> PcapResult<PcapBytesReader<R>> {
+        let header = reader.next_with(|src| PcapParser::new(src).map(|(rem, _)| src.len() - rem.len()))?;
+        let (_, parser) = PcapParser::new(&header)?;
+
+        Ok(PcapBytesReader { parser, reader, truncation: TruncationPolicy::default(), partial: false })
+    }
+
+    /// Sets what the reader does with a last record cut by the end of the pcap ([`TruncationPolicy::Error`] by default),
+    /// see [`PcapReader::with_truncation_policy`](super::PcapReader::with_truncation_policy).
+    pub fn with_truncation_policy(mut self, truncation: TruncationPolicy) -> Self {
+        self.truncation = truncation;
+        self
+    }
+
+    /// Skips the padding records if `ignore` is true (false by default), see [`PcapParser::set_ignore_padding`].
+    pub fn with_ignore_padding(mut self, ignore: bool) -> Self {
+        self.parser.set_ignore_padding(ignore);
+        self
+    }
+
+    /// Returns true if the last packet returned was cut by the end of the pcap, see [`TruncationPolicy::YieldPartial`].
+    pub fn is_partial(&self) -> bool {
+        self.partial
+    }
+
+    /// Returns the next [`PcapBytesPacket`].
+    pub fn next_packet(&mut self) -> Option<PcapResult<PcapBytesPacket>> {
+        loop {
+            match self.reader.has_data_left() {
+                Ok(true) => {},
+                Ok(false) => return None,
+                Err(e) => return Some(Err(PcapError::IoError(e))),
+            }
+
+            // The padding records are split off until a record or the end of the pcap, a cut one being discarded
+            let (parser, truncation) = (&self.parser, self.truncation);
+            match self.reader.next_with_cut(truncation != TruncationPolicy::Error, |src| parser.padding_len(src)) {
+                Ok((padding, false)) if padding.is_empty() => return self.read_packet().transpose(),
+                Ok(_) => {},
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+
+    /// Splits the next record off the buffer and slices the data of its packet.
+    ///
+    /// Returns `None` if the record is cut by the end of the pcap and discarded by the truncation policy.
+    fn read_packet(&mut self) -> PcapResult<Option<PcapBytesPacket>> {
+        let (parser, truncation) = (&self.parser, self.truncation);
+        let (record, cut) = self
+            .reader
+            .next_with_cut(truncation != TruncationPolicy::Error, |src| parser.next_packet(src).map(|(rem, _)| src.len() - rem.len()))?;
+
+        let packet = match cut {
+            false => parser.next_packet(&record)?.1,
+            true => {
+                let header = parser.header();
+                match partial_record(parser, truncation, &record)?.1 {
+                    Some(raw_packet) => raw_packet.try_into_pcap_packet(header.ts_resolution, header.snaplen)?,
+                    None => return Ok(None),
+                }
+            },
+        };
+        let data = record.slice_ref(&packet.data);
+        self.partial = cut;
+
+        Ok(Some(PcapBytesPacket { timestamp: packet.timestamp, orig_len: packet.orig_len, data }))
+    }
+
+    /// Returns the global header of the pcap.
+    pub fn header(&self) -> PcapHeader {
+        self.parser.header()
+    }
+
+    /// Consumes [`Self`], returning the wrapped reader.
+    pub fn into_reader(self) -> R {
+        self.reader.into_inner()
+    }
+
+    /// Gets a reference to the wrapped reader.
+    pub fn get_ref(&self) -> &R {
+        self.reader.get_ref()
+    }
+}
+
+impl<R: Read + Debug> Debug for PcapBytesReader<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PcapBytesReader")
+            .field("parser", &self.parser)
+            .field("reader", &self.reader)
+            .field("truncation", &self.truncation)
+            .field("partial", &self.partial)
+            .finish()
+    }
+}
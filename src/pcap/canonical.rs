@@ -0,0 +1,81 @@
+use std::io::{Read, Write};
+
+use super::{PcapHeader, PcapReader, PcapWriter};
+use crate::errors::*;
+use crate::{Endianness, TsResolution};
+
+
+/// Parameters of the canonical form of a pcap, see [`canonicalize`].
+///
+/// The default parameters are the canonical ones:
+/// ```rust,ignore
+/// CanonicalForm {
+///     endianness: Endianness::Little,
+///     ts_resolution: TsResolution::NanoSecond,
+///     snaplen: 262144,
+/// };
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct CanonicalForm {
+    /// Endianness of the output
+    pub endianness: Endianness,
+    /// Timestamp resolution of the output
+    pub ts_resolution: TsResolution,
+    /// Snaplen of the output, longer packets are truncated
+    pub snaplen: u32,
+}
+
+impl Default for CanonicalForm {
+    fn default() -> Self {
+        CanonicalForm {
+            endianness: Endianness::Little,
+            ts_resolution: TsResolution::NanoSecond,
+            snaplen: 262144,
+        }
+    }
+}
+
+/// Writes the canonical form of a pcap, so that two pcaps containing the same packets
+/// have the same canonical form, whatever the tools that wrote them.
+///
+/// The canonical form has:
+/// - a global header with version 2.4, no timezone correction and no timestamp accuracy, the datalink of the input and the parameters of
+///   the [`CanonicalForm`]
+/// - the packets sorted by timestamp, then by data, then by original length (the order of equal packets doesn't matter)
+/// - the data of the packets truncated to the snaplen, with their original length preserved
+///
+/// All the packets are kept in memory to be sorted.
+/// The timestamps are truncated if the output has a lower resolution than the input.
+pub fn canonicalize<R: Read, W: Write>(reader: &mut PcapReader<R>, writer: W, form: CanonicalForm) -> PcapResult<PcapWriter<W>> {
+    let header = PcapHeader {
+        version_major: 2,
+        version_minor: 4,
+        ts_correction: 0,
+        ts_accuracy: 0,
+        snaplen: form.snaplen,
+        datalink: reader.header().datalink,
+        ts_resolution: form.ts_resolution,
+        endianness: form.endianness,
+    };
+
+    let mut packets = vec![];
+    while let Some(packet) = reader.next_packet() {
+        let mut packet = packet?.into_owned();
+
+        packet.data.to_mut().truncate(form.snaplen as usize);
+        if form.ts_resolution == TsResolution::MicroSecond {
+            packet.timestamp -= std::time::Duration::from_nanos(packet.timestamp.subsec_nanos() as u64 % 1000);
+        }
+
+        packets.push(packet);
+    }
+
+    packets.sort_by(|a, b| (a.timestamp, &a.data, a.orig_len).cmp(&(b.timestamp, &b.data, b.orig_len)));
+
+    let mut pcap_writer = PcapWriter::with_header(writer, header)?;
+    for packet in &packets {
+        pcap_writer.write_packet(packet)?;
+    }
+
+    Ok(pcap_writer)
+}
@@ -0,0 +1,324 @@
+//! Checks a whole pcap, reporting all the problems found instead of stopping at the first one.
+
+use std::io::Read;
+use std::ops::Range;
+use std::time::Duration;
+
+use super::{PcapPacketHeader, PcapQuirk, PcapReader};
+use crate::errors::*;
+use crate::TsResolution;
+
+
+/// Options of [`check`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct CheckOptions {
+    /// Maximum number of findings kept in the report, the other ones are only counted
+    pub max_findings: usize,
+    /// Reports the packets whose timestamp is before the one of the previous packet
+    pub check_time_order: bool,
+    /// Maximum number of bytes skipped to find the next packet after a framing error, 0 to stop at the first one
+    pub max_resync_skip: usize,
+}
+
+impl Default for CheckOptions {
+    fn default() -> Self {
+        CheckOptions { max_findings: 100, check_time_order: true, max_resync_skip: 1024 * 1024 }
+    }
+}
+
+/// Severity of a [`Finding`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Severity {
+    /// The capture can be read but looks wrong
+    Warning,
+    /// The reader returns an error
+    Error,
+}
+
+/// Problem found by [`check`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Finding {
+    /// Offset, from the start of the pcap, of the global header or of the packet with the problem
+    pub offset: u64,
+    /// Severity of the problem
+    pub severity: Severity,
+    /// Stable code of the problem, see [`check`]
+    pub code: &'static str,
+    /// Human readable description of the problem, which isn't stable
+    pub message: String,
+}
+
+/// Report of [`check`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CheckReport {
+    /// Findings in the order of the capture, at most [`CheckOptions::max_findings`]
+    pub findings: Vec<Finding>,
+    /// Number of findings with [`Severity::Error`], including the ones not kept
+    pub nb_errors: u64,
+    /// Number of findings with [`Severity::Warning`], including the ones not kept
+    pub nb_warnings: u64,
+    /// Number of packets whose header was read
+    pub nb_packets: u64,
+    /// Number of bytes checked, i.e. the offset where the check stopped
+    pub nb_bytes: u64,
+}
+
+impl CheckReport {
+    /// Returns true if no error was found, there can be warnings.
+    pub fn is_ok(&self) -> bool {
+        self.nb_errors == 0
+    }
+
+    /// Returns true if some findings were not kept because of [`CheckOptions::max_findings`].
+    pub fn is_capped(&self) -> bool {
+        (self.findings.len() as u64) < self.nb_errors + self.nb_warnings
+    }
+
+    fn add(&mut self, options: &CheckOptions, offset: u64, severity: Severity, code: &'static str, message: String) {
+        match severity {
+            Severity::Warning => self.nb_warnings += 1,
+            Severity::Error => self.nb_errors += 1,
+        }
+
+        if self.findings.len() < options.max_findings {
+            self.findings.push(Finding { offset, severity, code, message });
+        }
+    }
+
+    fn add_error(&mut self, options: &CheckOptions, offset: u64, err: &PcapError) {
+        if err.is_eof_like() {
+            let message = "The capture is truncated".to_owned();
+            self.add(options, offset, Severity::Error, "PCAP_TRUNCATED_PACKET", message);
+        }
+        else {
+            self.add(options, offset, Severity::Error, err.code(), err.to_string());
+        }
+    }
+}
+
+/// Checks a whole pcap in one streaming pass, continuing after the problems that don't break the framing of the packets.
+///
+/// After a problem preventing to find the next packet (suspicious endianness, implausible packet header not followed
+/// by a plausible one...), the check resynchronises on the next packet whose header is plausible and is followed by
+/// a header with plausible lengths or by the end of the pcap, skipping at most [`CheckOptions::max_resync_skip`] bytes.
+/// The reading stops if no such packet is found, at an invalid global header or at a truncated packet.
+///
+/// The codes of the findings are either a code of [`PcapError::code`] or one of these codes,
+/// which are stable in the same way:
+/// - `PCAP_QUIRK_SWAPPED_SNAPLEN_DATALINK`: warning, see [`PcapQuirk::SwappedSnaplenDatalink`]
+/// - `PCAP_TRUNCATED_PACKET`: error, the capture ends in the middle of a packet
+/// - `PCAP_INCL_LEN_GT_SNAPLEN`: error, the captured length of a packet is bigger than the snaplen
+/// - `PCAP_INCL_LEN_GT_ORIG_LEN`: error, the captured length of a packet is bigger than its original length
+/// - `PCAP_INVALID_TS_FRAC`: error, the fractional part of a timestamp is bigger than a second
+/// - `PCAP_TIME_NOT_ORDERED`: warning, the timestamp of a packet is before the one of the previous packet
+/// - `PCAP_RESYNCHRONIZED`: warning, bytes were skipped after a framing error to find the next packet
+/// - `PCAP_HEURISTIC_TIMEZONE_SHIFT`: warning, only reported by [`TimezoneShift::to_finding`]
+pub fn check<R: Read>(reader: R, options: CheckOptions) -> CheckReport {
+    let mut report = CheckReport::default();
+
+    let mut reader = match PcapReader::with_quirks(reader, &[PcapQuirk::SwappedSnaplenDatalink]) {
+        Ok(reader) => reader,
+        Err(e) => {
+            report.add_error(&options, 0, &e);
+            return report;
+        },
+    };
+
+    for quirk in reader.fixed_quirks() {
+        let code = match quirk {
+            PcapQuirk::SwappedSnaplenDatalink => "PCAP_QUIRK_SWAPPED_SNAPLEN_DATALINK",
+        };
+        report.add(&options, 0, Severity::Warning, code, format!("The global header has the quirk {quirk:?}"));
+    }
+
+    let header = reader.header();
+    let frac_limit = match header.ts_resolution {
+        TsResolution::MicroSecond => 1_000_000,
+        TsResolution::NanoSecond => 1_000_000_000,
+    };
+
+    let mut offset = reader.data_start();
+    let mut prev_ts = None;
+    loop {
+        let (packet_header, framed) = match reader.peek_framed_header() {
+            Ok(Some(next)) => next,
+            Ok(None) => break,
+            Err(e) => {
+                report.add_error(&options, offset, &e);
+                if e.is_eof_like() || !resync(&mut reader, &options, &mut report, &mut offset) {
+                    break;
+                }
+                continue;
+            },
+        };
+
+        let PcapPacketHeader { ts_sec, ts_frac, incl_len, orig_len } = packet_header;
+        if incl_len > header.snaplen {
+            let message = format!("The captured length {incl_len} is bigger than the snaplen {}", header.snaplen);
+            report.add(&options, offset, Severity::Error, "PCAP_INCL_LEN_GT_SNAPLEN", message);
+        }
+        if incl_len > orig_len {
+            let message = format!("The captured length {incl_len} is bigger than the original length {orig_len}");
+            report.add(&options, offset, Severity::Error, "PCAP_INCL_LEN_GT_ORIG_LEN", message);
+        }
+        if ts_frac >= frac_limit {
+            let message = format!("The fractional part of the timestamp {ts_frac} is bigger than a second");
+            report.add(&options, offset, Severity::Error, "PCAP_INVALID_TS_FRAC", message);
+        }
+
+        // The header is garbage, the packets start somewhere after it
+        if !framed {
+            if !resync(&mut reader, &options, &mut report, &mut offset) {
+                break;
+            }
+            continue;
+        }
+
+        if options.check_time_order && prev_ts.is_some_and(|prev_ts| (ts_sec, ts_frac) < prev_ts) {
+            let message = "The timestamp is before the one of the previous packet".to_owned();
+            report.add(&options, offset, Severity::Warning, "PCAP_TIME_NOT_ORDERED", message);
+        }
+        prev_ts = Some((ts_sec, ts_frac));
+
+        report.nb_packets += 1;
+        if let Err(e) = reader.read_header().and_then(|_| reader.skip_payload(&packet_header)) {
+            report.add_error(&options, offset, &e);
+            break;
+        }
+
+        offset += (header.record_header_len() + incl_len as usize) as u64;
+    }
+
+    report.nb_bytes = offset;
+    report
+}
+
+/// Skips the bytes preceding the next framed packet after a framing error, reporting them, returns false if no packet
+/// is found in [`CheckOptions::max_resync_skip`] bytes.
+fn resync<R: Read>(reader: &mut PcapReader<R>, options: &CheckOptions, report: &mut CheckReport, offset: &mut u64) -> bool {
+    let skip = match reader.resync(options.max_resync_skip) {
+        Ok(Some(skip)) => skip,
+        Ok(None) => return false,
+        Err(e) => {
+            report.add_error(options, *offset, &e);
+            return false;
+        },
+    };
+
+    let message = format!("{skip} bytes were skipped to find the next packet");
+    report.add(options, *offset, Severity::Warning, "PCAP_RESYNCHRONIZED", message);
+    *offset += skip as u64;
+    true
+}
+
+/// Options of [`detect_timezone_shift`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TimezoneOptions {
+    /// Reports a non-zero `ts_correction` of the global header
+    pub check_ts_correction: bool,
+    /// Window in which the first packet is expected, e.g. the time of the capture according to its ticket
+    pub expected_window: Option<Range<Duration>>,
+    /// Granularity of the offsets between timezones, in seconds
+    pub step_secs: u64,
+    /// Biggest offset searched, in seconds
+    pub max_offset_secs: u64,
+}
+
+impl Default for TimezoneOptions {
+    fn default() -> Self {
+        TimezoneOptions { check_ts_correction: true, expected_window: None, step_secs: 1800, max_offset_secs: 14 * 3600 }
+    }
+}
+
+/// Reason of a [`TimezoneShift`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum TimezoneShiftReason {
+    /// The `ts_correction` of the global header isn't 0
+    TsCorrection,
+    /// The first packet is outside of the expected window, but inside it once shifted by a multiple of the step
+    OutsideExpectedWindow,
+}
+
+/// Suspected timezone shift of a pcap, found by [`detect_timezone_shift`].
+///
+/// It is a heuristic: the timestamps can be right and the capture simply unexpected.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TimezoneShift {
+    /// Seconds to add to the timestamps to get UTC, which can be given to
+    /// [`PcapReader::with_epoch_offset`] to fix them
+    pub offset_secs: i64,
+    /// Why the shift is suspected
+    pub reason: TimezoneShiftReason,
+    /// Offset, from the start of the pcap, of the global header or of the first packet showing the shift
+    pub offset: u64,
+}
+
+impl TimezoneShift {
+    /// Returns the shift as a [`Finding`] with the code `PCAP_HEURISTIC_TIMEZONE_SHIFT`, see [`check`].
+    pub fn to_finding(&self) -> Finding {
+        let reason = match self.reason {
+            TimezoneShiftReason::TsCorrection => "the ts_correction of the global header isn't 0",
+            TimezoneShiftReason::OutsideExpectedWindow => "the first packet is outside of the expected window",
+        };
+
+        Finding {
+            offset: self.offset,
+            severity: Severity::Warning,
+            code: "PCAP_HEURISTIC_TIMEZONE_SHIFT",
+            message: format!("The timestamps are likely shifted by {}s, {reason}", -self.offset_secs),
+        }
+    }
+}
+
+/// Detects the pcaps whose timestamps are likely in a local time instead of UTC, returns the suspected shift.
+///
+/// It is a heuristic, looking at:
+/// - the `ts_correction` of the global header, which is always 0 in the pcaps in UTC: following libpcap, the local time
+///   plus the `ts_correction` gives UTC
+/// - the timestamp of the first packet, if an expected window is given: the pcap is suspected if the first packet is
+///   outside of the window but inside it once shifted by a multiple of [`TimezoneOptions::step_secs`].
+///   The smallest shift is returned.
+///
+/// # Errors
+/// The pcap is invalid or the underlying data are not readable.
+pub fn detect_timezone_shift<R: Read>(reader: R, options: &TimezoneOptions) -> PcapResult<Option<TimezoneShift>> {
+    let mut reader = PcapReader::new(reader)?;
+
+    let ts_correction = reader.header().ts_correction;
+    if options.check_ts_correction && ts_correction != 0 {
+        return Ok(Some(TimezoneShift { offset_secs: ts_correction as i64, reason: TimezoneShiftReason::TsCorrection, offset: 0 }));
+    }
+
+    let window = match &options.expected_window {
+        Some(window) => window,
+        None => return Ok(None),
+    };
+    let data_start = reader.data_start();
+    let first = match reader.next_packet() {
+        Some(packet) => packet?.timestamp,
+        None => return Ok(None),
+    };
+    if window.contains(&first) || options.step_secs == 0 {
+        return Ok(None);
+    }
+
+    let shift = |offset_secs| TimezoneShift { offset_secs, reason: TimezoneShiftReason::OutsideExpectedWindow, offset: data_start };
+    for step in 1..=options.max_offset_secs / options.step_secs {
+        let offset = Duration::from_secs(step * options.step_secs);
+        if first.checked_add(offset).is_some_and(|ts| window.contains(&ts)) {
+            return Ok(Some(shift(offset.as_secs() as i64)));
+        }
+        if first.checked_sub(offset).is_some_and(|ts| window.contains(&ts)) {
+            return Ok(Some(shift(-(offset.as_secs() as i64))));
+        }
+    }
+
+    Ok(None)
+}
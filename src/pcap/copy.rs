@@ -0,0 +1,75 @@
+use std::io::{Read, Write};
+use std::time::Duration;
+
+use super::{PcapPacket, PcapReader, PcapWriter};
+use crate::errors::*;
+use crate::pcapng::{Block, PcapNgReader};
+
+
+/// Copies the remaining packets of a pcap to a writer, returns the number of packets read.
+///
+/// The packets are decoded and written with [`PcapWriter::write_packet`], so they all go through the transform
+/// and the fixed caplen of the writer, and their timestamps are converted to its resolution.
+///
+/// # Errors
+/// The reader or the writer returns an error.
+///
+/// # Example
+/// ```rust
+/// use std::time::Duration;
+///
+/// use pcap_file::pcap::{copy_packets, zero_ethernet_addresses, PcapPacket, PcapReader, PcapWriter};
+/// use pcap_file::DataLink;
+///
+/// let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+/// pcap_writer.write_packet(&PcapPacket::new(Duration::ZERO, 14, &[0xAA; 14])).unwrap();
+/// let pcap = pcap_writer.into_writer();
+///
+/// let mut anonymized = PcapWriter::new(Vec::new()).unwrap();
+/// anonymized.set_transform(zero_ethernet_addresses(DataLink::ETHERNET));
+/// assert_eq!(copy_packets(&mut PcapReader::new(&pcap[..]).unwrap(), &mut anonymized).unwrap(), 1);
+/// ```
+pub fn copy_packets<R: Read, W: Write>(reader: &mut PcapReader<R>, writer: &mut PcapWriter<W>) -> PcapResult<u64> {
+    let mut nb_packets = 0;
+    while let Some(packet) = reader.next_packet() {
+        writer.write_packet(&packet?)?;
+        nb_packets += 1;
+    }
+
+    Ok(nb_packets)
+}
+
+/// Converts the remaining packets of a pcapng to a pcap, returns the number of packets read.
+///
+/// The packets are the [`EnhancedPacketBlock`](crate::pcapng::blocks::enhanced_packet::EnhancedPacketBlock)s and the
+/// [`SimplePacketBlock`](crate::pcapng::blocks::simple_packet::SimplePacketBlock)s, which have no timestamp and are
+/// written with the Unix epoch. The other blocks are skipped. The packets are written with [`PcapWriter::write_packet`],
+/// so they all go through the transform and the fixed caplen of the writer.
+///
+/// # Errors
+/// An interface of the pcapng doesn't have the datalink of the writer ([`PcapError::InvalidConfiguration`]).
+///
+/// The reader or the writer returns an error.
+pub fn convert_pcapng<R: Read, W: Write>(reader: &mut PcapNgReader<R>, writer: &mut PcapWriter<W>) -> PcapResult<u64> {
+    let datalink = writer.state().header().datalink;
+    if reader.interfaces().iter().any(|interface| interface.linktype != datalink) {
+        return Err(PcapError::InvalidConfiguration("convert_pcapng: interface datalink != pcap datalink"));
+    }
+
+    let mut nb_packets = 0;
+    while let Some(block) = reader.next_block() {
+        let packet = match block? {
+            Block::InterfaceDescription(interface) if interface.linktype != datalink => {
+                return Err(PcapError::InvalidConfiguration("convert_pcapng: interface datalink != pcap datalink"));
+            },
+            Block::EnhancedPacket(epb) => PcapPacket { timestamp: epb.timestamp, orig_len: epb.original_len, data: epb.data },
+            Block::SimplePacket(spb) => PcapPacket { timestamp: Duration::ZERO, orig_len: spb.original_len, data: spb.data },
+            _ => continue,
+        };
+
+        writer.write_packet(&packet)?;
+        nb_packets += 1;
+    }
+
+    Ok(nb_packets)
+}
@@ -0,0 +1,288 @@
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::fmt::Debug;
+
+use super::reader::partial_record;
+use super::{PcapParser, RawPcapPacket, TruncationPolicy};
+use crate::errors::*;
+use crate::pcap::{PcapHeader, PcapPacket};
+
+
+/// Decodes a pcap fed as a sequence of chunks, e.g. the buffers of a capture queue.
+///
+/// The packets and the global header can span several chunks. The packets fully contained in a chunk are borrowed from it,
+/// only the packets spanning two or more chunks are copied into an owned [`PcapPacket`].
+///
+/// # Example
+///
+/// ```rust
+/// use std::time::Duration;
+///
+/// use pcap_file::pcap::{ChunkedPcapDecoder, PcapPacket, PcapWriter};
+///
+/// let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+/// pcap_writer.write_packet(&PcapPacket::new(Duration::ZERO, 4, &[1, 2, 3, 4])).unwrap();
+/// let pcap = pcap_writer.into_writer();
+///
+/// let mut decoder = ChunkedPcapDecoder::new();
+/// for chunk in pcap.chunks(10) {
+///     decoder.feed(chunk);
+///
+///     while let Some(packet) = decoder.poll() {
+///         assert_eq!(&packet.unwrap().data[..], &[1, 2, 3, 4]);
+///     }
+/// }
+/// ```
+#[derive(Default)]
+pub struct ChunkedPcapDecoder<'a> {
+    /// Parser, once the global header is decoded
+    parser: Option<PcapParser>,
+    /// Chunks not decoded yet, the first one can be partially decoded
+    chunks: VecDeque<&'a [u8]>,
+    truncation: TruncationPolicy,
+    ignore_padding: bool,
+    /// True if the last packet returned was cut by the end of the pcap
+    partial: bool,
+}
+
+impl<'a> ChunkedPcapDecoder<'a> {
+    /// Creates a new [`ChunkedPcapDecoder`] expecting a global header in the first chunk(s).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets what the decoder does with a last record cut by the end of the pcap ([`TruncationPolicy::Error`] by default),
+    /// see [`ChunkedPcapDecoder::poll_end`].
+    pub fn with_truncation_policy(mut self, truncation: TruncationPolicy) -> Self {
+        self.truncation = truncation;
+        self
+    }
+
+    /// Skips the padding records if `ignore` is true (false by default), see [`PcapParser::set_ignore_padding`].
+    pub fn with_ignore_padding(mut self, ignore: bool) -> Self {
+        self.ignore_padding = ignore;
+        if let Some(parser) = &mut self.parser {
+            parser.set_ignore_padding(ignore);
+        }
+        self
+    }
+
+    /// Returns true if the last packet returned was cut by the end of the pcap, see [`TruncationPolicy::YieldPartial`].
+    pub fn is_partial(&self) -> bool {
+        self.partial
+    }
+
+    /// Appends a chunk to the data to decode.
+    pub fn feed(&mut self, chunk: &'a [u8]) {
+        if !chunk.is_empty() {
+            self.chunks.push_back(chunk);
+        }
+    }
+
+    /// Returns the next packet, or [`None`] if more chunks are needed to decode it.
+    ///
+    /// An error is returned again by the next calls, the decoder can't skip invalid data.
+    pub fn poll(&mut self) -> Option<PcapResult<PcapPacket<'a>>> {
+        if self.parser.is_none() {
+            let mut header = [0_u8; 24];
+            if !self.peek_exact(0, &mut header) {
+                return None;
+            }
+
+            // The header is only consumed if it is valid, so that the error is returned again
+            match PcapParser::new(&header) {
+                Ok((_, mut parser)) => {
+                    parser.set_ignore_padding(self.ignore_padding);
+                    self.parser = Some(parser);
+                },
+                Err(e) => return Some(Err(e)),
+            }
+            self.consume(header.len());
+        }
+
+        // The padding records spanning several chunks are skipped before, the parser skipping the other ones
+        while self.ignore_padding && self.skip_padding_record() {}
+
+        // Can unwrap because the parser is set above
+        let parser = self.parser.as_ref().unwrap();
+        let chunk = *self.chunks.front()?;
+
+        match parser.next_packet(chunk) {
+            Ok((rem, packet)) => {
+                self.consume(chunk.len() - rem.len());
+                self.partial = false;
+                Some(Ok(packet))
+            },
+            Err(PcapError::IncompleteBuffer) => self.poll_spanning_packet().transpose(),
+            Err(e) => Some(Err(e)),
+        }
+    }
+
+    /// Decodes a packet spanning several chunks, copying it.
+    fn poll_spanning_packet(&mut self) -> PcapResult<Option<PcapPacket<'a>>> {
+        // Can unwrap because the caller checks that the parser is set
+        let parser = self.parser.as_ref().unwrap();
+        let header = parser.header();
+        let header_len = header.record_header_len();
+
+        let mut record_header = vec![0_u8; header_len];
+        if !self.peek_exact(0, &mut record_header) {
+            return Ok(None);
+        }
+
+        let (_, packet_header) = parser.next_record_header(&record_header)?;
+        if parser.is_ignored_padding(&packet_header) {
+            // Wait for the whole padding record, skipped by the next poll
+            return Ok(None);
+        }
+
+        // Don't wait for an invalid length, which can be anything
+        if packet_header.incl_len > header.snaplen {
+            return Err(PcapError::InvalidField("PacketHeader incl_len > snap_len"));
+        }
+
+        if self.buffered_len() < header_len + packet_header.incl_len as usize {
+            return Ok(None);
+        }
+
+        let mut data = vec![0_u8; packet_header.incl_len as usize];
+        self.peek_exact(header_len, &mut data);
+
+        let raw_packet = RawPcapPacket {
+            ts_sec: packet_header.ts_sec,
+            ts_frac: packet_header.ts_frac,
+            incl_len: packet_header.incl_len,
+            orig_len: packet_header.orig_len,
+            data: Cow::Owned(data),
+        };
+
+        // The packet is only consumed if it is valid, so that the error is returned again
+        let packet = raw_packet.try_into_pcap_packet(header.ts_resolution, header.snaplen)?;
+        self.consume(header_len + packet.data.len());
+        self.partial = false;
+
+        Ok(Some(packet))
+    }
+
+    /// Returns the next packet like [`ChunkedPcapDecoder::poll`], once all the chunks are fed.
+    ///
+    /// The data left after the last whole packet is then a record cut by the end of the pcap, e.g. a capture killed
+    /// while writing: it is a [`PcapError::TruncatedPacket`], or it is discarded or returned as a partial
+    /// packet, as set by [`ChunkedPcapDecoder::with_truncation_policy`].
+    pub fn poll_end(&mut self) -> Option<PcapResult<PcapPacket<'a>>> {
+        if let Some(packet) = self.poll() {
+            return Some(packet);
+        }
+
+        let len = self.buffered_len();
+        if len == 0 {
+            return None;
+        }
+        if self.truncation == TruncationPolicy::Error {
+            return Some(Err(PcapError::TruncatedPacket));
+        }
+
+        let mut rest = vec![0_u8; len];
+        self.peek_exact(0, &mut rest);
+        self.consume(len);
+
+        // The global header itself can be cut, there is no packet then
+        let parser = self.parser.as_ref()?;
+        let header = parser.header();
+        let raw_packet = match partial_record(parser, self.truncation, &rest) {
+            Ok((_, raw_packet)) => raw_packet?,
+            Err(e) => return Some(Err(e)),
+        };
+        self.partial = true;
+
+        let packet = raw_packet.try_into_pcap_packet(header.ts_resolution, header.snaplen);
+        Some(packet.map(PcapPacket::into_owned))
+    }
+
+    /// Skips the next record if it is a padding record ignored by the parser and if it is fully fed, returns true if it
+    /// is skipped.
+    fn skip_padding_record(&mut self) -> bool {
+        let parser = match &self.parser {
+            Some(parser) => parser,
+            None => return false,
+        };
+        let header_len = parser.header().record_header_len();
+
+        let mut record_header = vec![0_u8; header_len];
+        if !self.peek_exact(0, &mut record_header) {
+            return false;
+        }
+
+        let len = match parser.next_record_header(&record_header) {
+            Ok((_, header)) if parser.is_ignored_padding(&header) => header_len + header.incl_len as usize,
+            _ => return false,
+        };
+        if self.buffered_len() < len {
+            return false;
+        }
+
+        self.consume(len);
+        true
+    }
+
+    /// Copies `buf.len()` bytes, starting `skip` bytes after the next byte, into `buf` without consuming them.
+    ///
+    /// Returns false if there aren't enough bytes.
+    fn peek_exact(&self, mut skip: usize, buf: &mut [u8]) -> bool {
+        if self.buffered_len() < skip + buf.len() {
+            return false;
+        }
+
+        let mut copied = 0;
+        for chunk in &self.chunks {
+            if copied == buf.len() {
+                break;
+            }
+
+            let skipped = skip.min(chunk.len());
+            let chunk = &chunk[skipped..];
+            skip -= skipped;
+            let len = chunk.len().min(buf.len() - copied);
+            buf[copied..copied + len].copy_from_slice(&chunk[..len]);
+            copied += len;
+        }
+
+        true
+    }
+
+    /// Consumes the next `len` bytes, which must be buffered.
+    fn consume(&mut self, mut len: usize) {
+        while len > 0 {
+            // Can unwrap because the bytes are buffered
+            let chunk = self.chunks.front_mut().unwrap();
+            let n = chunk.len().min(len);
+            *chunk = &chunk[n..];
+            len -= n;
+
+            if chunk.is_empty() {
+                self.chunks.pop_front();
+            }
+        }
+    }
+
+    /// Returns the number of bytes fed but not decoded yet.
+    pub fn buffered_len(&self) -> usize {
+        self.chunks.iter().map(|chunk| chunk.len()).sum()
+    }
+
+    /// Returns the global header of the pcap, once it is decoded.
+    pub fn header(&self) -> Option<PcapHeader> {
+        self.parser.as_ref().map(PcapParser::header)
+    }
+}
+
+impl Debug for ChunkedPcapDecoder<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The chunks belong to the caller, only their sizes are shown
+        f.debug_struct("ChunkedPcapDecoder")
+            .field("parser", &self.parser)
+            .field("nb_chunks", &self.chunks.len())
+            .field("buffered_len", &self.buffered_len())
+            .finish()
+    }
+}
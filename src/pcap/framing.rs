@@ -0,0 +1,302 @@
+use std::fmt::Debug;
+use std::io::{Read, Write};
+
+use byteorder_slice::{BigEndian, ByteOrder, LittleEndian};
+
+use super::header::format_from_magic;
+use super::reader::check_len;
+use super::RECORD_HEADER_LEN;
+use crate::errors::*;
+use crate::read_buffer::ReadBuffer;
+use crate::Endianness;
+
+
+/// Default maximum `incl_len` accepted by a [`RawPcapReader`], the biggest snaplen used by libpcap.
+pub const DEFAULT_MAX_INCL_LEN: u32 = 262144;
+
+/// Reads the records of a pcap without interpreting them, e.g. for a byte-level splitter.
+///
+/// Only the magic number of the global header is decoded, to know the endianness of the `incl_len` of the records.
+/// Each record is returned as its 16 bytes header and its data, exactly as they were read: the timestamps, the
+/// `orig_len` and the snaplen are neither decoded nor validated.
+///
+/// The only check is a cap on `incl_len` ([`DEFAULT_MAX_INCL_LEN`] by default), which bounds the memory allocated
+/// for a corrupted length. The records are split off a buffer like in the other readers, which share this framing,
+/// so the reader doesn't need to be buffered.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::fs::File;
+/// use std::io::BufWriter;
+///
+/// use pcap_file::pcap::{RawPcapReader, RawPcapWriter};
+///
+/// let file_in = File::open("test.pcap").expect("Error opening file");
+/// let mut reader = RawPcapReader::new(file_in).unwrap();
+///
+/// let file_out = BufWriter::new(File::create("copy.pcap").expect("Error creating file"));
+/// let mut writer = RawPcapWriter::new(file_out, *reader.raw_header_bytes()).unwrap();
+///
+/// while let Some(record) = reader.next_record() {
+///     let (header, data) = record.unwrap();
+///     writer.write_record(&header, data).unwrap();
+/// }
+/// ```
+pub struct RawPcapReader<R: Read> {
+    reader: ReadBuffer<R>,
+    raw_header: [u8; 24],
+    endianness: Endianness,
+    max_incl_len: u32,
+    ignore_padding: bool,
+}
+
+impl<R: Read> RawPcapReader<R> {
+    /// Creates a new [`RawPcapReader`], reading the global header of the pcap.
+    ///
+    /// # Errors
+    /// The magic number of the global header is not the one of a supported pcap.
+    ///
+    /// The underlying data are not readable.
+    pub fn new(reader: R) -> PcapResult<RawPcapReader<R>> {
+        let mut reader = ReadBuffer::new(reader);
+        let (raw_header, endianness) = reader.parse_with(
+            |src| check_len(src, 24),
+            |src| {
+                // Can unwrap because the probe checked the length
+                let raw_header: [u8; 24] = src[..24].try_into().unwrap();
+                Ok((&src[24..], (raw_header, header_endianness(&raw_header)?)))
+            },
+        )?;
+
+        Ok(RawPcapReader { reader, raw_header, endianness, max_incl_len: DEFAULT_MAX_INCL_LEN, ignore_padding: false })
+    }
+
+    /// Sets the maximum `incl_len` of the records, a bigger one is returned as an error.
+    ///
+    /// The buffer of the reader grows up to this size if needed.
+    pub fn with_max_incl_len(mut self, max_incl_len: u32) -> Self {
+        self.max_incl_len = max_incl_len;
+        self.reader.set_max_capacity(RECORD_HEADER_LEN.saturating_add(max_incl_len as usize));
+        self
+    }
+
+    /// Skips the padding records if `ignore` is true (false by default), see
+    /// [`PcapReader::with_ignore_padding`](super::PcapReader::with_ignore_padding).
+    pub fn with_ignore_padding(mut self, ignore: bool) -> Self {
+        self.ignore_padding = ignore;
+        self
+    }
+
+    /// Returns the next record: its header and its data, as they were read.
+    ///
+    /// Returns [`None`] at the end of the pcap, which must be at the end of a record.
+    /// Nothing is consumed when an error is returned, but the framing can't be recovered after a too big or cut record.
+    pub fn next_record(&mut self) -> Option<PcapResult<([u8; RECORD_HEADER_LEN], &[u8])>> {
+        loop {
+            match self.reader.has_data_left() {
+                Ok(true) => {},
+                Ok(false) => return None,
+                Err(e) => return Some(Err(PcapError::IoError(e))),
+            }
+
+            if !self.ignore_padding {
+                break;
+            }
+
+            // The padding records are skipped until a record or the end of the pcap
+            let endianness = self.endianness;
+            match self.reader.parse_with(|src| raw_padding_len(src, endianness).map(drop), |src| {
+                let len = raw_padding_len(src, endianness)?;
+                Ok((&src[len..], len))
+            }) {
+                Ok(0) => break,
+                Ok(_) => {},
+                Err(e) => return Some(Err(e)),
+            }
+        }
+
+        let (endianness, max_incl_len) = (self.endianness, self.max_incl_len);
+        let record = self.reader.parse_with(
+            |src| split_capped_record(src, endianness, max_incl_len).map(|_| ()),
+            |src| split_capped_record(src, endianness, max_incl_len).map(|(rem, header, data)| (rem, (*header, data))),
+        );
+        Some(record)
+    }
+
+    /// Returns the global header exactly as it was read.
+    pub fn raw_header_bytes(&self) -> &[u8; 24] {
+        &self.raw_header
+    }
+
+    /// Returns the endianness of the pcap, given by its magic number.
+    pub fn endianness(&self) -> Endianness {
+        self.endianness
+    }
+
+    /// Consumes [`Self`], returning the wrapped reader.
+    ///
+    /// The data is read in advance, so the reader can be positioned anywhere after the last record returned.
+    /// Use [`RawPcapReader::into_parts`] to continue reading right after it.
+    pub fn into_reader(self) -> R {
+        self.reader.into_inner()
+    }
+
+    /// Consumes [`Self`], returning the wrapped reader and the data read in advance from it, which starts right
+    /// after the last record returned.
+    pub fn into_parts(self) -> (R, Vec<u8>) {
+        self.reader.into_parts()
+    }
+}
+
+impl<R: Read + Debug> Debug for RawPcapReader<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RawPcapReader")
+            .field("reader", &self.reader)
+            .field("endianness", &self.endianness)
+            .field("max_incl_len", &self.max_incl_len)
+            .field("ignore_padding", &self.ignore_padding)
+            .finish_non_exhaustive()
+    }
+}
+
+
+/// Writes the records of a pcap without interpreting them, the counterpart of the [`RawPcapReader`].
+///
+/// The global header and the record headers are written as given. Only the `incl_len` of the records is decoded, with
+/// the endianness given by the magic number of the global header, to check that it is the length of their data.
+pub struct RawPcapWriter<W: Write> {
+    writer: W,
+    endianness: Endianness,
+}
+
+impl<W: Write> Debug for RawPcapWriter<W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RawPcapWriter")
+            .field("writer", &std::any::type_name::<W>())
+            .field("endianness", &self.endianness)
+            .finish()
+    }
+}
+
+impl<W: Write> RawPcapWriter<W> {
+    /// Creates a new [`RawPcapWriter`], writing the global header as given.
+    ///
+    /// # Errors
+    /// The magic number of the global header is not the one of a supported pcap.
+    ///
+    /// The underlying writer can't be written to.
+    pub fn new(mut writer: W, raw_header: [u8; 24]) -> PcapResult<RawPcapWriter<W>> {
+        let endianness = header_endianness(&raw_header)?;
+        writer.write_all(&raw_header).map_err(PcapError::IoError)?;
+
+        Ok(RawPcapWriter { writer, endianness })
+    }
+
+    /// Writes a record: its header and its data, as given.
+    ///
+    /// Returns the number of bytes written.
+    ///
+    /// # Errors
+    /// The `incl_len` of the header is not the length of the data ([`PcapError::LengthMismatch`]).
+    ///
+    /// The underlying writer can't be written to.
+    pub fn write_record(&mut self, header: &[u8; RECORD_HEADER_LEN], data: &[u8]) -> PcapResult<usize> {
+        let incl_len = record_incl_len(header, self.endianness);
+        if incl_len as usize != data.len() {
+            return Err(PcapError::LengthMismatch { incl_len, data_len: data.len() });
+        }
+
+        self.writer.write_all(header).map_err(PcapError::IoError)?;
+        self.writer.write_all(data).map_err(PcapError::IoError)?;
+
+        Ok(RECORD_HEADER_LEN + data.len())
+    }
+
+    /// Consumes [`Self`], returning the wrapped writer.
+    pub fn into_writer(self) -> W {
+        self.writer
+    }
+}
+
+/// Returns the endianness given by the magic number of a global header.
+fn header_endianness(raw_header: &[u8; 24]) -> PcapResult<Endianness> {
+    let (_, endianness) = format_from_magic(BigEndian::read_u32(&raw_header[..4]))?;
+    Ok(endianness)
+}
+
+/// Returns the `incl_len` of a record header, which must be at least 16 bytes long.
+fn record_incl_len(header: &[u8], endianness: Endianness) -> u32 {
+    match endianness {
+        Endianness::Big => BigEndian::read_u32(&header[8..12]),
+        Endianness::Little => LittleEndian::read_u32(&header[8..12]),
+    }
+}
+
+/// Splits the next record off a slice like [`split_record`], checking its `incl_len` against `max_incl_len` before
+/// waiting for its data, so that a corrupted length doesn't fill the buffer.
+fn split_capped_record(slice: &[u8], endianness: Endianness, max_incl_len: u32) -> PcapResult<(&[u8], &[u8; RECORD_HEADER_LEN], &[u8])> {
+    if slice.len() >= RECORD_HEADER_LEN && record_incl_len(slice, endianness) > max_incl_len {
+        return Err(PcapError::InvalidField("RawPcapReader: incl_len > max_incl_len"));
+    }
+
+    match endianness {
+        Endianness::Big => split_record::<BigEndian>(slice, RECORD_HEADER_LEN),
+        Endianness::Little => split_record::<LittleEndian>(slice, RECORD_HEADER_LEN),
+    }
+}
+
+/// Splits the next record off a slice whose record headers are `header_len` bytes long, decoding only its `incl_len`.
+///
+/// Returns the remainder, the standard 16 bytes of the record header and the data of the record. It is the framing
+/// of all the readers, the richer ones decoding and validating the header afterwards.
+pub(crate) fn split_record<B: ByteOrder>(slice: &[u8], header_len: usize) -> PcapResult<(&[u8], &[u8; RECORD_HEADER_LEN], &[u8])> {
+    if slice.len() < header_len {
+        return Err(PcapError::IncompleteBuffer);
+    }
+
+    // Can unwrap because the length check is done before
+    let header: &[u8; RECORD_HEADER_LEN] = slice[..RECORD_HEADER_LEN].try_into().unwrap();
+    let data_end = header_len + B::read_u32(&header[8..12]) as usize;
+    if slice.len() < data_end {
+        return Err(PcapError::IncompleteBuffer);
+    }
+
+    Ok((&slice[data_end..], header, &slice[header_len..data_end]))
+}
+
+/// Returns the length of the padding records starting a slice, see [`padding_len`].
+fn raw_padding_len(slice: &[u8], endianness: Endianness) -> PcapResult<usize> {
+    match endianness {
+        Endianness::Big => padding_len::<BigEndian>(slice, RECORD_HEADER_LEN),
+        Endianness::Little => padding_len::<LittleEndian>(slice, RECORD_HEADER_LEN),
+    }
+}
+
+/// Returns the length of the padding records starting a slice whose record headers are `header_len` bytes long,
+/// see [`PcapPacketHeader::padding`](super::PcapPacketHeader::padding).
+///
+/// Returns [`PcapError::IncompleteBuffer`] if the slice ends inside a padding record or inside the record header
+/// following them, which can be the one of another padding record.
+pub(crate) fn padding_len<B: ByteOrder>(slice: &[u8], header_len: usize) -> PcapResult<usize> {
+    let mut len = 0;
+    loop {
+        let rem = &slice[len..];
+        if rem.is_empty() {
+            return Ok(len);
+        }
+        if rem.len() < header_len {
+            return Err(PcapError::IncompleteBuffer);
+        }
+
+        // The timestamp and the orig_len of a padding header are the same in both endiannesses
+        if rem[..8] != [0xFF; 8] || rem[12..16] != [0; 4] {
+            return Ok(len);
+        }
+
+        len = len.saturating_add(header_len + B::read_u32(&rem[8..12]) as usize);
+        if slice.len() < len {
+            return Err(PcapError::IncompleteBuffer);
+        }
+    }
+}
@@ -4,12 +4,14 @@ use byteorder_slice::byteorder::WriteBytesExt;
 use byteorder_slice::result::ReadSlice;
 use byteorder_slice::{BigEndian, ByteOrder, LittleEndian};
 
+use super::RECORD_HEADER_LEN;
 use crate::errors::*;
 use crate::{DataLink, Endianness, TsResolution};
 
 
 /// Pcap Global Header
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct PcapHeader {
     /// Major version number
     pub version_major: u16,
@@ -43,42 +45,120 @@ impl PcapHeader {
     /// or if there is a reading error.
     ///
     /// [`PcapError::IncompleteBuffer`] indicates that there is not enough data in the buffer.
-    pub fn from_slice(mut slice: &[u8]) -> PcapResult<(&[u8], PcapHeader)> {
+    ///
+    /// # Validation policy
+    /// Only the magic number and the major version (which must be 2) can cause an error.
+    /// All the other fields (minor version, ts_correction, ts_accuracy, snaplen, datalink) are passed through
+    /// as is, because many writers fill them with unusual values.
+    ///
+    /// The only exception is a header with a known [`PcapQuirk`], which returns [`PcapError::SuspectedQuirk`].
+    /// Use [`PcapHeader::from_slice_with_quirks`] to fix it.
+    pub fn from_slice(slice: &[u8]) -> PcapResult<(&[u8], PcapHeader)> {
+        Self::from_slice_with_quirks(slice, &[]).map(|(rem, header, _)| (rem, header))
+    }
+
+    /// Creates a new [`PcapHeader`] from a slice of bytes, fixing the given quirks if they are detected.
+    ///
+    /// Returns the remainder, the header and the quirks that were fixed.
+    pub fn from_slice_with_quirks<'a>(slice: &'a [u8], quirks: &[PcapQuirk]) -> PcapResult<(&'a [u8], PcapHeader, Vec<PcapQuirk>)> {
+        let (rem, mut header) = Self::parse(slice)?;
+        let mut fixed = vec![];
+
+        // Snaplen and datalink byte swapped relative to the other fields (e.g. datalink 0x01000000 instead of 1)
+        if is_swapped_datalink(header.datalink) {
+            let datalink = u32::from(header.datalink);
+            if !quirks.contains(&PcapQuirk::SwappedSnaplenDatalink) {
+                return Err(PcapError::SuspectedQuirk(PcapQuirk::SwappedSnaplenDatalink));
+            }
+
+            header.datalink = DataLink::from(datalink.swap_bytes());
+            header.snaplen = header.snaplen.swap_bytes();
+            fixed.push(PcapQuirk::SwappedSnaplenDatalink);
+        }
+
+        Ok((rem, header, fixed))
+    }
+
+    /// Creates a new [`PcapHeader`] from a slice of bytes, ignoring its magic number and using the given format instead.
+    ///
+    /// It bypasses the validation of the magic number, so it must only be used to recover pcaps whose magic number
+    /// is corrupted but whose format is known out-of-band. The other fields are validated like in [`PcapHeader::from_slice`].
+    pub fn from_slice_with_format(slice: &[u8], format: PcapFormat) -> PcapResult<(&[u8], PcapHeader)> {
+        if slice.len() < 24 {
+            return Err(PcapError::IncompleteBuffer);
+        }
+
+        let mut fixed = [0_u8; 24];
+        fixed.copy_from_slice(&slice[..24]);
+        BigEndian::write_u32(&mut fixed[..4], format.magic());
+
+        let (_, header) = Self::from_slice(&fixed)?;
+        Ok((&slice[24..], header))
+    }
+
+    /// Parses the header without checking the quirks.
+    fn parse(mut slice: &[u8]) -> PcapResult<(&[u8], PcapHeader)> {
         // Check that slice.len() > PcapHeader length
         if slice.len() < 24 {
             return Err(PcapError::IncompleteBuffer);
         }
 
         let magic_number = slice.read_u32::<BigEndian>().unwrap();
+        let (_, endianness) = format_from_magic(magic_number)?;
 
-        match magic_number {
-            0xA1B2C3D4 => return init_pcap_header::<BigEndian>(slice, TsResolution::MicroSecond, Endianness::Big),
-            0xA1B23C4D => return init_pcap_header::<BigEndian>(slice, TsResolution::NanoSecond, Endianness::Big),
-            0xD4C3B2A1 => return init_pcap_header::<LittleEndian>(slice, TsResolution::MicroSecond, Endianness::Little),
-            0x4D3CB2A1 => return init_pcap_header::<LittleEndian>(slice, TsResolution::NanoSecond, Endianness::Little),
-            _ => return Err(PcapError::InvalidField("PcapHeader: wrong magic number")),
-        };
+        let header = match endianness {
+            Endianness::Big => read_fields::<BigEndian>(&mut slice, magic_number),
+            Endianness::Little => read_fields::<LittleEndian>(&mut slice, magic_number),
+        }?;
+
+        return Ok((slice, header));
 
         // Inner function used for the initialisation of the PcapHeader.
-        // Must check the srcclength before calling it.
-        fn init_pcap_header<B: ByteOrder>(
-            mut src: &[u8],
-            ts_resolution: TsResolution,
-            endianness: Endianness,
-        ) -> PcapResult<(&[u8], PcapHeader)> {
-            let header = PcapHeader {
-                version_major: src.read_u16::<B>().unwrap(),
-                version_minor: src.read_u16::<B>().unwrap(),
-                ts_correction: src.read_i32::<B>().unwrap(),
-                ts_accuracy: src.read_u32::<B>().unwrap(),
-                snaplen: src.read_u32::<B>().unwrap(),
-                datalink: DataLink::from(src.read_u32::<B>().unwrap()),
-                ts_resolution,
-                endianness,
-            };
+        // Must check the src length before calling it.
+        fn read_fields<B: ByteOrder>(src: &mut &[u8], magic_number: u32) -> PcapResult<PcapHeader> {
+            PcapHeader::from_fields(
+                magic_number,
+                src.read_u16::<B>().unwrap(),
+                src.read_u16::<B>().unwrap(),
+                src.read_i32::<B>().unwrap(),
+                src.read_u32::<B>().unwrap(),
+                src.read_u32::<B>().unwrap(),
+                src.read_u32::<B>().unwrap(),
+            )
+        }
+    }
+
+    /// Creates a new [`PcapHeader`] from the raw values of its fields.
+    ///
+    /// `magic` is the magic number as read in big endian, so it also gives the endianness of the pcap
+    /// (e.g. `0xD4C3B2A1` for a little endian pcap with microsecond resolution).
+    ///
+    /// The fields are validated like in [`PcapHeader::from_slice`].
+    pub fn from_fields(
+        magic: u32,
+        version_major: u16,
+        version_minor: u16,
+        ts_correction: i32,
+        ts_accuracy: u32,
+        snaplen: u32,
+        network: u32,
+    ) -> PcapResult<PcapHeader> {
+        let (ts_resolution, endianness) = format_from_magic(magic)?;
 
-            Ok((src, header))
+        if version_major != 2 {
+            return Err(PcapError::InvalidField("PcapHeader: version_major != 2"));
         }
+
+        Ok(PcapHeader {
+            version_major,
+            version_minor,
+            ts_correction,
+            ts_accuracy,
+            snaplen,
+            datalink: DataLink::from(network),
+            ts_resolution,
+            endianness,
+        })
     }
 
     /// Writes a [`PcapHeader`] to a writer.
@@ -107,8 +187,116 @@ impl PcapHeader {
             Ok(24)
         }
     }
+
+    /// Writes a [`PcapHeader`] to the start of a slice, returns the number of bytes written.
+    ///
+    /// Returns [`PcapError::IncompleteBuffer`] without writing anything if the slice is shorter than 24 bytes.
+    pub fn write_to_slice(&self, buf: &mut [u8]) -> PcapResult<usize> {
+        if buf.len() < 24 {
+            return Err(PcapError::IncompleteBuffer);
+        }
+
+        self.write_to(&mut &mut buf[..])
+    }
+
+    /// Checks that the header would be accepted by a writer, i.e. that the pcap written with it can be read.
+    ///
+    /// It performs the same checks as [`PcapWriter::with_header`](super::PcapWriter::with_header) and the other
+    /// writers, without needing a sink: a header which passes it can't make them fail, barring I/O errors.
+    /// The magic number is derived from the endianness and the resolution, so they are always consistent.
+    ///
+    /// # Errors
+    /// [`PcapError::InvalidConfiguration`]: the `version_major` isn't 2, the snaplen is 0, or the datalink looks like
+    /// a known datalink byte swapped, which the readers take for [`PcapQuirk::SwappedSnaplenDatalink`].
+    pub fn validate(&self) -> PcapResult<()> {
+        if self.version_major != 2 {
+            return Err(PcapError::InvalidConfiguration("PcapHeader: version_major != 2"));
+        }
+        if self.snaplen == 0 {
+            return Err(PcapError::InvalidConfiguration("PcapHeader: snaplen == 0"));
+        }
+        if is_swapped_datalink(self.datalink) {
+            return Err(PcapError::InvalidConfiguration("PcapHeader: datalink is a known datalink byte swapped"));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the length in bytes of the header preceding each record of the pcap.
+    ///
+    /// All the variants currently supported use the standard record header of [`RECORD_HEADER_LEN`](super::RECORD_HEADER_LEN) bytes.
+    pub fn record_header_len(&self) -> usize {
+        RECORD_HEADER_LEN
+    }
+}
+
+/// Format of a pcap, given by its magic number.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct PcapFormat {
+    /// Endianness of the pcap
+    pub endianness: Endianness,
+    /// Timestamp resolution of the pcap
+    pub ts_resolution: TsResolution,
 }
 
+impl PcapFormat {
+    /// Returns the magic number of the format, as read in big endian.
+    pub fn magic(self) -> u32 {
+        let magic: u32 = match self.ts_resolution {
+            TsResolution::MicroSecond => 0xA1B2C3D4,
+            TsResolution::NanoSecond => 0xA1B23C4D,
+        };
+
+        match self.endianness {
+            Endianness::Big => magic,
+            Endianness::Little => magic.swap_bytes(),
+        }
+    }
+}
+
+/// Known defects of some pcap writers, that can be fixed when reading.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PcapQuirk {
+    /// The snaplen and the datalink of the global header are byte swapped relative to the other fields
+    SwappedSnaplenDatalink,
+}
+
+/// Returns true if the datalink doesn't fit in 16 bits and is a known datalink once byte swapped, i.e. looks like
+/// [`PcapQuirk::SwappedSnaplenDatalink`].
+pub(crate) fn is_swapped_datalink(datalink: DataLink) -> bool {
+    let datalink = u32::from(datalink);
+    datalink > 0xFFFF && !matches!(DataLink::from(datalink.swap_bytes()), DataLink::Unknown(_))
+}
+
+/// Returns the timestamp resolution and the endianness corresponding to a magic number read in big endian.
+pub(crate) fn format_from_magic(magic_number: u32) -> PcapResult<(TsResolution, Endianness)> {
+    match magic_number {
+        0xA1B2C3D4 => Ok((TsResolution::MicroSecond, Endianness::Big)),
+        0xA1B23C4D => Ok((TsResolution::NanoSecond, Endianness::Big)),
+        0xD4C3B2A1 => Ok((TsResolution::MicroSecond, Endianness::Little)),
+        0x4D3CB2A1 => Ok((TsResolution::NanoSecond, Endianness::Little)),
+        _ => match UNSUPPORTED_MAGICS.iter().find(|(magic, _)| *magic == magic_number) {
+            Some((_, name)) => Err(PcapError::UnsupportedVariant(name)),
+            None => Err(PcapError::BadMagic),
+        },
+    }
+}
+
+/// Magic numbers (read in big endian) of known formats that are not supported, with their name.
+///
+/// Some other variants (Nokia IPSO, AIX) use the standard magic numbers and can't be recognized by them.
+const UNSUPPORTED_MAGICS: &[(u32, &str)] = &[
+    (0xA1B2CD34, "Kuznetzov modified pcap"),
+    (0x34CDB2A1, "Kuznetzov modified pcap"),
+    (0x1C0001AC, "IXIA hardware pcap"),
+    (0xAC01001C, "IXIA hardware pcap"),
+    (0x1C0001AB, "IXIA software pcap"),
+    (0xAB01001C, "IXIA software pcap"),
+    (0xA12B3C4D, "Navtel pcap"),
+    (0x4D3C2BA1, "Navtel pcap"),
+    (0x0A0D0D0A, "pcapng file"),
+];
+
 /// Creates a new [`PcapHeader`] with these parameters:
 ///
 /// ```rust,ignore
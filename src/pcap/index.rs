@@ -0,0 +1,187 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fmt::Debug;
+use std::hash::Hasher;
+use std::io::{Read, Seek};
+
+use super::PcapReader;
+use crate::errors::*;
+
+
+/// Sparse index of the packet positions of a pcap.
+///
+/// The position of one packet every `stride` packets is stored, the other ones are found by
+/// scanning forward from the nearest indexed packet.
+///
+/// The index is tied to the capture it was built from by an identity hash of its global header and first packet,
+/// which is checked by every lookup.
+#[derive(Clone, Eq, PartialEq)]
+pub struct PcapIndex {
+    stride: usize,
+    offsets: Vec<u64>,
+    nb_packets: u64,
+    end_offset: u64,
+    identity: u64,
+}
+
+impl PcapIndex {
+    /// Builds the index of a pcap, storing the position of one packet every `stride` packets.
+    ///
+    /// The whole pcap is read, then the reader is restored to its previous position.
+    ///
+    /// # Panics
+    /// `stride` is 0.
+    pub fn build<R: Read + Seek>(reader: &mut PcapReader<R>, stride: usize) -> PcapResult<Self> {
+        assert!(stride > 0, "The stride of a PcapIndex can't be 0");
+
+        let old_pos = reader.save_position()?;
+        let identity = identity_of(reader)?;
+
+        reader.rewind()?;
+        let mut offsets = vec![];
+        let mut nb_packets = 0;
+        // The end of the last packet, a record cut and discarded by the truncation policy isn't a packet
+        let end_offset = loop {
+            let pos = reader.position().map_err(PcapError::IoError)?;
+            match reader.next_raw_packet() {
+                Some(packet) => packet?,
+                None => break pos,
+            };
+
+            if nb_packets % stride as u64 == 0 {
+                offsets.push(pos);
+            }
+            nb_packets += 1;
+        };
+
+        reader.restore_position(old_pos)?;
+
+        Ok(PcapIndex { stride, offsets, nb_packets, end_offset, identity })
+    }
+
+    /// Returns the stride of the index.
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// Returns the number of packets in the indexed pcap.
+    pub fn nb_packets(&self) -> u64 {
+        self.nb_packets
+    }
+
+    /// Returns the offset of the end of the last packet.
+    pub(crate) fn end_offset(&self) -> u64 {
+        self.end_offset
+    }
+
+    /// Returns the offset of the packet number `packet_no` (starting at 0), or [`None`] if the pcap has less packets.
+    ///
+    /// On success, the reader is positioned on the packet.
+    ///
+    /// # Errors
+    /// The reader doesn't read the pcap the index was built from.
+    ///
+    /// The underlying reader can't be read or seeked.
+    pub fn offset_of<R: Read + Seek>(&self, reader: &mut PcapReader<R>, packet_no: u64) -> PcapResult<Option<u64>> {
+        self.check_identity(reader)?;
+
+        if packet_no >= self.nb_packets {
+            return Ok(None);
+        }
+
+        let stride = self.stride as u64;
+        reader.seek_to(self.offsets[(packet_no / stride) as usize])?;
+        if reader.skip_packets(packet_no % stride)? < packet_no % stride {
+            return Err(PcapError::IndexMismatch);
+        }
+
+        reader.position().map(Some).map_err(PcapError::IoError)
+    }
+
+    /// Returns the number (starting at 0) of the packet starting at `offset`, or [`None`] if the offset is past the last packet.
+    ///
+    /// On success, the reader is positioned on the packet.
+    ///
+    /// # Errors
+    /// The reader doesn't read the pcap the index was built from.
+    ///
+    /// The offset isn't the start of a packet.
+    ///
+    /// The underlying reader can't be read or seeked.
+    pub fn packet_at_offset<R: Read + Seek>(&self, reader: &mut PcapReader<R>, offset: u64) -> PcapResult<Option<u64>> {
+        self.check_identity(reader)?;
+
+        if offset >= self.end_offset {
+            return Ok(None);
+        }
+
+        // Nearest indexed packet before the offset
+        let entry = match self.offsets.partition_point(|&entry_offset| entry_offset <= offset) {
+            0 => return Err(PcapError::NotARecordBoundary(offset)),
+            i => i - 1,
+        };
+
+        reader.seek_to(self.offsets[entry])?;
+        for packet_no in entry as u64 * self.stride as u64.. {
+            let pos = reader.position().map_err(PcapError::IoError)?;
+            if pos == offset {
+                return Ok(Some(packet_no));
+            }
+            if pos > offset {
+                break;
+            }
+
+            reader.next_raw_packet().ok_or(PcapError::IncompleteBuffer)??;
+        }
+
+        Err(PcapError::NotARecordBoundary(offset))
+    }
+
+    fn check_identity<R: Read + Seek>(&self, reader: &mut PcapReader<R>) -> PcapResult<()> {
+        if identity_of(reader)? != self.identity {
+            return Err(PcapError::IndexMismatch);
+        }
+
+        Ok(())
+    }
+}
+
+impl Debug for PcapIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PcapIndex")
+            .field("stride", &self.stride)
+            .field("nb_offsets", &self.offsets.len())
+            .field("nb_packets", &self.nb_packets)
+            .field("end_offset", &self.end_offset)
+            .field("identity", &self.identity)
+            .finish()
+    }
+}
+
+/// Hashes the global header and the first packet of the pcap, restoring the position of the reader.
+fn identity_of<R: Read + Seek>(reader: &mut PcapReader<R>) -> PcapResult<u64> {
+    let old_pos = reader.save_position()?;
+    reader.rewind()?;
+    let header = reader.header();
+
+    let mut hasher = DefaultHasher::new();
+    hasher.write_u16(header.version_major);
+    hasher.write_u16(header.version_minor);
+    hasher.write_i32(header.ts_correction);
+    hasher.write_u32(header.ts_accuracy);
+    hasher.write_u32(header.snaplen);
+    hasher.write_u32(header.datalink.into());
+    hasher.write_u8(header.ts_resolution as u8);
+    hasher.write_u8(header.endianness as u8);
+
+    if let Some(packet) = reader.next_raw_packet() {
+        let packet = packet?;
+        hasher.write_u32(packet.ts_sec);
+        hasher.write_u32(packet.ts_frac);
+        hasher.write_u32(packet.incl_len);
+        hasher.write_u32(packet.orig_len);
+        hasher.write(&packet.data);
+    }
+    reader.restore_position(old_pos)?;
+
+    Ok(hasher.finish())
+}
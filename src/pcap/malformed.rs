@@ -0,0 +1,174 @@
+use std::fmt::Debug;
+
+use super::{PcapHeader, PcapPacketHeader};
+use crate::common::DebugBytes;
+use crate::Endianness;
+
+
+/// Builds pcaps malformed on purpose, e.g. to test how a packet-processing service handles them.
+///
+/// The global headers and the records are written as given, in the order of the calls, without any validation.
+/// The corruptions of a record are set on its [`MalformedRecord`] and can be combined, and the whole pcap can be
+/// cut at an exact offset.
+///
+/// # Example
+/// ```rust
+/// use pcap_file::pcap::{MalformedPcapBuilder, MalformedRecord, PcapHeader, PcapReader};
+///
+/// let pcap = MalformedPcapBuilder::new(PcapHeader::default())
+///     .record(MalformedRecord::new(1, 0, &[1, 2, 3, 4]))
+///     .record(MalformedRecord::new(2, 0, &[5, 6, 7, 8]).with_ts_frac(1_000_000))
+///     .duplicate_global_header()
+///     .record(MalformedRecord::new(3, 0, &[9; 10]).with_incl_len(100).with_swapped_endianness())
+///     .into_bytes();
+///
+/// let mut pcap_reader = PcapReader::new(&pcap[..]).unwrap();
+/// assert!(pcap_reader.next_packet().unwrap().is_ok());
+/// assert!(pcap_reader.next_packet().unwrap().is_err());
+/// ```
+#[derive(Clone)]
+pub struct MalformedPcapBuilder {
+    header: PcapHeader,
+    bytes: Vec<u8>,
+    truncate_at: Option<usize>,
+}
+
+impl MalformedPcapBuilder {
+    /// Creates a new [`MalformedPcapBuilder`], writing the global header, which isn't validated.
+    ///
+    /// The records are written with the endianness of the header.
+    pub fn new(header: PcapHeader) -> Self {
+        MalformedPcapBuilder { header, bytes: vec![], truncate_at: None }.global_header(header)
+    }
+
+    /// Writes a record.
+    pub fn record(mut self, record: MalformedRecord) -> Self {
+        let endianness = match record.swapped {
+            false => self.header.endianness,
+            true => self.header.endianness.opposite(),
+        };
+
+        let PcapPacketHeader { ts_sec, ts_frac, incl_len, orig_len } = record.header;
+        for field in [ts_sec, ts_frac, incl_len, orig_len] {
+            let field = match endianness {
+                Endianness::Big => field.to_be_bytes(),
+                Endianness::Little => field.to_le_bytes(),
+            };
+            self.bytes.extend_from_slice(&field);
+        }
+        self.bytes.extend_from_slice(&record.data);
+
+        self
+    }
+
+    /// Writes a global header in the middle of the pcap, like in pcaps concatenated with `cat`.
+    ///
+    /// The records following it are still written with the endianness of the first global header.
+    pub fn global_header(mut self, header: PcapHeader) -> Self {
+        // Can unwrap because writing to a Vec can't fail
+        header.write_to(&mut self.bytes).unwrap();
+        self
+    }
+
+    /// Writes the first global header again, in the middle of the pcap.
+    pub fn duplicate_global_header(self) -> Self {
+        let header = self.header;
+        self.global_header(header)
+    }
+
+    /// Cuts the pcap at `offset` bytes from its start, whatever is written after the call.
+    ///
+    /// Nothing is cut if the pcap is shorter.
+    pub fn truncate_at(mut self, offset: usize) -> Self {
+        self.truncate_at = Some(offset);
+        self
+    }
+
+    /// Returns the number of bytes written so far, i.e. the offset of the next record, before any truncation.
+    pub fn offset(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Returns the bytes of the pcap.
+    pub fn into_bytes(mut self) -> Vec<u8> {
+        if let Some(offset) = self.truncate_at {
+            self.bytes.truncate(offset);
+        }
+
+        self.bytes
+    }
+}
+
+impl Debug for MalformedPcapBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MalformedPcapBuilder")
+            .field("header", &self.header)
+            .field("bytes", &DebugBytes(&self.bytes))
+            .field("truncate_at", &self.truncate_at)
+            .finish()
+    }
+}
+
+
+/// Record written by a [`MalformedPcapBuilder`], whose corruptions can be combined.
+///
+/// It starts as a valid record, whose `incl_len` and `orig_len` are the length of its data.
+#[derive(Clone)]
+pub struct MalformedRecord {
+    header: PcapPacketHeader,
+    data: Vec<u8>,
+    swapped: bool,
+}
+
+impl MalformedRecord {
+    /// Creates a new valid [`MalformedRecord`] from its timestamp, in the resolution of the pcap, and its data.
+    pub fn new(ts_sec: u32, ts_frac: u32, data: &[u8]) -> Self {
+        let len = data.len() as u32;
+        let header = PcapPacketHeader { ts_sec, ts_frac, incl_len: len, orig_len: len };
+
+        MalformedRecord { header, data: data.to_vec(), swapped: false }
+    }
+
+    /// Sets the fractional part of the timestamp, e.g. to a second or more.
+    pub fn with_ts_frac(mut self, ts_frac: u32) -> Self {
+        self.header.ts_frac = ts_frac;
+        self
+    }
+
+    /// Sets the `incl_len`, whatever the length of the data.
+    ///
+    /// The data are written as given, so the next records are decoded from the wrong offset.
+    pub fn with_incl_len(mut self, incl_len: u32) -> Self {
+        self.header.incl_len = incl_len;
+        self
+    }
+
+    /// Sets the `orig_len`, e.g. smaller than the `incl_len`.
+    pub fn with_orig_len(mut self, orig_len: u32) -> Self {
+        self.header.orig_len = orig_len;
+        self
+    }
+
+    /// Keeps only the first `len` bytes of the data, without changing the `incl_len`, so the record has fewer
+    /// bytes than it declares. At the end of the pcap, it is a record cut by the end of the capture.
+    pub fn with_short_data(mut self, len: usize) -> Self {
+        self.data.truncate(len);
+        self
+    }
+
+    /// Writes the header of the record with the opposite endianness of the pcap.
+    pub fn with_swapped_endianness(mut self) -> Self {
+        self.swapped = true;
+        self
+    }
+}
+
+impl Debug for MalformedRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MalformedRecord")
+            .field("header", &self.header)
+            .field("data", &DebugBytes(&self.data))
+            .field("swapped", &self.swapped)
+            .finish()
+    }
+}
@@ -0,0 +1,349 @@
+//! Merges several pcaps into one, or reads them together, ordering their packets by timestamp.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fmt::Debug;
+use std::io::{Read, Write};
+use std::time::Duration;
+
+use super::{PcapPacket, PcapReader, PcapWriter};
+use crate::errors::*;
+
+
+/// Correction of the clock of an input of [`merge`], e.g. to merge the captures of hosts whose clocks differ.
+///
+/// The corrected timestamp of a packet is `timestamp + offset_nanos + elapsed * drift_ppb / 10^9`,
+/// `elapsed` being the time between the first packet of the input and the packet, before correction.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ClockCorrection {
+    /// Nanoseconds added to the timestamps, can be negative
+    pub offset_nanos: i64,
+    /// Nanoseconds added per second elapsed since the first packet of the input, can be negative
+    pub drift_ppb: i64,
+}
+
+impl ClockCorrection {
+    /// Creates a [`ClockCorrection`] adding a fixed offset, which can be negative, to the timestamps.
+    pub fn offset(offset_nanos: i64) -> Self {
+        ClockCorrection { offset_nanos, drift_ppb: 0 }
+    }
+
+    /// Creates a [`ClockCorrection`] adding an offset and correcting a drift of the clock, see [`ClockCorrection`].
+    pub fn linear(offset_nanos: i64, drift_ppb: i64) -> Self {
+        ClockCorrection { offset_nanos, drift_ppb }
+    }
+
+    /// Returns the corrected timestamp in nanoseconds, which can be negative, or None if it overflows.
+    fn apply(&self, timestamp: Duration, first: Duration) -> Option<i128> {
+        let timestamp = i128::try_from(timestamp.as_nanos()).ok()?;
+        let first = i128::try_from(first.as_nanos()).ok()?;
+        let drift = timestamp.checked_sub(first)?.checked_mul(self.drift_ppb as i128)? / 1_000_000_000;
+
+        timestamp.checked_add(self.offset_nanos as i128)?.checked_add(drift)
+    }
+}
+
+/// What [`merge`] does with a timestamp corrected to before the Unix epoch.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum NegativeTimestamp {
+    /// Return an error
+    #[default]
+    Error,
+    /// Replace the timestamp by the Unix epoch
+    Clamp,
+}
+
+/// Statistics of a [`merge`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct MergeStats {
+    /// Number of packets written
+    pub nb_packets: u64,
+    /// Statistics of each input, in the order of the inputs
+    pub inputs: Vec<MergeInputStats>,
+}
+
+/// Statistics of an input of a [`merge`].
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct MergeInputStats {
+    /// Number of packets read
+    pub nb_packets: u64,
+    /// Smallest corrected timestamp
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::nanos"))]
+    pub min_timestamp: Option<Duration>,
+    /// Biggest corrected timestamp
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::nanos"))]
+    pub max_timestamp: Option<Duration>,
+    /// Number of timestamps replaced by the Unix epoch, see [`NegativeTimestamp::Clamp`]
+    pub nb_clamped: u64,
+}
+
+/// Merges the packets of several pcaps into a writer, in the order of their corrected timestamps.
+///
+/// The inputs are read in a streaming way, only one packet per input is kept in memory. The timestamps of each input
+/// are corrected by its [`ClockCorrection`] before being ordered, and the packets are written with their corrected
+/// timestamps. The packets with the same timestamp are written in the order of the inputs.
+///
+/// The inputs must be ordered by timestamp, as the merge only compares the next packet of each input.
+///
+/// # Errors
+/// The inputs have different datalinks, an input or the writer returns an error,
+/// or a corrected timestamp overflows or is before the Unix epoch with [`NegativeTimestamp::Error`].
+///
+/// # Example
+/// ```rust
+/// use std::time::Duration;
+///
+/// use pcap_file::pcap::{merge, ClockCorrection, NegativeTimestamp, PcapPacket, PcapReader, PcapWriter};
+///
+/// let mut host_a = PcapWriter::new(Vec::new()).unwrap();
+/// host_a.write_packet(&PcapPacket::new(Duration::from_millis(1000), 1, &[1])).unwrap();
+/// let mut host_b = PcapWriter::new(Vec::new()).unwrap();
+/// host_b.write_packet(&PcapPacket::new(Duration::from_millis(1010), 1, &[2])).unwrap();
+/// let (host_a, host_b) = (host_a.into_writer(), host_b.into_writer());
+///
+/// // The clock of the host B is 37ms ahead
+/// let inputs = vec![
+///     (PcapReader::new(&host_a[..]).unwrap(), ClockCorrection::default()),
+///     (PcapReader::new(&host_b[..]).unwrap(), ClockCorrection::offset(-37_000_000)),
+/// ];
+/// let mut writer = PcapWriter::new(Vec::new()).unwrap();
+/// let stats = merge(inputs, &mut writer, NegativeTimestamp::Error).unwrap();
+///
+/// assert_eq!(stats.inputs[1].min_timestamp, Some(Duration::from_millis(973)));
+/// ```
+pub fn merge<R: Read, W: Write>(
+    inputs: Vec<(PcapReader<R>, ClockCorrection)>,
+    writer: &mut PcapWriter<W>,
+    negative: NegativeTimestamp,
+) -> PcapResult<MergeStats> {
+    if inputs.windows(2).any(|inputs| inputs[0].0.header().datalink != inputs[1].0.header().datalink) {
+        return Err(PcapError::InvalidConfiguration("merge: the inputs have different datalinks"));
+    }
+
+    let mut stats = MergeStats { nb_packets: 0, inputs: vec![MergeInputStats::default(); inputs.len()] };
+    let mut inputs: Vec<_> = inputs
+        .into_iter()
+        .map(|(reader, correction)| MergeInput { reader, correction, first: None })
+        .collect();
+    let mut heads = vec![None; inputs.len()];
+    let mut queue = BinaryHeap::new();
+
+    for (idx, input) in inputs.iter_mut().enumerate() {
+        if let Some(packet) = input.next_packet(negative, &mut stats.inputs[idx])? {
+            queue.push(Reverse((packet.timestamp, idx)));
+            heads[idx] = Some(packet);
+        }
+    }
+
+    while let Some(Reverse((_, idx))) = queue.pop() {
+        // Can unwrap because the queue only contains the inputs with a head
+        let packet = heads[idx].take().unwrap();
+        writer.write_packet(&packet)?;
+        stats.nb_packets += 1;
+
+        if let Some(packet) = inputs[idx].next_packet(negative, &mut stats.inputs[idx])? {
+            queue.push(Reverse((packet.timestamp, idx)));
+            heads[idx] = Some(packet);
+        }
+    }
+
+    Ok(stats)
+}
+
+/// Identifier of a source of a [`MultiCapture`], its index in the readers given to [`MultiCapture::new`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct SourceId(pub usize);
+
+/// Reads several pcaps together, returning their packets in the order of their timestamps, each one tagged with the
+/// [`SourceId`] of its pcap, e.g. to compare the captures of both sides of a firewall.
+///
+/// It is the reader counterpart of [`merge`], without clock correction: only one packet per source is kept in memory,
+/// and the packets with the same timestamp are returned in the order of the sources. The sources must be ordered by
+/// timestamp and can have different datalinks.
+///
+/// A source at its end is finished while the other ones keep being read. An error of a source is returned once,
+/// the source is then finished too.
+///
+/// # Example
+/// ```rust
+/// use std::time::Duration;
+///
+/// use pcap_file::pcap::{MultiCapture, PcapPacket, PcapReader, PcapWriter, SourceId};
+///
+/// let mut side_a = PcapWriter::new(Vec::new()).unwrap();
+/// side_a.write_packet(&PcapPacket::new(Duration::from_millis(20), 1, &[1])).unwrap();
+/// let mut side_b = PcapWriter::new(Vec::new()).unwrap();
+/// side_b.write_packet(&PcapPacket::new(Duration::from_millis(10), 1, &[2])).unwrap();
+/// let (side_a, side_b) = (side_a.into_writer(), side_b.into_writer());
+///
+/// let mut capture = MultiCapture::new(vec![PcapReader::new(&side_a[..]).unwrap(), PcapReader::new(&side_b[..]).unwrap()]);
+///
+/// let (source, packet) = capture.next_packet().unwrap().unwrap();
+/// assert_eq!((source, &packet.data[..]), (SourceId(1), &[2][..]));
+/// assert!(capture.is_finished(SourceId(1)));
+/// ```
+pub struct MultiCapture<R: Read> {
+    inputs: Vec<MergeInput<R>>,
+    stats: Vec<MergeInputStats>,
+    heads: Vec<Option<PcapPacket<'static>>>,
+    finished: Vec<bool>,
+    /// Sources whose first packet isn't read yet
+    unread: Vec<usize>,
+    queue: BinaryHeap<Reverse<(Duration, usize)>>,
+    /// Error of a source, returned by the next call
+    error: Option<PcapError>,
+}
+
+impl<R: Read> MultiCapture<R> {
+    /// Creates a new [`MultiCapture`] reading the given pcaps, their [`SourceId`] is their index in `readers`.
+    pub fn new(readers: Vec<PcapReader<R>>) -> Self {
+        let nb_sources = readers.len();
+        let inputs = readers
+            .into_iter()
+            .map(|reader| MergeInput { reader, correction: ClockCorrection::default(), first: None })
+            .collect();
+
+        MultiCapture {
+            inputs,
+            stats: vec![MergeInputStats::default(); nb_sources],
+            heads: vec![None; nb_sources],
+            finished: vec![false; nb_sources],
+            unread: (0..nb_sources).rev().collect(),
+            queue: BinaryHeap::new(),
+            error: None,
+        }
+    }
+
+    /// Returns the next packet in timestamp order with the [`SourceId`] of its pcap.
+    pub fn next_packet(&mut self) -> Option<PcapResult<(SourceId, PcapPacket<'static>)>> {
+        if let Some(e) = self.error.take() {
+            return Some(Err(e));
+        }
+
+        while let Some(idx) = self.unread.pop() {
+            if let Err(e) = self.read_head(idx) {
+                return Some(Err(e));
+            }
+        }
+
+        let Reverse((_, idx)) = self.queue.pop()?;
+        // Can unwrap because the queue only contains the sources with a head
+        let packet = self.heads[idx].take().unwrap();
+
+        // Read the next packet of the source right away so that its end is known, its error is returned next
+        if let Err(e) = self.read_head(idx) {
+            self.error = Some(e);
+        }
+
+        Some(Ok((SourceId(idx), packet)))
+    }
+
+    /// Reads the next packet of a source into its head, or marks it finished.
+    fn read_head(&mut self, idx: usize) -> PcapResult<()> {
+        match self.inputs[idx].next_packet(NegativeTimestamp::Error, &mut self.stats[idx]) {
+            Ok(Some(packet)) => {
+                self.queue.push(Reverse((packet.timestamp, idx)));
+                self.heads[idx] = Some(packet);
+            },
+            Ok(None) => self.finished[idx] = true,
+            Err(e) => {
+                self.finished[idx] = true;
+                return Err(e);
+            },
+        }
+
+        Ok(())
+    }
+
+    /// Returns the number of sources.
+    pub fn nb_sources(&self) -> usize {
+        self.inputs.len()
+    }
+
+    /// Returns the progress of a source: the number of packets read from it and their timestamps range.
+    ///
+    /// The next packet of the source, waiting to be returned, is counted as it is already read.
+    ///
+    /// # Panics
+    /// The source doesn't exist.
+    pub fn progress(&self, source: SourceId) -> &MergeInputStats {
+        &self.stats[source.0]
+    }
+
+    /// Returns true if all the packets of a source were returned, or if it returned an error.
+    ///
+    /// # Panics
+    /// The source doesn't exist.
+    pub fn is_finished(&self, source: SourceId) -> bool {
+        self.finished[source.0] && self.heads[source.0].is_none()
+    }
+
+    /// Returns the reader of a source, e.g. to get its header.
+    ///
+    /// # Panics
+    /// The source doesn't exist.
+    pub fn reader(&self, source: SourceId) -> &PcapReader<R> {
+        &self.inputs[source.0].reader
+    }
+
+    /// Consumes [`Self`], returning the readers in the order of their [`SourceId`].
+    pub fn into_readers(self) -> Vec<PcapReader<R>> {
+        self.inputs.into_iter().map(|input| input.reader).collect()
+    }
+}
+
+impl<R: Read + Debug> Debug for MultiCapture<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let readers: Vec<_> = self.inputs.iter().map(|input| &input.reader).collect();
+        f.debug_struct("MultiCapture")
+            .field("readers", &readers)
+            .field("stats", &self.stats)
+            .field("finished", &self.finished)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Input of a [`merge`] or a [`MultiCapture`].
+struct MergeInput<R: Read> {
+    reader: PcapReader<R>,
+    correction: ClockCorrection,
+    /// Timestamp of the first packet, before correction
+    first: Option<Duration>,
+}
+
+impl<R: Read> MergeInput<R> {
+    /// Reads the next packet and corrects its timestamp.
+    fn next_packet(&mut self, negative: NegativeTimestamp, stats: &mut MergeInputStats) -> PcapResult<Option<PcapPacket<'static>>> {
+        let mut packet = match self.reader.next_packet() {
+            Some(packet) => packet?.into_owned(),
+            None => return Ok(None),
+        };
+
+        let first = *self.first.get_or_insert(packet.timestamp);
+        let corrected = self
+            .correction
+            .apply(packet.timestamp, first)
+            .ok_or(PcapError::InvalidField("merge: corrected timestamp out of range"))?;
+
+        let corrected = match (corrected, negative) {
+            (0.., _) => corrected,
+            (_, NegativeTimestamp::Error) => return Err(PcapError::InvalidField("merge: corrected timestamp before the Unix epoch")),
+            (_, NegativeTimestamp::Clamp) => {
+                stats.nb_clamped += 1;
+                0
+            },
+        };
+
+        let secs =
+            u64::try_from(corrected / 1_000_000_000).map_err(|_| PcapError::InvalidField("merge: corrected timestamp out of range"))?;
+        packet.timestamp = Duration::new(secs, (corrected % 1_000_000_000) as u32);
+
+        stats.nb_packets += 1;
+        stats.min_timestamp = Some(stats.min_timestamp.map_or(packet.timestamp, |min| min.min(packet.timestamp)));
+        stats.max_timestamp = Some(stats.max_timestamp.map_or(packet.timestamp, |max| max.max(packet.timestamp)));
+
+        Ok(Some(packet))
+    }
+}
@@ -1,13 +1,45 @@
 //! Contains the Pcap parser, reader and writer
 
+mod adapters;
+mod aligned;
+mod buf_reader;
+#[cfg(feature = "bytes")]
+mod bytes_reader;
+mod canonical;
+mod check;
+mod copy;
+mod decoder;
+mod framing;
 mod header;
+mod index;
+mod malformed;
+mod merge;
 mod packet;
 mod parser;
 mod reader;
+mod rotating;
+mod stats;
 mod writer;
+mod writer_state;
 
+pub use adapters::*;
+pub use aligned::*;
+pub use buf_reader::*;
+#[cfg(feature = "bytes")]
+pub use bytes_reader::*;
+pub use canonical::*;
+pub use check::*;
+pub use copy::*;
+pub use decoder::*;
+pub use framing::*;
 pub use header::*;
+pub use index::*;
+pub use malformed::*;
+pub use merge::*;
 pub use packet::*;
 pub use parser::*;
 pub use reader::*;
+pub use rotating::*;
+pub use stats::*;
 pub use writer::*;
+pub use writer_state::*;
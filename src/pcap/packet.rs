@@ -1,19 +1,24 @@
 use std::borrow::Cow;
+use std::fmt::Debug;
 use std::io::Write;
 use std::time::Duration;
 
-use byteorder_slice::byteorder::WriteBytesExt;
 use byteorder_slice::result::ReadSlice;
-use byteorder_slice::ByteOrder;
+use byteorder_slice::{BigEndian, ByteOrder, LittleEndian};
 use derive_into_owned::IntoOwned;
 
+use super::framing::split_record;
+use crate::common::DebugBytes;
 use crate::errors::*;
-use crate::TsResolution;
+use crate::pcap::PcapHeader;
+use crate::{DataLink, Endianness, TsResolution};
 
 /// Pcap packet.
 ///
 /// The payload can be owned or borrowed.
-#[derive(Clone, Debug, IntoOwned)]
+///
+/// Its [`Debug`] representation only shows the first bytes of the data.
+#[derive(Clone, IntoOwned)]
 pub struct PcapPacket<'a> {
     /// Timestamp EPOCH of the packet with a nanosecond resolution
     pub timestamp: Duration,
@@ -35,15 +40,64 @@ impl<'a> PcapPacket<'a> {
     }
 
     /// Parses a new borrowed [`PcapPacket`] from a slice.
-    pub fn from_slice<B: ByteOrder>(slice: &'a [u8], ts_resolution: TsResolution, snap_len: u32) -> PcapResult<(&'a [u8], PcapPacket<'a>)> {
-        let (rem, raw_packet) = RawPcapPacket::from_slice::<B>(slice)?;
-        let s = Self::try_from_raw_packet(raw_packet, ts_resolution, snap_len)?;
+    ///
+    /// The endianness, the timestamp resolution and the snaplen are taken from the global header of the pcap,
+    /// so they can't mismatch the file. To decode a packet with other parameters, parse a [`RawPcapPacket`]
+    /// and call [`RawPcapPacket::try_into_pcap_packet`] explicitly.
+    pub fn from_slice(slice: &'a [u8], header: &PcapHeader) -> PcapResult<(&'a [u8], PcapPacket<'a>)> {
+        let header_len = header.record_header_len();
+        let (rem, raw_packet) = match header.endianness {
+            Endianness::Big => RawPcapPacket::from_slice_with_header_len::<BigEndian>(slice, header_len)?,
+            Endianness::Little => RawPcapPacket::from_slice_with_header_len::<LittleEndian>(slice, header_len)?,
+        };
+        let s = Self::try_from_raw_packet(raw_packet, header.ts_resolution, header.snaplen)?;
 
         Ok((rem, s))
     }
 
+    /// Returns the data of the packet after its link-layer header, i.e. starting at its network layer,
+    /// e.g. to give it to a protocol decoder.
+    ///
+    /// Returns [`None`] if the length of the link-layer header isn't fixed (see [`DataLink::header_len`])
+    /// or if the data is shorter than it.
+    pub fn network_data(&self, datalink: DataLink) -> Option<&[u8]> {
+        self.data.get(datalink.header_len()?..)
+    }
+
+    /// Parses the headers of the packet with [`etherparse`], from its link-layer header down to its transport layer.
+    ///
+    /// The Ethernet (including the VLAN tags) and Linux cooked (SLL) headers are parsed by `etherparse`. For the other
+    /// data links, the data after the link-layer header (see [`PcapPacket::network_data`]) is parsed as an IP packet.
+    ///
+    /// Returns [`None`] if the length of the link-layer header isn't fixed or if the data is shorter than it.
+    #[cfg(feature = "etherparse")]
+    pub fn parse_headers(&self, datalink: DataLink) -> Option<Result<etherparse::SlicedPacket<'_>, etherparse::err::packet::SliceError>> {
+        match datalink {
+            DataLink::ETHERNET => Some(etherparse::SlicedPacket::from_ethernet(&self.data)),
+            DataLink::LINUX_SLL => Some(etherparse::SlicedPacket::from_linux_sll(&self.data)),
+            _ => self.network_data(datalink).map(etherparse::SlicedPacket::from_ip),
+        }
+    }
+
     /// Writes a [`PcapPacket`] to a writer.
     pub fn write_to<W: Write, B: ByteOrder>(&self, writer: &mut W, ts_resolution: TsResolution, snap_len: u32) -> PcapResult<usize> {
+        let PcapPacketHeader { ts_sec, ts_frac, incl_len, orig_len } = self.record_header(ts_resolution)?;
+
+        // Validate the packet length //
+        if incl_len > snap_len {
+            return Err(PcapError::InvalidField("PcapPacket: incl_len > snap_len"));
+        }
+
+        if incl_len > orig_len {
+            return Err(PcapError::InvalidField("PcapPacket: incl_len > orig_len"));
+        }
+
+        write_record::<_, B>(writer, &PcapPacketHeader { ts_sec, ts_frac, incl_len, orig_len }, &self.data)
+    }
+
+    /// Returns the record header of the packet in the given timestamp resolution.
+    /// The lengths are not validated against the snaplen.
+    pub(crate) fn record_header(&self, ts_resolution: TsResolution) -> PcapResult<PcapPacketHeader> {
         // Transforms PcapPacket::ts into ts_sec and ts_frac //
         let ts_sec = self
             .timestamp
@@ -56,24 +110,51 @@ impl<'a> PcapPacket<'a> {
             ts_frac /= 1000;
         }
 
-        // Validate the packet length //
         let incl_len = self.data.len().try_into().map_err(|_| PcapError::InvalidField("PcapPacket: incl_len > u32::MAX"))?;
-        let orig_len = self.orig_len;
 
-        if incl_len > snap_len {
-            return Err(PcapError::InvalidField("PcapPacket: incl_len > snap_len"));
-        }
+        Ok(PcapPacketHeader { ts_sec, ts_frac, incl_len, orig_len: self.orig_len })
+    }
 
-        if incl_len > orig_len {
-            return Err(PcapError::InvalidField("PcapPacket: incl_len > orig_len"));
+    /// Writes a [`PcapPacket`] to the start of a slice, returns the number of bytes written.
+    ///
+    /// Returns [`PcapError::IncompleteBuffer`] without writing anything if the slice can't hold the packet.
+    pub fn write_to_slice<B: ByteOrder>(&self, buf: &mut [u8], ts_resolution: TsResolution, snap_len: u32) -> PcapResult<usize> {
+        if buf.len() < RECORD_HEADER_LEN + self.data.len() {
+            return Err(PcapError::IncompleteBuffer);
         }
 
-        let raw_packet = RawPcapPacket { ts_sec, ts_frac, incl_len, orig_len, data: Cow::Borrowed(&self.data[..]) };
+        self.write_to::<_, B>(&mut &mut buf[..], ts_resolution, snap_len)
+    }
 
-        raw_packet.write_to::<_, B>(writer)
+    /// Returns true if the data of the packet is shorter than its original length, i.e. the packet was snapped.
+    ///
+    /// A snapped packet isn't corrupted, even if its data is shorter than the minimum frame length of its datalink
+    /// (e.g. a metadata-only capture with a snaplen of 14 only keeps the Ethernet headers).
+    pub fn is_truncated(&self) -> bool {
+        self.data.len() < self.orig_len as usize
+    }
+
+    /// Returns the timestamp of the packet as a number of nanoseconds since EPOCH.
+    ///
+    /// An [`u128`] is used because it can hold any [`Duration`] without overflowing.
+    pub fn unix_nanos(&self) -> u128 {
+        self.timestamp.as_nanos()
+    }
+
+    /// Converts the payload of the packet into a [`bytes::Bytes`].
+    ///
+    /// An owned payload is converted without copy, a borrowed one is copied.
+    #[cfg(feature = "bytes")]
+    pub fn into_bytes(self) -> bytes::Bytes {
+        match self.data {
+            Cow::Borrowed(data) => bytes::Bytes::copy_from_slice(data),
+            Cow::Owned(data) => bytes::Bytes::from(data),
+        }
     }
 
     /// Tries to create a [`PcapPacket`] from a [`RawPcapPacket`].
+    ///
+    /// The timestamp resolution and the snaplen must be the ones of the pcap the packet comes from.
     pub fn try_from_raw_packet(raw: RawPcapPacket<'a>, ts_resolution: TsResolution, snap_len: u32) -> PcapResult<Self> {
         // Validate timestamps //
         let ts_sec = raw.ts_sec;
@@ -101,11 +182,68 @@ impl<'a> PcapPacket<'a> {
     }
 }
 
+impl Debug for PcapPacket<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PcapPacket")
+            .field("timestamp", &self.timestamp)
+            .field("orig_len", &self.orig_len)
+            .field("data", &DebugBytes(&self.data))
+            .finish()
+    }
+}
+
+
+/// What a reader or a writer does with a timestamp out of range once shifted by its epoch offset,
+/// see [`PcapReader::with_epoch_offset`](super::PcapReader::with_epoch_offset).
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum EpochShiftPolicy {
+    /// Return a [`PcapError::InvalidField`]
+    #[default]
+    Error,
+    /// Clamp the timestamp to the epoch, or to the biggest timestamp if it overflows
+    Clamp,
+}
+
+/// Adds `offset` seconds, which can be negative, to a timestamp.
+///
+/// Returns an error if the result is before the Unix epoch or overflows, unless the policy clamps it.
+pub(crate) fn shift_timestamp(timestamp: Duration, offset: i64, policy: EpochShiftPolicy) -> PcapResult<Duration> {
+    let shifted = match offset {
+        0.. => timestamp.checked_add(Duration::from_secs(offset as u64)).ok_or(Duration::MAX),
+        _ => timestamp.checked_sub(Duration::from_secs(offset.unsigned_abs())).ok_or(Duration::ZERO),
+    };
+
+    match (shifted, policy) {
+        (Ok(shifted), _) => Ok(shifted),
+        (Err(clamped), EpochShiftPolicy::Clamp) => Ok(clamped),
+        (Err(_), EpochShiftPolicy::Error) => {
+            Err(PcapError::InvalidField("PcapPacket: timestamp out of range after applying the epoch offset"))
+        },
+    }
+}
+
+
+/// Length in bytes of the standard header preceding the data of each packet of a pcap.
+pub const RECORD_HEADER_LEN: usize = 16;
+
+/// Writes a record, its header in the given endianness followed by its data, returns the number of bytes written.
+///
+/// All the packets written by the crate go through it. The header isn't validated.
+pub(crate) fn write_record<W: Write, B: ByteOrder>(writer: &mut W, header: &PcapPacketHeader, data: &[u8]) -> PcapResult<usize> {
+    // Write the header in one call, which matters for unbuffered writers
+    writer.write_all(&header.to_bytes::<B>()).map_err(PcapError::IoError)?;
+    writer.write_all(data).map_err(PcapError::IoError)?;
+
+    Ok(RECORD_HEADER_LEN + data.len())
+}
+
 
 /// Raw Pcap packet with its header and data.
 /// The fields of the packet are not validated.
 /// The payload can be owned or borrowed.
-#[derive(Clone, Debug, IntoOwned)]
+///
+/// Its [`Debug`] representation only shows the first bytes of the data.
+#[derive(Clone, IntoOwned)]
 pub struct RawPcapPacket<'a> {
     /// Timestamp in seconds
     pub ts_sec: u32,
@@ -121,44 +259,173 @@ pub struct RawPcapPacket<'a> {
 
 impl<'a> RawPcapPacket<'a> {
     /// Parses a new borrowed [`RawPcapPacket`] from a slice.
-    pub fn from_slice<B: ByteOrder>(mut slice: &'a [u8]) -> PcapResult<(&'a [u8], Self)> {
+    pub fn from_slice<B: ByteOrder>(slice: &'a [u8]) -> PcapResult<(&'a [u8], Self)> {
+        Self::from_slice_with_header_len::<B>(slice, RECORD_HEADER_LEN)
+    }
+
+    /// Parses a new borrowed [`RawPcapPacket`] from a slice whose record header is `header_len` bytes long.
+    ///
+    /// The bytes following the standard 16 bytes header are skipped.
+    pub(crate) fn from_slice_with_header_len<B: ByteOrder>(slice: &'a [u8], header_len: usize) -> PcapResult<(&'a [u8], Self)> {
+        debug_assert!(header_len >= RECORD_HEADER_LEN, "a record header is at least {RECORD_HEADER_LEN} bytes long");
+        let (rem, header, data) = split_record::<B>(slice, header_len)?;
+        // Can unwrap because the header is 16 bytes long
+        let (_, PcapPacketHeader { ts_sec, ts_frac, incl_len, orig_len }) = PcapPacketHeader::from_slice::<B>(header).unwrap();
+
+        Ok((rem, RawPcapPacket { ts_sec, ts_frac, incl_len, orig_len, data: Cow::Borrowed(data) }))
+    }
+
+    /// Writes a [`RawPcapPacket`] to a writer.
+    /// The fields of the packet are not validated.
+    pub fn write_to<W: Write, B: ByteOrder>(&self, writer: &mut W) -> PcapResult<usize> {
+        let RawPcapPacket { ts_sec, ts_frac, incl_len, orig_len, .. } = *self;
+        write_record::<_, B>(writer, &PcapPacketHeader { ts_sec, ts_frac, incl_len, orig_len }, &self.data)
+    }
+
+    /// Returns the timestamp of the packet as a number of nanoseconds since EPOCH.
+    ///
+    /// The fields are not validated, so a fractional part bigger than a second is simply added to the seconds.
+    /// An [`u128`] is used because it can hold any combination of the timestamp fields without overflowing.
+    pub fn unix_nanos(&self, ts_resolution: TsResolution) -> u128 {
+        let frac_nanos = match ts_resolution {
+            TsResolution::MicroSecond => self.ts_frac as u128 * 1000,
+            TsResolution::NanoSecond => self.ts_frac as u128,
+        };
+
+        self.ts_sec as u128 * 1_000_000_000 + frac_nanos
+    }
+
+    /// Tries to convert a [`RawPcapPacket`] into a [`PcapPacket`].
+    ///
+    /// The timestamp resolution and the snaplen must be the ones of the pcap the packet comes from.
+    pub fn try_into_pcap_packet(self, ts_resolution: TsResolution, snap_len: u32) -> PcapResult<PcapPacket<'a>> {
+        PcapPacket::try_from_raw_packet(self, ts_resolution, snap_len)
+    }
+}
+
+impl Debug for RawPcapPacket<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RawPcapPacket")
+            .field("ts_sec", &self.ts_sec)
+            .field("ts_frac", &self.ts_frac)
+            .field("incl_len", &self.incl_len)
+            .field("orig_len", &self.orig_len)
+            .field("data", &DebugBytes(&self.data))
+            .finish()
+    }
+}
+
+/// Header of a pcap packet, without its data.
+/// The fields of the header are not validated.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct PcapPacketHeader {
+    /// Timestamp in seconds
+    pub ts_sec: u32,
+    /// Nanosecond or microsecond part of the timestamp
+    pub ts_frac: u32,
+    /// Number of octets of the packet saved in file
+    pub incl_len: u32,
+    /// Original length of the packet on the wire
+    pub orig_len: u32,
+}
+
+impl PcapPacketHeader {
+    /// Parses a new [`PcapPacketHeader`] from a slice.
+    pub fn from_slice<B: ByteOrder>(slice: &[u8]) -> PcapResult<(&[u8], Self)> {
+        Self::from_slice_with_header_len::<B>(slice, RECORD_HEADER_LEN)
+    }
+
+    /// Parses a new [`PcapPacketHeader`] from a slice whose record header is `header_len` bytes long.
+    ///
+    /// The bytes following the standard 16 bytes header are skipped.
+    pub(crate) fn from_slice_with_header_len<B: ByteOrder>(mut slice: &[u8], header_len: usize) -> PcapResult<(&[u8], Self)> {
+        debug_assert!(header_len >= RECORD_HEADER_LEN, "a record header is at least {RECORD_HEADER_LEN} bytes long");
+
         // Check header length
-        if slice.len() < 16 {
+        if slice.len() < header_len {
             return Err(PcapError::IncompleteBuffer);
         }
 
-        // Read packet header  //
         // Can unwrap because the length check is done before
         let ts_sec = slice.read_u32::<B>().unwrap();
         let ts_frac = slice.read_u32::<B>().unwrap();
         let incl_len = slice.read_u32::<B>().unwrap();
         let orig_len = slice.read_u32::<B>().unwrap();
 
-        let pkt_len = incl_len as usize;
-        if slice.len() < pkt_len {
-            return Err(PcapError::IncompleteBuffer);
+        // Jump over the non standard part of the header
+        let rem = &slice[header_len - RECORD_HEADER_LEN..];
+
+        Ok((rem, PcapPacketHeader { ts_sec, ts_frac, incl_len, orig_len }))
+    }
+
+    /// Sets both timestamp fields of the header, `ts_frac` being in the given resolution.
+    ///
+    /// # Errors
+    /// `ts_frac` is a second or more in the resolution, e.g. nanoseconds given for a microsecond resolution.
+    pub fn with_timestamp(mut self, ts_sec: u32, ts_frac: u32, ts_resolution: TsResolution) -> PcapResult<Self> {
+        match ts_resolution {
+            TsResolution::MicroSecond if ts_frac >= 1_000_000 => {
+                return Err(PcapError::InvalidField("PcapPacketHeader: ts_frac >= 1_000_000 for a microsecond resolution"))
+            },
+            TsResolution::NanoSecond if ts_frac >= 1_000_000_000 => {
+                return Err(PcapError::InvalidField("PcapPacketHeader: ts_frac >= 1_000_000_000 for a nanosecond resolution"))
+            },
+            _ => {},
         }
 
-        let packet = RawPcapPacket { ts_sec, ts_frac, incl_len, orig_len, data: Cow::Borrowed(&slice[..pkt_len]) };
-        let rem = &slice[pkt_len..];
+        self.ts_sec = ts_sec;
+        self.ts_frac = ts_frac;
 
-        Ok((rem, packet))
+        Ok(self)
     }
 
-    /// Writes a [`RawPcapPacket`] to a writer.
-    /// The fields of the packet are not validated.
-    pub fn write_to<W: Write, B: ByteOrder>(&self, writer: &mut W) -> PcapResult<usize> {
-        writer.write_u32::<B>(self.ts_sec).map_err(PcapError::IoError)?;
-        writer.write_u32::<B>(self.ts_frac).map_err(PcapError::IoError)?;
-        writer.write_u32::<B>(self.incl_len).map_err(PcapError::IoError)?;
-        writer.write_u32::<B>(self.orig_len).map_err(PcapError::IoError)?;
-        writer.write_all(&self.data).map_err(PcapError::IoError)?;
+    /// Serializes the header in the given endianness.
+    pub(crate) fn to_bytes<B: ByteOrder>(self) -> [u8; RECORD_HEADER_LEN] {
+        let mut bytes = [0_u8; RECORD_HEADER_LEN];
+        B::write_u32(&mut bytes[0..4], self.ts_sec);
+        B::write_u32(&mut bytes[4..8], self.ts_frac);
+        B::write_u32(&mut bytes[8..12], self.incl_len);
+        B::write_u32(&mut bytes[12..16], self.orig_len);
 
-        Ok(16 + self.data.len())
+        bytes
     }
 
-    /// Tries to convert a [`RawPcapPacket`] into a [`PcapPacket`].
-    pub fn try_into_pcap_packet(self, ts_resolution: TsResolution, snap_len: u32) -> PcapResult<PcapPacket<'a>> {
-        PcapPacket::try_from_raw_packet(self, ts_resolution, snap_len)
+    /// Returns the header of a padding record with `incl_len` bytes of zeros, see [`PcapWriter::pad_to`](super::PcapWriter::pad_to).
+    ///
+    /// Both timestamp fields are `u32::MAX` and the `orig_len` is 0, which no valid packet has: the `ts_frac` is out of range
+    /// for both resolutions, so the readers validating the records reject it unless they skip the padding records.
+    pub fn padding(incl_len: u32) -> Self {
+        PcapPacketHeader { ts_sec: u32::MAX, ts_frac: u32::MAX, incl_len, orig_len: 0 }
+    }
+
+    /// Returns true if it is the header of a padding record, see [`PcapPacketHeader::padding`].
+    pub fn is_padding(&self) -> bool {
+        self.ts_sec == u32::MAX && self.ts_frac == u32::MAX && self.orig_len == 0
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nonstandard_record_header_len() {
+        // A 24 bytes record header: the standard 16 bytes followed by 8 bytes which are skipped
+        let header = PcapPacketHeader { ts_sec: 1, ts_frac: 2, incl_len: 3, orig_len: 4 };
+        let record = [&header.to_bytes::<LittleEndian>()[..], &[0xEE; 8], &[5, 6, 7, 8]].concat();
+
+        let (rem, parsed) = PcapPacketHeader::from_slice_with_header_len::<LittleEndian>(&record, 24).unwrap();
+        assert_eq!((parsed, rem), (header, &[5, 6, 7, 8][..]));
+
+        let (rem, packet) = RawPcapPacket::from_slice_with_header_len::<LittleEndian>(&record, 24).unwrap();
+        assert_eq!((packet.ts_sec, packet.ts_frac, packet.incl_len, packet.orig_len), (1, 2, 3, 4));
+        assert_eq!((&packet.data[..], rem), (&[5, 6, 7][..], &[8][..]));
+
+        // The whole record header is needed, not only its standard part
+        let cut_header = PcapPacketHeader::from_slice_with_header_len::<LittleEndian>(&record[..20], 24);
+        assert!(matches!(cut_header, Err(PcapError::IncompleteBuffer)));
+        let cut_data = RawPcapPacket::from_slice_with_header_len::<LittleEndian>(&record[..26], 24);
+        assert!(matches!(cut_data, Err(PcapError::IncompleteBuffer)));
     }
 }
@@ -1,9 +1,11 @@
-use byteorder_slice::{BigEndian, LittleEndian};
+use byteorder_slice::{BigEndian, ByteOrder, LittleEndian};
 
-use super::RawPcapPacket;
+use super::framing::padding_len;
+use super::header::format_from_magic;
+use super::{PcapFormat, PcapPacketHeader, PcapQuirk, RawPcapPacket, RECORD_HEADER_LEN};
 use crate::errors::*;
 use crate::pcap::{PcapHeader, PcapPacket};
-use crate::Endianness;
+use crate::{Endianness, TsResolution};
 
 
 /// Parses a Pcap from a slice of bytes.
@@ -40,9 +42,14 @@ use crate::Endianness;
 ///     }
 /// }
 /// ```
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct PcapParser {
     header: PcapHeader,
+    /// Global header as read, before any fix
+    raw_header: [u8; 24],
+    record_endianness: Endianness,
+    fixed_quirks: Vec<PcapQuirk>,
+    ignore_padding: bool,
 }
 
 impl PcapParser {
@@ -50,26 +57,225 @@ impl PcapParser {
     ///
     /// Returns the remainder and the parser.
     pub fn new(slice: &[u8]) -> PcapResult<(&[u8], PcapParser)> {
-        let (slice, header) = PcapHeader::from_slice(slice)?;
+        Self::with_quirks(slice, &[])
+    }
+
+    /// Creates a new [`PcapParser`], fixing the given quirks of the global header if they are detected,
+    /// see [`PcapHeader::from_slice_with_quirks`].
+    ///
+    /// Returns the remainder and the parser.
+    pub fn with_quirks<'a>(slice: &'a [u8], quirks: &[PcapQuirk]) -> PcapResult<(&'a [u8], PcapParser)> {
+        let (rem, header, fixed_quirks) = PcapHeader::from_slice_with_quirks(slice, quirks)?;
+
+        let parser = PcapParser {
+            header,
+            raw_header: raw_header(slice),
+            record_endianness: header.endianness,
+            fixed_quirks,
+            ignore_padding: false,
+        };
+
+        Ok((rem, parser))
+    }
+
+    /// Creates a new [`PcapParser`], ignoring the magic number of the global header and using the given format instead,
+    /// see [`PcapHeader::from_slice_with_format`].
+    ///
+    /// Returns the remainder and the parser.
+    pub fn with_format(slice: &[u8], format: PcapFormat) -> PcapResult<(&[u8], PcapParser)> {
+        let (rem, header) = PcapHeader::from_slice_with_format(slice, format)?;
 
-        let parser = PcapParser { header };
+        let parser = PcapParser {
+            header,
+            raw_header: raw_header(slice),
+            record_endianness: header.endianness,
+            fixed_quirks: vec![],
+            ignore_padding: false,
+        };
 
-        Ok((slice, parser))
+        Ok((rem, parser))
+    }
+
+    /// Returns the global header exactly as it was read, e.g. to hash it, even if it was fixed or its magic number ignored.
+    pub fn raw_header_bytes(&self) -> &[u8] {
+        &self.raw_header
+    }
+
+    /// Skips the padding records written by [`PcapWriter::pad_to`](super::PcapWriter::pad_to) if `ignore` is true
+    /// (false by default), see [`PcapPacketHeader::padding`].
+    ///
+    /// They are skipped by [`PcapParser::next_packet`], [`PcapParser::next_raw_packet`] and
+    /// [`PcapParser::next_packet_header`], which return the record following them. A slice ending with padding records
+    /// returns [`PcapError::IncompleteBuffer`] like an empty one.
+    pub fn set_ignore_padding(&mut self, ignore: bool) {
+        self.ignore_padding = ignore;
+    }
+
+    /// Returns the quirks of the global header that were fixed.
+    pub fn fixed_quirks(&self) -> &[PcapQuirk] {
+        &self.fixed_quirks
     }
 
     /// Returns the remainder and the next [`PcapPacket`].
     pub fn next_packet<'a>(&self, slice: &'a [u8]) -> PcapResult<(&'a [u8], PcapPacket<'a>)> {
-        match self.header.endianness {
-            Endianness::Big => PcapPacket::from_slice::<BigEndian>(slice, self.header.ts_resolution, self.header.snaplen),
-            Endianness::Little => PcapPacket::from_slice::<LittleEndian>(slice, self.header.ts_resolution, self.header.snaplen),
-        }
+        let (rem, raw_packet) = self.next_raw_packet(slice)?;
+        let packet = raw_packet.try_into_pcap_packet(self.header.ts_resolution, self.header.snaplen)?;
+
+        Ok((rem, packet))
     }
 
     /// Returns the remainder and the next [`RawPcapPacket`].
+    ///
+    /// Returns [`PcapError::SuspiciousEndianness`] if the packet header is implausible
+    /// but would be plausible with the opposite endianness, and [`PcapError::EmbeddedGlobalHeader`] if a valid global
+    /// header starts the slice, e.g. in concatenated pcaps: it can be parsed by [`PcapParser::new`] to continue.
     pub fn next_raw_packet<'a>(&self, slice: &'a [u8]) -> PcapResult<(&'a [u8], RawPcapPacket<'a>)> {
-        match self.header.endianness {
-            Endianness::Big => RawPcapPacket::from_slice::<BigEndian>(slice),
-            Endianness::Little => RawPcapPacket::from_slice::<LittleEndian>(slice),
+        let slice = &slice[self.padding_len(slice)?..];
+
+        // Check the endianness before waiting for the data, because a byte swapped incl_len is usually huge
+        self.check_embedded_header(slice)?;
+        self.check_record_endianness(slice)?;
+
+        let header_len = self.header.record_header_len();
+        match self.record_endianness {
+            Endianness::Big => RawPcapPacket::from_slice_with_header_len::<BigEndian>(slice, header_len),
+            Endianness::Little => RawPcapPacket::from_slice_with_header_len::<LittleEndian>(slice, header_len),
+        }
+    }
+
+    /// Returns the remainder and the header of the next packet, the remainder starts with the data of the packet.
+    ///
+    /// Returns [`PcapError::SuspiciousEndianness`] and [`PcapError::EmbeddedGlobalHeader`] like [`PcapParser::next_raw_packet`].
+    pub fn next_packet_header<'a>(&self, slice: &'a [u8]) -> PcapResult<(&'a [u8], PcapPacketHeader)> {
+        self.next_record_header(&slice[self.padding_len(slice)?..])
+    }
+
+    /// Returns the remainder and the header of the next record like [`PcapParser::next_packet_header`], without
+    /// skipping the padding records.
+    pub(crate) fn next_record_header<'a>(&self, slice: &'a [u8]) -> PcapResult<(&'a [u8], PcapPacketHeader)> {
+        self.check_embedded_header(slice)?;
+        self.check_record_endianness(slice)?;
+
+        let header_len = self.header.record_header_len();
+        match self.record_endianness {
+            Endianness::Big => PcapPacketHeader::from_slice_with_header_len::<BigEndian>(slice, header_len),
+            Endianness::Little => PcapPacketHeader::from_slice_with_header_len::<LittleEndian>(slice, header_len),
+        }
+    }
+
+    /// Returns the length of the padding records starting the slice if they are ignored, see [`PcapParser::set_ignore_padding`].
+    ///
+    /// Returns [`PcapError::IncompleteBuffer`] if the slice ends inside a padding record or inside the record header
+    /// following them.
+    pub(crate) fn padding_len(&self, slice: &[u8]) -> PcapResult<usize> {
+        if !self.ignore_padding {
+            return Ok(0);
+        }
+
+        let header_len = self.header.record_header_len();
+        match self.record_endianness {
+            Endianness::Big => padding_len::<BigEndian>(slice, header_len),
+            Endianness::Little => padding_len::<LittleEndian>(slice, header_len),
+        }
+    }
+
+    /// Returns true if the record header is the one of a padding record skipped by the parser.
+    pub(crate) fn is_ignored_padding(&self, header: &PcapPacketHeader) -> bool {
+        self.ignore_padding && header.is_padding()
+    }
+
+    /// Returns [`PcapError::EmbeddedGlobalHeader`] if a valid global header starts the slice instead of a packet header.
+    ///
+    /// A global header is only looked for if the packet header isn't plausible, or is the one of an empty packet whose
+    /// `orig_len` is 0, which is how a global header whose `thiszone` and `sigfigs` are 0 reads. So a packet whose
+    /// timestamp happens to look like a global header is still read as a packet.
+    pub(crate) fn check_embedded_header(&self, slice: &[u8]) -> PcapResult<()> {
+        if slice.len() >= RECORD_HEADER_LEN && self.is_next_header_plausible(slice) && slice[8..16] != [0; 8] {
+            return Ok(());
+        }
+
+        check_global_header(slice)
+    }
+
+    /// Returns [`PcapError::SuspiciousEndianness`] if the packet header at the start of the slice is implausible
+    /// but would be plausible with the opposite endianness.
+    fn check_record_endianness(&self, slice: &[u8]) -> PcapResult<()> {
+        if slice.len() >= RECORD_HEADER_LEN
+            && !self.is_record_header_plausible(slice, self.record_endianness)
+            && self.is_record_header_plausible(slice, self.record_endianness.opposite())
+        {
+            return Err(PcapError::SuspiciousEndianness);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the endianness used to decode the packet headers.
+    ///
+    /// It is the endianness of the global header unless [`PcapParser::detect_record_endianness`] switched it.
+    pub fn record_endianness(&self) -> Endianness {
+        self.record_endianness
+    }
+
+    /// Checks the endianness of the packet header at the start of the slice.
+    ///
+    /// If the packet header is implausible with the endianness of the global header
+    /// but plausible with the opposite one, the parser switches to the opposite endianness
+    /// to decode the packets.
+    ///
+    /// Returns the endianness that will be used to decode the packets.
+    pub fn detect_record_endianness(&mut self, slice: &[u8]) -> PcapResult<Endianness> {
+        if slice.len() < RECORD_HEADER_LEN {
+            return Err(PcapError::IncompleteBuffer);
+        }
+
+        let endianness = self.header.endianness;
+        if !self.is_record_header_plausible(slice, endianness) && self.is_record_header_plausible(slice, endianness.opposite()) {
+            self.record_endianness = endianness.opposite();
+        }
+        else {
+            self.record_endianness = endianness;
+        }
+
+        Ok(self.record_endianness)
+    }
+
+    /// Returns true if the packet header at the start of the slice is plausible with the endianness of the packet headers.
+    /// The slice must be at least 16 bytes long.
+    pub(crate) fn is_next_header_plausible(&self, slice: &[u8]) -> bool {
+        self.is_record_header_plausible(slice, self.record_endianness)
+    }
+
+    /// Returns true if the lengths of the packet header at the start of the slice are plausible with the endianness
+    /// of the packet headers, whatever its timestamp. The slice must be at least 16 bytes long.
+    pub(crate) fn are_next_lengths_plausible(&self, slice: &[u8]) -> bool {
+        let (incl_len, orig_len) = match self.record_endianness {
+            Endianness::Big => (BigEndian::read_u32(&slice[8..12]), BigEndian::read_u32(&slice[12..16])),
+            Endianness::Little => (LittleEndian::read_u32(&slice[8..12]), LittleEndian::read_u32(&slice[12..16])),
+        };
+
+        incl_len <= orig_len && (self.header.snaplen == 0 || incl_len <= self.header.snaplen)
+    }
+
+    /// Returns true if the packet header at the start of the slice is plausible with the given endianness.
+    /// The slice must be at least 16 bytes long.
+    fn is_record_header_plausible(&self, slice: &[u8], endianness: Endianness) -> bool {
+        return match endianness {
+            Endianness::Big => inner::<BigEndian>(slice, &self.header),
+            Endianness::Little => inner::<LittleEndian>(slice, &self.header),
+        };
+
+        fn inner<B: ByteOrder>(slice: &[u8], header: &PcapHeader) -> bool {
+            let ts_frac = B::read_u32(&slice[4..8]);
+            let incl_len = B::read_u32(&slice[8..12]);
+            let orig_len = B::read_u32(&slice[12..16]);
+
+            let max_frac = match header.ts_resolution {
+                TsResolution::MicroSecond => 1_000_000,
+                TsResolution::NanoSecond => 1_000_000_000,
+            };
+
+            ts_frac < max_frac && incl_len <= orig_len && (header.snaplen == 0 || incl_len <= header.snaplen)
         }
     }
 
@@ -78,3 +284,25 @@ impl PcapParser {
         self.header
     }
 }
+
+/// Copies the global header at the start of a slice, which was successfully parsed.
+fn raw_header(slice: &[u8]) -> [u8; 24] {
+    let mut raw_header = [0_u8; 24];
+    raw_header.copy_from_slice(&slice[..24]);
+    raw_header
+}
+
+/// Returns [`PcapError::EmbeddedGlobalHeader`] if a valid global header starts the slice.
+///
+/// Only the slices starting with a pcap magic number are parsed, so the other packet headers cost one comparison.
+fn check_global_header(slice: &[u8]) -> PcapResult<()> {
+    if slice.len() < 4 || format_from_magic(BigEndian::read_u32(slice)).is_err() {
+        return Ok(());
+    }
+
+    match PcapHeader::from_slice(slice) {
+        Ok(_) => Err(PcapError::EmbeddedGlobalHeader),
+        Err(PcapError::IncompleteBuffer) => Err(PcapError::IncompleteBuffer),
+        Err(_) => Ok(()),
+    }
+}
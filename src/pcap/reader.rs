@@ -1,9 +1,24 @@
-use std::io::Read;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::fs::File;
+use std::hash::Hash;
+use std::io::{Read, Seek};
+use std::net::IpAddr;
+use std::path::Path;
+use std::time::Duration;
 
-use super::{PcapParser, RawPcapPacket};
+use super::packet::shift_timestamp;
+use super::{
+    AlignedBuffer, AlignedPacket, BoundedDedupByKey, CaptureStats, DedupByKey, EnumeratePackets, EpochShiftPolicy, GroupByTimestamp, MapErr,
+    PcapFormat, PcapIndex, PcapPacketHeader, PcapParser, PcapQuirk, Progress, RawPcapPacket, RemapIps, RemapMacs, Sample, SplitOnGap,
+    RECORD_HEADER_LEN,
+};
+use crate::common::SplitMix64;
 use crate::errors::*;
 use crate::pcap::{PcapHeader, PcapPacket};
 use crate::read_buffer::ReadBuffer;
+use crate::{Endianness, TsResolution, ZeroReadRetry};
 
 
 /// Reads a pcap from a reader.
@@ -26,10 +41,92 @@ use crate::read_buffer::ReadBuffer;
 ///     //Do something
 /// }
 /// ```
-#[derive(Debug)]
 pub struct PcapReader<R: Read> {
     parser: PcapParser,
     reader: ReadBuffer<R>,
+    data_limit: Option<usize>,
+    data_truncated: bool,
+    /// `incl_len` of the last packet header read and length of its payload left in the pcap, see [`PcapReader::read_header`]
+    pending_payload: Option<(u32, usize)>,
+    epoch_offset: i64,
+    epoch_shift: EpochShiftPolicy,
+    concatenated: ConcatenatedPcaps,
+    truncation: TruncationPolicy,
+    ignore_padding: bool,
+    /// True if the last packet returned was cut by the end of the pcap
+    partial: bool,
+    /// Number of bytes of the pcap occupied by the last packet returned
+    consumed: u64,
+    /// Number of bytes of the global headers adopted and of the padding records skipped
+    skipped: u64,
+    /// Number of packets read, to locate the section boundaries
+    nb_packets: u64,
+    boundaries: Vec<SectionBoundary>,
+    /// Parser of the first global header, restored when rewinding the pcap
+    first_parser: PcapParser,
+    /// Offset of the first packet from the start of the reader
+    data_start: u64,
+}
+
+impl<R: Read + Debug> Debug for PcapReader<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PcapReader")
+            .field("parser", &self.parser)
+            .field("reader", &self.reader)
+            .field("data_limit", &self.data_limit)
+            .field("data_truncated", &self.data_truncated)
+            .field("pending_payload", &self.pending_payload)
+            .field("epoch_offset", &self.epoch_offset)
+            .field("epoch_shift", &self.epoch_shift)
+            .field("concatenated", &self.concatenated)
+            .field("truncation", &self.truncation)
+            .field("ignore_padding", &self.ignore_padding)
+            .field("partial", &self.partial)
+            .field("consumed", &self.consumed)
+            .field("nb_packets", &self.nb_packets)
+            .field("boundaries", &self.boundaries)
+            .field("data_start", &self.data_start)
+            .finish()
+    }
+}
+
+/// What a [`PcapReader`] does with a global header found where a packet header is expected, e.g. in pcaps
+/// concatenated with `cat`, see [`PcapReader::with_concatenated`].
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum ConcatenatedPcaps {
+    /// Return [`PcapError::EmbeddedGlobalHeader`]
+    #[default]
+    Error,
+    /// Adopt the new global header if it has the same datalink, else return an error
+    SameDatalink,
+    /// Adopt the new global header whatever its datalink
+    AnyDatalink,
+}
+
+/// What a reader does with a last record cut by the end of the pcap, e.g. a capture killed while writing,
+/// see [`PcapReader::with_truncation_policy`].
+///
+/// The same policy is taken by the [`PcapBufReader`](super::PcapBufReader), the `PcapBytesReader` and the
+/// [`ChunkedPcapDecoder`](super::ChunkedPcapDecoder).
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum TruncationPolicy {
+    /// Return the error of the truncated record, a [`PcapError::TruncatedPacket`]
+    #[default]
+    Error,
+    /// Discard the truncated record and end the pcap cleanly, as if it wasn't there
+    StopClean,
+    /// Return the truncated record with the data available, [`PcapReader::is_partial`] telling it is partial.
+    /// A record whose header itself is cut has no packet to return, it is discarded like with [`TruncationPolicy::StopClean`].
+    YieldPartial,
+}
+
+/// Global header adopted by a [`PcapReader`] in the middle of the pcap, see [`PcapReader::with_concatenated`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct SectionBoundary {
+    /// Number of packets read before the global header
+    pub nb_packets_before: u64,
+    /// Global header adopted
+    pub header: PcapHeader,
 }
 
 impl<R: Read> PcapReader<R> {
@@ -44,38 +141,396 @@ impl<R: Read> PcapReader<R> {
     ///
     /// The underlying data are not readable.
     pub fn new(reader: R) -> Result<PcapReader<R>, PcapError> {
+        Self::with_quirks(reader, &[])
+    }
+
+    /// Creates a new [`PcapReader`] from an existing reader, fixing the given quirks of the global header
+    /// if they are detected, see [`PcapHeader::from_slice_with_quirks`].
+    ///
+    /// # Errors
+    /// Same as [`PcapReader::new`].
+    pub fn with_quirks(reader: R, quirks: &[PcapQuirk]) -> Result<PcapReader<R>, PcapError> {
+        let mut reader = ReadBuffer::new(reader);
+        let parser = reader.parse_with(|src| check_len(src, 24), |src| PcapParser::with_quirks(src, quirks))?;
+
+        Ok(PcapReader::from_parser(parser, reader))
+    }
+
+    /// Creates a new [`PcapReader`] from a reader whose pcap has a corrupted magic number, e.g. zeroed by a buggy tool,
+    /// parsing it with the given format.
+    ///
+    /// It bypasses the validation of the magic number: it is a recovery tool, which must only be used when the format
+    /// of the pcap is known out-of-band, see [`PcapHeader::from_slice_with_format`].
+    ///
+    /// # Errors
+    /// Same as [`PcapReader::new`], except for the magic number.
+    pub fn with_forced_format(reader: R, format: PcapFormat) -> Result<PcapReader<R>, PcapError> {
+        let mut reader = ReadBuffer::new(reader);
+        let parser = reader.parse_with(|src| check_len(src, 24), |src| PcapParser::with_format(src, format))?;
+
+        Ok(PcapReader::from_parser(parser, reader))
+    }
+
+    /// Creates a new [`PcapReader`] from a reader whose pcap can start after some junk bytes,
+    /// e.g. left by a botched concatenation or by the header of another format.
+    ///
+    /// The global header is searched at each of the first `max_skip + 1` offsets, the bytes before the first
+    /// valid global header are skipped. Returns the reader and the number of bytes skipped.
+    ///
+    /// # Errors
+    /// No valid global header starts in the first `max_skip + 1` bytes, the error is the one of [`PcapReader::new`].
+    ///
+    /// The underlying data are not readable.
+    pub fn new_seek_magic(reader: R, max_skip: usize) -> Result<(PcapReader<R>, usize), PcapError> {
         let mut reader = ReadBuffer::new(reader);
-        let parser = reader.parse_with(PcapParser::new)?;
 
-        Ok(PcapReader { parser, reader })
+        let mut first_err = None;
+        for skip in 0..=max_skip {
+            match reader.peek_with(|src| check_len(src, skip + 24), |src| PcapParser::new(&src[skip..])) {
+                Ok(_) => {
+                    reader.parse_with(|src| check_len(src, skip), |src| Ok((&src[skip..], ())))?;
+                    let parser = reader.parse_with(|src| check_len(src, 24), PcapParser::new)?;
+                    let mut pcap_reader = PcapReader::from_parser(parser, reader);
+                    pcap_reader.data_start += skip as u64;
+
+                    return Ok((pcap_reader, skip));
+                },
+                // The data is too short for a global header at this offset, nor at the next ones
+                Err(e) if e.is_eof_like() => return Err(first_err.unwrap_or(e)),
+                Err(e) => {
+                    first_err.get_or_insert(e);
+                },
+            }
+        }
+
+        // Can unwrap because there is at least one offset and all of them returned an error
+        Err(first_err.unwrap())
+    }
+
+    /// Creates a new [`PcapReader`] from the parser of its global header, already consumed from the buffer.
+    fn from_parser(parser: PcapParser, reader: ReadBuffer<R>) -> Self {
+        PcapReader {
+            first_parser: parser.clone(),
+            data_start: 24,
+            parser,
+            reader,
+            data_limit: None,
+            data_truncated: false,
+            pending_payload: None,
+            epoch_offset: 0,
+            epoch_shift: EpochShiftPolicy::Error,
+            concatenated: ConcatenatedPcaps::Error,
+            truncation: TruncationPolicy::Error,
+            ignore_padding: false,
+            partial: false,
+            consumed: 0,
+            skipped: 0,
+            nb_packets: 0,
+            boundaries: vec![],
+        }
     }
 
     /// Consumes [`Self`], returning the wrapped reader.
+    ///
+    /// The data is read in advance, so the reader can be positioned anywhere after the last packet returned.
+    /// Use [`PcapReader::into_parts`] or [`PcapReader::into_rewound_reader`] to continue reading right after it.
     pub fn into_reader(self) -> R {
         self.reader.into_inner()
     }
 
+    /// Consumes [`Self`], returning the wrapped reader and the data read in advance from it.
+    ///
+    /// The data read in advance always starts right after the last packet returned, even if the last call
+    /// to [`PcapReader::next_packet`] returned an error.
+    pub fn into_parts(self) -> (R, Vec<u8>) {
+        self.reader.into_parts()
+    }
+
+    /// Limits the data of the packets returned to their first `limit` bytes, the rest of the data is discarded.
+    ///
+    /// The lengths of the packets (`orig_len`, `incl_len`) are left intact and [`PcapReader::is_data_truncated`]
+    /// tells if the data of the last packet returned was truncated by the limit.
+    pub fn with_data_limit(mut self, limit: usize) -> Self {
+        self.data_limit = Some(limit);
+        self
+    }
+
+    /// Sets the number of seconds, which can be negative, to add to the timestamps of the pcap to get Unix timestamps,
+    /// e.g. `946684800` for a device counting the seconds from 2000-01-01.
+    ///
+    /// Only the timestamps of the [`PcapPacket`]s are shifted, the raw packets and headers are left intact.
+    /// A packet whose shifted timestamp is before the Unix epoch is handled according to
+    /// [`PcapReader::with_epoch_shift_policy`], an error by default.
+    pub fn with_epoch_offset(mut self, offset: i64) -> Self {
+        self.epoch_offset = offset;
+        self
+    }
+
+    /// Sets what the reader does with a packet whose timestamp is out of range once shifted by the epoch offset
+    /// ([`EpochShiftPolicy::Error`] by default), see [`PcapReader::with_epoch_offset`].
+    ///
+    /// The packet is consumed in both cases, the next one can be read after an error.
+    pub fn with_epoch_shift_policy(mut self, policy: EpochShiftPolicy) -> Self {
+        self.epoch_shift = policy;
+        self
+    }
+
+    /// Sets what the reader does with a global header found where a packet header is expected, e.g. in pcaps
+    /// concatenated with `cat` ([`ConcatenatedPcaps::Error`] by default).
+    ///
+    /// When a global header is adopted, the packets after it are decoded with its endianness, timestamp resolution and
+    /// snaplen, and a [`SectionBoundary`] is added to [`PcapReader::section_boundaries`]. [`PcapReader::header`]
+    /// returns the last global header adopted.
+    ///
+    /// The global headers are detected from their own magic number, so the sections can differ in endianness and
+    /// resolution, e.g. a big endian pcap appended to a little endian one by hosts of different architectures.
+    pub fn with_concatenated(mut self, concatenated: ConcatenatedPcaps) -> Self {
+        self.concatenated = concatenated;
+        self
+    }
+
+    /// Sets what the reader does with a last record cut by the end of the pcap ([`TruncationPolicy::Error`] by default).
+    ///
+    /// The policy is applied by [`PcapReader::next_packet`], [`PcapReader::next_raw_packet`], [`PcapReader::read_header`]
+    /// and all the methods and adapters built on them, e.g. [`PcapReader::capture_stats`], including the lookups of
+    /// [`PcapReader::nth_packet`] and of the [`PcapIndex`], so that a pipeline picks the behavior once. With concatenated
+    /// pcaps, only the last record of the last section can be cut.
+    ///
+    /// A record is only considered cut if the underlying reader has no more data, a record longer than the buffer of the
+    /// reader is still an error.
+    pub fn with_truncation_policy(mut self, truncation: TruncationPolicy) -> Self {
+        self.truncation = truncation;
+        self
+    }
+
+    /// Skips the padding records written by [`PcapWriter::pad_to`](super::PcapWriter::pad_to) if `ignore` is true
+    /// (false by default), see [`PcapPacketHeader::padding`].
+    ///
+    /// They are skipped by all the read methods, they aren't counted as packets and their validity isn't checked.
+    /// Otherwise they are returned as invalid packets, or as they are by [`PcapReader::next_raw_packet`].
+    /// They are skipped by the [`PcapParser`] of the reader, like by the other readers, see [`PcapParser::set_ignore_padding`].
+    pub fn with_ignore_padding(mut self, ignore: bool) -> Self {
+        self.ignore_padding = ignore;
+        self.parser.set_ignore_padding(ignore);
+        self.first_parser.set_ignore_padding(ignore);
+        self
+    }
+
+    /// Retries the reads of the underlying reader returning no data in the middle of a record, as set by the policy
+    /// (no retries by default), e.g. for a pcap on a network filesystem.
+    ///
+    /// The retries apply to all the reads of the records, their headers, their data and the data skipped. The global
+    /// header is read by the constructor, before the policy is set.
+    pub fn with_zero_read_retry(mut self, retry: ZeroReadRetry) -> Self {
+        self.reader.set_zero_read_retry(retry);
+        self
+    }
+
+    /// Returns true if the last packet returned was cut by the end of the pcap, see [`TruncationPolicy::YieldPartial`].
+    ///
+    /// Its data is shorter than the `incl_len` of its record.
+    pub fn is_partial(&self) -> bool {
+        self.partial
+    }
+
+    /// Returns the offset of the first packet from the start of the reader: the length of the global header,
+    /// plus the bytes skipped before it by [`PcapReader::new_seek_magic`].
+    ///
+    /// The positions of the packets, e.g. for [`PcapReader::seek_to`], are the ones of the underlying reader,
+    /// so it must be at the start of the data when the [`PcapReader`] is created.
+    pub fn data_start(&self) -> u64 {
+        self.data_start
+    }
+
+    /// Returns the number of bytes of the pcap occupied by the last packet returned, its record header included,
+    /// e.g. for a quota billing the stored bytes.
+    ///
+    /// It is the length of the record header plus the `incl_len`, whatever the data limit, or the bytes actually
+    /// left for a partial packet. The global headers and padding records skipped before the packet aren't counted,
+    /// see [`PcapReader::skipped_bytes`].
+    pub fn consumed_bytes(&self) -> u64 {
+        self.consumed
+    }
+
+    /// Returns the number of bytes of the global headers adopted (see [`PcapReader::with_concatenated`]) and of the
+    /// padding records skipped (see [`PcapReader::with_ignore_padding`]) so far.
+    pub fn skipped_bytes(&self) -> u64 {
+        self.skipped
+    }
+
+    /// Returns the global headers adopted so far, see [`PcapReader::with_concatenated`].
+    pub fn section_boundaries(&self) -> &[SectionBoundary] {
+        &self.boundaries
+    }
+
+    /// Adopts the global headers found before the next packet header, if allowed by [`PcapReader::with_concatenated`].
+    fn adopt_embedded_headers(&mut self) -> PcapResult<()> {
+        if self.concatenated == ConcatenatedPcaps::Error {
+            return Ok(());
+        }
+
+        while self.reader.has_data_left().map_err(PcapError::IoError)? {
+            let parser = &self.parser;
+            let embedded = self.reader.peek_with(
+                |src| match parser.check_embedded_header(src) {
+                    Err(PcapError::IncompleteBuffer) => Err(PcapError::IncompleteBuffer),
+                    _ => Ok(()),
+                },
+                |src| Ok((src, parser.check_embedded_header(src).is_err())),
+            )?;
+            if !embedded {
+                return Ok(());
+            }
+
+            let header = self.reader.peek_with(|src| check_len(src, 24), PcapHeader::from_slice)?;
+            if self.concatenated == ConcatenatedPcaps::SameDatalink && header.datalink != self.parser.header().datalink {
+                return Err(PcapError::InvalidField("PcapReader: embedded global header with a different datalink"));
+            }
+
+            self.parser = self.reader.parse_with(|src| check_len(src, 24), PcapParser::new)?;
+            self.parser.set_ignore_padding(self.ignore_padding);
+            self.skipped += 24;
+            self.boundaries.push(SectionBoundary { nb_packets_before: self.nb_packets, header });
+        }
+
+        Ok(())
+    }
+
+    /// Skips the padding records found before the next packet header, if allowed by [`PcapReader::with_ignore_padding`].
+    ///
+    /// The parser skips them too, but only before a record: they are skipped here at the end of the pcap and before an
+    /// embedded global header. Returns true if a padding record was skipped.
+    fn skip_padding_record(&mut self) -> PcapResult<bool> {
+        if !self.ignore_padding || !self.reader.has_data_left().map_err(PcapError::IoError)? {
+            return Ok(false);
+        }
+
+        // The errors are left to the next read
+        let parser = &self.parser;
+        let header = match self.reader.peek_with(|src| parser.next_record_header(src).map(drop), |src| parser.next_record_header(src)) {
+            Ok(header) if parser.is_ignored_padding(&header) => header,
+            _ => return Ok(false),
+        };
+
+        let len = self.parser.header().record_header_len() + header.incl_len as usize;
+        self.skip_bytes(len)?;
+        self.skipped += len as u64;
+        Ok(true)
+    }
+
+    /// Adopts the embedded global headers and skips the padding records found before the next packet header.
+    fn skip_to_next_packet(&mut self) -> PcapResult<()> {
+        loop {
+            self.adopt_embedded_headers()?;
+            if !self.skip_padding_record()? {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Returns true if the data of the last packet returned was truncated by the limit set with [`PcapReader::with_data_limit`].
+    pub fn is_data_truncated(&self) -> bool {
+        self.data_truncated
+    }
+
     /// Returns the next [`PcapPacket`].
     pub fn next_packet(&mut self) -> Option<Result<PcapPacket, PcapError>> {
+        self.next_packet_with_read_len().map(|(packet, _)| packet)
+    }
+
+    /// Returns the next [`PcapPacket`] like [`PcapReader::next_packet`], with the number of bytes read for it: the
+    /// bytes it occupies (see [`PcapReader::consumed_bytes`]) and the ones skipped before it.
+    pub(crate) fn next_packet_with_read_len(&mut self) -> Option<(PcapResult<PcapPacket<'_>>, u64)> {
+        let skipped = self.skipped;
+        if let Err(e) = self.skip_pending_payload().and_then(|_| self.skip_to_next_packet()) {
+            return Some((Err(e), self.skipped - skipped));
+        }
+
         match self.reader.has_data_left() {
             Ok(has_data) => {
                 if has_data {
-                    Some(self.reader.parse_with(|src| self.parser.next_packet(src)))
+                    let (parser, truncation) = (&self.parser, self.truncation);
+                    let header = parser.header();
+                    let packet = self.reader.parse_with_cut(
+                        truncation != TruncationPolicy::Error,
+                        |src| parser.next_raw_packet(src).map(drop),
+                        |src, cut| {
+                            parse_record(parser, truncation, src, cut, |record| {
+                                record.try_into_pcap_packet(header.ts_resolution, header.snaplen)
+                            })
+                        },
+                    );
+                    let mut packet = match packet {
+                        Ok(Some((packet, partial))) => {
+                            self.partial = partial;
+                            Ok(packet)
+                        },
+                        // The cut record is discarded
+                        Ok(None) => return None,
+                        Err(e) => Err(e),
+                    };
+                    if let Ok(packet) = &mut packet {
+                        self.consumed = (header.record_header_len() + packet.data.len()) as u64;
+                        self.data_truncated = truncate_data(&mut packet.data, self.data_limit);
+                        self.nb_packets += 1;
+                    }
+
+                    let read_len = self.skipped - skipped + if packet.is_ok() { self.consumed } else { 0 };
+
+                    // Shifted once consumed, so that a packet out of range doesn't block the reader
+                    let packet = packet.and_then(|mut packet| {
+                        packet.timestamp = shift_timestamp(packet.timestamp, self.epoch_offset, self.epoch_shift)?;
+                        Ok(packet)
+                    });
+                    Some((packet, read_len))
                 }
                 else {
                     None
                 }
             },
-            Err(e) => Some(Err(PcapError::IoError(e))),
+            Err(e) => Some((Err(PcapError::IoError(e)), self.skipped - skipped)),
+        }
+    }
+
+    /// Returns the next packet, copied into the given [`AlignedBuffer`] so that its data is aligned.
+    pub fn next_aligned_packet<'a>(&mut self, buffer: &'a mut AlignedBuffer) -> Option<Result<AlignedPacket<'a>, PcapError>> {
+        match self.next_packet()? {
+            Ok(packet) => Some(Ok(buffer.copy_packet(&packet))),
+            Err(e) => Some(Err(e)),
         }
     }
 
     /// Returns the next [`RawPcapPacket`].
     pub fn next_raw_packet(&mut self) -> Option<Result<RawPcapPacket, PcapError>> {
+        if let Err(e) = self.skip_pending_payload().and_then(|_| self.skip_to_next_packet()) {
+            return Some(Err(e));
+        }
+
         match self.reader.has_data_left() {
             Ok(has_data) => {
                 if has_data {
-                    Some(self.reader.parse_with(|src| self.parser.next_raw_packet(src)))
+                    let (parser, truncation) = (&self.parser, self.truncation);
+                    let packet = self.reader.parse_with_cut(
+                        truncation != TruncationPolicy::Error,
+                        |src| parser.next_raw_packet(src).map(drop),
+                        |src, cut| parse_record(parser, truncation, src, cut, Ok),
+                    );
+                    let mut packet = match packet {
+                        Ok(Some((packet, partial))) => {
+                            self.partial = partial;
+                            Ok(packet)
+                        },
+                        // The cut record is discarded
+                        Ok(None) => return None,
+                        Err(e) => Err(e),
+                    };
+                    if let Ok(packet) = &mut packet {
+                        self.consumed = (self.parser.header().record_header_len() + packet.data.len()) as u64;
+                        self.data_truncated = truncate_data(&mut packet.data, self.data_limit);
+                        self.nb_packets += 1;
+                    }
+
+                    Some(packet)
                 }
                 else {
                     None
@@ -85,8 +540,794 @@ impl<R: Read> PcapReader<R> {
         }
     }
 
+    /// Returns the endianness used to decode the packet headers, see [`PcapParser::record_endianness`].
+    pub fn record_endianness(&self) -> Endianness {
+        self.parser.record_endianness()
+    }
+
+    /// Reads the header of the next packet, or returns [`None`] if there are no more packets.
+    ///
+    /// It must be followed by a call to [`PcapReader::read_payload`] or [`PcapReader::skip_payload`],
+    /// else the payload is skipped by the next read.
+    ///
+    /// The truncation policy applies like with [`PcapReader::next_packet`]: a cut record ends the pcap or, with
+    /// [`TruncationPolicy::YieldPartial`], its payload is the data left.
+    pub fn read_header(&mut self) -> PcapResult<Option<PcapPacketHeader>> {
+        self.skip_pending_payload()?;
+        self.skip_to_next_packet()?;
+
+        if !self.reader.has_data_left().map_err(PcapError::IoError)? {
+            return Ok(None);
+        }
+
+        // The whole record is buffered anyway to skip its payload, so it is checked before its header is returned
+        let (parser, truncation) = (&self.parser, self.truncation);
+        let header_len = parser.header().record_header_len();
+        let record = self.reader.peek_with_cut(
+            truncation != TruncationPolicy::Error,
+            |src| parser.next_raw_packet(src).map(drop),
+            |src, cut| {
+                let (rem, record) = parse_record(parser, truncation, src, cut, Ok)?;
+                let record = record.map(|(RawPcapPacket { ts_sec, ts_frac, incl_len, orig_len, data }, partial)| {
+                    (PcapPacketHeader { ts_sec, ts_frac, incl_len, orig_len }, data.len(), partial)
+                });
+                Ok((rem, record))
+            },
+        )?;
+
+        let (header, payload_len, partial) = match record {
+            Some(record) => record,
+            None => {
+                // The cut record is discarded
+                let len = self.reader.buffer().len();
+                self.skip_bytes(len)?;
+                return Ok(None);
+            },
+        };
+
+        self.skip_bytes(header_len)?;
+        self.pending_payload = Some((header.incl_len, payload_len));
+        self.consumed = (header_len + payload_len) as u64;
+        self.partial = partial;
+        self.nb_packets += 1;
+
+        Ok(Some(header))
+    }
+
+    /// Reads the payload of the packet whose header was just returned by [`PcapReader::read_header`].
+    pub fn read_payload(&mut self, header: &PcapPacketHeader) -> PcapResult<Vec<u8>> {
+        let len = self.take_pending_payload(header)?;
+        self.reader.parse_with(|src| check_len(src, len), |src| Ok((&src[len..], src[..len].to_vec())))
+    }
+
+    /// Skips the payload of the packet whose header was just returned by [`PcapReader::read_header`].
+    pub fn skip_payload(&mut self, header: &PcapPacketHeader) -> PcapResult<()> {
+        let len = self.take_pending_payload(header)?;
+        self.skip_bytes(len)
+    }
+
+    /// Checks that the header is the one of the pending payload, returns the length of the payload.
+    fn take_pending_payload(&mut self, header: &PcapPacketHeader) -> PcapResult<usize> {
+        match self.pending_payload {
+            Some((incl_len, len)) if incl_len == header.incl_len => {
+                self.pending_payload = None;
+                Ok(len)
+            },
+            _ => Err(PcapError::InvalidField("PcapReader: the header isn't the one of the last packet header read")),
+        }
+    }
+
+    /// Skips the payload of the last packet header read, if it wasn't read.
+    fn skip_pending_payload(&mut self) -> PcapResult<()> {
+        match self.pending_payload.take() {
+            Some((_, len)) => self.skip_bytes(len),
+            None => Ok(()),
+        }
+    }
+
+    /// Skips the next `len` bytes of the pcap.
+    fn skip_bytes(&mut self, len: usize) -> PcapResult<()> {
+        self.reader.parse_with(|src| check_len(src, len), |src| Ok((&src[len..], ())))
+    }
+
+    /// Returns the header of the next packet without consuming it, and true if the packet is framed, i.e. if its header
+    /// is plausible or if it is followed by a header with plausible lengths or by the end of the pcap.
+    pub(crate) fn peek_framed_header(&mut self) -> PcapResult<Option<(PcapPacketHeader, bool)>> {
+        self.skip_pending_payload()?;
+        self.skip_to_next_packet()?;
+
+        if !self.reader.has_data_left().map_err(PcapError::IoError)? {
+            return Ok(None);
+        }
+
+        let parser = &self.parser;
+        let header = self.reader.peek_with(|src| parser.next_packet_header(src).map(drop), |src| parser.next_packet_header(src))?;
+        let header_len = parser.header().record_header_len();
+        let framed = match buffer_window(&mut self.reader, 0, header_len)? {
+            Some(src) if parser.is_next_header_plausible(src) => true,
+            _ => is_record_followed(parser, &mut self.reader, 0, header.incl_len)?,
+        };
+
+        Ok(Some((header, framed)))
+    }
+
+    /// Skips the bytes preceding the next framed packet after a framing error, see [`PcapReader::peek_framed_header`].
+    ///
+    /// Returns the number of bytes skipped, or `None` if no framed packet with a plausible header starts in the next
+    /// `max_skip` bytes, nothing is skipped then.
+    pub(crate) fn resync(&mut self, max_skip: usize) -> PcapResult<Option<usize>> {
+        self.skip_pending_payload()?;
+
+        let parser = &self.parser;
+        let header_len = parser.header().record_header_len();
+        for skip in 1..=max_skip {
+            let incl_len = match buffer_window(&mut self.reader, skip, header_len)? {
+                Some(src) if parser.is_next_header_plausible(src) => parser.next_packet_header(src).map(|(_, header)| header.incl_len),
+                Some(_) => continue,
+                None => return Ok(None),
+            };
+
+            if let Ok(incl_len) = incl_len {
+                if is_record_followed(parser, &mut self.reader, skip, incl_len)? {
+                    self.skip_bytes(skip)?;
+                    return Ok(Some(skip));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Skips the next `n` packets without copying their data, returns the number of packets skipped,
+    /// which is less than `n` if the end of the pcap is reached.
+    pub(crate) fn skip_packets(&mut self, n: u64) -> PcapResult<u64> {
+        for skipped in 0..n {
+            match self.next_raw_packet() {
+                Some(packet) => packet?,
+                None => return Ok(skipped),
+            };
+        }
+
+        Ok(n)
+    }
+
+    /// Calls `f` on the next packet without consuming it.
+    pub(crate) fn peek_packet_with<O>(&mut self, f: impl FnOnce(&PcapPacket) -> O) -> Option<PcapResult<O>> {
+        if let Err(e) = self.skip_pending_payload().and_then(|_| self.skip_to_next_packet()) {
+            return Some(Err(e));
+        }
+
+        match self.reader.has_data_left() {
+            Ok(true) => {},
+            Ok(false) => return None,
+            Err(e) => return Some(Err(PcapError::IoError(e))),
+        }
+
+        let (data_limit, epoch_offset, epoch_shift) = (self.data_limit, self.epoch_offset, self.epoch_shift);
+        let (parser, truncation) = (&self.parser, self.truncation);
+        let header = parser.header();
+        let packet = self.reader.peek_with_cut(
+            truncation != TruncationPolicy::Error,
+            |src| parser.next_raw_packet(src).map(drop),
+            |src, cut| {
+                parse_record(parser, truncation, src, cut, |record| {
+                    record.try_into_pcap_packet(header.ts_resolution, header.snaplen)
+                })
+            },
+        );
+
+        Some(packet.transpose()?.and_then(|(mut packet, _)| {
+            packet.timestamp = shift_timestamp(packet.timestamp, epoch_offset, epoch_shift)?;
+            truncate_data(&mut packet.data, data_limit);
+            Ok(f(&packet))
+        }))
+    }
+
+    /// Checks the endianness of the next packet header, see [`PcapParser::detect_record_endianness`].
+    ///
+    /// Returns the endianness that will be used to decode the packets.
+    pub fn detect_record_endianness(&mut self) -> PcapResult<Endianness> {
+        let parser = &mut self.parser;
+        self.reader
+            .peek_with(|src| check_len(src, RECORD_HEADER_LEN), |src| Ok((src, parser.detect_record_endianness(src)?)))
+    }
+
+    /// Returns the offset and the `incl_len` of all the remaining packets whose `incl_len` is bigger than the snaplen,
+    /// which strict parsers reject.
+    ///
+    /// Only the packet headers are parsed, the data of the packets is skipped. The offsets are the ones of the packet
+    /// headers from the start of the reader, counting the packets from the current position of the reader, so they are
+    /// exact if no packet was read before.
+    ///
+    /// # Errors
+    /// A packet header can't be read, e.g. the pcap is truncated.
+    pub fn snaplen_violations(&mut self) -> PcapResult<Vec<(u64, u32)>> {
+        let header = self.header();
+        let mut violations = vec![];
+
+        let mut offset = self.data_start;
+        while let Some(packet_header) = self.read_header()? {
+            if packet_header.incl_len > header.snaplen {
+                violations.push((offset, packet_header.incl_len));
+            }
+
+            self.skip_payload(&packet_header)?;
+            offset += (header.record_header_len() + packet_header.incl_len as usize) as u64;
+        }
+
+        Ok(violations)
+    }
+
+    /// Computes the [`CaptureStats`] of all the remaining packets.
+    ///
+    /// Only the packet headers are parsed, the data of the packets is skipped.
+    ///
+    /// # Errors
+    /// A packet header can't be read, e.g. the pcap is truncated.
+    pub fn capture_stats(&mut self) -> PcapResult<CaptureStats> {
+        let ts_resolution = self.header().ts_resolution;
+        let mut stats = CaptureStats::default();
+
+        while let Some(packet_header) = self.read_header()? {
+            stats.add(&packet_header, ts_resolution);
+            self.skip_payload(&packet_header)?;
+        }
+
+        Ok(stats)
+    }
+
+    /// Returns the histogram of the original lengths of the remaining packets: the number of packets per `orig_len`.
+    ///
+    /// It gives the real sizes of the packets on the wire, even if their data was truncated by the snaplen.
+    /// Only the packet headers are parsed, the data of the packets is skipped.
+    ///
+    /// # Errors
+    /// A packet header can't be read, e.g. the pcap is truncated.
+    pub fn orig_len_histogram(&mut self) -> PcapResult<BTreeMap<u32, u64>> {
+        let mut histogram = BTreeMap::new();
+
+        while let Some(packet_header) = self.read_header()? {
+            *histogram.entry(packet_header.orig_len).or_insert(0) += 1;
+            self.skip_payload(&packet_header)?;
+        }
+
+        Ok(histogram)
+    }
+
+    /// Returns true if the timestamps of the remaining packets never decrease, e.g. before applying a filter
+    /// which stops at the first packet after a time range.
+    ///
+    /// Only the packet headers are parsed, the data of the packets is skipped. The scan stops at the first packet
+    /// older than the previous one, so the reader is left after it.
+    ///
+    /// # Errors
+    /// A packet header can't be read, e.g. the pcap is truncated.
+    pub fn is_time_ordered(&mut self) -> PcapResult<bool> {
+        let mut prev = None;
+
+        while let Some(packet_header) = self.read_header()? {
+            // The resolution can change with the global headers adopted, see `with_concatenated`
+            let ts_frac = match self.header().ts_resolution {
+                TsResolution::MicroSecond => packet_header.ts_frac as u64 * 1000,
+                TsResolution::NanoSecond => packet_header.ts_frac as u64,
+            };
+            let ts = (packet_header.ts_sec, ts_frac);
+            if prev.is_some_and(|prev| ts < prev) {
+                self.skip_payload(&packet_header)?;
+                return Ok(false);
+            }
+
+            prev = Some(ts);
+            self.skip_payload(&packet_header)?;
+        }
+
+        Ok(true)
+    }
+
+    /// Returns the number of remaining packets per window of `window`, e.g. to plot the packet rate and spot the
+    /// bursts: the start of each window, with the number of packets whose timestamp is in it.
+    ///
+    /// The windows are aligned on the Unix epoch, like [`RotationPolicy::EverySeconds`](super::RotationPolicy::EverySeconds).
+    /// There is a point for each window from the one of the first packet to the one of the last packet, the empty
+    /// windows included, so a long silence with a short window gives many points: the series is bounded by
+    /// `max_points`. The timestamps are expected to be monotonic: a packet older than the current window is counted in it.
+    ///
+    /// The timestamps are shifted by the epoch offset, like the ones of [`PcapReader::next_packet`], see
+    /// [`PcapReader::with_epoch_offset`]. The `ts_correction` of the global header isn't applied.
+    ///
+    /// Only the packet headers are parsed, the data of the packets is skipped.
+    ///
+    /// # Errors
+    /// The window is 0, or the series would have more than `max_points` points ([`PcapError::InvalidConfiguration`]).
+    ///
+    /// A packet header can't be read, e.g. the pcap is truncated, or a timestamp is out of range once shifted, see
+    /// [`PcapReader::with_epoch_shift_policy`].
+    pub fn rate_series(mut self, window: Duration, max_points: usize) -> PcapResult<Vec<(Duration, u64)>> {
+        if window.is_zero() {
+            return Err(PcapError::InvalidConfiguration("PcapReader: rate window == 0"));
+        }
+        let window = window.as_nanos();
+
+        // Index of each window since the epoch, with its number of packets
+        let mut series: Vec<(u128, u64)> = vec![];
+        while let Some(packet_header) = self.read_header()? {
+            // The resolution can change with the global headers adopted, see `with_concatenated`
+            let ts_frac = match self.header().ts_resolution {
+                TsResolution::MicroSecond => packet_header.ts_frac as u64 * 1000,
+                TsResolution::NanoSecond => packet_header.ts_frac as u64,
+            };
+            let timestamp = Duration::from_secs(packet_header.ts_sec as u64) + Duration::from_nanos(ts_frac);
+            let index = shift_timestamp(timestamp, self.epoch_offset, self.epoch_shift)?.as_nanos() / window;
+
+            match series.last_mut() {
+                Some((current, nb_packets)) if index <= *current => *nb_packets += 1,
+                last => {
+                    // The empty windows since the current one are checked against the bound before being pushed
+                    let first = last.map_or(index, |(current, _)| *current + 1);
+                    if series.len() as u128 + (index - first) >= max_points as u128 {
+                        return Err(PcapError::InvalidConfiguration("PcapReader: rate series longer than max_points"));
+                    }
+                    series.extend((first..index).map(|empty| (empty, 0)));
+                    series.push((index, 1));
+                },
+            }
+            self.skip_payload(&packet_header)?;
+        }
+
+        let start = |index: u128| {
+            let nanos = index * window;
+            Duration::new((nanos / 1_000_000_000) as u64, (nanos % 1_000_000_000) as u32)
+        };
+        Ok(series.into_iter().map(|(index, nb_packets)| (start(index), nb_packets)).collect())
+    }
+
+    /// Folds all the remaining packets into an accumulator, e.g. to compute the total size of the packets,
+    /// without keeping the packets in memory.
+    ///
+    /// Stops at the first error.
+    pub fn fold_packets<B, F: FnMut(B, &PcapPacket) -> B>(mut self, init: B, mut f: F) -> PcapResult<B> {
+        let mut acc = init;
+        while let Some(packet) = self.next_packet() {
+            acc = f(acc, &packet?);
+        }
+
+        Ok(acc)
+    }
+
+    /// Calls `f` on all the remaining packets, like [`Iterator::try_for_each`].
+    ///
+    /// Stops at the first error, returned by the reader or by `f`.
+    pub fn try_for_each<F: FnMut(PcapPacket) -> PcapResult<()>>(mut self, mut f: F) -> PcapResult<()> {
+        while let Some(packet) = self.next_packet() {
+            f(packet?)?;
+        }
+
+        Ok(())
+    }
+
+    /// Computes the SHA-256 of the content of all the remaining packets, e.g. for an integrity manifest.
+    ///
+    /// The global header isn't hashed, and the packets are hashed in a canonical form independent of the
+    /// endianness and of the timestamp resolution of the pcap, so two pcaps with the same packets have the same hash
+    /// whatever their headers. Each packet is hashed as its timestamp seconds (u64), its timestamp nanoseconds (u32),
+    /// its original length (u32) and its captured length (u32), all in little endian, followed by its data.
+    ///
+    /// The packets are the ones returned by [`PcapReader::next_packet`], so the epoch offset and the data limit
+    /// of the reader apply.
+    ///
+    /// # Errors
+    /// A packet is invalid or the underlying data are not readable.
+    #[cfg(feature = "sha2")]
+    pub fn content_hash(&mut self) -> PcapResult<[u8; 32]> {
+        use sha2::{Digest, Sha256};
+
+        let mut hasher = Sha256::new();
+        while let Some(packet) = self.next_packet() {
+            let packet = packet?;
+            hasher.update(packet.timestamp.as_secs().to_le_bytes());
+            hasher.update(packet.timestamp.subsec_nanos().to_le_bytes());
+            hasher.update(packet.orig_len.to_le_bytes());
+            hasher.update((packet.data.len() as u32).to_le_bytes());
+            hasher.update(&packet.data);
+        }
+
+        Ok(hasher.finalize().into())
+    }
+
+    /// Returns an adapter calling `callback(packets_read, bytes_read)` every `every` packets read.
+    ///
+    /// `bytes_read` includes the global header, the global headers adopted and the padding records skipped, and the
+    /// whole record of each packet whatever the data limit, see [`PcapReader::consumed_bytes`].
+    ///
+    /// # Panics
+    /// `every` is 0.
+    pub fn on_progress<F: FnMut(usize, u64)>(self, every: usize, callback: F) -> Progress<R, F> {
+        Progress::new(self, every, callback)
+    }
+
+    /// Returns an adapter pairing each packet with its index in the pcap, e.g. to match the packet numbers
+    /// of external logs.
+    ///
+    /// The index starts at 0, or at the value given to [`EnumeratePackets::starting_at`], and counts the packets
+    /// successfully read: an error doesn't consume a packet (the reader returns it again if called again),
+    /// so it is paired with the index of the packet that couldn't be read and doesn't increment the index.
+    pub fn enumerate_packets(self) -> EnumeratePackets<R> {
+        EnumeratePackets::new(self)
+    }
+
+    /// Returns an adapter yielding one packet every `n` packets (the 1st, the n+1th...), the other ones are skipped
+    /// without copying their data.
+    ///
+    /// The packets are sampled by position, not by time, so the sample may be biased for bursty traffic.
+    ///
+    /// # Panics
+    /// `n` is 0.
+    pub fn sample(self, n: usize) -> Sample<R> {
+        Sample::new(self, n)
+    }
+
+    /// Returns an adapter dropping the packets whose key, computed by `key`, was already seen,
+    /// so that only the first packet of each key is returned (e.g. the first packet of each flow).
+    ///
+    /// The keys seen are kept in a [`HashSet`](std::collections::HashSet) for the whole life of the adapter,
+    /// so the memory used grows with the number of distinct keys. For untrusted captures, which can contain
+    /// any number of distinct keys, use [`PcapReader::dedup_by_key_bounded`].
+    pub fn dedup_by_key<K: Eq + Hash, F: FnMut(&PcapPacket) -> K>(self, key: F) -> DedupByKey<R, K, F> {
+        DedupByKey::new(self, key)
+    }
+
+    /// Returns an adapter dropping the packets whose key, computed by `key`, is one of the `capacity`
+    /// most recently seen keys.
+    ///
+    /// Unlike [`PcapReader::dedup_by_key`], the memory used is bounded, which makes it safe for untrusted captures,
+    /// but a duplicate whose key was evicted since its last occurrence is returned again.
+    ///
+    /// # Panics
+    /// `capacity` is 0.
+    pub fn dedup_by_key_bounded<K: Eq + Hash + Clone, F: FnMut(&PcapPacket) -> K>(
+        self,
+        key: F,
+        capacity: usize,
+    ) -> BoundedDedupByKey<R, K, F> {
+        BoundedDedupByKey::new(self, key, capacity)
+    }
+
+    /// Returns an adapter grouping the consecutive packets having the same timestamp,
+    /// e.g. the packets of a burst that the timestamp resolution can't tell apart.
+    ///
+    /// Only consecutive packets are grouped, so a pcap that isn't sorted by time can have several groups
+    /// with the same timestamp. The packets are copied, only the packets of one group are kept in memory.
+    pub fn group_by_timestamp(self) -> GroupByTimestamp<R> {
+        GroupByTimestamp::new(self)
+    }
+
+    /// Returns an adapter converting the errors into another error type, e.g. the error type of the application,
+    /// so that they can be propagated with `?` without converting them in the processing loop.
+    pub fn map_err<E, F: FnMut(PcapError) -> E>(self, f: F) -> MapErr<R, F> {
+        MapErr::new(self, f)
+    }
+
+    /// Returns an adapter rewriting the destination and source MAC addresses (the first 12 bytes) of the packets
+    /// with `map`, e.g. to anonymize a capture before sharing it.
+    ///
+    /// The same MAC is always given to `map`, so a deterministic `map` gives a consistent pseudonymization.
+    /// Only the pcaps whose datalink is Ethernet are rewritten, the packets of the other ones are returned as read.
+    /// The rewritten data are owned, and the packets too short to contain a full Ethernet header are skipped,
+    /// as they can't be anonymized.
+    pub fn remap_macs<F: FnMut([u8; 6]) -> [u8; 6]>(self, map: F) -> RemapMacs<R, F> {
+        RemapMacs::new(self, map)
+    }
+
+    /// Returns an adapter rewriting the source and destination IP addresses of the IPv4 and IPv6 packets with `map`,
+    /// recomputing the checksum of the IPv4 headers, see [`remap_ip_addresses`](crate::remap_ip_addresses).
+    ///
+    /// With the `sha2` feature, [`PrefixPreservingAnonymizer`](crate::PrefixPreservingAnonymizer) gives a mapping
+    /// keeping the subnets. The packets which aren't IP packets are returned as read, and the IP packets whose IP header
+    /// is cut, e.g. by the snaplen, are skipped, as they can't be anonymized.
+    pub fn remap_ips<F: FnMut(IpAddr) -> IpAddr>(self, map: F) -> RemapIps<R, F> {
+        RemapIps::new(self, map)
+    }
+
+    /// Returns an adapter splitting the packets into groups, e.g. sessions or bursts, whenever the timestamps of two
+    /// consecutive packets differ by more than `threshold`.
+    ///
+    /// The groups can be returned as vectors of packets ([`SplitOnGap::next_group`]) or marked on a stream
+    /// of packets ([`SplitOnGap::next_packet`]).
+    pub fn split_on_gap(self, threshold: Duration) -> SplitOnGap<R> {
+        SplitOnGap::new(self, threshold)
+    }
+
+    /// Returns a uniform random sample of `k` packets, in their order in the pcap, using reservoir sampling.
+    ///
+    /// The pcap is read in a single pass and at most `k` packets are kept in memory.
+    /// The same seed always gives the same sample.
+    pub fn reservoir_sample(mut self, k: usize, seed: u64) -> PcapResult<Vec<PcapPacket<'static>>> {
+        let mut rng = SplitMix64::new(seed);
+        // Don't trust k to preallocate the reservoir
+        let mut reservoir = Vec::with_capacity(k.min(1024));
+
+        let mut idx = 0_u64;
+        while let Some(packet) = self.next_packet() {
+            let packet = packet?;
+
+            if reservoir.len() < k {
+                reservoir.push((idx, packet.into_owned()));
+            }
+            else {
+                let slot = rng.below(idx + 1) as usize;
+                if slot < k {
+                    reservoir[slot] = (idx, packet.into_owned());
+                }
+            }
+
+            idx += 1;
+        }
+
+        reservoir.sort_unstable_by_key(|(idx, _)| *idx);
+        Ok(reservoir.into_iter().map(|(_, packet)| packet).collect())
+    }
+
     /// Returns the global header of the pcap.
     pub fn header(&self) -> PcapHeader {
         self.parser.header()
     }
+
+    /// Returns the quirks of the global header that were fixed.
+    pub fn fixed_quirks(&self) -> &[PcapQuirk] {
+        self.parser.fixed_quirks()
+    }
+
+    /// Returns the 24 bytes of the global header exactly as they were read, e.g. to hash them.
+    ///
+    /// Unlike the bytes of [`PcapHeader::write_to`], they are the original ones even if the header was fixed
+    /// (see [`PcapReader::with_quirks`]) or if its magic number was ignored (see [`PcapReader::with_forced_format`]).
+    pub fn raw_header_bytes(&self) -> &[u8] {
+        self.parser.raw_header_bytes()
+    }
+}
+
+impl PcapReader<File> {
+    /// Opens the pcap file at the given path.
+    ///
+    /// The file isn't wrapped in a [`BufReader`](std::io::BufReader) because the [`PcapReader`] already buffers its input.
+    ///
+    /// # Errors
+    /// The file can't be opened or doesn't start with a valid pcap header.
+    /// The error is a [`PcapError::WithPath`] containing the path of the file.
+    pub fn from_path(path: impl AsRef<Path>) -> PcapResult<Self> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|e| PcapError::IoError(e).with_path(path))?;
+
+        PcapReader::new(file).map_err(|e| e.with_path(path))
+    }
+}
+
+impl<R: Read + Seek> PcapReader<R> {
+    /// Consumes [`Self`], returning the wrapped reader positioned right after the last packet returned,
+    /// even if the last call to [`PcapReader::next_packet`] returned an error.
+    pub fn into_rewound_reader(self) -> std::io::Result<R> {
+        self.reader.into_rewound_inner()
+    }
+
+    /// Returns the packet number `n` (starting at 0) of the pcap, or [`None`] if the pcap has less packets.
+    ///
+    /// If an index of the pcap is given, the reader seeks to the nearest indexed packet, else it scans the pcap from its start.
+    /// In both cases, the packets before packet `n` are skipped without copying their data.
+    ///
+    /// Afterwards, the reader is positioned right after packet `n`, or at the end of the pcap if it has less packets.
+    pub fn nth_packet(&mut self, n: u64, index: Option<&PcapIndex>) -> PcapResult<Option<PcapPacket<'static>>> {
+        match index {
+            Some(index) => {
+                if index.offset_of(self, n)?.is_none() {
+                    self.seek_to(index.end_offset())?;
+                    return Ok(None);
+                }
+            },
+            None => {
+                self.rewind()?;
+                if self.skip_packets(n)? < n {
+                    return Ok(None);
+                }
+            },
+        }
+
+        self.next_packet().transpose().map(|packet| packet.map(PcapPacket::into_owned))
+    }
+
+    /// Returns the position of the next packet with the state of its section, restored by [`PcapReader::restore_position`].
+    pub(crate) fn save_position(&mut self) -> PcapResult<SavedPosition> {
+        Ok(SavedPosition {
+            pos: self.position().map_err(PcapError::IoError)?,
+            parser: self.parser.clone(),
+            nb_packets: self.nb_packets,
+            boundaries: self.boundaries.clone(),
+        })
+    }
+
+    /// Seeks back to a position returned by [`PcapReader::save_position`].
+    pub(crate) fn restore_position(&mut self, saved: SavedPosition) -> PcapResult<()> {
+        let parser = std::mem::replace(&mut self.parser, saved.parser);
+        if let Err(e) = self.seek_to(saved.pos) {
+            self.parser = parser;
+            return Err(e);
+        }
+
+        self.nb_packets = saved.nb_packets;
+        self.boundaries = saved.boundaries;
+        Ok(())
+    }
+
+    /// Seeks back to the first packet of the pcap, under the global header of its first section.
+    pub(crate) fn rewind(&mut self) -> PcapResult<()> {
+        let parser = std::mem::replace(&mut self.parser, self.first_parser.clone());
+        if let Err(e) = self.seek_to(self.data_start) {
+            self.parser = parser;
+            return Err(e);
+        }
+
+        self.nb_packets = 0;
+        self.boundaries.clear();
+        Ok(())
+    }
+
+    /// Returns the position in the underlying reader of the next packet.
+    ///
+    /// The position can be restored later with [`PcapReader::seek_to`].
+    pub fn position(&mut self) -> std::io::Result<u64> {
+        self.reader.position()
+    }
+
+    /// Seeks the underlying reader to the given position, which must be the start of a packet
+    /// or the end of the file (typically a value previously returned by [`PcapReader::position`]).
+    ///
+    /// # Errors
+    /// The position is inside the global header or the data at the given position is not a valid packet,
+    /// in which case the reader is left at its previous position.
+    ///
+    /// The underlying reader can't be seeked.
+    pub fn seek_to(&mut self, pos: u64) -> PcapResult<()> {
+        if pos < self.data_start {
+            return Err(PcapError::InvalidField("PcapReader: position inside the global header"));
+        }
+
+        let old_pos = self.reader.position().map_err(PcapError::IoError)?;
+        self.reader.seek_to(pos).map_err(PcapError::IoError)?;
+
+        // Check that the position is a record boundary by parsing the packet at this position
+        let res = match self.reader.has_data_left() {
+            Ok(true) => {
+                let (parser, truncation) = (&self.parser, self.truncation);
+                let header = parser.header();
+                self.reader
+                    .parse_with_cut(
+                        truncation != TruncationPolicy::Error,
+                        |src| parser.next_raw_packet(src).map(drop),
+                        |src, cut| {
+                            parse_record(parser, truncation, src, cut, |record| {
+                                record.try_into_pcap_packet(header.ts_resolution, header.snaplen)
+                            })
+                        },
+                    )
+                    .map(drop)
+            },
+            Ok(false) => Ok(()),
+            Err(e) => Err(PcapError::IoError(e)),
+        };
+
+        match res {
+            Ok(()) => {
+                self.pending_payload = None;
+                self.reader.seek_to(pos).map_err(PcapError::IoError)
+            },
+            Err(e) => {
+                self.reader.seek_to(old_pos).map_err(PcapError::IoError)?;
+                Err(e)
+            },
+        }
+    }
+}
+
+/// Position of a [`PcapReader`] with the state of its section, see [`PcapReader::save_position`].
+pub(crate) struct SavedPosition {
+    pos: u64,
+    parser: PcapParser,
+    nb_packets: u64,
+    boundaries: Vec<SectionBoundary>,
+}
+
+/// Returns [`PcapError::IncompleteBuffer`] if the slice is shorter than `len`.
+pub(crate) fn check_len(src: &[u8], len: usize) -> PcapResult<()> {
+    if src.len() < len {
+        return Err(PcapError::IncompleteBuffer);
+    }
+
+    Ok(())
+}
+
+/// Returns the `len` bytes starting `skip` bytes after the position of the reader without consuming them,
+/// or `None` if the pcap ends before.
+fn buffer_window<R: Read>(reader: &mut ReadBuffer<R>, skip: usize, len: usize) -> PcapResult<Option<&[u8]>> {
+    match reader.peek_with(|src| check_len(src, skip + len), |src| Ok((src, ()))) {
+        Ok(()) => {},
+        Err(e) if e.is_eof_like() => return Ok(None),
+        Err(e) => return Err(e),
+    }
+
+    Ok(Some(&reader.buffer()[skip..skip + len]))
+}
+
+/// Returns true if the record starting `skip` bytes after the position of the reader, whose captured length is
+/// `incl_len`, is followed by a packet header with plausible lengths or by the end of the pcap.
+fn is_record_followed<R: Read>(parser: &PcapParser, reader: &mut ReadBuffer<R>, skip: usize, incl_len: u32) -> PcapResult<bool> {
+    let end = skip + parser.header().record_header_len() + incl_len as usize;
+    match buffer_window(reader, end, parser.header().record_header_len())? {
+        Some(next) => Ok(parser.are_next_lengths_plausible(next)),
+        // The window can also be bigger than the buffer, the record is then not followed
+        None => Ok(reader.is_eof() && reader.buffer().len() == end),
+    }
+}
+
+/// Record returned by [`parse_record`] and true if it is partial, or `None` if the cut record is discarded.
+type ParsedRecord<T> = Option<(T, bool)>;
+
+/// Parses the next record with `convert`, or the rest of the pcap if it is `cut`, as set by the truncation policy.
+fn parse_record<'a, T>(
+    parser: &PcapParser,
+    truncation: TruncationPolicy,
+    src: &'a [u8],
+    cut: bool,
+    convert: impl FnOnce(RawPcapPacket<'a>) -> PcapResult<T>,
+) -> PcapResult<(&'a [u8], ParsedRecord<T>)> {
+    let (rem, record, partial) = match cut {
+        false => {
+            let (rem, record) = parser.next_raw_packet(src)?;
+            (rem, Some(record), false)
+        },
+        true => {
+            let (rem, record) = partial_record(parser, truncation, src)?;
+            (rem, record, true)
+        },
+    };
+
+    match record {
+        Some(record) => Ok((rem, Some((convert(record)?, partial)))),
+        None => Ok((rem, None)),
+    }
+}
+
+/// Parses the rest of the pcap, which is a cut record, into a partial record if the truncation policy allows it.
+///
+/// The remainder is always empty.
+pub(crate) fn partial_record<'a>(
+    parser: &PcapParser,
+    truncation: TruncationPolicy,
+    src: &'a [u8],
+) -> PcapResult<(&'a [u8], Option<RawPcapPacket<'a>>)> {
+    let rem = &src[src.len()..];
+    match parser.next_packet_header(src) {
+        Ok((data, header)) if truncation == TruncationPolicy::YieldPartial => {
+            let PcapPacketHeader { ts_sec, ts_frac, incl_len, orig_len } = header;
+            let data = Cow::Borrowed(&data[..data.len().min(incl_len as usize)]);
+            Ok((rem, Some(RawPcapPacket { ts_sec, ts_frac, incl_len, orig_len, data })))
+        },
+        Ok(_) => Ok((rem, None)),
+        // The header itself is cut
+        Err(e) if e.is_eof_like() => Ok((rem, None)),
+        Err(e) => Err(e),
+    }
+}
+
+/// Truncates the data to the limit, returns true if it was truncated.
+fn truncate_data(data: &mut Cow<[u8]>, limit: Option<usize>) -> bool {
+    match limit {
+        Some(limit) if data.len() > limit => {
+            match data {
+                Cow::Borrowed(data) => *data = &data[..limit],
+                Cow::Owned(data) => data.truncate(limit),
+            }
+
+            true
+        },
+        _ => false,
+    }
 }
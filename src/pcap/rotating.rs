@@ -0,0 +1,278 @@
+use std::fmt::Debug;
+use std::io::Write;
+
+use super::{CaptureStats, PcapHeader, PcapPacket, PcapWriter, PcapWriterState, RECORD_HEADER_LEN};
+use crate::errors::*;
+use crate::AtomicFile;
+
+
+/// When a [`RotatingPcapWriter`] starts a new file.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum RotationPolicy {
+    /// Starts a new file when the timestamp of a packet enters a new period of N seconds, like `tcpdump -G`.
+    ///
+    /// The periods are aligned on the Unix epoch, i.e. with 3600 a file contains the packets of one hour,
+    /// from hh:00:00 to hh:59:59. The timestamps of the packets are used, not the wall clock, and a packet older
+    /// than the current period is written to the current file.
+    EverySeconds(u64),
+    /// Starts a new file when a packet would make the current file bigger than N bytes, like `tcpdump -C`.
+    ///
+    /// The size includes the global header. A file always contains at least one packet, even if it is bigger than N bytes.
+    /// The size of a packet is computed from its data, before the transform and the fixed caplen of the writer.
+    EveryBytes(u64),
+}
+
+/// Hook configuring the writer of each file, see [`RotatingPcapWriter::set_configure`].
+pub type ConfigureWriter<W> = Box<dyn FnMut(&mut PcapWriter<W>) -> PcapResult<()> + Send>;
+
+/// Writes packets to a sequence of pcaps, starting a new one when the [`RotationPolicy`] says so, e.g. to archive a
+/// continuous capture.
+///
+/// The writers are created by `make_writer`, which gets the index of the file starting at 0, and each one gets its own
+/// global header. The previous writer is flushed and finalized before the next one is created. If the next one can't be
+/// created, the error is returned and it is created again by the next packet. With [`RotatingPcapWriter::new_atomic`],
+/// each file is written atomically.
+///
+/// The transform, the statistics, the epoch offset or the fixed caplen of the writers are set by the hook given to
+/// [`RotatingPcapWriter::set_configure`], called on each new writer. A new file can also be started by the caller,
+/// e.g. one file per burst with [`PcapReader::split_on_gap`](super::PcapReader::split_on_gap), see
+/// [`RotatingPcapWriter::start_new_file`].
+///
+/// # Example
+/// ```rust,no_run
+/// use std::time::Duration;
+///
+/// use pcap_file::pcap::{PcapHeader, PcapPacket, RotatingPcapWriter, RotationPolicy};
+///
+/// // One file per minute
+/// let make_writer = |idx: usize| std::fs::File::create(format!("capture_{idx}.pcap")).unwrap();
+/// let mut writer = RotatingPcapWriter::new(PcapHeader::default(), RotationPolicy::EverySeconds(60), make_writer).unwrap();
+///
+/// writer.write_packet(&PcapPacket::new(Duration::from_secs(10), 4, &[1, 2, 3, 4])).unwrap();
+/// writer.write_packet(&PcapPacket::new(Duration::from_secs(70), 4, &[1, 2, 3, 4])).unwrap();
+///
+/// assert_eq!(writer.file_index(), 1);
+/// ```
+pub struct RotatingPcapWriter<W: Write, F: FnMut(usize) -> W> {
+    header: PcapHeader,
+    policy: RotationPolicy,
+    make_writer: F,
+    /// Writer of the current file, [`None`] if the previous file is finished but the current one couldn't be started
+    writer: Option<PcapWriter<W>>,
+    /// Index of the current file
+    file_index: usize,
+    /// Number of bytes written to the current file, including the global header
+    file_len: u64,
+    /// Number of packets written to the current file
+    file_nb_packets: u64,
+    /// Period of [`RotationPolicy::EverySeconds`] of the current file, set by its first packet
+    period: Option<u64>,
+    /// Finalizes the writer of a file, once flushed
+    finish_writer: fn(W) -> PcapResult<()>,
+    configure: Option<ConfigureWriter<W>>,
+    /// Statistics of the files finished, by file index
+    finished_stats: Vec<Option<CaptureStats>>,
+}
+
+impl<W: Write, F: FnMut(usize) -> W> RotatingPcapWriter<W, F> {
+    /// Creates a new [`RotatingPcapWriter`] and its first file, with the index 0.
+    ///
+    /// # Errors
+    /// The policy has a limit of 0 ([`PcapError::InvalidConfiguration`]), or the header is invalid or the first writer
+    /// can't be written to, see [`PcapWriter::with_header`].
+    pub fn new(header: PcapHeader, policy: RotationPolicy, mut make_writer: F) -> PcapResult<Self> {
+        if let RotationPolicy::EverySeconds(0) | RotationPolicy::EveryBytes(0) = policy {
+            return Err(PcapError::InvalidConfiguration("RotatingPcapWriter: rotation limit == 0"));
+        }
+
+        let writer = PcapWriter::with_header(make_writer(0), header)?;
+
+        Ok(RotatingPcapWriter {
+            header,
+            policy,
+            make_writer,
+            writer: Some(writer),
+            file_index: 0,
+            file_len: 24,
+            file_nb_packets: 0,
+            period: None,
+            finish_writer: |_| Ok(()),
+            configure: None,
+            finished_stats: Vec::new(),
+        })
+    }
+
+    /// Writes a [`PcapPacket`], starting a new file before it if needed.
+    ///
+    /// # Errors
+    /// The packet is invalid, see [`PcapWriter::write_packet`], in which case no file is started.
+    ///
+    /// The previous writer can't be flushed or finished, or the new one can't be written to or configured.
+    pub fn write_packet(&mut self, packet: &PcapPacket) -> PcapResult<usize> {
+        // Validated before rotating, so that an invalid packet doesn't start an empty file
+        PcapWriterState::resume(self.header).packet_header(packet)?;
+
+        let (rotate, period) = match self.policy {
+            RotationPolicy::EverySeconds(secs) => {
+                let period = packet.timestamp.as_secs() / secs;
+                (self.period.is_some_and(|current| period > current), Some(period))
+            },
+            RotationPolicy::EveryBytes(max) => {
+                let record_len = (RECORD_HEADER_LEN + packet.data.len()) as u64;
+                (self.file_nb_packets > 0 && self.file_len + record_len > max, None)
+            },
+        };
+        if rotate {
+            self.rotate()?;
+        }
+        if self.period.is_none() {
+            self.period = period;
+        }
+
+        let writer = match self.writer {
+            Some(ref mut writer) => writer,
+            None => self.start_file()?,
+        };
+        let len = writer.write_packet(packet)?;
+        self.file_len += len as u64;
+        self.file_nb_packets += 1;
+
+        Ok(len)
+    }
+
+    /// Starts a new file, whatever the [`RotationPolicy`], e.g. when [`SplitOnGap::next_packet`](super::SplitOnGap::next_packet)
+    /// marks the start of a new group. Nothing is done if no packet was written to the current file.
+    ///
+    /// # Errors
+    /// The current writer can't be flushed or finished, or the new one can't be written to or configured.
+    pub fn start_new_file(&mut self) -> PcapResult<()> {
+        match self.file_nb_packets {
+            0 => Ok(()),
+            _ => self.rotate(),
+        }
+    }
+
+    /// Flushes and finalizes the current writer, then starts the next file.
+    ///
+    /// The next file is started by the next packet if the current writer can't be finalized.
+    fn rotate(&mut self) -> PcapResult<()> {
+        if let Some(writer) = &mut self.writer {
+            writer.flush()?;
+        }
+
+        if let Some(previous) = self.writer.take() {
+            self.finished_stats.push(previous.stats().copied());
+            self.file_index += 1;
+            self.file_len = 24;
+            self.file_nb_packets = 0;
+            self.period = None;
+            (self.finish_writer)(previous.into_writer())?;
+        }
+        self.start_file()?;
+
+        Ok(())
+    }
+
+    /// Creates the writer of the current file, writes its global header and configures it.
+    fn start_file(&mut self) -> PcapResult<&mut PcapWriter<W>> {
+        let mut writer = PcapWriter::with_header((self.make_writer)(self.file_index), self.header)?;
+        if let Some(configure) = &mut self.configure {
+            configure(&mut writer)?;
+        }
+
+        Ok(self.writer.insert(writer))
+    }
+
+    /// Returns the index of the current file, i.e. the number of files started before it.
+    pub fn file_index(&self) -> usize {
+        self.file_index
+    }
+
+    /// Returns the number of bytes written to the current file, including the global header.
+    pub fn file_len(&self) -> u64 {
+        self.file_len
+    }
+
+    /// Returns the [`PcapWriter`] of the current file, [`None`] if it couldn't be started yet.
+    pub fn get_ref(&self) -> Option<&PcapWriter<W>> {
+        self.writer.as_ref()
+    }
+
+    /// Sets a hook configuring the writer of each file, e.g. to set its transform or to collect its statistics,
+    /// called on the current writer, then on each new writer once its global header is written.
+    ///
+    /// # Errors
+    /// The hook returns an error for the current writer, in which case it isn't kept.
+    pub fn set_configure<C>(&mut self, mut configure: C) -> PcapResult<()>
+    where
+        C: FnMut(&mut PcapWriter<W>) -> PcapResult<()> + Send + 'static,
+    {
+        if let Some(writer) = &mut self.writer {
+            configure(writer)?;
+        }
+        self.configure = Some(Box::new(configure));
+
+        Ok(())
+    }
+
+    /// Returns the statistics of the files finished, by file index, collected if enabled by
+    /// [`PcapWriter::set_collect_stats`] in the hook given to [`RotatingPcapWriter::set_configure`].
+    ///
+    /// The statistics of the current file are returned by the [`PcapWriter::stats`] of [`RotatingPcapWriter::get_ref`].
+    pub fn finished_stats(&self) -> &[Option<CaptureStats>] {
+        &self.finished_stats
+    }
+
+    /// Flushes the current writer.
+    pub fn flush(&mut self) -> PcapResult<()> {
+        match &mut self.writer {
+            Some(writer) => writer.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Consumes [`Self`], returning the writer of the current file, [`None`] if it couldn't be started.
+    pub fn into_writer(self) -> Option<W> {
+        self.writer.map(PcapWriter::into_writer)
+    }
+
+    /// Flushes the current writer and finalizes it, e.g. renames the current file into place for
+    /// [`RotatingPcapWriter::new_atomic`].
+    pub fn finish(self) -> PcapResult<()> {
+        let Some(mut writer) = self.writer else { return Ok(()) };
+        writer.flush()?;
+        (self.finish_writer)(writer.into_writer())
+    }
+}
+
+impl<F: FnMut(usize) -> AtomicFile> RotatingPcapWriter<AtomicFile, F> {
+    /// Creates a new [`RotatingPcapWriter`] writing [`AtomicFile`]s, so that each file is only renamed into place
+    /// once complete: when the next file is started, or by [`RotatingPcapWriter::finish`] for the last one.
+    ///
+    /// `make_writer` is called once the previous file is finished. The temporary file of the current file is
+    /// removed if the writer is dropped without being finished.
+    ///
+    /// # Errors
+    /// See [`RotatingPcapWriter::new`].
+    pub fn new_atomic(header: PcapHeader, policy: RotationPolicy, make_writer: F) -> PcapResult<Self> {
+        let mut writer = RotatingPcapWriter::new(header, policy, make_writer)?;
+        writer.finish_writer = AtomicFile::finish;
+
+        Ok(writer)
+    }
+}
+
+impl<W: Write + Debug, F: FnMut(usize) -> W> Debug for RotatingPcapWriter<W, F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RotatingPcapWriter")
+            .field("header", &self.header)
+            .field("policy", &self.policy)
+            .field("writer", &self.writer)
+            .field("file_index", &self.file_index)
+            .field("file_len", &self.file_len)
+            .field("file_nb_packets", &self.file_nb_packets)
+            .field("period", &self.period)
+            .field("finished_stats", &self.finished_stats.len())
+            .finish_non_exhaustive()
+    }
+}
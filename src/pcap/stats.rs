@@ -0,0 +1,49 @@
+use std::time::Duration;
+
+use super::PcapPacketHeader;
+use crate::TsResolution;
+
+
+/// Statistics of the packets of a capture, see [`PcapReader::capture_stats`](super::PcapReader::capture_stats)
+/// and [`PcapWriter::set_collect_stats`](super::PcapWriter::set_collect_stats).
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CaptureStats {
+    /// Number of packets
+    pub nb_packets: u64,
+    /// Number of bytes of data captured, i.e. the sum of the `incl_len` of the packets
+    pub nb_bytes: u64,
+    /// Number of bytes of the original packets, i.e. the sum of the `orig_len` of the packets
+    pub nb_orig_bytes: u64,
+    /// Number of packets whose data is truncated, i.e. whose `incl_len` is smaller than their `orig_len`
+    pub nb_truncated: u64,
+    /// Timestamp of the first packet, in the order of the capture
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::nanos"))]
+    pub first_timestamp: Option<Duration>,
+    /// Timestamp of the last packet, in the order of the capture
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::nanos"))]
+    pub last_timestamp: Option<Duration>,
+}
+
+impl CaptureStats {
+    /// Adds a packet from its record header, whose timestamp is in the given resolution.
+    ///
+    /// The header isn't validated: a fractional part of the timestamp bigger than a second is added to the seconds.
+    pub fn add(&mut self, header: &PcapPacketHeader, ts_resolution: TsResolution) {
+        let ts_nsec = match ts_resolution {
+            TsResolution::MicroSecond => header.ts_frac as u64 * 1000,
+            TsResolution::NanoSecond => header.ts_frac as u64,
+        };
+        let timestamp = Duration::from_secs(header.ts_sec as u64) + Duration::from_nanos(ts_nsec);
+
+        self.nb_packets += 1;
+        self.nb_bytes += header.incl_len as u64;
+        self.nb_orig_bytes += header.orig_len as u64;
+        if header.incl_len < header.orig_len {
+            self.nb_truncated += 1;
+        }
+
+        self.first_timestamp.get_or_insert(timestamp);
+        self.last_timestamp = Some(timestamp);
+    }
+}
@@ -1,11 +1,16 @@
-use std::io::Write;
+use std::borrow::Cow;
+use std::fmt::Debug;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::Duration;
 
-use byteorder_slice::{BigEndian, LittleEndian};
-
-use super::RawPcapPacket;
+use super::packet::shift_timestamp;
+use super::{CaptureStats, EpochShiftPolicy, PcapPacketHeader, PcapWriterState, RawPcapPacket, DEFAULT_MAX_INCL_LEN, RECORD_HEADER_LEN};
 use crate::errors::*;
 use crate::pcap::{PcapHeader, PcapPacket};
-use crate::{Endianness, TsResolution};
+use crate::common::DebugBytes;
+use crate::{AtomicFile, DataLink, Endianness, TsResolution};
 
 
 /// Writes a pcap to a writer.
@@ -31,14 +36,83 @@ use crate::{Endianness, TsResolution};
 ///     pcap_writer.write_packet(&pkt).unwrap();
 /// }
 /// ```
-#[derive(Debug)]
 pub struct PcapWriter<W: Write> {
-    endianness: Endianness,
-    snaplen: u32,
-    ts_resolution: TsResolution,
+    state: PcapWriterState,
+    autoflush: bool,
+    /// Length to which the data of all the packets is padded or truncated
+    fixed_caplen: Option<u32>,
+    epoch_offset: i64,
+    epoch_shift: EpochShiftPolicy,
+    /// Statistics of the packets written, if enabled
+    stats: Option<CaptureStats>,
+    /// Timestamp of the next packet written by [`PcapWriter::write_auto`]
+    auto_timestamp: Duration,
+    /// Increment of the timestamp after each packet written by [`PcapWriter::write_auto`]
+    auto_step: Duration,
+    transform: Option<PacketTransform>,
+    /// Data of the packet given to the transform
+    transform_buf: Vec<u8>,
+    /// Length of the pcap written, including the global header
+    position: u64,
     writer: W,
 }
 
+/// Hook called on each packet before it is written, see [`PcapWriter::set_transform`].
+pub type PacketTransform = Box<dyn FnMut(&mut PcapPacketHeader, &mut TransformData) -> PcapResult<Keep> + Send>;
+
+/// Data of a packet given to a transform, see [`PcapWriter::set_transform`].
+///
+/// The data of a borrowed packet are only copied when the transform modifies them, into a buffer reused for all
+/// the packets. The data of an owned packet given to [`PcapWriter::write_packet_owned`] are never copied.
+pub struct TransformData<'a> {
+    /// Data of a borrowed packet, until they are modified
+    borrowed: Option<&'a [u8]>,
+    buf: &'a mut Vec<u8>,
+}
+
+impl TransformData<'_> {
+    /// Returns the data to modify them, copying them first if they are borrowed.
+    pub fn to_mut(&mut self) -> &mut Vec<u8> {
+        if let Some(data) = self.borrowed.take() {
+            self.buf.clear();
+            self.buf.extend_from_slice(data);
+        }
+
+        self.buf
+    }
+
+    /// Returns true if the data are the ones of a borrowed packet, i.e. they haven't been copied.
+    pub fn is_borrowed(&self) -> bool {
+        self.borrowed.is_some()
+    }
+}
+
+impl std::ops::Deref for TransformData<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.borrowed.unwrap_or(&self.buf[..])
+    }
+}
+
+impl Debug for TransformData<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TransformData")
+            .field("data", &DebugBytes(self))
+            .field("borrowed", &self.is_borrowed())
+            .finish()
+    }
+}
+
+/// Tells a [`PcapWriter`] if the packet given to its transform must be written.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Keep {
+    /// Write the packet
+    Yes,
+    /// Drop the packet
+    No,
+}
+
 impl<W: Write> PcapWriter<W> {
     /// Creates a new [`PcapWriter`] from an existing writer.
     ///
@@ -71,16 +145,74 @@ impl<W: Write> PcapWriter<W> {
     /// It also writes the pcap header to the file.
     ///
     /// # Errors
-    /// The writer can't be written to.
+    /// The header is invalid, see [`PcapHeader::validate`]. Nothing is written to the writer in this case.
+    ///
+    /// The writer can't be written to ([`PcapError::IoError`]).
     pub fn with_header(mut writer: W, header: PcapHeader) -> PcapResult<PcapWriter<W>> {
-        header.write_to(&mut writer)?;
+        // Validated before writing anything, so that an invalid header leaves the writer untouched
+        let (header_bytes, state) = PcapWriterState::start(header)?;
+        writer.write_all(&header_bytes).map_err(PcapError::IoError)?;
+
+        Ok(PcapWriter::from_state(state, header_bytes.len() as u64, writer))
+    }
 
-        Ok(PcapWriter {
-            endianness: header.endianness,
-            snaplen: header.snaplen,
-            ts_resolution: header.ts_resolution,
+    /// Creates a new [`PcapWriter`] whose global header is already written, the pcap being `position` bytes long.
+    fn from_state(state: PcapWriterState, position: u64, writer: W) -> Self {
+        PcapWriter {
+            state,
+            autoflush: false,
+            fixed_caplen: None,
+            epoch_offset: 0,
+            epoch_shift: EpochShiftPolicy::Error,
+            stats: None,
+            auto_timestamp: Duration::ZERO,
+            auto_step: resolution_unit(state.header().ts_resolution),
+            transform: None,
+            transform_buf: Vec::new(),
+            position,
             writer,
-        })
+        }
+    }
+
+    /// Creates a new [`PcapWriter`] writing the data of all the packets with exactly `caplen` bytes, e.g. for
+    /// replay hardware requiring a fixed `incl_len`.
+    ///
+    /// The data of each packet is truncated or padded with zeros to `caplen` bytes, and its `incl_len` is set to
+    /// `caplen`, while its `orig_len` is kept. The padding changes the data, so it is lossy for the short packets:
+    /// their padded records have an `incl_len` bigger than their `orig_len`, so they can only be read back as
+    /// [`RawPcapPacket`]s.
+    ///
+    /// All the packets are padded, including the ones written by [`PcapWriter::write_raw_packet`] and the ones
+    /// returned by the transform, but not the ones written by [`PcapWriter::write_raw_packet_unchecked`].
+    ///
+    /// # Errors
+    /// `caplen` is bigger than the snaplen of the header or the header is invalid ([`PcapError::InvalidConfiguration`]).
+    /// Nothing is written to the writer in this case.
+    ///
+    /// The writer can't be written to ([`PcapError::IoError`]).
+    pub fn with_fixed_caplen(writer: W, header: PcapHeader, caplen: u32) -> PcapResult<PcapWriter<W>> {
+        if caplen > header.snaplen {
+            return Err(PcapError::InvalidConfiguration("PcapWriter: fixed caplen > snaplen"));
+        }
+
+        let mut pcap_writer = PcapWriter::with_header(writer, header)?;
+        pcap_writer.fixed_caplen = Some(caplen);
+
+        Ok(pcap_writer)
+    }
+
+    /// Sets the length to which the data of the next packets is truncated or padded, see [`PcapWriter::with_fixed_caplen`],
+    /// or writes them with their own length if `None` (the default).
+    ///
+    /// # Errors
+    /// `caplen` is bigger than the snaplen of the header ([`PcapError::InvalidConfiguration`]).
+    pub fn set_fixed_caplen(&mut self, caplen: Option<u32>) -> PcapResult<()> {
+        if caplen.is_some_and(|caplen| caplen > self.state.header().snaplen) {
+            return Err(PcapError::InvalidConfiguration("PcapWriter: fixed caplen > snaplen"));
+        }
+
+        self.fixed_caplen = caplen;
+        Ok(())
     }
 
     /// Consumes [`Self`], returning the wrapped writer.
@@ -88,22 +220,407 @@ impl<W: Write> PcapWriter<W> {
         self.writer
     }
 
+    /// Get a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
     /// Writes a [`PcapPacket`].
+    ///
+    /// Returns 0 if the packet was dropped by the transform.
     pub fn write_packet(&mut self, packet: &PcapPacket) -> PcapResult<usize> {
-        match self.endianness {
-            Endianness::Big => packet.write_to::<_, BigEndian>(&mut self.writer, self.ts_resolution, self.snaplen),
-            Endianness::Little => packet.write_to::<_, LittleEndian>(&mut self.writer, self.ts_resolution, self.snaplen),
+        self.write_packet_inner(PcapPacket { timestamp: packet.timestamp, orig_len: packet.orig_len, data: Cow::Borrowed(&packet.data) })
+    }
+
+    /// Writes a [`PcapPacket`] taken by value.
+    ///
+    /// It is the same as [`PcapWriter::write_packet`], except that the data of an owned packet are given to the
+    /// transform set by [`PcapWriter::set_transform`] without being copied. Without transform, both are equivalent.
+    ///
+    /// Returns 0 if the packet was dropped by the transform.
+    pub fn write_packet_owned(&mut self, packet: PcapPacket) -> PcapResult<usize> {
+        self.write_packet_inner(packet)
+    }
+
+    /// Writes a packet shifted by the epoch offset, giving its data to the transform without copy if they are owned.
+    fn write_packet_inner(&mut self, mut packet: PcapPacket) -> PcapResult<usize> {
+        if self.epoch_offset != 0 {
+            let offset = self.epoch_offset.checked_neg().ok_or(PcapError::InvalidField("PcapWriter: invalid epoch offset"))?;
+            packet.timestamp = shift_timestamp(packet.timestamp, offset, self.epoch_shift)?;
+        }
+
+        if self.transform.is_some() {
+            let header = packet.record_header(self.state.header().ts_resolution)?;
+            return match packet.data {
+                Cow::Borrowed(data) => self.write_transformed(header, data, true),
+                Cow::Owned(data) => self.transform_data(header, None, data, true),
+            };
         }
+
+        if let Some(caplen) = self.fixed_caplen {
+            let header = packet.record_header(self.state.header().ts_resolution)?;
+            if header.incl_len > header.orig_len {
+                return Err(PcapError::InvalidField("PcapPacket: incl_len > orig_len"));
+            }
+            return self.write_fixed_caplen(caplen, header, &packet.data);
+        }
+
+        let header = self.state.packet_header(&packet)?;
+        self.write_record(header, &packet.data)
+    }
+
+    /// Writes a packet from its timestamp and its data, which can be anything convertible to a byte slice
+    /// (`Vec<u8>`, `&[u8]`, arrays...).
+    ///
+    /// `ts_frac` is the fractional part of the timestamp in the timestamp resolution of the writer, i.e. microseconds
+    /// for [`TsResolution::MicroSecond`] and nanoseconds for [`TsResolution::NanoSecond`], like in the records of the pcap.
+    /// Use [`PcapWriter::write_micros`], [`PcapWriter::write_nanos`] or [`PcapWriter::write_at`] to give a timestamp
+    /// whose unit doesn't depend on the writer.
+    ///
+    /// The original length of the packet is the length of the data.
+    ///
+    /// # Errors
+    /// `ts_frac` is a second or more in the timestamp resolution of the writer.
+    pub fn write_data<D: AsRef<[u8]>>(&mut self, ts_sec: u32, ts_frac: u32, data: D) -> PcapResult<usize> {
+        let ts_nsec = match self.state.header().ts_resolution {
+            TsResolution::MicroSecond if ts_frac < 1_000_000 => ts_frac * 1000,
+            TsResolution::NanoSecond if ts_frac < 1_000_000_000 => ts_frac,
+            _ => return Err(PcapError::InvalidField("PcapWriter: ts_frac out of range for the timestamp resolution")),
+        };
+
+        self.write_at(Duration::new(ts_sec as u64, ts_nsec), data)
+    }
+
+    /// Writes a packet from its timestamp, with its fractional part in microseconds whatever the resolution
+    /// of the writer, and its data.
+    ///
+    /// The original length of the packet is the length of the data.
+    ///
+    /// # Errors
+    /// `ts_usec` is a second or more.
+    pub fn write_micros<D: AsRef<[u8]>>(&mut self, ts_sec: u32, ts_usec: u32, data: D) -> PcapResult<usize> {
+        if ts_usec >= 1_000_000 {
+            return Err(PcapError::InvalidField("PcapWriter: ts_usec >= 1_000_000"));
+        }
+
+        self.write_at(Duration::new(ts_sec as u64, ts_usec * 1000), data)
+    }
+
+    /// Writes a packet from its timestamp, with its fractional part in nanoseconds whatever the resolution
+    /// of the writer, and its data.
+    ///
+    /// With [`TsResolution::MicroSecond`], the nanoseconds are truncated to microseconds.
+    /// The original length of the packet is the length of the data.
+    ///
+    /// # Errors
+    /// `ts_nsec` is a second or more.
+    pub fn write_nanos<D: AsRef<[u8]>>(&mut self, ts_sec: u32, ts_nsec: u32, data: D) -> PcapResult<usize> {
+        if ts_nsec >= 1_000_000_000 {
+            return Err(PcapError::InvalidField("PcapWriter: ts_nsec >= 1_000_000_000"));
+        }
+
+        self.write_at(Duration::new(ts_sec as u64, ts_nsec), data)
+    }
+
+    /// Writes a packet from its timestamp and its data.
+    ///
+    /// With [`TsResolution::MicroSecond`], the nanoseconds of the timestamp are truncated to microseconds.
+    /// The original length of the packet is the length of the data.
+    pub fn write_at<D: AsRef<[u8]>>(&mut self, timestamp: Duration, data: D) -> PcapResult<usize> {
+        let data = data.as_ref();
+        let orig_len = data.len().try_into().map_err(|_| PcapError::InvalidField("PcapPacket: incl_len > u32::MAX"))?;
+
+        self.write_packet(&PcapPacket::new(timestamp, orig_len, data))
+    }
+
+    /// Writes a packet from its timestamp, its original length and its data in a [`bytes::Buf`],
+    /// e.g. a [`bytes::Bytes`] returned by a [`PcapBytesReader`](super::PcapBytesReader).
+    ///
+    /// The data are only copied if they aren't contiguous.
+    ///
+    /// Only available with the `bytes` feature.
+    #[cfg(feature = "bytes")]
+    pub fn write_buf<B: bytes::Buf>(&mut self, timestamp: Duration, orig_len: u32, mut data: B) -> PcapResult<usize> {
+        if data.chunk().len() == data.remaining() {
+            return self.write_packet(&PcapPacket::new(timestamp, orig_len, data.chunk()));
+        }
+
+        let data = data.copy_to_bytes(data.remaining());
+        self.write_packet(&PcapPacket::new(timestamp, orig_len, &data))
+    }
+
+    /// Writes a packet with the given data and an automatic timestamp, e.g. to generate synthetic traffic.
+    ///
+    /// The first packet has the timestamp set by [`PcapWriter::set_timestamp_start`] (0 by default), and each call
+    /// increments the timestamp by the step set by [`PcapWriter::set_timestamp_step`] (the timestamp resolution of
+    /// the writer by default). The original length of the packet is the length of the data.
+    ///
+    /// The timestamp is only incremented if the packet is written or dropped by the transform.
+    pub fn write_auto(&mut self, data: &[u8]) -> PcapResult<usize> {
+        let orig_len = data.len().try_into().map_err(|_| PcapError::InvalidField("PcapPacket: incl_len > u32::MAX"))?;
+        let len = self.write_packet(&PcapPacket::new(self.auto_timestamp, orig_len, data))?;
+
+        self.auto_timestamp = self.auto_timestamp.saturating_add(self.auto_step);
+        Ok(len)
+    }
+
+    /// Sets the timestamp of the next packet written by [`PcapWriter::write_auto`].
+    ///
+    /// It is truncated to the timestamp resolution of the writer when the packet is written.
+    pub fn set_timestamp_start(&mut self, start: Duration) {
+        self.auto_timestamp = start;
+    }
+
+    /// Sets the increment of the timestamp after each packet written by [`PcapWriter::write_auto`].
+    ///
+    /// # Errors
+    /// The step is zero or is not a multiple of the timestamp resolution of the writer, e.g. 1500ns with
+    /// [`TsResolution::MicroSecond`], which would not give evenly increasing timestamps.
+    pub fn set_timestamp_step(&mut self, step: Duration) -> PcapResult<()> {
+        let unit = resolution_unit(self.state.header().ts_resolution).as_nanos();
+        if step.is_zero() || !step.as_nanos().is_multiple_of(unit) {
+            return Err(PcapError::InvalidField("PcapWriter: timestamp step not a multiple of the timestamp resolution"));
+        }
+
+        self.auto_step = step;
+        Ok(())
     }
 
     /// Writes a [`RawPcapPacket`].
+    ///
+    /// Only its `incl_len` is validated, its other fields are written as is.
+    /// Use [`PcapWriter::write_raw_packet_unchecked`] to write a record whose `incl_len` isn't the length of the data.
+    ///
+    /// Returns 0 if the packet was dropped by the transform.
+    ///
+    /// # Errors
+    /// The `incl_len` of the packet is not the length of its data ([`PcapError::LengthMismatch`]).
     pub fn write_raw_packet(&mut self, packet: &RawPcapPacket) -> PcapResult<usize> {
-        match self.endianness {
-            Endianness::Big => packet.write_to::<_, BigEndian>(&mut self.writer),
-            Endianness::Little => packet.write_to::<_, LittleEndian>(&mut self.writer),
+        let header = self.state.raw_packet_header(packet)?;
+
+        if self.transform.is_some() {
+            return self.write_transformed(header, &packet.data, false);
+        }
+
+        if let Some(caplen) = self.fixed_caplen {
+            return self.write_fixed_caplen(caplen, header, &packet.data);
+        }
+
+        self.write_record(header, &packet.data)
+    }
+
+    /// Writes a [`RawPcapPacket`] trusting its `incl_len`, e.g. to craft malformed pcaps to test their readers.
+    ///
+    /// Exactly `incl_len` bytes of data are written, the extra bytes being dropped. None of the fields are validated,
+    /// and the packet doesn't go through the transform nor the fixed caplen, so the record is written as given.
+    ///
+    /// # Errors
+    /// The data of the packet are shorter than its `incl_len` ([`PcapError::LengthMismatch`]).
+    pub fn write_raw_packet_unchecked(&mut self, packet: &RawPcapPacket) -> PcapResult<usize> {
+        let RawPcapPacket { ts_sec, ts_frac, incl_len, orig_len, .. } = *packet;
+        let data = packet
+            .data
+            .get(..incl_len as usize)
+            .ok_or(PcapError::LengthMismatch { incl_len, data_len: packet.data.len() })?;
+
+        self.write_record(PcapPacketHeader { ts_sec, ts_frac, incl_len, orig_len }, data)
+    }
+
+    /// Flushes the underlying writer after each packet written if `autoflush` is true (false by default).
+    ///
+    /// It makes the packets visible immediately, e.g. to another process reading a growing pcap,
+    /// but it costs a flush (typically a system call) per packet, which greatly reduces the throughput.
+    pub fn set_autoflush(&mut self, autoflush: bool) {
+        self.autoflush = autoflush;
+    }
+
+    /// Sets a transform called on each packet before it is written, e.g. to anonymize it.
+    ///
+    /// The transform gets the record header, in the timestamp resolution of the writer, and the data of the packet,
+    /// which are only copied if the transform modifies them, see [`TransformData`].
+    /// It can modify both, the `incl_len` of the header being then set to the length of the data,
+    /// and returns [`Keep::No`] to drop the packet. Its errors are returned by the write methods.
+    ///
+    /// All the packets go through the transform, including the ones written by [`PcapWriter::write_raw_packet`],
+    /// except the ones written by [`PcapWriter::write_raw_packet_unchecked`].
+    /// The packets returned by the transform are validated like the ones given to [`PcapWriter::write_packet`],
+    /// except the ones coming from [`PcapWriter::write_raw_packet`].
+    pub fn set_transform<F>(&mut self, transform: F)
+    where
+        F: FnMut(&mut PcapPacketHeader, &mut TransformData) -> PcapResult<Keep> + Send + 'static,
+    {
+        self.transform = Some(Box::new(transform));
+    }
+
+    /// Removes the transform set by [`PcapWriter::set_transform`].
+    pub fn remove_transform(&mut self) {
+        self.transform = None;
+    }
+
+    /// Writes a packet through the transform, validating it if `validate` is true.
+    fn write_transformed(&mut self, header: PcapPacketHeader, data: &[u8], validate: bool) -> PcapResult<usize> {
+        let buf = std::mem::take(&mut self.transform_buf);
+        self.transform_data(header, Some(data), buf, validate)
+    }
+
+    /// Gives the data to the transform, the borrowed ones if any, else the ones of `buf`, and writes the packet returned.
+    /// `buf` is then kept to be reused.
+    fn transform_data(
+        &mut self,
+        mut header: PcapPacketHeader,
+        borrowed: Option<&[u8]>,
+        mut buf: Vec<u8>,
+        validate: bool,
+    ) -> PcapResult<usize> {
+        // Can unwrap because the callers check that there is a transform
+        let transform = self.transform.as_mut().unwrap();
+
+        let mut data = TransformData { borrowed, buf: &mut buf };
+        let keep = transform(&mut header, &mut data);
+        let res = match keep {
+            Ok(Keep::Yes) => self.write_transformed_packet(header, &data, validate),
+            Ok(Keep::No) => Ok(0),
+            Err(e) => Err(e),
+        };
+
+        self.transform_buf = buf;
+        res
+    }
+
+    /// Writes a packet returned by the transform.
+    fn write_transformed_packet(&mut self, header: PcapPacketHeader, data: &[u8], validate: bool) -> PcapResult<usize> {
+        let incl_len = data.len().try_into().map_err(|_| PcapError::InvalidField("PcapPacket: incl_len > u32::MAX"))?;
+        let PcapPacketHeader { ts_sec, ts_frac, orig_len, .. } = header;
+        let raw_packet = RawPcapPacket { ts_sec, ts_frac, incl_len, orig_len, data: Cow::Borrowed(data) };
+
+        let header = if validate {
+            let PcapHeader { ts_resolution, snaplen, .. } = self.state.header();
+            let packet = raw_packet.try_into_pcap_packet(ts_resolution, snaplen)?;
+            self.state.packet_header(&packet)?
+        }
+        else {
+            PcapPacketHeader { incl_len, ..header }
+        };
+
+        match self.fixed_caplen {
+            Some(caplen) => self.write_fixed_caplen(caplen, header, data),
+            None => self.write_record(header, data),
+        }
+    }
+
+    /// Writes a record, whose header is already validated if needed.
+    fn write_record(&mut self, header: PcapPacketHeader, data: &[u8]) -> PcapResult<usize> {
+        let len = self.state.write_record(&mut self.writer, &header, data)?;
+        self.position += len as u64;
+
+        self.count_packet(&header);
+        self.autoflush()?;
+        Ok(len)
+    }
+
+    /// Writes a packet whose data is truncated or padded with zeros to `caplen` bytes.
+    fn write_fixed_caplen(&mut self, caplen: u32, header: PcapPacketHeader, data: &[u8]) -> PcapResult<usize> {
+        let data = &data[..data.len().min(caplen as usize)];
+        let header = PcapPacketHeader { incl_len: caplen, ..header };
+
+        // The padding isn't in the data, so it is written after it
+        let len = self.state.write_record(&mut self.writer, &header, data)?;
+        let padding = caplen as u64 - data.len() as u64;
+        std::io::copy(&mut std::io::repeat(0).take(padding), &mut self.writer).map_err(PcapError::IoError)?;
+        self.position += len as u64 + padding;
+
+        self.count_packet(&header);
+        self.autoflush()?;
+        Ok(len + padding as usize)
+    }
+
+    /// Writes a padding record so that the length of the pcap is a multiple of `block_size`, e.g. to store the
+    /// archived pcaps in whole blocks of 4 KiB. It is meant to be called once, after the last packet.
+    ///
+    /// The padding record has the reserved header returned by [`PcapPacketHeader::padding`] and zeros as data,
+    /// it is at least 16 bytes long, so up to `block_size + 15` bytes are written. Nothing is written if the length
+    /// is already a multiple of `block_size`. The length of a pcap given to [`PcapWriter::with_header`] is counted
+    /// from its global header, the one of a file opened by [`PcapWriter::append_path`] from the start of the file.
+    ///
+    /// The padding record isn't a valid packet: the readers must skip it, see
+    /// [`PcapReader::with_ignore_padding`](super::PcapReader::with_ignore_padding).
+    ///
+    /// Returns the number of bytes written.
+    ///
+    /// # Errors
+    /// `block_size` is 0 or bigger than [`DEFAULT_MAX_INCL_LEN`](super::DEFAULT_MAX_INCL_LEN),
+    /// which bounds the `incl_len` of the padding record.
+    ///
+    /// The writer can't be written to ([`PcapError::IoError`]).
+    pub fn pad_to(&mut self, block_size: u32) -> PcapResult<usize> {
+        if block_size == 0 || block_size > DEFAULT_MAX_INCL_LEN {
+            return Err(PcapError::InvalidField("PcapWriter: block_size not in [1, DEFAULT_MAX_INCL_LEN]"));
+        }
+
+        let block_size = block_size as u64;
+        let mut len = (block_size - self.position % block_size) % block_size;
+        if len == 0 {
+            return Ok(0);
+        }
+        while len < RECORD_HEADER_LEN as u64 {
+            len += block_size;
+        }
+
+        let padding = len - RECORD_HEADER_LEN as u64;
+        self.state.write_record(&mut self.writer, &PcapPacketHeader::padding(padding as u32), &[])?;
+        std::io::copy(&mut std::io::repeat(0).take(padding), &mut self.writer).map_err(PcapError::IoError)?;
+        self.position += len;
+
+        self.autoflush()?;
+        Ok(len as usize)
+    }
+
+    /// Sets the number of seconds, which can be negative, that the readers of the pcap add to its timestamps
+    /// to get Unix timestamps, see [`PcapReader::with_epoch_offset`](super::PcapReader::with_epoch_offset).
+    ///
+    /// The offset is subtracted from the timestamps of the [`PcapPacket`]s written, the raw packets are written as is.
+    /// A packet whose shifted timestamp is before the epoch of the pcap is handled according to
+    /// [`PcapWriter::set_epoch_shift_policy`], rejected by default.
+    pub fn set_epoch_offset(&mut self, offset: i64) {
+        self.epoch_offset = offset;
+    }
+
+    /// Sets what the writer does with a packet whose timestamp is out of range once shifted by the epoch offset
+    /// ([`EpochShiftPolicy::Error`] by default), see [`PcapWriter::set_epoch_offset`].
+    pub fn set_epoch_shift_policy(&mut self, policy: EpochShiftPolicy) {
+        self.epoch_shift = policy;
+    }
+
+    /// Collects the [`CaptureStats`] of the packets written from now on if `collect` is true (false by default),
+    /// e.g. to build a manifest of the pcap without reading it again.
+    ///
+    /// The statistics are the ones of the records written, after the transform and the fixed caplen,
+    /// including the raw packets. Enabling it again resets the statistics.
+    pub fn set_collect_stats(&mut self, collect: bool) {
+        self.stats = collect.then(CaptureStats::default);
+    }
+
+    /// Returns the statistics of the packets written, if enabled by [`PcapWriter::set_collect_stats`].
+    pub fn stats(&self) -> Option<&CaptureStats> {
+        self.stats.as_ref()
+    }
+
+    /// Adds a packet written to the statistics, if enabled.
+    fn count_packet(&mut self, header: &PcapPacketHeader) {
+        if let Some(stats) = &mut self.stats {
+            stats.add(header, self.state.header().ts_resolution);
         }
     }
 
+    /// Flushes the underlying writer if autoflush is enabled.
+    fn autoflush(&mut self) -> PcapResult<()> {
+        if self.autoflush {
+            self.flush()?;
+        }
+
+        Ok(())
+    }
+
     /// Flush data
     pub fn flush(&mut self) -> PcapResult<()> {
         self.writer.flush().map_err(PcapError::IoError)
@@ -111,17 +628,159 @@ impl<W: Write> PcapWriter<W> {
 
     /// Returns the endianess used by the writer.
     pub fn endianness(&self) -> Endianness {
-        self.endianness
+        self.state.header().endianness
     }
 
     /// Returns the snaplen used by the writer, i.e. an unsigned value indicating the maximum number of octets captured
     /// from each packet.
     pub fn snaplen(&self) -> u32 {
-        self.snaplen
+        self.state.header().snaplen
     }
 
     /// Returns the timestamp resolution of the writer.
     pub fn ts_resolution(&self) -> TsResolution {
-        self.ts_resolution
+        self.state.header().ts_resolution
+    }
+
+    /// Returns the [`PcapWriterState`] serializing the packets, e.g. to serialize some packets in memory.
+    pub fn state(&self) -> &PcapWriterState {
+        &self.state
+    }
+}
+
+impl<W: Write + Debug> Debug for PcapWriter<W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PcapWriter")
+            .field("state", &self.state)
+            .field("autoflush", &self.autoflush)
+            .field("fixed_caplen", &self.fixed_caplen)
+            .field("epoch_offset", &self.epoch_offset)
+            .field("epoch_shift", &self.epoch_shift)
+            .field("stats", &self.stats)
+            .field("auto_timestamp", &self.auto_timestamp)
+            .field("auto_step", &self.auto_step)
+            .field("transform", &self.transform.is_some())
+            .field("position", &self.position)
+            .field("writer", &std::any::type_name::<W>())
+            .finish_non_exhaustive()
+    }
+}
+
+impl PcapWriter<BufWriter<File>> {
+    /// Creates the file at the given path, truncating it if it exists, and writes the given header to it.
+    ///
+    /// The file is wrapped in a [`BufWriter`], call [`PcapWriter::flush`] to get the write errors.
+    ///
+    /// # Errors
+    /// The header is invalid, see [`PcapWriter::with_header`], the file isn't created in this case.
+    ///
+    /// The file can't be created or written to.
+    /// The error is a [`PcapError::WithPath`] containing the path of the file.
+    pub fn create(path: impl AsRef<Path>, header: PcapHeader) -> PcapResult<Self> {
+        let path = path.as_ref();
+        header.validate().map_err(|e| e.with_path(path))?;
+        let file = File::create(path).map_err(|e| PcapError::IoError(e).with_path(path))?;
+
+        PcapWriter::with_header(BufWriter::new(file), header).map_err(|e| e.with_path(path))
+    }
+
+    /// Opens the pcap file at the given path to append packets to it, using the parameters of its header.
+    ///
+    /// If the file doesn't exist or is empty, it is created with the default header (see [`PcapWriter::new`]).
+    ///
+    /// The file is wrapped in a [`BufWriter`], call [`PcapWriter::flush`] to get the write errors.
+    ///
+    /// # Errors
+    /// The file can't be opened or doesn't start with a valid pcap header.
+    /// The error is a [`PcapError::WithPath`] containing the path of the file.
+    pub fn append_path(path: impl AsRef<Path>) -> PcapResult<Self> {
+        let path = path.as_ref();
+        Self::append_path_inner(path).map_err(|e| e.with_path(path))
+    }
+
+    fn append_path_inner(path: &Path) -> PcapResult<Self> {
+        let mut file = OpenOptions::new().read(true).append(true).create(true).open(path).map_err(PcapError::IoError)?;
+
+        let mut header_bytes = Vec::with_capacity(24);
+        (&mut file).take(24).read_to_end(&mut header_bytes).map_err(PcapError::IoError)?;
+        if header_bytes.is_empty() {
+            return PcapWriter::new(BufWriter::new(file));
+        }
+
+        let (_, header) = PcapHeader::from_slice(&header_bytes)?;
+        let position = file.seek(SeekFrom::End(0)).map_err(PcapError::IoError)?;
+
+        Ok(PcapWriter::from_state(PcapWriterState::resume(header), position, BufWriter::new(file)))
+    }
+}
+
+impl PcapWriter<AtomicFile> {
+    /// Creates the pcap at a temporary path next to the given path, and writes the given header to it.
+    /// The pcap is only renamed to the given path by [`PcapWriter::finish`], so that a crashed job never leaves
+    /// a half-written pcap there, see [`AtomicFile`].
+    ///
+    /// The temporary file is removed if the writer is dropped without being finished.
+    ///
+    /// # Errors
+    /// The header is invalid, see [`PcapWriter::with_header`], the file isn't created in this case.
+    ///
+    /// The temporary file can't be created or written to.
+    /// The error is a [`PcapError::WithPath`] containing the path of the file.
+    pub fn create_atomic(path: impl AsRef<Path>, header: PcapHeader) -> PcapResult<Self> {
+        let path = path.as_ref();
+        header.validate().map_err(|e| e.with_path(path))?;
+        let file = AtomicFile::create(path)?;
+
+        PcapWriter::with_header(file, header).map_err(|e| e.with_path(path))
+    }
+
+    /// Flushes and syncs the pcap, then renames it to its final path, see [`AtomicFile::finish`].
+    pub fn finish(self) -> PcapResult<()> {
+        self.into_writer().finish()
+    }
+}
+
+
+/// Returns the smallest duration representable in the given resolution.
+fn resolution_unit(ts_resolution: TsResolution) -> Duration {
+    match ts_resolution {
+        TsResolution::MicroSecond => Duration::from_micros(1),
+        TsResolution::NanoSecond => Duration::from_nanos(1),
+    }
+}
+
+
+/// Returns a transform zeroing the source and destination MAC addresses of the packets of a pcap with the given datalink,
+/// see [`PcapWriter::set_transform`].
+///
+/// The bytes of the addresses that were captured are zeroed if the datalink is [`DataLink::ETHERNET`],
+/// the packets of the other datalinks are left intact. The packet is never dropped.
+pub fn zero_ethernet_addresses(
+    datalink: DataLink,
+) -> impl FnMut(&mut PcapPacketHeader, &mut TransformData) -> PcapResult<Keep> + Send + 'static {
+    let is_ethernet = datalink == DataLink::ETHERNET;
+    move |_, data| {
+        let len = data.len().min(12);
+        if is_ethernet && data[..len].iter().any(|&byte| byte != 0) {
+            data.to_mut()[..len].fill(0);
+        }
+
+        Ok(Keep::Yes)
+    }
+}
+
+/// Returns a transform removing the `fcs_len` bytes of FCS at the end of the packets, see [`PcapWriter::set_transform`].
+///
+/// The classic pcap format doesn't tell if the packets have an FCS, the caller asserts its length, typically 4 for Ethernet.
+/// The original length is reduced by `fcs_len` and the FCS bytes still captured are removed from the data,
+/// the packet is never dropped.
+pub fn strip_fcs(fcs_len: u32) -> impl FnMut(&mut PcapPacketHeader, &mut TransformData) -> PcapResult<Keep> + Send + 'static {
+    move |header, data| {
+        header.orig_len = header.orig_len.saturating_sub(fcs_len);
+        if data.len() > header.orig_len as usize {
+            data.to_mut().truncate(header.orig_len as usize);
+        }
+
+        Ok(Keep::Yes)
     }
 }
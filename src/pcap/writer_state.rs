@@ -0,0 +1,129 @@
+use std::io::Write;
+
+use byteorder_slice::{BigEndian, LittleEndian};
+
+use super::packet::write_record;
+use super::{PcapPacketHeader, RawPcapPacket, RECORD_HEADER_LEN};
+use crate::errors::*;
+use crate::pcap::{PcapHeader, PcapPacket};
+use crate::Endianness;
+
+
+/// Serializes a pcap into bytes, without doing any I/O.
+///
+/// It is the counterpart of the [`PcapParser`](super::PcapParser): the caller owns the buffers and writes them
+/// wherever it wants. The [`PcapWriter`](super::PcapWriter) is built on it.
+///
+/// # Example
+/// ```rust
+/// use std::time::Duration;
+///
+/// use pcap_file::pcap::{PcapHeader, PcapPacket, PcapWriterState};
+///
+/// // Creates a new state and serializes the pcap header
+/// let (mut out, state) = PcapWriterState::start(PcapHeader::default()).unwrap();
+///
+/// let packet = PcapPacket::new(Duration::from_secs(1), 4, &[1, 2, 3, 4]);
+/// state.serialize_packet(&packet, &mut out).unwrap();
+///
+/// assert_eq!(out.len(), 24 + 16 + 4);
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct PcapWriterState {
+    header: PcapHeader,
+}
+
+impl PcapWriterState {
+    /// Creates a new [`PcapWriterState`].
+    ///
+    /// Returns the serialized global header and the state, whose header has the normalized data link
+    /// (see [`DataLink::normalize`](crate::DataLink::normalize)).
+    ///
+    /// # Errors
+    /// The header is invalid, see [`PcapHeader::validate`].
+    pub fn start(header: PcapHeader) -> PcapResult<(Vec<u8>, PcapWriterState)> {
+        header.validate()?;
+        let header = PcapHeader { datalink: header.datalink.normalize(), ..header };
+
+        let mut bytes = vec![0_u8; 24];
+        header.write_to_slice(&mut bytes)?;
+
+        Ok((bytes, PcapWriterState { header }))
+    }
+
+    /// Creates a [`PcapWriterState`] continuing a pcap whose global header is already written, without validating it.
+    pub(crate) fn resume(header: PcapHeader) -> PcapWriterState {
+        PcapWriterState { header }
+    }
+
+    /// Returns the global header of the pcap.
+    pub fn header(&self) -> PcapHeader {
+        self.header
+    }
+
+    /// Returns the record header of a [`PcapPacket`], in the timestamp resolution of the pcap.
+    ///
+    /// # Errors
+    /// The timestamp doesn't fit in the record header, or the length of the data is bigger than the snaplen
+    /// or than the original length.
+    pub fn packet_header(&self, packet: &PcapPacket) -> PcapResult<PcapPacketHeader> {
+        let header = packet.record_header(self.header.ts_resolution)?;
+
+        if header.incl_len > self.header.snaplen {
+            return Err(PcapError::InvalidField("PcapPacket: incl_len > snap_len"));
+        }
+        if header.incl_len > header.orig_len {
+            return Err(PcapError::InvalidField("PcapPacket: incl_len > orig_len"));
+        }
+
+        Ok(header)
+    }
+
+    /// Serializes a record header in the endianness of the pcap, the header isn't validated.
+    pub fn serialize_header(&self, header: &PcapPacketHeader) -> [u8; RECORD_HEADER_LEN] {
+        match self.header.endianness {
+            Endianness::Big => header.to_bytes::<BigEndian>(),
+            Endianness::Little => header.to_bytes::<LittleEndian>(),
+        }
+    }
+
+    /// Writes a record in the endianness of the pcap, the header isn't validated.
+    pub(crate) fn write_record<W: Write>(&self, writer: &mut W, header: &PcapPacketHeader, data: &[u8]) -> PcapResult<usize> {
+        match self.header.endianness {
+            Endianness::Big => write_record::<_, BigEndian>(writer, header, data),
+            Endianness::Little => write_record::<_, LittleEndian>(writer, header, data),
+        }
+    }
+
+    /// Appends the bytes of a [`PcapPacket`] to `buf`, returns the number of bytes appended.
+    ///
+    /// # Errors
+    /// Same as [`PcapWriterState::packet_header`], nothing is appended in this case.
+    pub fn serialize_packet(&self, packet: &PcapPacket, buf: &mut Vec<u8>) -> PcapResult<usize> {
+        let header = self.packet_header(packet)?;
+        self.write_record(buf, &header, &packet.data)
+    }
+
+    /// Returns the record header of a [`RawPcapPacket`], its fields being kept as is.
+    ///
+    /// # Errors
+    /// The `incl_len` of the packet is not the length of its data ([`PcapError::LengthMismatch`]).
+    pub(crate) fn raw_packet_header(&self, packet: &RawPcapPacket) -> PcapResult<PcapPacketHeader> {
+        let RawPcapPacket { ts_sec, ts_frac, incl_len, orig_len, .. } = *packet;
+        if incl_len as usize != packet.data.len() {
+            return Err(PcapError::LengthMismatch { incl_len, data_len: packet.data.len() });
+        }
+
+        Ok(PcapPacketHeader { ts_sec, ts_frac, incl_len, orig_len })
+    }
+
+    /// Appends the bytes of a [`RawPcapPacket`] to `buf`, as is, returns the number of bytes appended.
+    ///
+    /// # Errors
+    /// The `incl_len` of the packet is not the length of its data ([`PcapError::LengthMismatch`]),
+    /// nothing is appended in this case.
+    pub fn serialize_raw_packet(&self, packet: &RawPcapPacket, buf: &mut Vec<u8>) -> PcapResult<usize> {
+        let header = self.raw_packet_header(packet)?;
+        self.write_record(buf, &header, &packet.data)
+    }
+}
@@ -1,6 +1,7 @@
 //! Common block types.
 
 use std::borrow::Cow;
+use std::fmt::Debug;
 use std::io::{Result as IoResult, Write};
 
 use byteorder_slice::byteorder::WriteBytesExt;
@@ -12,11 +13,13 @@ use super::enhanced_packet::EnhancedPacketBlock;
 use super::interface_description::InterfaceDescriptionBlock;
 use super::interface_statistics::InterfaceStatisticsBlock;
 use super::name_resolution::NameResolutionBlock;
+use super::opt_common::MAX_OPTIONS_PER_BLOCK;
 use super::packet::PacketBlock;
 use super::section_header::SectionHeaderBlock;
 use super::simple_packet::SimplePacketBlock;
 use super::systemd_journal_export::SystemdJournalExportBlock;
 use super::unknown::UnknownBlock;
+use crate::common::DebugBytes;
 use crate::errors::PcapError;
 use crate::PcapResult;
 
@@ -37,6 +40,10 @@ pub const INTERFACE_STATISTIC_BLOCK: u32 = 0x00000005;
 pub const ENHANCED_PACKET_BLOCK: u32 = 0x00000006;
 /// Systemd journal export block type
 pub const SYSTEMD_JOURNAL_EXPORT_BLOCK: u32 = 0x00000009;
+/// Custom block type, the block can be copied to a new file
+pub const CUSTOM_BLOCK: u32 = 0x00000BAD;
+/// Custom block type, the block must not be copied to a new file
+pub const CUSTOM_BLOCK_NO_COPY: u32 = 0x40000BAD;
 
 //   0               1               2               3
 //   0 1 2 3 4 5 6 7 0 1 2 3 4 5 6 7 0 1 2 3 4 5 6 7 0 1 2 3 4 5 6 7
@@ -51,7 +58,7 @@ pub const SYSTEMD_JOURNAL_EXPORT_BLOCK: u32 = 0x00000009;
 //  |                      Block Total Length                       |
 //  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
 /// PcapNg Block
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub struct RawBlock<'a> {
     /// Type field
     pub type_: u32,
@@ -142,6 +149,17 @@ impl<'a> RawBlock<'a> {
     }
 }
 
+impl Debug for RawBlock<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RawBlock")
+            .field("type_", &self.type_)
+            .field("initial_len", &self.initial_len)
+            .field("body", &DebugBytes(&self.body))
+            .field("trailer_len", &self.trailer_len)
+            .finish()
+    }
+}
+
 /// PcapNg parsed blocks
 #[derive(Clone, Debug, IntoOwned, Eq, PartialEq)]
 pub enum Block<'a> {
@@ -209,6 +227,11 @@ impl<'a> Block<'a> {
     ///
     /// The RawBlock must be Borrowed.
     pub fn try_from_raw_block<B: ByteOrder>(raw_block: RawBlock<'a>) -> Result<Block<'a>, PcapError> {
+        Self::try_from_raw_block_with_max_options::<B>(raw_block, MAX_OPTIONS_PER_BLOCK)
+    }
+
+    /// Tries to create a [`Block`] from a [`RawBlock`], with at most `max_options` options in the block.
+    pub(crate) fn try_from_raw_block_with_max_options<B: ByteOrder>(raw_block: RawBlock<'a>, max_options: usize) -> PcapResult<Block<'a>> {
         let body = match raw_block.body {
             Cow::Borrowed(b) => b,
             _ => panic!("The raw block is not borrowed"),
@@ -216,15 +239,15 @@ impl<'a> Block<'a> {
 
         match raw_block.type_ {
             SECTION_HEADER_BLOCK => {
-                let (_, block) = SectionHeaderBlock::from_slice::<BigEndian>(body)?;
+                let (_, block) = SectionHeaderBlock::from_slice_with_max_options(body, max_options)?;
                 Ok(Block::SectionHeader(block))
             },
             INTERFACE_DESCRIPTION_BLOCK => {
-                let (_, block) = InterfaceDescriptionBlock::from_slice::<B>(body)?;
+                let (_, block) = InterfaceDescriptionBlock::from_slice_with_max_options::<B>(body, max_options)?;
                 Ok(Block::InterfaceDescription(block))
             },
             PACKET_BLOCK => {
-                let (_, block) = PacketBlock::from_slice::<B>(body)?;
+                let (_, block) = PacketBlock::from_slice_with_max_options::<B>(body, max_options)?;
                 Ok(Block::Packet(block))
             },
             SIMPLE_PACKET_BLOCK => {
@@ -232,15 +255,15 @@ impl<'a> Block<'a> {
                 Ok(Block::SimplePacket(block))
             },
             NAME_RESOLUTION_BLOCK => {
-                let (_, block) = NameResolutionBlock::from_slice::<B>(body)?;
+                let (_, block) = NameResolutionBlock::from_slice_with_max_options::<B>(body, max_options)?;
                 Ok(Block::NameResolution(block))
             },
             INTERFACE_STATISTIC_BLOCK => {
-                let (_, block) = InterfaceStatisticsBlock::from_slice::<B>(body)?;
+                let (_, block) = InterfaceStatisticsBlock::from_slice_with_max_options::<B>(body, max_options)?;
                 Ok(Block::InterfaceStatistics(block))
             },
             ENHANCED_PACKET_BLOCK => {
-                let (_, block) = EnhancedPacketBlock::from_slice::<B>(body)?;
+                let (_, block) = EnhancedPacketBlock::from_slice_with_max_options::<B>(body, max_options)?;
                 Ok(Block::EnhancedPacket(block))
             },
             SYSTEMD_JOURNAL_EXPORT_BLOCK => {
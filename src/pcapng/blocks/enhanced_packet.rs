@@ -2,6 +2,7 @@
 
 use std::borrow::Cow;
 use std::cell::Cell;
+use std::fmt::Debug;
 use std::io::{Result as IoResult, Write};
 use std::time::Duration;
 
@@ -11,13 +12,18 @@ use byteorder_slice::ByteOrder;
 use derive_into_owned::IntoOwned;
 
 use super::block_common::{Block, PcapNgBlock};
-use super::interface_description::TsResolution;
-use super::opt_common::{CustomBinaryOption, CustomUtf8Option, PcapNgOption, UnknownOption, WriteOptTo};
+use super::interface_description::{InterfaceDescriptionBlock, TsResolution};
+use super::opt_common::{CustomBinaryOption, CustomUtf8Option, PcapNgOption, UnknownOption, WriteOptTo, MAX_OPTIONS_PER_BLOCK};
+use crate::common::DebugBytes;
 use crate::errors::PcapError;
 
 
+/// Bits 5-8 of the epb_flags option, giving the FCS length in bytes
+const FLAGS_FCS_LEN_MASK: u32 = 0x1E0;
+const FLAGS_FCS_LEN_SHIFT: u32 = 5;
+
 /// An Enhanced Packet Block (EPB) is the standard container for storing the packets coming from the network.
-#[derive(Clone, Debug, IntoOwned, Eq, PartialEq)]
+#[derive(Clone, IntoOwned, Eq, PartialEq)]
 pub struct EnhancedPacketBlock<'a> {
     /// It specifies the interface this packet comes from.
     ///
@@ -49,6 +55,43 @@ impl<'a> EnhancedPacketBlock<'a> {
         self.write_ts_resolution.set(ts_resolution)
     }
 
+    /// Returns the decoded epb_flags option of the packet, None if it is absent.
+    pub fn flags(&self) -> Option<EpbFlags> {
+        self.options.iter().find_map(|opt| match opt {
+            EnhancedPacketOption::Flags(flags) => Some(EpbFlags(*flags)),
+            _ => None,
+        })
+    }
+
+    /// Returns the number of FCS bytes at the end of the original packet.
+    ///
+    /// The FCS length of the epb_flags option, if not 0, overrides the if_fcslen option of the interface of the packet.
+    /// None is returned if neither of them gives the length.
+    pub fn fcs_len(&self, interface: &InterfaceDescriptionBlock) -> Option<u8> {
+        self.flags().and_then(|flags| flags.fcs_len()).or_else(|| interface.fcs_len())
+    }
+
+    /// Removes the `fcs_len` bytes of FCS at the end of the packet, e.g. the length returned by [`Self::fcs_len`].
+    ///
+    /// The original length is reduced by `fcs_len` and the FCS bytes still captured are removed from the data,
+    /// the data doesn't change if the FCS wasn't captured. The FCS length of the epb_flags option is reset to 0,
+    /// the if_fcslen option of the interface must be removed by the caller when writing the stripped packets.
+    pub fn strip_fcs(&mut self, fcs_len: u8) {
+        self.original_len = self.original_len.saturating_sub(fcs_len as u32);
+
+        let data_len = self.data.len().min(self.original_len as usize);
+        match &mut self.data {
+            Cow::Borrowed(data) => *data = &data[..data_len],
+            Cow::Owned(data) => data.truncate(data_len),
+        }
+
+        for opt in &mut self.options {
+            if let EnhancedPacketOption::Flags(flags) = opt {
+                *flags &= !FLAGS_FCS_LEN_MASK;
+            }
+        }
+    }
+
     /// Ajust the parsed timestamp field with the right [`TsResolution`].
     /// Must be called only once.
     pub(crate) fn adjust_parsed_timestamp(&mut self, ts_resolution: TsResolution) {
@@ -56,8 +99,12 @@ impl<'a> EnhancedPacketBlock<'a> {
     }
 }
 
-impl<'a> PcapNgBlock<'a> for EnhancedPacketBlock<'a> {
-    fn from_slice<B: ByteOrder>(mut slice: &'a [u8]) -> Result<(&'a [u8], Self), PcapError> {
+impl<'a> EnhancedPacketBlock<'a> {
+    /// Parses the block from a slice, with at most `max_options` options.
+    pub(crate) fn from_slice_with_max_options<B: ByteOrder>(
+        mut slice: &'a [u8],
+        max_options: usize,
+    ) -> Result<(&'a [u8], Self), PcapError> {
         if slice.len() < 20 {
             return Err(PcapError::InvalidField("EnhancedPacketBlock: block length length < 20"));
         }
@@ -82,7 +129,7 @@ impl<'a> PcapNgBlock<'a> for EnhancedPacketBlock<'a> {
         let data = &slice[..captured_len as usize];
         slice = &slice[tot_len..];
 
-        let (slice, options) = EnhancedPacketOption::opts_from_slice::<B>(slice)?;
+        let (slice, options) = EnhancedPacketOption::opts_from_slice::<B>(slice, max_options)?;
         let block = EnhancedPacketBlock {
             interface_id,
             timestamp,
@@ -94,6 +141,12 @@ impl<'a> PcapNgBlock<'a> for EnhancedPacketBlock<'a> {
 
         Ok((slice, block))
     }
+}
+
+impl<'a> PcapNgBlock<'a> for EnhancedPacketBlock<'a> {
+    fn from_slice<B: ByteOrder>(slice: &'a [u8]) -> Result<(&'a [u8], Self), PcapError> {
+        Self::from_slice_with_max_options::<B>(slice, MAX_OPTIONS_PER_BLOCK)
+    }
 
     fn write_to<B: ByteOrder, W: Write>(&self, writer: &mut W) -> IoResult<usize> {
         let pad_len = (4 - (&self.data.len() % 4)) % 4;
@@ -138,6 +191,107 @@ impl Default for EnhancedPacketBlock<'_> {
     }
 }
 
+impl Debug for EnhancedPacketBlock<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EnhancedPacketBlock")
+            .field("interface_id", &self.interface_id)
+            .field("timestamp", &self.timestamp)
+            .field("original_len", &self.original_len)
+            .field("data", &DebugBytes(&self.data))
+            .field("options", &self.options)
+            .field("write_ts_resolution", &self.write_ts_resolution)
+            .finish()
+    }
+}
+
+
+/* ----- */
+
+/// The epb_flags option of an [`EnhancedPacketBlock`], see [`EnhancedPacketBlock::flags`].
+///
+/// It gives the direction and the reception type of the packet, the length of its FCS, whether the NIC checked its
+/// checksums, e.g. to tell a real bad checksum from an artifact of the checksum offloading, and its link-layer errors.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct EpbFlags(pub u32);
+
+impl EpbFlags {
+    /// Returns the direction of the packet (bits 0-1), None if it isn't available.
+    pub fn direction(&self) -> Option<PacketDirection> {
+        match self.0 & 0b11 {
+            1 => Some(PacketDirection::Inbound),
+            2 => Some(PacketDirection::Outbound),
+            _ => None,
+        }
+    }
+
+    /// Returns the reception type of the packet (bits 2-4), None if it isn't specified or is unknown.
+    pub fn reception_type(&self) -> Option<ReceptionType> {
+        match (self.0 >> 2) & 0b111 {
+            1 => Some(ReceptionType::Unicast),
+            2 => Some(ReceptionType::Multicast),
+            3 => Some(ReceptionType::Broadcast),
+            4 => Some(ReceptionType::Promiscuous),
+            _ => None,
+        }
+    }
+
+    /// Returns the number of FCS bytes at the end of the packet (bits 5-8), None if it isn't available.
+    pub fn fcs_len(&self) -> Option<u8> {
+        match (self.0 & FLAGS_FCS_LEN_MASK) >> FLAGS_FCS_LEN_SHIFT {
+            0 => None,
+            len => Some(len as u8),
+        }
+    }
+
+    /// Returns true if the checksums of the packet weren't computed yet when it was captured (bit 9),
+    /// typically because they are computed by the NIC for outbound packets.
+    pub fn checksum_not_ready(&self) -> bool {
+        self.0 & (1 << 9) != 0
+    }
+
+    /// Returns true if the checksums of the packet were checked and are valid (bit 10), typically by the NIC.
+    pub fn checksum_valid(&self) -> bool {
+        self.0 & (1 << 10) != 0
+    }
+
+    /// Returns true if the packet is a TCP segment before its segmentation by the NIC (bit 11).
+    pub fn tcp_segmentation_offloaded(&self) -> bool {
+        self.0 & (1 << 11) != 0
+    }
+
+    /// Returns the link-layer dependent errors (bits 16-31), e.g. bit 24 of the flags (bit 8 of the result) for a CRC error.
+    pub fn link_layer_errors(&self) -> u16 {
+        (self.0 >> 16) as u16
+    }
+
+    /// Returns true if the packet has a CRC error (bit 24).
+    pub fn crc_error(&self) -> bool {
+        self.0 & (1 << 24) != 0
+    }
+}
+
+/// Direction of a packet, see [`EpbFlags::direction`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PacketDirection {
+    /// Received by the interface
+    Inbound,
+    /// Sent by the interface
+    Outbound,
+}
+
+/// Reception type of a packet, see [`EpbFlags::reception_type`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ReceptionType {
+    /// Sent to the interface
+    Unicast,
+    /// Sent to a multicast group
+    Multicast,
+    /// Sent to all the hosts
+    Broadcast,
+    /// Received in promiscuous mode, not sent to the interface
+    Promiscuous,
+}
+
 
 /* ----- */
 
@@ -12,7 +12,7 @@ use derive_into_owned::IntoOwned;
 use once_cell::sync::Lazy;
 
 use super::block_common::{Block, PcapNgBlock};
-use super::opt_common::{CustomBinaryOption, CustomUtf8Option, PcapNgOption, UnknownOption, WriteOptTo};
+use super::opt_common::{CustomBinaryOption, CustomUtf8Option, PcapNgOption, UnknownOption, WriteOptTo, MAX_OPTIONS_PER_BLOCK};
 use crate::errors::PcapError;
 use crate::DataLink;
 
@@ -25,6 +25,7 @@ pub struct InterfaceDescriptionBlock<'a> {
     ///
     /// The list of Standardized Link Layer Type codes is available in the
     /// [tcpdump.org link-layer header types registry.](http://www.tcpdump.org/linktypes.html).
+    /// It is 16 bits long in a pcapng, a bigger code can't be written.
     pub linktype: DataLink,
 
     /// Maximum number of octets captured from each packet.
@@ -37,8 +38,12 @@ pub struct InterfaceDescriptionBlock<'a> {
     pub options: Vec<InterfaceDescriptionOption<'a>>,
 }
 
-impl<'a> PcapNgBlock<'a> for InterfaceDescriptionBlock<'a> {
-    fn from_slice<B: ByteOrder>(mut slice: &'a [u8]) -> Result<(&'a [u8], Self), PcapError> {
+impl<'a> InterfaceDescriptionBlock<'a> {
+    /// Parses the block from a slice, with at most `max_options` options.
+    pub(crate) fn from_slice_with_max_options<B: ByteOrder>(
+        mut slice: &'a [u8],
+        max_options: usize,
+    ) -> Result<(&'a [u8], Self), PcapError> {
         if slice.len() < 8 {
             return Err(PcapError::InvalidField("InterfaceDescriptionBlock: block length < 8"));
         }
@@ -51,15 +56,24 @@ impl<'a> PcapNgBlock<'a> for InterfaceDescriptionBlock<'a> {
         }
 
         let snaplen = slice.read_u32::<B>().unwrap();
-        let (slice, options) = InterfaceDescriptionOption::opts_from_slice::<B>(slice)?;
+        let (slice, options) = InterfaceDescriptionOption::opts_from_slice::<B>(slice, max_options)?;
 
         let block = InterfaceDescriptionBlock { linktype, snaplen, options };
 
         Ok((slice, block))
     }
+}
+
+impl<'a> PcapNgBlock<'a> for InterfaceDescriptionBlock<'a> {
+    fn from_slice<B: ByteOrder>(slice: &'a [u8]) -> Result<(&'a [u8], Self), PcapError> {
+        Self::from_slice_with_max_options::<B>(slice, MAX_OPTIONS_PER_BLOCK)
+    }
 
     fn write_to<B: ByteOrder, W: Write>(&self, writer: &mut W) -> IoResult<usize> {
-        writer.write_u16::<B>(u32::from(self.linktype) as u16)?;
+        let linktype = u16::try_from(u32::from(self.linktype))
+            .map_err(|_| std::io::Error::other("InterfaceDescriptionBlock: linktype > u16::MAX"))?;
+
+        writer.write_u16::<B>(linktype)?;
         writer.write_u16::<B>(0)?;
         writer.write_u32::<B>(self.snaplen)?;
 
@@ -73,9 +87,9 @@ impl<'a> PcapNgBlock<'a> for InterfaceDescriptionBlock<'a> {
 }
 
 impl<'a> InterfaceDescriptionBlock<'a> {
-    /// Creates a new [`InterfaceDescriptionBlock`]
+    /// Creates a new [`InterfaceDescriptionBlock`], with the normalized linktype (see [`DataLink::normalize`])
     pub fn new(linktype: DataLink, snaplen: u32) -> Self {
-        Self { linktype, snaplen, options: vec![] }
+        Self { linktype: linktype.normalize(), snaplen, options: vec![] }
     }
 
     /// Returns the timestamp resolution of the interface.
@@ -92,6 +106,39 @@ impl<'a> InterfaceDescriptionBlock<'a> {
 
         ts_resol
     }
+
+    /// Returns the name of the interface, from the if_name option.
+    pub fn name(&self) -> Option<&str> {
+        self.options.iter().find_map(|opt| match opt {
+            InterfaceDescriptionOption::IfName(name) => Some(&name[..]),
+            _ => None,
+        })
+    }
+
+    /// Returns the capture filter of the interface, from the if_filter option.
+    ///
+    /// Only the libpcap filter string variant (filter type 0) is returned,
+    /// None is returned if the option is absent or contains another filter type (e.g. BPF bytecode).
+    pub fn filter(&self) -> Option<String> {
+        self.options.iter().find_map(|opt| match opt {
+            InterfaceDescriptionOption::IfFilter(filter) => match filter.split_first() {
+                Some((0, filter)) => Some(String::from_utf8_lossy(filter).into_owned()),
+                _ => None,
+            },
+            _ => None,
+        })
+    }
+
+    /// Returns the number of FCS bytes at the end of the packets of the interface, from the if_fcslen option.
+    ///
+    /// None is returned if the option is absent, in which case the presence of an FCS is unknown.
+    /// The packets can override it, see [`EnhancedPacketBlock::fcs_len`](super::enhanced_packet::EnhancedPacketBlock::fcs_len).
+    pub fn fcs_len(&self) -> Option<u8> {
+        self.options.iter().find_map(|opt| match opt {
+            InterfaceDescriptionOption::IfFcsLen(len) => Some(*len),
+            _ => None,
+        })
+    }
 }
 
 
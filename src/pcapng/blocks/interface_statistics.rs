@@ -9,7 +9,7 @@ use byteorder_slice::ByteOrder;
 use derive_into_owned::IntoOwned;
 
 use super::block_common::{Block, PcapNgBlock};
-use super::opt_common::{CustomBinaryOption, CustomUtf8Option, PcapNgOption, UnknownOption, WriteOptTo};
+use super::opt_common::{CustomBinaryOption, CustomUtf8Option, PcapNgOption, UnknownOption, WriteOptTo, MAX_OPTIONS_PER_BLOCK};
 use crate::errors::PcapError;
 
 
@@ -32,20 +32,30 @@ pub struct InterfaceStatisticsBlock<'a> {
     pub options: Vec<InterfaceStatisticsOption<'a>>,
 }
 
-impl<'a> PcapNgBlock<'a> for InterfaceStatisticsBlock<'a> {
-    fn from_slice<B: ByteOrder>(mut slice: &'a [u8]) -> Result<(&[u8], Self), PcapError> {
+impl<'a> InterfaceStatisticsBlock<'a> {
+    /// Parses the block from a slice, with at most `max_options` options.
+    pub(crate) fn from_slice_with_max_options<B: ByteOrder>(
+        mut slice: &'a [u8],
+        max_options: usize,
+    ) -> Result<(&'a [u8], Self), PcapError> {
         if slice.len() < 12 {
             return Err(PcapError::InvalidField("InterfaceStatisticsBlock: block length < 12"));
         }
 
         let interface_id = slice.read_u32::<B>().unwrap();
         let timestamp = slice.read_u64::<B>().unwrap();
-        let (slice, options) = InterfaceStatisticsOption::opts_from_slice::<B>(slice)?;
+        let (slice, options) = InterfaceStatisticsOption::opts_from_slice::<B>(slice, max_options)?;
 
         let block = InterfaceStatisticsBlock { interface_id, timestamp, options };
 
         Ok((slice, block))
     }
+}
+
+impl<'a> PcapNgBlock<'a> for InterfaceStatisticsBlock<'a> {
+    fn from_slice<B: ByteOrder>(slice: &'a [u8]) -> Result<(&[u8], Self), PcapError> {
+        Self::from_slice_with_max_options::<B>(slice, MAX_OPTIONS_PER_BLOCK)
+    }
 
     fn write_to<B: ByteOrder, W: Write>(&self, writer: &mut W) -> IoResult<usize> {
         writer.write_u32::<B>(self.interface_id)?;
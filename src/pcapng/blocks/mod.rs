@@ -13,3 +13,4 @@ pub mod systemd_journal_export;
 pub mod unknown;
 
 pub use block_common::*;
+pub use opt_common::MAX_OPTIONS_PER_BLOCK;
@@ -9,7 +9,7 @@ use byteorder_slice::ByteOrder;
 use derive_into_owned::IntoOwned;
 
 use super::block_common::{Block, PcapNgBlock};
-use super::opt_common::{CustomBinaryOption, CustomUtf8Option, PcapNgOption, UnknownOption, WriteOptTo};
+use super::opt_common::{CustomBinaryOption, CustomUtf8Option, PcapNgOption, UnknownOption, WriteOptTo, MAX_OPTIONS_PER_BLOCK};
 use crate::errors::PcapError;
 
 
@@ -23,8 +23,12 @@ pub struct NameResolutionBlock<'a> {
     pub options: Vec<NameResolutionOption<'a>>,
 }
 
-impl<'a> PcapNgBlock<'a> for NameResolutionBlock<'a> {
-    fn from_slice<B: ByteOrder>(mut slice: &'a [u8]) -> Result<(&'a [u8], Self), PcapError> {
+impl<'a> NameResolutionBlock<'a> {
+    /// Parses the block from a slice, with at most `max_options` options.
+    pub(crate) fn from_slice_with_max_options<B: ByteOrder>(
+        mut slice: &'a [u8],
+        max_options: usize,
+    ) -> Result<(&'a [u8], Self), PcapError> {
         let mut records = Vec::new();
 
         loop {
@@ -37,12 +41,18 @@ impl<'a> PcapNgBlock<'a> for NameResolutionBlock<'a> {
             }
         }
 
-        let (rem, options) = NameResolutionOption::opts_from_slice::<B>(slice)?;
+        let (rem, options) = NameResolutionOption::opts_from_slice::<B>(slice, max_options)?;
 
         let block = NameResolutionBlock { records, options };
 
         Ok((rem, block))
     }
+}
+
+impl<'a> PcapNgBlock<'a> for NameResolutionBlock<'a> {
+    fn from_slice<B: ByteOrder>(slice: &'a [u8]) -> Result<(&'a [u8], Self), PcapError> {
+        Self::from_slice_with_max_options::<B>(slice, MAX_OPTIONS_PER_BLOCK)
+    }
 
     fn write_to<B: ByteOrder, W: Write>(&self, writer: &mut W) -> IoResult<usize> {
         let mut len = 0;
@@ -9,6 +9,10 @@ use derive_into_owned::IntoOwned;
 use crate::errors::PcapError;
 
 
+/// Default maximum number of options parsed in a single block, see
+/// [`PcapNgParser::set_max_options_per_block`](crate::pcapng::PcapNgParser::set_max_options_per_block).
+pub const MAX_OPTIONS_PER_BLOCK: usize = 65536;
+
 /// Common fonctions of the PcapNg options
 pub(crate) trait PcapNgOption<'a> {
     /// Parse an option from a slice
@@ -17,7 +21,10 @@ pub(crate) trait PcapNgOption<'a> {
         Self: std::marker::Sized;
 
     /// Parse all options in a block
-    fn opts_from_slice<B: ByteOrder>(mut slice: &'a [u8]) -> Result<(&'a [u8], Vec<Self>), PcapError>
+    ///
+    /// The length of each option is bounded by the remaining block body and
+    /// the number of options is bounded by `max_options`.
+    fn opts_from_slice<B: ByteOrder>(mut slice: &'a [u8], max_options: usize) -> Result<(&'a [u8], Vec<Self>), PcapError>
     where
         Self: std::marker::Sized,
     {
@@ -28,11 +35,15 @@ pub(crate) trait PcapNgOption<'a> {
             return Ok((slice, options));
         }
 
+        let opts_len = slice.len();
+
         while !slice.is_empty() {
             if slice.len() < 4 {
                 return Err(PcapError::InvalidField("Option: slice.len() < 4"));
             }
 
+            let offset = opts_len - slice.len();
+
             let code = slice.read_u16::<B>().unwrap();
             let length = slice.read_u16::<B>().unwrap() as usize;
             let pad_len = (4 - (length % 4)) % 4;
@@ -42,7 +53,11 @@ pub(crate) trait PcapNgOption<'a> {
             }
 
             if slice.len() < length + pad_len {
-                return Err(PcapError::InvalidField("Option: length + pad.len() > slice.len()"));
+                return Err(PcapError::InvalidOption { code, offset, reason: "length + padding > remaining block length" });
+            }
+
+            if options.len() >= max_options {
+                return Err(PcapError::InvalidOption { code, offset, reason: "too many options in the block" });
             }
 
             let tmp_slice = &slice[..length];
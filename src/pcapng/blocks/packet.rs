@@ -1,6 +1,7 @@
 //! Packet Block.
 
 use std::borrow::Cow;
+use std::fmt::Debug;
 use std::io::{Result as IoResult, Write};
 
 use byteorder_slice::byteorder::WriteBytesExt;
@@ -9,12 +10,13 @@ use byteorder_slice::ByteOrder;
 use derive_into_owned::IntoOwned;
 
 use super::block_common::{Block, PcapNgBlock};
-use super::opt_common::{CustomBinaryOption, CustomUtf8Option, PcapNgOption, UnknownOption, WriteOptTo};
+use super::opt_common::{CustomBinaryOption, CustomUtf8Option, PcapNgOption, UnknownOption, WriteOptTo, MAX_OPTIONS_PER_BLOCK};
+use crate::common::DebugBytes;
 use crate::errors::PcapError;
 
 /// The Packet Block is obsolete, and MUST NOT be used in new files.
 /// Use the Enhanced Packet Block or Simple Packet Block instead.
-#[derive(Clone, Debug, IntoOwned, Eq, PartialEq)]
+#[derive(Clone, IntoOwned, Eq, PartialEq)]
 pub struct PacketBlock<'a> {
     /// It specifies the interface this packet comes from.
     pub interface_id: u16,
@@ -42,8 +44,12 @@ pub struct PacketBlock<'a> {
     pub options: Vec<PacketOption<'a>>,
 }
 
-impl<'a> PcapNgBlock<'a> for PacketBlock<'a> {
-    fn from_slice<B: ByteOrder>(mut slice: &'a [u8]) -> Result<(&'a [u8], Self), PcapError> {
+impl<'a> PacketBlock<'a> {
+    /// Parses the block from a slice, with at most `max_options` options.
+    pub(crate) fn from_slice_with_max_options<B: ByteOrder>(
+        mut slice: &'a [u8],
+        max_options: usize,
+    ) -> Result<(&'a [u8], Self), PcapError> {
         if slice.len() < 20 {
             return Err(PcapError::InvalidField("EnhancedPacketBlock: block length length < 20"));
         }
@@ -64,7 +70,7 @@ impl<'a> PcapNgBlock<'a> for PacketBlock<'a> {
         let data = &slice[..captured_len as usize];
         slice = &slice[tot_len..];
 
-        let (slice, options) = PacketOption::opts_from_slice::<B>(slice)?;
+        let (slice, options) = PacketOption::opts_from_slice::<B>(slice, max_options)?;
         let block = PacketBlock {
             interface_id,
             drop_count,
@@ -77,6 +83,12 @@ impl<'a> PcapNgBlock<'a> for PacketBlock<'a> {
 
         Ok((slice, block))
     }
+}
+
+impl<'a> PcapNgBlock<'a> for PacketBlock<'a> {
+    fn from_slice<B: ByteOrder>(slice: &'a [u8]) -> Result<(&'a [u8], Self), PcapError> {
+        Self::from_slice_with_max_options::<B>(slice, MAX_OPTIONS_PER_BLOCK)
+    }
 
     fn write_to<B: ByteOrder, W: Write>(&self, writer: &mut W) -> IoResult<usize> {
         writer.write_u16::<B>(self.interface_id)?;
@@ -99,6 +111,20 @@ impl<'a> PcapNgBlock<'a> for PacketBlock<'a> {
     }
 }
 
+impl Debug for PacketBlock<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PacketBlock")
+            .field("interface_id", &self.interface_id)
+            .field("drop_count", &self.drop_count)
+            .field("timestamp", &self.timestamp)
+            .field("captured_len", &self.captured_len)
+            .field("original_len", &self.original_len)
+            .field("data", &DebugBytes(&self.data))
+            .field("options", &self.options)
+            .finish()
+    }
+}
+
 /// Packet Block option
 #[derive(Clone, Debug, IntoOwned, Eq, PartialEq)]
 pub enum PacketOption<'a> {
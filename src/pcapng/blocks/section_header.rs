@@ -9,7 +9,7 @@ use byteorder_slice::{BigEndian, ByteOrder, LittleEndian};
 use derive_into_owned::IntoOwned;
 
 use super::block_common::{Block, PcapNgBlock};
-use super::opt_common::{CustomBinaryOption, CustomUtf8Option, PcapNgOption, UnknownOption, WriteOptTo};
+use super::opt_common::{CustomBinaryOption, CustomUtf8Option, PcapNgOption, UnknownOption, WriteOptTo, MAX_OPTIONS_PER_BLOCK};
 use crate::errors::PcapError;
 use crate::Endianness;
 
@@ -38,8 +38,9 @@ pub struct SectionHeaderBlock<'a> {
     pub options: Vec<SectionHeaderOption<'a>>,
 }
 
-impl<'a> PcapNgBlock<'a> for SectionHeaderBlock<'a> {
-    fn from_slice<B: ByteOrder>(mut slice: &'a [u8]) -> Result<(&'a [u8], Self), PcapError> {
+impl<'a> SectionHeaderBlock<'a> {
+    /// Parses the block from a slice, with at most `max_options` options.
+    pub(crate) fn from_slice_with_max_options(mut slice: &'a [u8], max_options: usize) -> Result<(&'a [u8], Self), PcapError> {
         if slice.len() < 16 {
             return Err(PcapError::InvalidField("SectionHeaderBlock: block length < 16"));
         }
@@ -52,8 +53,8 @@ impl<'a> PcapNgBlock<'a> for SectionHeaderBlock<'a> {
         };
 
         let (rem, major_version, minor_version, section_length, options) = match endianness {
-            Endianness::Big => parse_inner::<BigEndian>(slice)?,
-            Endianness::Little => parse_inner::<LittleEndian>(slice)?,
+            Endianness::Big => parse_inner::<BigEndian>(slice, max_options)?,
+            Endianness::Little => parse_inner::<LittleEndian>(slice, max_options)?,
         };
 
         let block = SectionHeaderBlock { endianness, major_version, minor_version, section_length, options };
@@ -61,15 +62,24 @@ impl<'a> PcapNgBlock<'a> for SectionHeaderBlock<'a> {
         return Ok((rem, block));
 
         #[allow(clippy::type_complexity)]
-        fn parse_inner<B: ByteOrder>(mut slice: &[u8]) -> Result<(&[u8], u16, u16, i64, Vec<SectionHeaderOption>), PcapError> {
+        fn parse_inner<B: ByteOrder>(
+            mut slice: &[u8],
+            max_options: usize,
+        ) -> Result<(&[u8], u16, u16, i64, Vec<SectionHeaderOption>), PcapError> {
             let maj_ver = slice.read_u16::<B>().unwrap();
             let min_ver = slice.read_u16::<B>().unwrap();
             let sec_len = slice.read_i64::<B>().unwrap();
-            let (rem, opts) = SectionHeaderOption::opts_from_slice::<B>(slice)?;
+            let (rem, opts) = SectionHeaderOption::opts_from_slice::<B>(slice, max_options)?;
 
             Ok((rem, maj_ver, min_ver, sec_len, opts))
         }
     }
+}
+
+impl<'a> PcapNgBlock<'a> for SectionHeaderBlock<'a> {
+    fn from_slice<B: ByteOrder>(slice: &'a [u8]) -> Result<(&'a [u8], Self), PcapError> {
+        Self::from_slice_with_max_options(slice, MAX_OPTIONS_PER_BLOCK)
+    }
 
     fn write_to<B: ByteOrder, W: Write>(&self, writer: &mut W) -> IoResult<usize> {
         match self.endianness {
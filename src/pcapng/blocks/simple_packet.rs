@@ -1,6 +1,7 @@
 //! Simple Packet Block (SPB).
 
 use std::borrow::Cow;
+use std::fmt::Debug;
 use std::io::{Result as IoResult, Write};
 
 use byteorder_slice::byteorder::WriteBytesExt;
@@ -9,13 +10,16 @@ use byteorder_slice::ByteOrder;
 use derive_into_owned::IntoOwned;
 
 use super::block_common::{Block, PcapNgBlock};
+use crate::common::DebugBytes;
 use crate::errors::PcapError;
 
 
 /// The Simple Packet Block (SPB) is a lightweight container for storing the packets coming from the network.
 /// 
 /// Its presence is optional.
-#[derive(Clone, Debug, IntoOwned, Eq, PartialEq)]
+///
+/// Its captured length is min(original_len, snaplen of the interface 0).
+#[derive(Clone, IntoOwned, Eq, PartialEq)]
 pub struct SimplePacketBlock<'a> {
     /// Actual length of the packet when it was transmitted on the network.
     pub original_len: u32,
@@ -31,7 +35,9 @@ impl<'a> PcapNgBlock<'a> for SimplePacketBlock<'a> {
         }
         let original_len = slice.read_u32::<B>().unwrap();
 
-        let packet = SimplePacketBlock { original_len, data: Cow::Borrowed(slice) };
+        // Remove the padding, the truncation to the snaplen is done by the parser
+        let data_len = slice.len().min(original_len as usize);
+        let packet = SimplePacketBlock { original_len, data: Cow::Borrowed(&slice[..data_len]) };
 
         Ok((&[], packet))
     }
@@ -50,3 +56,12 @@ impl<'a> PcapNgBlock<'a> for SimplePacketBlock<'a> {
         Block::SimplePacket(self)
     }
 }
+
+impl Debug for SimplePacketBlock<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SimplePacketBlock")
+            .field("original_len", &self.original_len)
+            .field("data", &DebugBytes(&self.data))
+            .finish()
+    }
+}
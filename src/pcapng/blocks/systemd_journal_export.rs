@@ -1,17 +1,19 @@
 //! Systemd Journal Export Block.
 
 use std::borrow::Cow;
+use std::fmt::Debug;
 use std::io::{Result as IoResult, Write};
 
 use byteorder_slice::ByteOrder;
 use derive_into_owned::IntoOwned;
 
 use super::block_common::{Block, PcapNgBlock};
+use crate::common::DebugBytes;
 use crate::errors::PcapError;
 
 
 /// The Systemd Journal Export Block is a lightweight containter for systemd Journal Export Format entry data.
-#[derive(Clone, Debug, IntoOwned, Eq, PartialEq)]
+#[derive(Clone, IntoOwned, Eq, PartialEq)]
 pub struct SystemdJournalExportBlock<'a> {
     /// A journal entry as described in the Journal Export Format documentation.
     pub journal_entry: Cow<'a, [u8]>,
@@ -36,3 +38,9 @@ impl<'a> PcapNgBlock<'a> for SystemdJournalExportBlock<'a> {
         Block::SystemdJournalExport(self)
     }
 }
+
+impl Debug for SystemdJournalExportBlock<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SystemdJournalExportBlock").field("journal_entry", &DebugBytes(&self.journal_entry)).finish()
+    }
+}
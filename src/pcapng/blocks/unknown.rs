@@ -1,17 +1,19 @@
 //! Unknown Block.
 
 use std::borrow::Cow;
+use std::fmt::Debug;
 use std::io::{Result as IoResult, Write};
 
 use byteorder_slice::ByteOrder;
 use derive_into_owned::IntoOwned;
 
 use super::block_common::{Block, PcapNgBlock};
+use crate::common::DebugBytes;
 use crate::PcapError;
 
 
 /// Unknown block
-#[derive(Clone, Debug, IntoOwned, Eq, PartialEq)]
+#[derive(Clone, IntoOwned, Eq, PartialEq)]
 pub struct UnknownBlock<'a> {
     /// Block type
     pub type_: u32,
@@ -45,3 +47,13 @@ impl<'a> PcapNgBlock<'a> for UnknownBlock<'a> {
         Block::Unknown(self)
     }
 }
+
+impl Debug for UnknownBlock<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UnknownBlock")
+            .field("type_", &self.type_)
+            .field("length", &self.length)
+            .field("value", &DebugBytes(&self.value))
+            .finish()
+    }
+}
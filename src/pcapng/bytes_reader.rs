@@ -0,0 +1,183 @@
+use std::fmt::Debug;
+use std::io::Read;
+use std::time::Duration;
+
+use byteorder_slice::{BigEndian, LittleEndian};
+use bytes::Bytes;
+
+use super::blocks::block_common::{Block, RawBlock};
+use super::blocks::enhanced_packet::EpbFlags;
+use super::blocks::interface_description::InterfaceDescriptionBlock;
+use super::blocks::section_header::SectionHeaderBlock;
+use super::PcapNgParser;
+use crate::bytes_buffer::BytesBuffer;
+use crate::common::DebugBytes;
+use crate::errors::*;
+use crate::Endianness;
+
+
+/// Packet of a pcapng whose data is a [`Bytes`], see [`PcapNgBytesReader`].
+#[derive(Clone, Eq, PartialEq)]
+pub struct PcapNgBytesPacket {
+    /// Interface the packet comes from, always 0 for a [`SimplePacketBlock`](super::blocks::simple_packet::SimplePacketBlock)
+    pub interface_id: u32,
+    /// Timestamp of the packet, None for a [`SimplePacketBlock`](super::blocks::simple_packet::SimplePacketBlock)
+    pub timestamp: Option<Duration>,
+    /// Actual length of the packet when it was transmitted on the network
+    pub original_len: u32,
+    /// Decoded epb_flags option of the packet, always None for a `SimplePacketBlock`
+    pub flags: Option<EpbFlags>,
+    /// The data coming from the network, including link-layer headers
+    pub data: Bytes,
+}
+
+impl Debug for PcapNgBytesPacket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PcapNgBytesPacket")
+            .field("interface_id", &self.interface_id)
+            .field("timestamp", &self.timestamp)
+            .field("original_len", &self.original_len)
+            .field("flags", &self.flags)
+            .field("data", &DebugBytes(&self.data))
+            .finish()
+    }
+}
+
+
+/// Reads the packets of a pcapng from a reader, returning their data as [`Bytes`] without copying them.
+///
+/// The packets are the [`EnhancedPacketBlock`](super::blocks::enhanced_packet::EnhancedPacketBlock)s and the
+/// [`SimplePacketBlock`](super::blocks::simple_packet::SimplePacketBlock)s, the other blocks are only used to
+/// update the section and the interfaces.
+///
+/// Like the [`PcapBytesReader`](crate::pcap::PcapBytesReader), the data are read in chunks of a fixed capacity (1MiB
+/// by default), and a packet keeps its whole chunk alive. A block can't be bigger than the capacity.
+///
+/// Only available with the `bytes` feature.
+///
+/// # Example
+/// ```rust,no_run
+/// use std::fs::File;
+///
+/// use pcap_file::pcapng::PcapNgBytesReader;
+///
+/// let file_in = File::open("test.pcapng").expect("Error opening file");
+/// let mut pcapng_reader = PcapNgBytesReader::new(file_in).unwrap();
+///
+/// let mut packets = vec![];
+/// while let Some(packet) = pcapng_reader.next_packet() {
+///     packets.push(packet.unwrap().data);
+/// }
+/// ```
+pub struct PcapNgBytesReader<R: Read> {
+    parser: PcapNgParser,
+    reader: BytesBuffer<R>,
+}
+
+impl<R: Read> PcapNgBytesReader<R> {
+    /// Creates a new [`PcapNgBytesReader`] from a reader.
+    ///
+    /// Parses the first block which must be a valid SectionHeaderBlock.
+    pub fn new(reader: R) -> PcapResult<PcapNgBytesReader<R>> {
+        Self::from_buffer(BytesBuffer::new(reader))
+    }
+
+    /// Creates a new [`PcapNgBytesReader`] reading chunks of `capacity` bytes, see [`PcapNgBytesReader`].
+    pub fn with_capacity(reader: R, capacity: usize) -> PcapResult<PcapNgBytesReader<R>> {
+        Self::from_buffer(BytesBuffer::with_capacity(reader, capacity))
+    }
+
+    /// Creates a new [`PcapNgBytesReader`] parsing the first block from the buffer.
+    fn from_buffer(mut reader: BytesBuffer<R>) -> PcapResult<PcapNgBytesReader<R>> {
+        let block = reader.next_with(|src| block_len(Endianness::Big, src))?;
+        let (_, parser) = PcapNgParser::new(&block)?;
+
+        Ok(PcapNgBytesReader { parser, reader })
+    }
+
+    /// Sets the maximum number of options parsed in a single block, see [`PcapNgParser::set_max_options_per_block`].
+    pub fn with_max_options_per_block(mut self, max_options: usize) -> Self {
+        self.parser.set_max_options_per_block(max_options);
+        self
+    }
+
+    /// Returns the next [`PcapNgBytesPacket`], skipping the blocks which aren't packets.
+    pub fn next_packet(&mut self) -> Option<PcapResult<PcapNgBytesPacket>> {
+        loop {
+            match self.reader.has_data_left() {
+                Ok(true) => {},
+                Ok(false) => return None,
+                Err(e) => return Some(Err(PcapError::IoError(e))),
+            }
+
+            match self.read_block() {
+                Ok(Some(packet)) => return Some(Ok(packet)),
+                Ok(None) => {},
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+
+    /// Splits the next block off the buffer, returns it if it is a packet.
+    fn read_block(&mut self) -> PcapResult<Option<PcapNgBytesPacket>> {
+        let endianness = self.parser.section().endianness;
+        let bytes = self.reader.next_with(|src| block_len(endianness, src))?;
+
+        // The data of the parsed blocks are borrowed from `bytes`
+        let packet = match self.parser.next_block(&bytes)?.1 {
+            Block::EnhancedPacket(block) => PcapNgBytesPacket {
+                interface_id: block.interface_id,
+                timestamp: Some(block.timestamp),
+                original_len: block.original_len,
+                flags: block.flags(),
+                data: bytes.slice_ref(&block.data),
+            },
+            Block::SimplePacket(block) => PcapNgBytesPacket {
+                interface_id: 0,
+                timestamp: None,
+                original_len: block.original_len,
+                flags: None,
+                data: bytes.slice_ref(&block.data),
+            },
+            _ => return Ok(None),
+        };
+
+        Ok(Some(packet))
+    }
+
+    /// Returns the current [`SectionHeaderBlock`].
+    pub fn section(&self) -> &SectionHeaderBlock<'static> {
+        self.parser.section()
+    }
+
+    /// Returns all the current [`InterfaceDescriptionBlock`].
+    pub fn interfaces(&self) -> &[InterfaceDescriptionBlock<'static>] {
+        self.parser.interfaces()
+    }
+
+    /// Consumes the [`Self`], returning the wrapped reader.
+    pub fn into_inner(self) -> R {
+        self.reader.into_inner()
+    }
+
+    /// Gets a reference to the wrapped reader.
+    pub fn get_ref(&self) -> &R {
+        self.reader.get_ref()
+    }
+}
+
+impl<R: Read + Debug> Debug for PcapNgBytesReader<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PcapNgBytesReader").field("parser", &self.parser).field("reader", &self.reader).finish()
+    }
+}
+
+/// Returns the length of the whole block at the start of the slice, without parsing its content.
+fn block_len(endianness: Endianness, src: &[u8]) -> PcapResult<usize> {
+    let (rem, _) = match endianness {
+        Endianness::Big => RawBlock::from_slice::<BigEndian>(src)?,
+        Endianness::Little => RawBlock::from_slice::<LittleEndian>(src)?,
+    };
+
+    Ok(src.len() - rem.len())
+}
@@ -3,11 +3,22 @@
 pub mod blocks;
 pub use blocks::{Block, PcapNgBlock, RawBlock};
 
+#[cfg(feature = "bytes")]
+pub(crate) mod bytes_reader;
+#[cfg(feature = "bytes")]
+pub use bytes_reader::*;
+
 pub(crate) mod parser;
 pub use parser::*;
 
 pub(crate) mod reader;
 pub use reader::*;
 
+pub(crate) mod resolver;
+pub use resolver::*;
+
+pub(crate) mod sections;
+pub use sections::*;
+
 pub(crate) mod writer;
 pub use writer::*;
@@ -1,10 +1,14 @@
+use std::borrow::Cow;
+use std::fmt::Debug;
+
 use byteorder_slice::{BigEndian, ByteOrder, LittleEndian};
 
 use super::blocks::block_common::{Block, RawBlock};
 use super::blocks::enhanced_packet::EnhancedPacketBlock;
 use super::blocks::interface_description::{InterfaceDescriptionBlock, TsResolution};
 use super::blocks::section_header::SectionHeaderBlock;
-use super::blocks::{INTERFACE_DESCRIPTION_BLOCK, SECTION_HEADER_BLOCK};
+use super::blocks::{INTERFACE_DESCRIPTION_BLOCK, MAX_OPTIONS_PER_BLOCK, SECTION_HEADER_BLOCK};
+use super::NameResolver;
 use crate::errors::PcapError;
 use crate::Endianness;
 
@@ -50,6 +54,10 @@ pub struct PcapNgParser {
     interfaces: Vec<InterfaceDescriptionBlock<'static>>,
     /// Timestamp resolutions corresponding to the interfaces
     ts_resolutions: Vec<TsResolution>,
+    /// Names of the name resolution blocks of the current section parsed so far by [`PcapNgParser::next_block`]
+    resolver: NameResolver,
+    /// Maximum number of options parsed in a single block
+    max_options: usize,
 }
 
 impl PcapNgParser {
@@ -64,7 +72,13 @@ impl PcapNgParser {
             _ => return Err(PcapError::InvalidField("PcapNg: SectionHeader invalid or missing")),
         };
 
-        let parser = PcapNgParser { section, interfaces: Vec::new(), ts_resolutions: Vec::new() };
+        let parser = PcapNgParser {
+            section,
+            interfaces: Vec::new(),
+            ts_resolutions: Vec::new(),
+            resolver: NameResolver::new(),
+            max_options: MAX_OPTIONS_PER_BLOCK,
+        };
 
         Ok((rem, parser))
     }
@@ -75,12 +89,12 @@ impl PcapNgParser {
         let mut res = match self.section.endianness {
             Endianness::Big => {
                 let (rem, raw_block) = self.next_raw_block_inner::<BigEndian>(src)?;
-                let block = raw_block.try_into_block::<BigEndian>()?;
+                let block = Block::try_from_raw_block_with_max_options::<BigEndian>(raw_block, self.max_options)?;
                 Ok((rem, block))
             },
             Endianness::Little => {
                 let (rem, raw_block) = self.next_raw_block_inner::<LittleEndian>(src)?;
-                let block = raw_block.try_into_block::<LittleEndian>()?;
+                let block = Block::try_from_raw_block_with_max_options::<LittleEndian>(raw_block, self.max_options)?;
                 Ok((rem, block))
             },
         };
@@ -95,6 +109,20 @@ impl PcapNgParser {
             blk.adjust_parsed_timestamp(*ts_resol);
         }
 
+        // If the block is a NameResolutionBlock, add its names to the resolver
+        if let Ok((_, Block::NameResolution(blk))) = &res {
+            self.resolver.add_block(blk);
+        }
+
+        // If the block is a SimplePacketBlock, truncate its data to the snaplen of the interface 0
+        if let (Ok((_, Block::SimplePacket(ref mut blk))), Some(interface)) = (&mut res, self.interfaces.first()) {
+            if interface.snaplen != 0 && blk.data.len() > interface.snaplen as usize {
+                if let Cow::Borrowed(data) = blk.data {
+                    blk.data = Cow::Borrowed(&data[..interface.snaplen as usize]);
+                }
+            }
+        }
+
         res
     }
 
@@ -110,15 +138,18 @@ impl PcapNgParser {
     /// Inner function to parse the next raw block.
     fn next_raw_block_inner<'a, B: ByteOrder>(&mut self, src: &'a [u8]) -> Result<(&'a [u8], RawBlock<'a>), PcapError> {
         let (rem, raw_block) = RawBlock::from_slice::<B>(src)?;
+        let parse = |raw_block: &RawBlock<'a>| Block::try_from_raw_block_with_max_options::<B>(raw_block.clone(), self.max_options);
 
         match raw_block.type_ {
             SECTION_HEADER_BLOCK => {
-                self.section = raw_block.clone().try_into_block::<B>()?.into_owned().into_section_header().unwrap();
+                self.section = parse(&raw_block)?.into_owned().into_section_header().unwrap();
                 self.interfaces.clear();
                 self.ts_resolutions.clear();
+                // The names are scoped to their section
+                self.resolver.clear();
             },
             INTERFACE_DESCRIPTION_BLOCK => {
-                let interface = raw_block.clone().try_into_block::<B>()?.into_owned().into_interface_description().unwrap();
+                let interface = parse(&raw_block)?.into_owned().into_interface_description().unwrap();
                 let ts_resolution = interface.ts_resolution()?;
 
                 self.interfaces.push(interface);
@@ -130,6 +161,14 @@ impl PcapNgParser {
         Ok((rem, raw_block))
     }
 
+    /// Sets the maximum number of options parsed in a single block ([`MAX_OPTIONS_PER_BLOCK`] by default).
+    ///
+    /// A block with more options is a [`PcapError::InvalidOption`]. The limit applies from the block following
+    /// the first SectionHeaderBlock.
+    pub fn set_max_options_per_block(&mut self, max_options: usize) {
+        self.max_options = max_options;
+    }
+
     /// Returns the current [`SectionHeaderBlock`].
     pub fn section(&self) -> &SectionHeaderBlock<'static> {
         &self.section
@@ -140,8 +179,29 @@ impl PcapNgParser {
         &self.interfaces[..]
     }
 
+    /// Returns the names of the [`NameResolutionBlock`](super::blocks::name_resolution::NameResolutionBlock)s of the
+    /// current section parsed so far.
+    ///
+    /// Only the blocks parsed with [`PcapNgParser::next_block`] are resolved, [`PcapNgParser::next_raw_block`] doesn't
+    /// decode them.
+    pub fn resolver(&self) -> &NameResolver {
+        &self.resolver
+    }
+
     /// Returns the [`InterfaceDescriptionBlock`] corresponding to the given packet.
     pub fn packet_interface(&self, packet: &EnhancedPacketBlock) -> Option<&InterfaceDescriptionBlock> {
         self.interfaces.get(packet.interface_id as usize)
     }
 }
+
+impl Debug for PcapNgParser {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PcapNgParser")
+            .field("section", &self.section)
+            .field("interfaces", &self.interfaces)
+            .field("ts_resolutions", &self.ts_resolutions)
+            .field("nb_resolved", &self.resolver.len())
+            .field("max_options", &self.max_options)
+            .finish()
+    }
+}
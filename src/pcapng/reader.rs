@@ -1,12 +1,18 @@
+use std::collections::VecDeque;
+use std::fmt::Debug;
 use std::io::Read;
 
+use byteorder_slice::{BigEndian, LittleEndian};
+
 use super::blocks::block_common::{Block, RawBlock};
 use super::blocks::enhanced_packet::EnhancedPacketBlock;
-use super::blocks::interface_description::InterfaceDescriptionBlock;
+use super::blocks::interface_description::{InterfaceDescriptionBlock, TsResolution};
 use super::blocks::section_header::SectionHeaderBlock;
-use super::PcapNgParser;
-use crate::errors::PcapError;
+use super::blocks::{ENHANCED_PACKET_BLOCK, INTERFACE_DESCRIPTION_BLOCK, PACKET_BLOCK, SECTION_HEADER_BLOCK, SIMPLE_PACKET_BLOCK};
+use super::{NameResolver, PcapNgParser};
+use crate::errors::{PcapError, PcapResult};
 use crate::read_buffer::ReadBuffer;
+use crate::{DataLink, Endianness, ZeroReadRetry};
 
 
 /// Reads a PcapNg from a reader.
@@ -31,6 +37,54 @@ use crate::read_buffer::ReadBuffer;
 pub struct PcapNgReader<R: Read> {
     parser: PcapNgParser,
     reader: ReadBuffer<R>,
+    /// Offset of the next block from the start of the pcapng
+    offset: u64,
+    /// Total length of the last block returned
+    consumed: u64,
+    early_blocks: EarlyBlockPolicy,
+    /// Packet blocks deferred until the first interface of their section
+    deferred: VecDeque<Vec<u8>>,
+    /// Total length of the deferred blocks
+    deferred_len: usize,
+    /// Deferred block returned by the last call to `next_block`
+    flushed: Vec<u8>,
+    nb_dropped: u64,
+}
+
+/// What a [`PcapNgReader`] does with the packet blocks found before the first [`InterfaceDescriptionBlock`] of their
+/// section, e.g. written by a buggy exporter, see [`PcapNgReader::with_early_block_policy`].
+///
+/// The packet blocks are the [`EnhancedPacketBlock`], the [`SimplePacketBlock`](super::blocks::simple_packet::SimplePacketBlock)
+/// and the obsolete [`PacketBlock`](super::blocks::packet::PacketBlock). The other blocks, including the
+/// [`InterfaceStatisticsBlock`](super::blocks::interface_statistics::InterfaceStatisticsBlock)s, are returned as they come.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum EarlyBlockPolicy {
+    /// Return [`PcapError::BlockBeforeInterface`] with the type and the offset of the block
+    #[default]
+    Strict,
+    /// Defer the packet blocks until the first interface of their section, which are then returned right after it.
+    ///
+    /// At most `max_bytes` of blocks are kept, a packet block exceeding it is dropped. The blocks still deferred at the
+    /// end of their section are dropped too, they are all counted by [`PcapNgReader::nb_dropped_early_blocks`].
+    Defer {
+        /// Maximum total length of the blocks deferred
+        max_bytes: usize,
+    },
+    /// Drop the packet blocks, counted by [`PcapNgReader::nb_dropped_early_blocks`].
+    Drop,
+}
+
+/// Description of an interface of a pcapng, returned by [`PcapNgReader::read_interfaces_only`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InterfaceInfo {
+    /// Link type of the interface
+    pub linktype: DataLink,
+    /// Maximum number of octets captured from each packet, 0 for no limit
+    pub snaplen: u32,
+    /// Name of the interface, from its if_name option
+    pub name: Option<String>,
+    /// Timestamp resolution of the interface, from its if_tsresol option
+    pub ts_resolution: TsResolution,
 }
 
 impl<R: Read> PcapNgReader<R> {
@@ -39,31 +93,180 @@ impl<R: Read> PcapNgReader<R> {
     /// Parses the first block which must be a valid SectionHeaderBlock.
     pub fn new(reader: R) -> Result<PcapNgReader<R>, PcapError> {
         let mut reader = ReadBuffer::new(reader);
-        let parser = reader.parse_with(PcapNgParser::new)?;
-        Ok(Self { parser, reader })
+        let (_, len) = reader.peek_with(|src| probe_block(Endianness::Big, src), |src| block_type_len(Endianness::Big, src))?;
+        let parser = reader.parse_with(|src| probe_block(Endianness::Big, src), PcapNgParser::new)?;
+
+        Ok(Self {
+            parser,
+            reader,
+            offset: len as u64,
+            consumed: 0,
+            early_blocks: EarlyBlockPolicy::Strict,
+            deferred: VecDeque::new(),
+            deferred_len: 0,
+            flushed: vec![],
+            nb_dropped: 0,
+        })
+    }
+
+    /// Reads the interfaces of a pcapng without reading its packets, e.g. to list the captures of a directory.
+    ///
+    /// Reads the SectionHeaderBlock, then the [`InterfaceDescriptionBlock`]s following it, and stops at the first
+    /// block which isn't one, usually the first packet. The interfaces declared after it, e.g. in another section,
+    /// aren't returned.
+    ///
+    /// # Errors
+    /// The pcapng doesn't start with a valid SectionHeaderBlock, or an interface is invalid.
+    ///
+    /// The underlying data are not readable.
+    pub fn read_interfaces_only(reader: R) -> PcapResult<Vec<InterfaceInfo>> {
+        let mut pcapng_reader = PcapNgReader::new(reader)?;
+        let endianness = pcapng_reader.section().endianness;
+
+        let mut interfaces = vec![];
+        while pcapng_reader.reader.has_data_left().map_err(PcapError::IoError)? {
+            let (block_type, _) =
+                pcapng_reader.reader.peek_with(|src| probe_block(endianness, src), |src| block_type_len(endianness, src))?;
+            if block_type != INTERFACE_DESCRIPTION_BLOCK {
+                break;
+            }
+
+            // Can't be None because there is data left
+            if let Block::InterfaceDescription(idb) = pcapng_reader.next_block().unwrap()? {
+                let ts_resolution = idb.ts_resolution()?;
+                interfaces.push(InterfaceInfo {
+                    linktype: idb.linktype,
+                    snaplen: idb.snaplen,
+                    name: idb.name().map(str::to_string),
+                    ts_resolution,
+                });
+            }
+        }
+
+        Ok(interfaces)
+    }
+
+    /// Sets what the reader does with the blocks referencing an interface found before the first interface
+    /// of their section ([`EarlyBlockPolicy::Strict`] by default).
+    ///
+    /// The policy only applies to [`PcapNgReader::next_block`], the raw blocks are returned as they come.
+    pub fn with_early_block_policy(mut self, early_blocks: EarlyBlockPolicy) -> Self {
+        self.early_blocks = early_blocks;
+        self
+    }
+
+    /// Sets the maximum number of options parsed in a single block
+    /// ([`MAX_OPTIONS_PER_BLOCK`](super::blocks::MAX_OPTIONS_PER_BLOCK) by default), see [`PcapNgParser::set_max_options_per_block`].
+    pub fn with_max_options_per_block(mut self, max_options: usize) -> Self {
+        self.parser.set_max_options_per_block(max_options);
+        self
+    }
+
+    /// Retries the reads of the underlying reader returning no data in the middle of a block, as set by the policy
+    /// (no retries by default), see [`PcapReader::with_zero_read_retry`](crate::pcap::PcapReader::with_zero_read_retry).
+    pub fn with_zero_read_retry(mut self, retry: ZeroReadRetry) -> Self {
+        self.reader.set_zero_read_retry(retry);
+        self
+    }
+
+    /// Returns the number of packet blocks dropped because they were before the first interface of their section,
+    /// see [`EarlyBlockPolicy`].
+    pub fn nb_dropped_early_blocks(&self) -> u64 {
+        self.nb_dropped
     }
 
     /// Returns the next [`Block`].
     pub fn next_block(&mut self) -> Option<Result<Block, PcapError>> {
-        match self.reader.has_data_left() {
-            Ok(has_data) => {
-                if has_data {
-                    Some(self.reader.parse_with(|src| self.parser.next_block(src)))
-                }
-                else {
-                    None
+        // The deferred blocks are returned once the interfaces of their section are known
+        if !self.parser.interfaces().is_empty() {
+            if let Some(block) = self.deferred.pop_front() {
+                self.deferred_len -= block.len();
+                self.consumed = block.len() as u64;
+                self.flushed = block;
+                return Some(self.parser.next_block(&self.flushed).map(|(_, block)| block));
+            }
+        }
+
+        loop {
+            match self.reader.has_data_left() {
+                Ok(true) => {},
+                Ok(false) => {
+                    self.drop_deferred();
+                    return None;
+                },
+                Err(e) => return Some(Err(PcapError::IoError(e))),
+            }
+
+            let endianness = self.parser.section().endianness;
+            let (block_type, len) = match self.reader.peek_with(|src| probe_block(endianness, src), |src| block_type_len(endianness, src)) {
+                Ok(block) => block,
+                Err(e) => return Some(Err(e)),
+            };
+
+            if block_type == SECTION_HEADER_BLOCK {
+                self.drop_deferred();
+            }
+            else if self.parser.interfaces().is_empty() {
+                let is_packet = matches!(block_type, ENHANCED_PACKET_BLOCK | SIMPLE_PACKET_BLOCK | PACKET_BLOCK);
+                let early_err = PcapError::BlockBeforeInterface { block_type, offset: self.offset };
+
+                match self.early_blocks {
+                    EarlyBlockPolicy::Strict if is_packet => return Some(Err(early_err)),
+                    EarlyBlockPolicy::Defer { max_bytes } if is_packet && self.deferred_len + len <= max_bytes => {
+                        let block = self
+                            .reader
+                            .parse_with(|src| probe_block(endianness, src), |src| Ok((&src[len..], src[..len].to_vec())));
+                        match block {
+                            Ok(block) => self.deferred.push_back(block),
+                            Err(e) => return Some(Err(e)),
+                        }
+                        self.deferred_len += len;
+                        self.offset += len as u64;
+                        continue;
+                    },
+                    // The packet blocks exceeding the deferred length are dropped
+                    EarlyBlockPolicy::Defer { .. } | EarlyBlockPolicy::Drop if is_packet => {
+                        if let Err(e) = self.reader.parse_with(|src| probe_block(endianness, src), |src| Ok((&src[len..], ()))) {
+                            return Some(Err(e));
+                        }
+                        self.nb_dropped += 1;
+                        self.offset += len as u64;
+                        continue;
+                    },
+                    _ => {},
                 }
-            },
-            Err(e) => Some(Err(PcapError::IoError(e))),
+            }
+
+            let block = self.reader.parse_with(|src| probe_block(endianness, src), |src| self.parser.next_block(src));
+            if block.is_ok() {
+                self.offset += len as u64;
+                self.consumed = len as u64;
+            }
+
+            return Some(block);
         }
     }
 
+    /// Drops the deferred blocks, whose section has no interface.
+    fn drop_deferred(&mut self) {
+        self.nb_dropped += self.deferred.len() as u64;
+        self.deferred.clear();
+        self.deferred_len = 0;
+    }
+
     /// Returns the next [`RawBlock`].
     pub fn next_raw_block(&mut self) -> Option<Result<RawBlock, PcapError>> {
         match self.reader.has_data_left() {
             Ok(has_data) => {
                 if has_data {
-                    Some(self.reader.parse_with(|src| self.parser.next_raw_block(src)))
+                    let endianness = self.parser.section().endianness;
+                    let block = self.reader.parse_with(|src| probe_block(endianness, src), |src| self.parser.next_raw_block(src));
+                    if let Ok(block) = &block {
+                        self.offset += block.initial_len as u64;
+                        self.consumed = block.initial_len as u64;
+                    }
+
+                    Some(block)
                 }
                 else {
                     None
@@ -73,6 +276,14 @@ impl<R: Read> PcapNgReader<R> {
         }
     }
 
+    /// Returns the number of bytes of the pcapng occupied by the last block returned, i.e. its total length,
+    /// its options and padding included.
+    ///
+    /// The blocks skipped or dropped before it, e.g. by the [`EarlyBlockPolicy`], aren't counted.
+    pub fn consumed_bytes(&self) -> u64 {
+        self.consumed
+    }
+
     /// Returns the current [`SectionHeaderBlock`].
     pub fn section(&self) -> &SectionHeaderBlock<'static> {
         self.parser.section()
@@ -83,6 +294,15 @@ impl<R: Read> PcapNgReader<R> {
         self.parser.interfaces()
     }
 
+    /// Returns the names of the [`NameResolutionBlock`](super::blocks::name_resolution::NameResolutionBlock)s of the
+    /// current section read so far.
+    ///
+    /// The names of a block only apply to the packets after it, see [`NameResolver`]. Only the blocks read with
+    /// [`PcapNgReader::next_block`] are resolved, [`PcapNgReader::next_raw_block`] doesn't decode them.
+    pub fn resolver(&self) -> &NameResolver {
+        self.parser.resolver()
+    }
+
     /// Returns the [`InterfaceDescriptionBlock`] corresponding to the given packet
     pub fn packet_interface(&self, packet: &EnhancedPacketBlock) -> Option<&InterfaceDescriptionBlock> {
         self.interfaces().get(packet.interface_id as usize)
@@ -98,3 +318,35 @@ impl<R: Read> PcapNgReader<R> {
         self.reader.get_ref()
     }
 }
+
+impl<R: Read + Debug> Debug for PcapNgReader<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PcapNgReader")
+            .field("parser", &self.parser)
+            .field("reader", &self.reader)
+            .field("offset", &self.offset)
+            .field("consumed", &self.consumed)
+            .field("early_blocks", &self.early_blocks)
+            .field("nb_deferred", &self.deferred.len())
+            .field("nb_dropped", &self.nb_dropped)
+            .finish()
+    }
+}
+
+/// Returns the type and the length of the block at the start of the slice, without consuming it.
+fn block_type_len(endianness: Endianness, src: &[u8]) -> PcapResult<(&[u8], (u32, usize))> {
+    let (rem, block) = match endianness {
+        Endianness::Big => RawBlock::from_slice::<BigEndian>(src)?,
+        Endianness::Little => RawBlock::from_slice::<LittleEndian>(src)?,
+    };
+
+    Ok((src, (block.type_, src.len() - rem.len())))
+}
+
+/// Checks that the slice starts with a whole block, without parsing its content.
+fn probe_block(endianness: Endianness, src: &[u8]) -> PcapResult<()> {
+    match endianness {
+        Endianness::Big => RawBlock::from_slice::<BigEndian>(src).map(drop),
+        Endianness::Little => RawBlock::from_slice::<LittleEndian>(src).map(drop),
+    }
+}
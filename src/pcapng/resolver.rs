@@ -0,0 +1,182 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::Read;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use super::blocks::name_resolution::{Ipv4Record, Ipv6Record, NameResolutionBlock, Record};
+use super::{Block, PcapNgReader};
+use crate::errors::*;
+
+
+/// Names of the addresses found in the [`NameResolutionBlock`]s of a pcapng.
+///
+/// The [`PcapNgReader`] fills its resolver as it reads the blocks, see [`PcapNgReader::resolver`]. Following the
+/// pcapng specification, a record only applies to the packets after its block: while reading, the resolver only
+/// knows the names of the blocks already read. Use [`NameResolver::from_pcapng`] to apply all the names of a
+/// pcapng to all its packets.
+///
+/// When an address is resolved by several records, the last one replaces the previous ones.
+///
+/// The number of addresses resolved is bounded, [`MAX_RESOLVED_ADDRESSES`] by default, so that an untrusted pcapng
+/// can't use all the memory: the records of new addresses are ignored once the resolver is full.
+///
+/// # Example
+/// ```rust
+/// use std::net::Ipv4Addr;
+///
+/// use pcap_file::pcapng::NameResolver;
+///
+/// let mut resolver = NameResolver::new();
+/// resolver.insert_v4(Ipv4Addr::new(10, 0, 0, 1), vec!["gateway.lan".to_owned()]);
+///
+/// assert_eq!(resolver.lookup_v4(Ipv4Addr::new(10, 0, 0, 1)), Some("gateway.lan"));
+/// assert_eq!(resolver.lookup_v4(Ipv4Addr::new(10, 0, 0, 2)), None);
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NameResolver {
+    v4: HashMap<Ipv4Addr, Vec<String>>,
+    v6: HashMap<Ipv6Addr, Vec<String>>,
+    /// Maximum number of addresses resolved
+    max_len: usize,
+}
+
+/// Default maximum number of addresses resolved by a [`NameResolver`].
+pub const MAX_RESOLVED_ADDRESSES: usize = 1 << 20;
+
+impl Default for NameResolver {
+    fn default() -> Self {
+        NameResolver::with_max_len(MAX_RESOLVED_ADDRESSES)
+    }
+}
+
+impl NameResolver {
+    /// Creates a new empty [`NameResolver`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new empty [`NameResolver`] resolving at most `max_len` addresses.
+    pub fn with_max_len(max_len: usize) -> Self {
+        NameResolver { v4: HashMap::new(), v6: HashMap::new(), max_len }
+    }
+
+    /// Creates a [`NameResolver`] from all the [`NameResolutionBlock`]s of a pcapng, wherever they are,
+    /// including in different sections.
+    ///
+    /// # Errors
+    /// The pcapng is invalid or the underlying data are not readable.
+    pub fn from_pcapng<R: Read>(reader: R) -> PcapResult<Self> {
+        let mut reader = PcapNgReader::new(reader)?;
+        let mut resolver = NameResolver::new();
+        while let Some(block) = reader.next_block() {
+            if let Block::NameResolution(block) = block? {
+                resolver.add_block(&block);
+            }
+        }
+
+        Ok(resolver)
+    }
+
+    /// Returns the first name of an IPv4 address.
+    pub fn lookup_v4(&self, addr: Ipv4Addr) -> Option<&str> {
+        self.names_v4(addr).first().map(String::as_str)
+    }
+
+    /// Returns the first name of an IPv6 address.
+    pub fn lookup_v6(&self, addr: Ipv6Addr) -> Option<&str> {
+        self.names_v6(addr).first().map(String::as_str)
+    }
+
+    /// Returns all the names of an IPv4 address.
+    pub fn names_v4(&self, addr: Ipv4Addr) -> &[String] {
+        self.v4.get(&addr).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Returns all the names of an IPv6 address.
+    pub fn names_v6(&self, addr: Ipv6Addr) -> &[String] {
+        self.v6.get(&addr).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Sets the names of an IPv4 address, replacing the previous ones.
+    ///
+    /// Returns false if the address is new and the resolver is full, the names are then ignored.
+    pub fn insert_v4(&mut self, addr: Ipv4Addr, names: Vec<String>) -> bool {
+        if self.len() >= self.max_len && !self.v4.contains_key(&addr) {
+            return false;
+        }
+
+        self.v4.insert(addr, names);
+        true
+    }
+
+    /// Sets the names of an IPv6 address, replacing the previous ones.
+    ///
+    /// Returns false if the address is new and the resolver is full, the names are then ignored.
+    pub fn insert_v6(&mut self, addr: Ipv6Addr, names: Vec<String>) -> bool {
+        if self.len() >= self.max_len && !self.v6.contains_key(&addr) {
+            return false;
+        }
+
+        self.v6.insert(addr, names);
+        true
+    }
+
+    /// Adds the records of a [`NameResolutionBlock`], the unknown records and the ones of new addresses once
+    /// the resolver is full are ignored.
+    pub fn add_block(&mut self, block: &NameResolutionBlock) {
+        for record in &block.records {
+            match record {
+                Record::Ipv4(record) => {
+                    // The parser guarantees the length of the address
+                    if let Ok(addr) = <[u8; 4]>::try_from(&record.ip_addr[..]) {
+                        self.insert_v4(addr.into(), record.names.iter().map(|name| name.to_string()).collect());
+                    }
+                },
+                Record::Ipv6(record) => {
+                    if let Ok(addr) = <[u8; 16]>::try_from(&record.ip_addr[..]) {
+                        self.insert_v6(addr.into(), record.names.iter().map(|name| name.to_string()).collect());
+                    }
+                },
+                Record::End | Record::Unknown(_) => {},
+            }
+        }
+    }
+
+    /// Returns the number of addresses resolved.
+    pub fn len(&self) -> usize {
+        self.v4.len() + self.v6.len()
+    }
+
+    /// Returns true if no address is resolved.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes all the names.
+    pub fn clear(&mut self) {
+        self.v4.clear();
+        self.v6.clear();
+    }
+
+    /// Returns a [`NameResolutionBlock`] containing all the names, sorted by address.
+    ///
+    /// The block can be written with [`PcapNgWriter::write_pcapng_block`](super::PcapNgWriter::write_pcapng_block).
+    pub fn to_block(&self) -> NameResolutionBlock<'static> {
+        // A record must have at least one name
+        let mut v4: Vec<_> = self.v4.iter().filter(|(_, names)| !names.is_empty()).collect();
+        v4.sort_unstable_by_key(|(addr, _)| **addr);
+        let mut v6: Vec<_> = self.v6.iter().filter(|(_, names)| !names.is_empty()).collect();
+        v6.sort_unstable_by_key(|(addr, _)| **addr);
+
+        let v4_records = v4.into_iter().map(|(addr, names)| {
+            let ip_addr = Cow::Owned(addr.octets().to_vec());
+            Record::Ipv4(Ipv4Record { ip_addr, names: names.iter().cloned().map(Cow::Owned).collect() })
+        });
+        let v6_records = v6.into_iter().map(|(addr, names)| {
+            let ip_addr = Cow::Owned(addr.octets().to_vec());
+            Record::Ipv6(Ipv6Record { ip_addr, names: names.iter().cloned().map(Cow::Owned).collect() })
+        });
+
+        NameResolutionBlock { records: v4_records.chain(v6_records).collect(), options: vec![] }
+    }
+}
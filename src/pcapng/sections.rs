@@ -0,0 +1,174 @@
+//! Splits a multi-section pcapng, e.g. concatenated captures, to process its sections in parallel.
+
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use byteorder_slice::byteorder::ReadBytesExt;
+use byteorder_slice::{BigEndian, LittleEndian};
+
+use super::blocks::block_common::Block;
+use super::blocks::SECTION_HEADER_BLOCK;
+use crate::errors::*;
+use crate::Endianness;
+
+
+/// Returns the byte ranges of the sections of a pcapng, each one starting with its SectionHeaderBlock.
+///
+/// The section headers are found by scanning the 32-bit aligned words for their block type, without parsing the
+/// other blocks. To not be fooled by the same bytes inside a packet, a candidate is only kept if it is a fully valid
+/// SectionHeaderBlock and if it is preceded by a block whose leading and trailing lengths match.
+///
+/// When a SectionHeaderBlock gives the length of its section, the scan jumps to the end of the section if it is
+/// validated the same way: the data ends there or a valid SectionHeaderBlock starts there, right after a whole block.
+/// Otherwise the length is ignored and the section is scanned.
+///
+/// The blocks inside the sections aren't validated, the readers of the sections return their errors.
+///
+/// # Errors
+/// The data doesn't start with a valid SectionHeaderBlock.
+pub fn section_ranges(data: &[u8]) -> PcapResult<Vec<Range<usize>>> {
+    let (mut endianness, mut section_end) = parse_section_header(data)?;
+
+    let mut starts: Vec<usize> = vec![0];
+    let mut offset = 4;
+    while offset + 4 <= data.len() {
+        // Can unwrap because there is always a section start
+        let section_start = *starts.last().unwrap();
+
+        // Fast path: jump to the end given by the section header if it is plausible
+        if let Some(end) = section_end.take().map(|len| section_start.saturating_add(len)) {
+            let ends_section = end == data.len() || (end < data.len() && parse_section_header(&data[end..]).is_ok());
+            if end > offset && ends_section && ends_block(&data[section_start..end], endianness) {
+                offset = end;
+                continue;
+            }
+        }
+
+        if read_u32(&data[offset..], Endianness::Big) != SECTION_HEADER_BLOCK {
+            offset += 4;
+            continue;
+        }
+
+        match parse_section_header(&data[offset..]) {
+            Ok((section_endianness, len)) if ends_block(&data[section_start..offset], endianness) => {
+                starts.push(offset);
+                endianness = section_endianness;
+                section_end = len;
+            },
+            _ => {},
+        }
+
+        offset += 4;
+    }
+
+    let ends = starts.iter().skip(1).copied().chain(std::iter::once(data.len()));
+    Ok(starts.iter().copied().zip(ends).map(|(start, end)| start..end).collect())
+}
+
+/// Returns the sections of a pcapng, which are valid pcapngs by themselves, see [`section_ranges`].
+///
+/// Each section can be read by its own [`PcapNgReader`](super::PcapNgReader), for example on its own thread.
+///
+/// # Errors
+/// The data doesn't start with a valid SectionHeaderBlock.
+pub fn split_sections(data: &[u8]) -> PcapResult<Vec<&[u8]>> {
+    Ok(section_ranges(data)?.into_iter().map(|range| &data[range]).collect())
+}
+
+/// Calls `f` on each section of a pcapng on at most `max_threads` threads, returns the results in the order of the sections.
+///
+/// `f` gets the index and the data of the section, see [`split_sections`].
+///
+/// # Errors
+/// The data doesn't start with a valid SectionHeaderBlock.
+///
+/// # Panics
+/// `max_threads` is 0, or `f` panics.
+///
+/// # Example
+/// ```rust,no_run
+/// use pcap_file::pcapng::{process_sections, PcapNgReader};
+///
+/// let data = std::fs::read("archive.pcapng").expect("Error reading file");
+///
+/// let nb_blocks = process_sections(&data, 4, |_, section| {
+///     let mut reader = PcapNgReader::new(section)?;
+///     let mut nb_blocks = 0;
+///     while let Some(block) = reader.next_block() {
+///         block?;
+///         nb_blocks += 1;
+///     }
+///     Ok::<_, pcap_file::PcapError>(nb_blocks)
+/// })
+/// .unwrap();
+///
+/// let total: usize = nb_blocks.into_iter().map(Result::unwrap).sum();
+/// ```
+pub fn process_sections<T, F>(data: &[u8], max_threads: usize, f: F) -> PcapResult<Vec<T>>
+where
+    T: Send,
+    F: Fn(usize, &[u8]) -> T + Sync,
+{
+    assert!(max_threads > 0, "Can't process the sections on 0 threads");
+
+    let sections = split_sections(data)?;
+    let results = Mutex::new((0..sections.len()).map(|_| None).collect::<Vec<Option<T>>>());
+    let next_section = AtomicUsize::new(0);
+
+    std::thread::scope(|scope| {
+        for _ in 0..max_threads.min(sections.len()) {
+            scope.spawn(|| loop {
+                let idx = next_section.fetch_add(1, Ordering::Relaxed);
+                let section = match sections.get(idx) {
+                    Some(section) => section,
+                    None => break,
+                };
+
+                let res = f(idx, section);
+                results.lock().unwrap()[idx] = Some(res);
+            });
+        }
+    });
+
+    // Can unwrap because all the sections were processed, otherwise a thread panicked and the scope too
+    Ok(results.into_inner().unwrap().into_iter().map(Option::unwrap).collect())
+}
+
+/// Parses the SectionHeaderBlock at the start of the slice, returns its endianness and the length of the whole section,
+/// SectionHeaderBlock included, if it gives it.
+fn parse_section_header(src: &[u8]) -> PcapResult<(Endianness, Option<usize>)> {
+    // Always use BigEndian here because we can't know the SectionHeaderBlock endianness
+    match Block::from_slice::<BigEndian>(src)? {
+        (rem, Block::SectionHeader(section)) => {
+            let block_len = src.len() - rem.len();
+            let section_len = usize::try_from(section.section_length).ok().and_then(|len| len.checked_add(block_len));
+            Ok((section.endianness, section_len))
+        },
+        _ => Err(PcapError::InvalidField("PcapNg: SectionHeader invalid or missing")),
+    }
+}
+
+/// Returns true if the slice ends with a whole block, i.e. its trailing length matches the leading length of the block.
+fn ends_block(src: &[u8], endianness: Endianness) -> bool {
+    if src.len() < 12 {
+        return false;
+    }
+
+    let trailer_len = read_u32(&src[src.len() - 4..], endianness) as usize;
+    if trailer_len < 12 || !trailer_len.is_multiple_of(4) || trailer_len > src.len() {
+        return false;
+    }
+
+    let block = &src[src.len() - trailer_len..];
+    read_u32(&block[4..], endianness) as usize == trailer_len
+}
+
+/// Reads a u32 from a slice of at least 4 bytes.
+fn read_u32(mut src: &[u8], endianness: Endianness) -> u32 {
+    // Can unwrap because the callers check the length
+    match endianness {
+        Endianness::Big => src.read_u32::<BigEndian>().unwrap(),
+        Endianness::Little => src.read_u32::<LittleEndian>().unwrap(),
+    }
+}
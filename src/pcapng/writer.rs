@@ -1,12 +1,20 @@
-use std::io::Write;
+use std::borrow::Cow;
+use std::fmt::Debug;
+use std::io::{Seek, SeekFrom, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use byteorder_slice::{BigEndian, ByteOrder, LittleEndian};
 
 use super::blocks::block_common::{Block, PcapNgBlock};
+use super::blocks::enhanced_packet::{EnhancedPacketBlock, EnhancedPacketOption};
 use super::blocks::interface_description::{InterfaceDescriptionBlock, TsResolution};
 use super::blocks::section_header::SectionHeaderBlock;
-use super::blocks::SECTION_HEADER_BLOCK;
-use super::RawBlock;
+use super::blocks::simple_packet::SimplePacketBlock;
+use super::blocks::interface_statistics::InterfaceStatisticsBlock;
+use super::blocks::unknown::UnknownBlock;
+use super::blocks::{CUSTOM_BLOCK_NO_COPY, SECTION_HEADER_BLOCK};
+use super::{NameResolver, RawBlock};
+use crate::pcap::PcapPacket;
 use crate::{Endianness, PcapError, PcapResult};
 
 
@@ -41,10 +49,37 @@ pub struct PcapNgWriter<W: Write> {
     /// Timestamp resolutions corresponding to the interfaces
     ts_resolutions: Vec<TsResolution>,
 
+    /// Block written by [`PcapNgWriter::keepalive`]
+    keepalive: KeepaliveBlock,
+    /// Number of keepalive blocks written
+    nb_keepalives: u64,
+
+    /// Length of the current SectionHeaderBlock
+    shb_len: u64,
+    /// Length of the blocks written after the current SectionHeaderBlock
+    section_len: u64,
+    /// Position of the current SectionHeaderBlock, if the section lengths are backfilled
+    backfill_start: Option<u64>,
+    /// Sections whose length is written by [`PcapNgWriter::finish`]: position, length and endianness
+    backfills: Vec<(u64, u64, Endianness)>,
+    /// Length of the blocks written since the creation of the writer
+    position: u64,
+
     /// Wrapped writer to which the block are written to.
     writer: W,
 }
 
+/// Block written by [`PcapNgWriter::keepalive`].
+///
+/// Both are ignored by the readers looking only at the packets.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum KeepaliveBlock {
+    /// An [`InterfaceStatisticsBlock`] without options for the interface 0, timestamped with the current time
+    InterfaceStatistics,
+    /// A custom block, that must not be copied, containing only the given Private Enterprise Number
+    Custom(u32),
+}
+
 impl<W: Write> PcapNgWriter<W> {
     /// Create a new [`PcapNgWriter`] from an existing writer.
     ///
@@ -77,12 +112,24 @@ impl<W: Write> PcapNgWriter<W> {
 
     /// Create a new [`PcapNgWriter`] from an existing writer with the given section header.
     pub fn with_section_header(mut writer: W, section: SectionHeaderBlock<'static>) -> PcapResult<Self> {
-        match section.endianness {
+        let shb_len = match section.endianness {
             Endianness::Big => section.clone().into_block().write_to::<BigEndian, _>(&mut writer).map_err(PcapError::IoError)?,
             Endianness::Little => section.clone().into_block().write_to::<LittleEndian, _>(&mut writer).map_err(PcapError::IoError)?,
         };
 
-        Ok(Self { section, interfaces: Vec::new(), ts_resolutions: Vec::new(), writer })
+        Ok(Self {
+            section,
+            interfaces: Vec::new(),
+            ts_resolutions: Vec::new(),
+            keepalive: KeepaliveBlock::InterfaceStatistics,
+            nb_keepalives: 0,
+            shb_len: shb_len as u64,
+            section_len: 0,
+            backfill_start: None,
+            backfills: vec![],
+            position: shb_len as u64,
+            writer,
+        })
     }
 
     /// Write a [`Block`].
@@ -117,6 +164,8 @@ impl<W: Write> PcapNgWriter<W> {
     /// pcap_ng_writer.write_block(&packet.into_block()).unwrap();
     /// ```
     pub fn write_block(&mut self, block: &Block) -> PcapResult<usize> {
+        let endianness = self.section.endianness;
+
         match block {
             Block::SectionHeader(blk) => {
                 self.section = blk.clone().into_owned();
@@ -124,16 +173,36 @@ impl<W: Write> PcapNgWriter<W> {
                 self.ts_resolutions.clear();
             },
             Block::InterfaceDescription(blk) => {
+                // The linktype of an InterfaceDescriptionBlock is 16 bits long, unlike the one of a pcap
+                if u32::from(blk.linktype) > u16::MAX as u32 {
+                    return Err(PcapError::InvalidField("InterfaceDescriptionBlock: linktype > u16::MAX"));
+                }
+
                 let ts_resolution = blk.ts_resolution()?;
                 self.ts_resolutions.push(ts_resolution);
 
-                self.interfaces.push(blk.clone().into_owned());
+                let mut interface = blk.clone().into_owned();
+                interface.linktype = interface.linktype.normalize();
+                self.interfaces.push(interface);
             },
             Block::InterfaceStatistics(blk) => {
                 if blk.interface_id as usize >= self.interfaces.len() {
                     return Err(PcapError::InvalidInterfaceId(blk.interface_id));
                 }
             },
+            Block::SimplePacket(blk) => {
+                // The captured length of a SimplePacketBlock is min(original_len, snaplen of the interface 0)
+                let interface = self.interfaces.first().ok_or(PcapError::InvalidInterfaceId(0))?;
+                let snaplen = if interface.snaplen == 0 { u32::MAX } else { interface.snaplen };
+
+                if blk.data.len() > snaplen as usize {
+                    return Err(PcapError::InvalidField("SimplePacketBlock: data length > snaplen of the interface 0"));
+                }
+
+                if blk.data.len() != blk.original_len.min(snaplen) as usize {
+                    return Err(PcapError::InvalidField("SimplePacketBlock: data length != min(original_len, snaplen)"));
+                }
+            },
             Block::EnhancedPacket(blk) => {
                 if blk.interface_id as usize >= self.interfaces.len() {
                     return Err(PcapError::InvalidInterfaceId(blk.interface_id));
@@ -146,10 +215,30 @@ impl<W: Write> PcapNgWriter<W> {
             _ => (),
         }
 
-        match self.section.endianness {
-            Endianness::Big => block.write_to::<BigEndian, _>(&mut self.writer).map_err(PcapError::IoError),
-            Endianness::Little => block.write_to::<LittleEndian, _>(&mut self.writer).map_err(PcapError::IoError),
+        let written = match self.section.endianness {
+            Endianness::Big => block.write_to::<BigEndian, _>(&mut self.writer).map_err(PcapError::IoError)?,
+            Endianness::Little => block.write_to::<LittleEndian, _>(&mut self.writer).map_err(PcapError::IoError)?,
+        };
+
+        self.count_block(endianness, matches!(block, Block::SectionHeader(_)), written);
+        Ok(written)
+    }
+
+    /// Counts a block written in the length of its section, `endianness` being the one of the section before the block.
+    fn count_block(&mut self, endianness: Endianness, is_section_header: bool, written: usize) {
+        self.position += written as u64;
+        if !is_section_header {
+            self.section_len += written as u64;
+            return;
         }
+
+        if let Some(start) = self.backfill_start {
+            self.backfills.push((start, self.section_len, endianness));
+            self.backfill_start = Some(start + self.shb_len + self.section_len);
+        }
+
+        self.shb_len = written as u64;
+        self.section_len = 0;
     }
 
     /// Write a [`PcapNgBlock`].
@@ -187,15 +276,185 @@ impl<W: Write> PcapNgWriter<W> {
         self.write_block(&block.into_block())
     }
 
+    /// Write a [`PcapPacket`] as an [`EnhancedPacketBlock`] of the given interface.
+    ///
+    /// If a comment is given, it is written as the `opt_comment` option of the block.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use std::fs::File;
+    ///
+    /// use pcap_file::pcap::PcapReader;
+    /// use pcap_file::pcapng::blocks::interface_description::InterfaceDescriptionBlock;
+    /// use pcap_file::pcapng::PcapNgWriter;
+    ///
+    /// let file_in = File::open("test.pcap").expect("Error opening file");
+    /// let mut pcap_reader = PcapReader::new(file_in).unwrap();
+    ///
+    /// let file_out = File::create("out.pcapng").expect("Error creating file");
+    /// let mut pcapng_writer = PcapNgWriter::new(file_out).unwrap();
+    ///
+    /// let header = pcap_reader.header();
+    /// let interface = InterfaceDescriptionBlock::new(header.datalink, header.snaplen);
+    /// pcapng_writer.write_pcapng_block(interface).unwrap();
+    ///
+    /// while let Some(pkt) = pcap_reader.next_packet() {
+    ///     let pkt = pkt.unwrap();
+    ///
+    ///     let comment = if pkt.data.len() > 1500 { Some("Jumbo frame") } else { None };
+    ///     pcapng_writer.write_packet(0, &pkt, comment).unwrap();
+    /// }
+    /// ```
+    pub fn write_packet(&mut self, interface_id: u32, packet: &PcapPacket, comment: Option<&str>) -> PcapResult<usize> {
+        let mut block = EnhancedPacketBlock::default();
+        block.interface_id = interface_id;
+        block.timestamp = packet.timestamp;
+        block.original_len = packet.orig_len;
+        block.data = Cow::Borrowed(&packet.data[..]);
+
+        if let Some(comment) = comment {
+            block.options.push(EnhancedPacketOption::Comment(Cow::Borrowed(comment)));
+        }
+
+        self.write_pcapng_block(block)
+    }
+
+    /// Writes a packet as an [`EnhancedPacketBlock`] of the given interface, from its data in a [`bytes::Buf`],
+    /// e.g. a [`bytes::Bytes`] returned by a [`PcapNgBytesReader`](super::PcapNgBytesReader).
+    ///
+    /// The data are only copied if they aren't contiguous.
+    ///
+    /// Only available with the `bytes` feature.
+    #[cfg(feature = "bytes")]
+    pub fn write_buf<B: bytes::Buf>(
+        &mut self,
+        interface_id: u32,
+        timestamp: std::time::Duration,
+        orig_len: u32,
+        mut data: B,
+    ) -> PcapResult<usize> {
+        if data.chunk().len() == data.remaining() {
+            return self.write_packet(interface_id, &PcapPacket::new(timestamp, orig_len, data.chunk()), None);
+        }
+
+        let data = data.copy_to_bytes(data.remaining());
+        self.write_packet(interface_id, &PcapPacket::new(timestamp, orig_len, &data), None)
+    }
+
+    /// Write a [`PcapPacket`] as a [`SimplePacketBlock`].
+    ///
+    /// A SimplePacketBlock has less overhead than an [`EnhancedPacketBlock`] but its timestamp is lost
+    /// and it always belongs to the interface 0.
+    ///
+    /// # Errors
+    /// There is no interface 0 or the packet doesn't fit in the snaplen of the interface 0.
+    ///
+    /// The packet data is truncated but its length is not the snaplen of the interface 0,
+    /// so it can't be represented by a SimplePacketBlock.
+    pub fn write_simple_packet(&mut self, packet: &PcapPacket) -> PcapResult<usize> {
+        let block = SimplePacketBlock { original_len: packet.orig_len, data: Cow::Borrowed(&packet.data[..]) };
+        self.write_pcapng_block(block)
+    }
+
+    /// Write the names of a [`NameResolver`] as a NameResolutionBlock.
+    ///
+    /// The names only apply to the packets written after the block.
+    pub fn write_name_resolver(&mut self, resolver: &NameResolver) -> PcapResult<usize> {
+        self.write_pcapng_block(resolver.to_block())
+    }
+
+    /// Set the block written by [`PcapNgWriter::keepalive`].
+    ///
+    /// Defaults to [`KeepaliveBlock::InterfaceStatistics`].
+    pub fn set_keepalive_block(&mut self, keepalive: KeepaliveBlock) {
+        self.keepalive = keepalive;
+    }
+
+    /// Write a harmless keepalive block, to be called by the user during idle periods
+    /// of a pcapng streamed to a consumer that needs to see some activity.
+    ///
+    /// The block written is configured by [`PcapNgWriter::set_keepalive_block`].
+    ///
+    /// # Errors
+    /// The keepalive is an [`InterfaceStatisticsBlock`] and there is no interface 0.
+    pub fn keepalive(&mut self) -> PcapResult<usize> {
+        let block = match self.keepalive {
+            KeepaliveBlock::InterfaceStatistics => {
+                let ts_resolution = self.ts_resolutions.first().ok_or(PcapError::InvalidInterfaceId(0))?;
+
+                let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+                let timestamp = (now.as_nanos() / ts_resolution.to_nano_secs() as u128) as u64;
+
+                InterfaceStatisticsBlock { interface_id: 0, timestamp, options: vec![] }.into_block()
+            },
+            KeepaliveBlock::Custom(pen) => {
+                let pen = match self.section.endianness {
+                    Endianness::Big => pen.to_be_bytes(),
+                    Endianness::Little => pen.to_le_bytes(),
+                };
+
+                UnknownBlock { type_: CUSTOM_BLOCK_NO_COPY, length: 16, value: Cow::Owned(pen.to_vec()) }.into_block()
+            },
+        };
+
+        let written = self.write_block(&block)?;
+        self.nb_keepalives += 1;
+
+        Ok(written)
+    }
+
+    /// Return the number of keepalive blocks written by [`PcapNgWriter::keepalive`].
+    pub fn keepalive_count(&self) -> u64 {
+        self.nb_keepalives
+    }
+
+    /// Write a padding block so that the length of the pcapng is a multiple of `block_size`, e.g. to store the
+    /// archived pcapngs in whole blocks of 4 KiB. It is meant to be called once, after the last packet.
+    ///
+    /// The padding block is a custom block that must not be copied, with the reserved Private Enterprise Number 0
+    /// and zeros as data, so the readers ignore it like any custom block. It is at least 16 bytes long, so up to
+    /// `block_size + 12` bytes are written. Nothing is written if the length is already a multiple of `block_size`.
+    /// The length is counted from the first SectionHeaderBlock written by the writer.
+    ///
+    /// Returns the number of bytes written.
+    ///
+    /// # Errors
+    /// `block_size` is 0 or not a multiple of 4, the alignment of the blocks.
+    ///
+    /// The writer can't be written to.
+    pub fn pad_to(&mut self, block_size: u32) -> PcapResult<usize> {
+        if block_size == 0 || !block_size.is_multiple_of(4) {
+            return Err(PcapError::InvalidField("PcapNgWriter: block_size not a positive multiple of 4"));
+        }
+
+        let block_size = block_size as u64;
+        let mut len = (block_size - self.position % block_size) % block_size;
+        if len == 0 {
+            return Ok(0);
+        }
+        // The type, the two lengths and the PEN
+        while len < 16 {
+            len += block_size;
+        }
+
+        let length = u32::try_from(len).map_err(|_| PcapError::InvalidField("PcapNgWriter: padding block length > u32::MAX"))?;
+        let padding = UnknownBlock { type_: CUSTOM_BLOCK_NO_COPY, length, value: Cow::Owned(vec![0; len as usize - 12]) };
+        self.write_block(&padding.into_block())
+    }
+
     /// Write a [`RawBlock`].
     ///
     /// Doesn't check the validity of the written blocks.
     pub fn write_raw_block(&mut self, block: &RawBlock) -> PcapResult<usize> {
-        return match self.section.endianness {
-            Endianness::Big => inner::<BigEndian, _>(&mut self.section, block, &mut self.writer),
-            Endianness::Little => inner::<LittleEndian, _>(&mut self.section, block, &mut self.writer),
+        let endianness = self.section.endianness;
+        let written = match endianness {
+            Endianness::Big => inner::<BigEndian, _>(&mut self.section, block, &mut self.writer)?,
+            Endianness::Little => inner::<LittleEndian, _>(&mut self.section, block, &mut self.writer)?,
         };
 
+        self.count_block(endianness, block.type_ == SECTION_HEADER_BLOCK, written);
+        return Ok(written);
+
         // Write a RawBlock to a writer
         fn inner<B: ByteOrder, W: Write>(section: &mut SectionHeaderBlock, block: &RawBlock, writer: &mut W) -> PcapResult<usize> {
             if block.type_ == SECTION_HEADER_BLOCK {
@@ -233,3 +492,71 @@ impl<W: Write> PcapNgWriter<W> {
         &self.interfaces
     }
 }
+
+impl<W: Write + Seek> PcapNgWriter<W> {
+    /// Writes the length of each section in its SectionHeaderBlock when [`PcapNgWriter::finish`] is called,
+    /// like mergecap does, so that the readers can skip whole sections, see [`section_ranges`](super::section_ranges).
+    ///
+    /// The section headers are written as given, typically with an unspecified length (-1), then overwritten by
+    /// `finish`, so the pcapng is only complete once `finish` returned. The current section, whose header is already
+    /// written, is backfilled too.
+    ///
+    /// # Errors
+    /// The position of the writer can't be read, or is before the end of the current section, e.g. because
+    /// the writer was moved with [`PcapNgWriter::get_mut`].
+    pub fn with_section_length_backfill(mut self) -> PcapResult<Self> {
+        let position = self.writer.stream_position().map_err(PcapError::IoError)?;
+        let start = position
+            .checked_sub(self.shb_len + self.section_len)
+            .ok_or(PcapError::InvalidConfiguration("PcapNgWriter: position of the writer before the end of the section"))?;
+
+        self.backfill_start = Some(start);
+        Ok(self)
+    }
+
+    /// Writes the section lengths if enabled by [`PcapNgWriter::with_section_length_backfill`], then flushes the writer
+    /// and returns it, positioned at the end of the pcapng.
+    ///
+    /// # Errors
+    /// The writer can't be written to or moved.
+    pub fn finish(mut self) -> PcapResult<W> {
+        if let Some(start) = self.backfill_start.take() {
+            self.backfills.push((start, self.section_len, self.section.endianness));
+        }
+
+        if !self.backfills.is_empty() {
+            let end = self.writer.stream_position().map_err(PcapError::IoError)?;
+
+            for &(start, len, endianness) in &self.backfills {
+                // The section length follows the block type, the block length, the magic and the versions
+                let len = match endianness {
+                    Endianness::Big => len.to_be_bytes(),
+                    Endianness::Little => len.to_le_bytes(),
+                };
+
+                self.writer.seek(SeekFrom::Start(start + 16)).map_err(PcapError::IoError)?;
+                self.writer.write_all(&len).map_err(PcapError::IoError)?;
+            }
+
+            self.writer.seek(SeekFrom::Start(end)).map_err(PcapError::IoError)?;
+        }
+
+        self.writer.flush().map_err(PcapError::IoError)?;
+        Ok(self.writer)
+    }
+}
+
+impl<W: Write + Debug> Debug for PcapNgWriter<W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PcapNgWriter")
+            .field("section", &self.section)
+            .field("interfaces", &self.interfaces)
+            .field("ts_resolutions", &self.ts_resolutions)
+            .field("keepalive", &self.keepalive)
+            .field("nb_keepalives", &self.nb_keepalives)
+            .field("backfill", &self.backfill_start.is_some())
+            .field("position", &self.position)
+            .field("writer", &std::any::type_name::<W>())
+            .finish()
+    }
+}
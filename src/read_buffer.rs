@@ -1,10 +1,10 @@
-use std::io::{Error, ErrorKind, Read};
+use std::fmt::Debug;
+use std::io::{Error, ErrorKind, Read, Seek, SeekFrom};
 
-use crate::PcapError;
+use crate::{PcapError, ZeroReadRetry};
 
 
 /// Internal structure that bufferize its input and allow to parse element from its buffer.
-#[derive(Debug)]
 pub(crate) struct ReadBuffer<R: Read> {
     /// Reader from which we read the data from
     reader: R,
@@ -14,6 +14,12 @@ pub(crate) struct ReadBuffer<R: Read> {
     pos: usize,
     /// Current end position of the buffer
     len: usize,
+    /// True if the last read of the reader returned no data
+    eof: bool,
+    /// Retries of the reads returning no data in the middle of an element
+    retry: Option<ZeroReadRetry>,
+    /// Size up to which the buffer can grow for an element bigger than it
+    max_capacity: usize,
 }
 
 impl<R: Read> ReadBuffer<R> {
@@ -24,89 +30,156 @@ impl<R: Read> ReadBuffer<R> {
 
     /// Creates a new ReadBuffer with the given capacity
     pub fn with_capacity(reader: R, capacity: usize) -> Self {
-        Self { reader, buffer: vec![0_u8; capacity], pos: 0, len: 0 }
+        Self { reader, buffer: vec![0_u8; capacity], pos: 0, len: 0, eof: false, retry: None, max_capacity: capacity }
+    }
+
+    /// Sets the retry policy of the reads returning no data in the middle of an element
+    pub fn set_zero_read_retry(&mut self, retry: ZeroReadRetry) {
+        self.retry = Some(retry);
+    }
+
+    /// Allows the buffer to grow up to `max_capacity` bytes for the elements bigger than it, it never grows by default
+    pub fn set_max_capacity(&mut self, max_capacity: usize) {
+        self.max_capacity = max_capacity;
     }
 
     /// Parse data from the internal buffer
     ///
-    /// Safety
+    /// The `probe` is called on the buffered data until it doesn't return [`PcapError::IncompleteBuffer`],
+    /// filling the buffer in between. It must not have side effects and must only fail with
+    /// [`PcapError::IncompleteBuffer`] if the `parser` would.
     ///
-    /// The parser must NOT keep a reference to the buffer in input.
-    pub fn parse_with<'a, 'b: 'a, 'c: 'a, F, O>(&'c mut self, mut parser: F) -> Result<O, PcapError>
+    /// The `parser` is then called once on the buffered data.
+    pub fn parse_with<'a, P, F, O>(&'a mut self, probe: P, parser: F) -> Result<O, PcapError>
     where
-        F: FnMut(&'a [u8]) -> Result<(&'a [u8], O), PcapError>,
-        F: 'b,
-        O: 'a,
+        P: FnMut(&[u8]) -> Result<(), PcapError>,
+        F: FnOnce(&'a [u8]) -> Result<(&'a [u8], O), PcapError>,
     {
-        loop {
-            let buf = &self.buffer[self.pos..self.len];
+        self.parse_with_inner(probe, |src, _| parser(src), true, false)
+    }
 
-            // Sound because 'b and 'c must outlive 'a so the buffer cannot be modified while someone has a ref on it
-            let buf: &'a [u8] = unsafe { std::mem::transmute(buf) };
+    /// Parse data from the internal buffer without consuming it, see [`ReadBuffer::parse_with`]
+    pub fn peek_with<'a, P, F, O>(&'a mut self, probe: P, parser: F) -> Result<O, PcapError>
+    where
+        P: FnMut(&[u8]) -> Result<(), PcapError>,
+        F: FnOnce(&'a [u8]) -> Result<(&'a [u8], O), PcapError>,
+    {
+        self.parse_with_inner(probe, |src, _| parser(src), false, false)
+    }
 
-            match parser(buf) {
-                Ok((rem, value)) => {
-                    self.advance_with_slice(rem);
-                    return Ok(value);
-                },
+    /// Parse data from the internal buffer like [`ReadBuffer::parse_with`], but if `cut_allowed` is true and the reader
+    /// ends before the `probe` succeeds, the `parser` is called on the rest of the data instead of failing.
+    ///
+    /// The second argument of the `parser` is true if the element is cut by the end of the data.
+    pub fn parse_with_cut<'a, P, F, O>(&'a mut self, cut_allowed: bool, probe: P, parser: F) -> Result<O, PcapError>
+    where
+        P: FnMut(&[u8]) -> Result<(), PcapError>,
+        F: FnOnce(&'a [u8], bool) -> Result<(&'a [u8], O), PcapError>,
+    {
+        self.parse_with_inner(probe, parser, true, cut_allowed)
+    }
+
+    /// Parse data from the internal buffer without consuming it, see [`ReadBuffer::parse_with_cut`]
+    pub fn peek_with_cut<'a, P, F, O>(&'a mut self, cut_allowed: bool, probe: P, parser: F) -> Result<O, PcapError>
+    where
+        P: FnMut(&[u8]) -> Result<(), PcapError>,
+        F: FnOnce(&'a [u8], bool) -> Result<(&'a [u8], O), PcapError>,
+    {
+        self.parse_with_inner(probe, parser, false, cut_allowed)
+    }
+
+    /// Parse data from the internal buffer, consuming it if `advance` is true
+    fn parse_with_inner<'a, P, F, O>(&'a mut self, mut probe: P, parser: F, advance: bool, cut_allowed: bool) -> Result<O, PcapError>
+    where
+        P: FnMut(&[u8]) -> Result<(), PcapError>,
+        F: FnOnce(&'a [u8], bool) -> Result<(&'a [u8], O), PcapError>,
+    {
+        // Fill the buffer until it contains the whole element.
+        // The probe only borrows the buffer during the call, so the buffer can be filled between two calls.
+        let mut cut = false;
+        loop {
+            match probe(&self.buffer[self.pos..self.len]) {
+                Ok(()) => break,
 
                 Err(PcapError::IncompleteBuffer) => {
-                    // The parsed data len should never be more than the buffer capacity
-                    if buf.len() == self.buffer.len() {
-                        return Err(PcapError::IoError(Error::from(ErrorKind::UnexpectedEof)));
+                    // The parsed data len should never be more than the buffer capacity, unless it can grow
+                    if self.len - self.pos == self.buffer.len() {
+                        if self.buffer.len() >= self.max_capacity {
+                            return Err(PcapError::IoError(Error::from(ErrorKind::UnexpectedEof)));
+                        }
+
+                        let capacity = self.buffer.len().saturating_mul(2).min(self.max_capacity);
+                        self.buffer.resize(capacity, 0);
                     }
 
-                    let nb_read = self.fill_buf().map_err(PcapError::IoError)?;
-                    if nb_read == 0 {
-                        return Err(PcapError::IoError(Error::from(ErrorKind::UnexpectedEof)));
+                    match self.fill_buf_retrying() {
+                        Ok(()) => {},
+                        // The reader has no more data, the element is cut
+                        Err(e) if cut_allowed && self.eof && e.is_eof_like() => {
+                            cut = true;
+                            break;
+                        },
+                        Err(e) => return Err(e),
                     }
                 },
 
                 Err(e) => return Err(e),
             }
         }
+
+        // Only the buffer is borrowed by the parsed value, so the position can still be updated
+        let buf = &self.buffer[self.pos..self.len];
+        let (rem, value) = parser(buf, cut)?;
+        if advance {
+            self.pos += buf.len() - rem.len();
+        }
+
+        Ok(value)
+    }
+
+    /// Fill the inner buffer in the middle of an element, retrying the reads returning no data as allowed by the retry policy
+    fn fill_buf_retrying(&mut self) -> Result<(), PcapError> {
+        let mut nb_retries = 0;
+        while self.fill_buf().map_err(PcapError::IoError)? == 0 {
+            match &mut self.retry {
+                None => return Err(PcapError::TruncatedPacket),
+                Some(retry) if nb_retries == retry.max_retries() => return Err(PcapError::UnexpectedEofMidPacket(nb_retries)),
+                Some(retry) => {
+                    nb_retries += 1;
+                    retry.backoff(nb_retries);
+                },
+            }
+        }
+
+        Ok(())
     }
 
     /// Fill the inner buffer.
     /// Copy the remaining data inside buffer at its start and the fill the end part with data from the reader.
     fn fill_buf(&mut self) -> Result<usize, std::io::Error> {
         // Copy the remaining data to the start of the buffer
-        let rem_len = unsafe {
-            let buf_ptr_mut = self.buffer.as_mut_ptr();
-            let rem_ptr_mut = buf_ptr_mut.add(self.pos);
-            std::ptr::copy(rem_ptr_mut, buf_ptr_mut, self.len - self.pos);
-            self.len - self.pos
-        };
+        self.buffer.copy_within(self.pos..self.len, 0);
+        let rem_len = self.len - self.pos;
 
         let nb_read = self.reader.read(&mut self.buffer[rem_len..])?;
 
         self.len = rem_len + nb_read;
         self.pos = 0;
+        self.eof = nb_read == 0;
 
         Ok(nb_read)
     }
 
-    /// Advance the internal buffer position.
-    fn advance(&mut self, nb_bytes: usize) {
-        assert!(self.pos + nb_bytes <= self.len);
-        self.pos += nb_bytes;
-    }
-
-    /// Advance the internal buffer position.
-    fn advance_with_slice(&mut self, rem: &[u8]) {
-        // Compute the length between the buffer and the slice
-        let diff_len = (rem.as_ptr() as usize)
-            .checked_sub(self.buffer().as_ptr() as usize)
-            .expect("Rem is not a sub slice of self.buffer");
-
-        self.advance(diff_len)
-    }
-
     /// Return the valid data of the internal buffer
     pub fn buffer(&self) -> &[u8] {
         &self.buffer[self.pos..self.len]
     }
 
+    /// Return true if the last read of the inner reader returned no data, i.e. the buffered data is the end of its data
+    pub fn is_eof(&self) -> bool {
+        self.eof
+    }
+
     /// Return true there are some data that can be read
     pub fn has_data_left(&mut self) -> Result<bool, std::io::Error> {
         // The buffer can be empty and the reader can still have data
@@ -125,37 +198,50 @@ impl<R: Read> ReadBuffer<R> {
         self.reader
     }
 
+    /// Return the inner reader and the data read from it but not parsed yet
+    pub fn into_parts(self) -> (R, Vec<u8>) {
+        let rem = self.buffer[self.pos..self.len].to_vec();
+        (self.reader, rem)
+    }
+
     /// Return a reference over the inner reader
     pub fn get_ref(&self) -> &R {
         &self.reader
     }
 }
 
-#[cfg(test)]
-mod test {
-    /*
-    // Shouldn't compile
-    #[test]
-    fn parse_with_safety() {
-        let a = &[0_u8; 10];
-        let b = &mut &a[..];
-
-        let input = vec![1_u8; 100];
-        let input_read = &mut &input[..];
-        let mut reader = super::ReadBuffer::new(input_read);
-
-        unsafe {
-            reader.parse_with(|buf| {
-                *b = buf;
-                Ok((buf, ()))
-            });
-        }
+impl<R: Read + Debug> Debug for ReadBuffer<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The buffer is usually megabytes long and the reader can hold the whole capture, only their state is shown
+        f.debug_struct("ReadBuffer")
+            .field("reader", &std::any::type_name::<R>())
+            .field("capacity", &self.buffer.len())
+            .field("buffered", &(self.len - self.pos))
+            .field("retry", &self.retry)
+            .finish()
+    }
+}
 
-        unsafe {
-            reader.has_data_left();
-        }
+impl<R: Read + Seek> ReadBuffer<R> {
+    /// Returns the position in the inner reader of the next byte to be parsed
+    pub fn position(&mut self) -> Result<u64, std::io::Error> {
+        let inner_pos = self.reader.stream_position()?;
+        Ok(inner_pos - (self.len - self.pos) as u64)
+    }
+
+    /// Return the inner reader, seeked back to the next byte to be parsed
+    pub fn into_rewound_inner(mut self) -> Result<R, std::io::Error> {
+        self.reader.seek(SeekFrom::Current(-((self.len - self.pos) as i64)))?;
+        Ok(self.reader)
+    }
+
+    /// Seeks the inner reader to the given position and discard the buffered data
+    pub fn seek_to(&mut self, pos: u64) -> Result<(), std::io::Error> {
+        self.reader.seek(SeekFrom::Start(pos))?;
+        self.pos = 0;
+        self.len = 0;
+        self.eof = false;
 
-        println!("{:?}", b);
+        Ok(())
     }
-    */
 }
@@ -0,0 +1,26 @@
+//! Helpers of the [`Serialize`](serde::Serialize) implementations of the reports and statistics of the crate, which
+//! are derived.
+//!
+//! The serialized form is part of the API and follows the same rules for all the types:
+//! - the structs are maps whose keys are the names of their fields;
+//! - the unit enums are lowercase strings, in snake case for the variants of several words;
+//! - the [`Duration`]s are integers counting nanoseconds, and `null` when they are optional and missing;
+//! - the offsets and the counters are integers, the stable codes of the findings are strings;
+//! - the [`DataLink`](crate::DataLink)s are their integer LINKTYPE value, which is also the one of the unknown datalinks.
+
+use std::time::Duration;
+
+use serde::ser::Error;
+use serde::Serializer;
+
+
+/// Serializes an optional [`Duration`] as a number of nanoseconds.
+pub(crate) fn nanos<S: Serializer>(duration: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error> {
+    match duration {
+        Some(duration) => {
+            let nanos = u64::try_from(duration.as_nanos()).map_err(|_| S::Error::custom("Duration: more than u64::MAX nanoseconds"))?;
+            serializer.serialize_some(&nanos)
+        },
+        None => serializer.serialize_none(),
+    }
+}
@@ -0,0 +1,168 @@
+//! Checks that the readers don't keep memory alive after an error.
+//!
+//! It is a separate test binary because it replaces the global allocator.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use pcap_file::pcap::{PcapPacket, PcapReader, PcapWriter};
+use pcap_file::pcapng::{PcapNgReader, PcapNgWriter};
+use pcap_file::{Endianness, PcapError};
+
+
+/// Allocator counting the number of bytes allocated by the threads that enabled the counting.
+///
+/// The other threads (e.g. the main thread of the test harness) can allocate at any time.
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static COUNTING: Cell<bool> = const { Cell::new(false) };
+    /// Number of allocations made by the thread, whether it is counting the bytes or not
+    static NB_ALLOCS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn is_counting() -> bool {
+    COUNTING.try_with(Cell::get).unwrap_or(false)
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = NB_ALLOCS.try_with(|nb_allocs| nb_allocs.set(nb_allocs.get() + 1));
+        if is_counting() {
+            ALLOCATED.fetch_add(layout.size(), Ordering::SeqCst);
+        }
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if is_counting() {
+            ALLOCATED.fetch_sub(layout.size(), Ordering::SeqCst);
+        }
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+#[test]
+fn errors_dont_keep_memory_alive() {
+    COUNTING.with(|counting| counting.set(true));
+
+    // Errors must stay cheap to move
+    assert!(std::mem::size_of::<PcapError>() <= 48);
+
+    // Pcap with a second packet header announcing 50MB of data
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    pcap_writer.write_packet(&PcapPacket::new(Duration::ZERO, 4, &[1, 2, 3, 4])).unwrap();
+    let mut pcap = pcap_writer.into_writer();
+    pcap.extend_from_slice(&hex::decode("000000000000000002faf08002faf080deadbeef").unwrap());
+
+    // PcapNg with a block announcing 50MB of data
+    let mut pcapng = PcapNgWriter::with_endianness(Vec::new(), Endianness::Little).unwrap().into_inner();
+    pcapng.extend_from_slice(&hex::decode("0600000080f0fa0200000000").unwrap());
+
+    let baseline = ALLOCATED.load(Ordering::SeqCst);
+
+    let mut pcap_reader = PcapReader::new(&pcap[..]).unwrap();
+    pcap_reader.next_packet().unwrap().unwrap();
+    let pcap_err = pcap_reader.next_packet().unwrap().unwrap_err();
+    drop(pcap_reader);
+
+    let mut pcapng_reader = PcapNgReader::new(&pcapng[..]).unwrap();
+    let pcapng_err = pcapng_reader.next_block().unwrap().unwrap_err();
+    drop(pcapng_reader);
+
+    // Only the errors are still alive
+    assert!(ALLOCATED.load(Ordering::SeqCst) - baseline < 1024, "{} bytes still allocated", ALLOCATED.load(Ordering::SeqCst) - baseline);
+
+    drop((pcap_err, pcapng_err));
+    assert_eq!(ALLOCATED.load(Ordering::SeqCst), baseline);
+}
+
+#[cfg(feature = "bytes")]
+#[test]
+fn bytes_readers_share_their_buffer() {
+    use pcap_file::pcap::PcapBytesReader;
+    use pcap_file::pcapng::blocks::interface_description::InterfaceDescriptionBlock;
+    use pcap_file::pcapng::PcapNgBytesReader;
+    use pcap_file::DataLink;
+
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    let mut pcapng_writer = PcapNgWriter::new(Vec::new()).unwrap();
+    pcapng_writer.write_pcapng_block(InterfaceDescriptionBlock::new(DataLink::ETHERNET, 0xFFFF)).unwrap();
+    for i in 0..1000_u32 {
+        let data = [i as u8; 100];
+        let packet = PcapPacket::new(Duration::from_secs(i as u64), 100, &data);
+        pcap_writer.write_packet(&packet).unwrap();
+        pcapng_writer.write_packet(0, &packet, None).unwrap();
+    }
+    let (pcap, pcapng) = (pcap_writer.into_writer(), pcapng_writer.into_inner());
+
+    let nb_allocs = |f: &mut dyn FnMut()| {
+        let before = NB_ALLOCS.with(Cell::get);
+        f();
+        NB_ALLOCS.with(Cell::get) - before
+    };
+
+    let mut owned = vec![];
+    let owned_allocs = nb_allocs(&mut || {
+        let mut reader = PcapReader::new(&pcap[..]).unwrap();
+        while let Some(packet) = reader.next_packet() {
+            owned.push(packet.unwrap().into_owned());
+        }
+    });
+
+    let mut shared = vec![];
+    let bytes_allocs = nb_allocs(&mut || {
+        let mut reader = PcapBytesReader::new(&pcap[..]).unwrap();
+        while let Some(packet) = reader.next_packet() {
+            shared.push(packet.unwrap());
+        }
+    });
+
+    assert!(owned_allocs >= 1000, "{owned_allocs}");
+    assert!(bytes_allocs < 50, "{bytes_allocs}");
+    assert!(owned.iter().zip(&shared).all(|(owned, shared)| owned.data[..] == shared.data[..] && owned.timestamp == shared.timestamp));
+
+    // Short reads are appended to the buffer without a new allocation
+    struct ShortReads<'a>(&'a [u8]);
+    impl std::io::Read for ShortReads<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let len = buf.len().min(7);
+            self.0.read(&mut buf[..len])
+        }
+    }
+    let mut short = vec![];
+    let short_allocs = nb_allocs(&mut || {
+        let mut reader = PcapBytesReader::new(ShortReads(&pcap)).unwrap();
+        while let Some(packet) = reader.next_packet() {
+            short.push(packet.unwrap());
+        }
+    });
+    assert!(short_allocs < 50, "{short_allocs}");
+    assert_eq!(short.len(), 1000);
+    assert!(short.iter().zip(&shared).all(|(short, shared)| short.data == shared.data && short.timestamp == shared.timestamp));
+
+    let mut writer = PcapWriter::new(Vec::new()).unwrap();
+    for packet in &shared {
+        writer.write_buf(packet.timestamp, packet.orig_len, packet.data.clone()).unwrap();
+    }
+    assert_eq!(writer.into_writer(), pcap);
+
+    let mut shared_ng = vec![];
+    let bytes_ng_allocs = nb_allocs(&mut || {
+        let mut reader = PcapNgBytesReader::new(&pcapng[..]).unwrap();
+        while let Some(packet) = reader.next_packet() {
+            shared_ng.push(packet.unwrap());
+        }
+    });
+
+    assert!(bytes_ng_allocs < 50, "{bytes_ng_allocs}");
+    assert!(shared.iter().zip(&shared_ng).all(|(pcap, pcapng)| pcap.data == pcapng.data && Some(pcap.timestamp) == pcapng.timestamp));
+    assert_eq!(shared_ng.len(), 1000);
+}
@@ -1,10 +1,11 @@
 extern crate pcap_file;
 
 use std::borrow::Cow;
+use std::io::Cursor;
 use std::time::Duration;
 
-use pcap_file::pcap::{PcapHeader, PcapPacket, PcapReader, PcapWriter};
-use pcap_file::TsResolution;
+use pcap_file::pcap::{EpochShiftPolicy, PcapHeader, PcapIndex, PcapPacket, PcapQuirk, PcapReader, PcapWriter};
+use pcap_file::{DataLink, Endianness, PcapError, TsResolution};
 
 static DATA: &[u8; 1455] = include_bytes!("little_endian.pcap");
 
@@ -137,3 +138,2610 @@ fn little_endian() {
     assert_eq!(pkt.orig_len, pkt_truth.orig_len);
     assert_eq!(pkt.data, pkt_truth.data);
 }
+
+#[cfg(feature = "arbitrary")]
+#[test]
+fn arbitrary_pcap_round_trip() {
+    use arbitrary::{Arbitrary, Unstructured};
+
+    let seed: Vec<u8> = (0..4096_u32).map(|i| (i.wrapping_mul(2654435761) >> 13) as u8).collect();
+    // The edge seeds give the extreme values of the fields, e.g. a zero snaplen if it isn't excluded
+    for seed in [&seed[..], &[0; 64], &[0xFF; 64], &[]] {
+        let pcap = pcap_file::ArbitraryPcap::arbitrary(&mut Unstructured::new(seed)).unwrap();
+        pcap.header.validate().unwrap();
+        let data = pcap.to_bytes();
+
+        let mut pcap_reader = PcapReader::new(&data[..]).unwrap();
+        assert_eq!(pcap_reader.header(), pcap.header);
+
+        for expected in &pcap.packets {
+            let pkt = pcap_reader.next_packet().unwrap().unwrap();
+            assert_eq!(pkt.data, expected.data);
+            assert_eq!(pkt.orig_len, expected.orig_len);
+        }
+        assert!(pcap_reader.next_packet().is_none());
+    }
+}
+
+#[test]
+fn seek_to_position() {
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    for i in 0..3_u8 {
+        let data = [i; 10];
+        pcap_writer.write_packet(&PcapPacket::new(Duration::new(i as u64, 0), 10, &data)).unwrap();
+    }
+    let data = pcap_writer.into_writer();
+
+    let mut pcap_reader = PcapReader::new(std::io::Cursor::new(data)).unwrap();
+    pcap_reader.next_packet().unwrap().unwrap();
+
+    let pos = pcap_reader.position().unwrap();
+    assert_eq!(pos, 24 + 16 + 10);
+
+    while let Some(pkt) = pcap_reader.next_packet() {
+        pkt.unwrap();
+    }
+
+    // Not a record boundary
+    assert!(pcap_reader.seek_to(pos + 1).is_err());
+    assert!(pcap_reader.next_packet().is_none());
+
+    pcap_reader.seek_to(pos).unwrap();
+    let pkt = pcap_reader.next_packet().unwrap().unwrap();
+    assert_eq!(&pkt.data[..], &[1_u8; 10]);
+}
+
+/// Header written by some SSL-VPN appliances: garbage ts_correction and nonzero ts_accuracy.
+#[test]
+fn header_opaque_fields() {
+    let data = hex::decode(
+        "d4c3b2a102000400efbeadde03000000ffff000001000000\
+    0a00000000000000040000000400000001020304",
+    )
+    .unwrap();
+
+    let mut pcap_reader = PcapReader::new(&data[..]).unwrap();
+    let header = pcap_reader.header();
+    assert_eq!(header.ts_correction, 0xDEADBEEF_u32 as i32);
+    assert_eq!(header.ts_accuracy, 3);
+    assert_eq!(header.snaplen, 0xFFFF);
+
+    let pkt = pcap_reader.next_packet().unwrap().unwrap();
+    assert_eq!(&pkt.data[..], &[1, 2, 3, 4]);
+
+    // The major version is one of the only fields that can cause an error
+    let mut data = data;
+    data[4] = 1;
+    assert!(PcapReader::new(&data[..]).is_err());
+}
+
+/// Little endian global header followed by big endian packet headers.
+#[test]
+fn suspicious_endianness() {
+    use pcap_file::pcap::{MalformedPcapBuilder, MalformedRecord};
+
+    let header = PcapHeader { endianness: Endianness::Little, ..Default::default() };
+    let data = MalformedPcapBuilder::new(header)
+        .record(MalformedRecord::new(10, 1, &[0xDE, 0xAD, 0xBE, 0xEF]).with_swapped_endianness())
+        .into_bytes();
+
+    let mut pcap_reader = PcapReader::new(&data[..]).unwrap();
+    assert!(matches!(pcap_reader.next_packet(), Some(Err(pcap_file::PcapError::SuspiciousEndianness))));
+
+    let mut pcap_reader = PcapReader::new(&data[..]).unwrap();
+    assert_eq!(pcap_reader.record_endianness(), pcap_file::Endianness::Little);
+    assert_eq!(pcap_reader.detect_record_endianness().unwrap(), pcap_file::Endianness::Big);
+    assert_eq!(pcap_reader.record_endianness(), pcap_file::Endianness::Big);
+    assert_eq!(pcap_reader.header().endianness, pcap_file::Endianness::Little);
+
+    let pkt = pcap_reader.next_packet().unwrap().unwrap();
+    assert_eq!(pkt.timestamp, Duration::new(10, 1000));
+    assert_eq!(&pkt.data[..], &[0xDE, 0xAD, 0xBE, 0xEF]);
+}
+
+#[test]
+fn write_data() {
+    let header = PcapHeader { ts_resolution: TsResolution::NanoSecond, ..Default::default() };
+    let mut pcap_writer = PcapWriter::with_header(Vec::new(), header).unwrap();
+
+    pcap_writer.write_data(1, 5, vec![1_u8, 2, 3]).unwrap();
+    pcap_writer.write_data(2, 999_999_999, [4_u8; 2]).unwrap();
+    pcap_writer.write_data(3, 0, &b"abc"[..]).unwrap();
+    assert!(pcap_writer.write_data(4, 1_000_000_000, [0_u8]).is_err());
+
+    let out = pcap_writer.into_writer();
+    let mut pcap_reader = PcapReader::new(&out[..]).unwrap();
+
+    let pkt = pcap_reader.next_packet().unwrap().unwrap();
+    assert_eq!((pkt.timestamp, pkt.orig_len, &pkt.data[..]), (Duration::new(1, 5), 3, &[1_u8, 2, 3][..]));
+    let pkt = pcap_reader.next_packet().unwrap().unwrap();
+    assert_eq!(pkt.timestamp, Duration::new(2, 999_999_999));
+    let pkt = pcap_reader.next_packet().unwrap().unwrap();
+    assert_eq!(&pkt.data[..], b"abc");
+    assert!(pcap_reader.next_packet().is_none());
+}
+
+#[test]
+fn aligned_packets() {
+    use pcap_file::pcap::AlignedBuffer;
+
+    // Packets of odd lengths, so that every other record starts at an odd offset
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    for i in 0..10_u8 {
+        let data = vec![i; 2 * i as usize + 1];
+        pcap_writer.write_packet(&PcapPacket::new(Duration::new(i as u64, 0), data.len() as u32, &data)).unwrap();
+    }
+    let out = pcap_writer.into_writer();
+
+    let mut pcap_reader = PcapReader::new(&out[..]).unwrap();
+    let mut buffer = AlignedBuffer::new(8);
+
+    let mut i = 0_u8;
+    while let Some(pkt) = pcap_reader.next_aligned_packet(&mut buffer) {
+        let pkt = pkt.unwrap();
+        assert_eq!(pkt.data.as_ptr() as usize % 8, 0);
+        assert_eq!(pkt.data, &vec![i; 2 * i as usize + 1][..]);
+        i += 1;
+    }
+    assert_eq!(i, 10);
+}
+
+/// Writes a pcap containing `nb_packets` packets of 10 bytes, one second apart.
+fn sample_pcap(nb_packets: u8) -> Vec<u8> {
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    for i in 0..nb_packets {
+        let data = [i; 10];
+        pcap_writer.write_packet(&PcapPacket::new(Duration::new(i as u64, 0), 10, &data)).unwrap();
+    }
+
+    pcap_writer.into_writer()
+}
+
+/// Writes a pcap containing the frames, with [`frames_to_pcap`](pcap_file::easy::frames_to_pcap).
+fn frames_pcap(datalink: DataLink, frames: &[&[u8]]) -> Vec<u8> {
+    let mut data = Vec::new();
+    pcap_file::easy::frames_to_pcap(frames, datalink, &mut data).unwrap();
+
+    data
+}
+
+#[test]
+fn new_seek_magic() {
+    let pcap = sample_pcap(2);
+    let junk = [b"JUNK!".as_slice(), &pcap].concat();
+
+    let (mut pcap_reader, skipped) = PcapReader::new_seek_magic(&junk[..], 64).unwrap();
+    assert_eq!(skipped, 5);
+    assert_eq!(pcap_reader.next_packet().unwrap().unwrap().data[..], [0; 10]);
+    assert_eq!(PcapReader::new_seek_magic(&pcap[..], 64).unwrap().1, 0);
+
+    // The packets start after the bytes skipped
+    let (mut pcap_reader, _) = PcapReader::new_seek_magic(Cursor::new(&junk[..]), 64).unwrap();
+    assert_eq!(pcap_reader.data_start(), 29);
+    assert!(pcap_reader.seek_to(28).is_err());
+    let index = PcapIndex::build(&mut pcap_reader, 1).unwrap();
+    assert_eq!(index.nb_packets(), 2);
+    assert_eq!(pcap_reader.nth_packet(1, None).unwrap().unwrap().data[..], [1; 10]);
+    assert_eq!(pcap_reader.nth_packet(0, None).unwrap().unwrap().data[..], [0; 10]);
+
+    // The scan is bounded
+    assert!(PcapReader::new_seek_magic(&junk[..], 4).is_err());
+    assert!(PcapReader::new_seek_magic(&[0_u8; 100][..], 64).is_err());
+    assert!(PcapReader::new_seek_magic(&b"JUNK!"[..], 64).is_err());
+}
+
+#[test]
+fn writer_state() {
+    use pcap_file::pcap::{PcapHeader, PcapWriterState, RawPcapPacket};
+    use pcap_file::Endianness;
+
+    let header = PcapHeader { endianness: Endianness::Big, snaplen: 8, ..Default::default() };
+    let packet = PcapPacket::new(Duration::new(1, 2000), 4, &[1, 2, 3, 4]);
+    let mismatched = RawPcapPacket { ts_sec: 3, ts_frac: 4, incl_len: 10, orig_len: 1, data: Cow::Borrowed(&[5; 2]) };
+    let raw = RawPcapPacket { incl_len: 2, ..mismatched.clone() };
+
+    let (mut out, state) = PcapWriterState::start(header).unwrap();
+    assert_eq!(state.serialize_packet(&packet, &mut out).unwrap(), 20);
+    let err = state.serialize_raw_packet(&mismatched, &mut out).unwrap_err();
+    assert!(matches!(err, PcapError::LengthMismatch { incl_len: 10, data_len: 2 }));
+    assert_eq!(state.serialize_raw_packet(&raw, &mut out).unwrap(), 18);
+    assert_eq!(out[24..44], hex::decode("00000001000000020000000400000004 01020304".replace(' ', "")).unwrap()[..]);
+
+    // Same bytes and same errors as the writer
+    let mut pcap_writer = PcapWriter::with_header(Vec::new(), header).unwrap();
+    pcap_writer.write_packet(&packet).unwrap();
+    assert!(matches!(pcap_writer.write_raw_packet(&mismatched), Err(PcapError::LengthMismatch { .. })));
+    pcap_writer.write_raw_packet(&raw).unwrap();
+    assert_eq!(pcap_writer.into_writer(), out);
+
+    // Nothing is appended on error
+    let too_long = PcapPacket::new(Duration::ZERO, 10, &[0; 10]);
+    assert!(state.serialize_packet(&too_long, &mut out).is_err());
+    assert_eq!(out.len(), 24 + 20 + 18);
+    assert!(PcapWriterState::start(PcapHeader { snaplen: 0, ..header }).is_err());
+}
+
+#[test]
+fn forced_format() {
+    use pcap_file::pcap::PcapFormat;
+    use pcap_file::{Endianness, TsResolution};
+
+    let mut pcap = sample_pcap(2);
+    pcap[..4].fill(0);
+    assert!(PcapReader::new(&pcap[..]).is_err());
+
+    let format = PcapFormat { endianness: Endianness::native(), ts_resolution: TsResolution::NanoSecond };
+    let mut pcap_reader = PcapReader::with_forced_format(&pcap[..], format).unwrap();
+    assert_eq!(pcap_reader.header().ts_resolution, TsResolution::NanoSecond);
+    assert_eq!(pcap_reader.next_packet().unwrap().unwrap().data[..], [0; 10]);
+    assert_eq!(pcap_reader.next_packet().unwrap().unwrap().timestamp, Duration::new(1, 0));
+
+    // The other fields are still validated
+    let format = PcapFormat { endianness: Endianness::native().opposite(), ts_resolution: TsResolution::MicroSecond };
+    assert!(PcapReader::with_forced_format(&pcap[..], format).is_err());
+}
+
+#[test]
+fn detect_timezone_shift() {
+    use pcap_file::pcap::{detect_timezone_shift, PcapHeader, TimezoneOptions, TimezoneShiftReason};
+
+    let hour = 3600;
+    let pcap = |ts_correction: i32, first_secs: u64| {
+        let header = PcapHeader { ts_correction, ..Default::default() };
+        let mut pcap_writer = PcapWriter::with_header(Vec::new(), header).unwrap();
+        pcap_writer.write_packet(&PcapPacket::new(Duration::from_secs(first_secs), 1, &[0])).unwrap();
+        pcap_writer.into_writer()
+    };
+
+    let shift = detect_timezone_shift(&pcap(2 * hour, 0)[..], &TimezoneOptions::default()).unwrap().unwrap();
+    // The local time plus the ts_correction gives UTC
+    assert_eq!((shift.offset_secs, shift.reason), (2 * hour as i64, TimezoneShiftReason::TsCorrection));
+    assert_eq!((shift.to_finding().code, shift.to_finding().offset), ("PCAP_HEURISTIC_TIMEZONE_SHIFT", 0));
+
+    // Captured in UTC+5:30 around 1_000_000s
+    let window = Duration::from_secs(1_000_000)..Duration::from_secs(1_000_600);
+    let options = TimezoneOptions { expected_window: Some(window), ..Default::default() };
+    let local = pcap(0, 1_000_100 + 5 * 3600 + 1800);
+    let shift = detect_timezone_shift(&local[..], &options).unwrap().unwrap();
+    assert_eq!((shift.offset_secs, shift.reason), (-(5 * 3600 + 1800), TimezoneShiftReason::OutsideExpectedWindow));
+    assert_eq!(shift.to_finding().offset, 24);
+
+    let mut fixed = PcapReader::new(&local[..]).unwrap().with_epoch_offset(shift.offset_secs);
+    assert_eq!(fixed.next_packet().unwrap().unwrap().timestamp, Duration::from_secs(1_000_100));
+
+    assert_eq!(detect_timezone_shift(&pcap(0, 1_000_100)[..], &options).unwrap(), None);
+    assert_eq!(detect_timezone_shift(&pcap(0, 1_000_100 + 1234)[..], &options).unwrap(), None);
+    assert_eq!(detect_timezone_shift(&pcap(0, 1_000_100 + 20 * 3600)[..], &options).unwrap(), None);
+}
+
+#[test]
+fn raw_header_bytes() {
+    use pcap_file::pcap::PcapQuirk;
+
+    let pcap = sample_pcap(1);
+    assert_eq!(PcapReader::new(&pcap[..]).unwrap().raw_header_bytes(), &pcap[..24]);
+
+    // The bytes of a fixed header are the original ones
+    let mut swapped = pcap.clone();
+    swapped[16..20].reverse();
+    swapped[20..24].reverse();
+    let pcap_reader = PcapReader::with_quirks(&swapped[..], &[PcapQuirk::SwappedSnaplenDatalink]).unwrap();
+    assert_eq!(pcap_reader.raw_header_bytes(), &swapped[..24]);
+    let mut rewritten = vec![];
+    pcap_reader.header().write_to(&mut rewritten).unwrap();
+    assert_ne!(&rewritten[..], pcap_reader.raw_header_bytes());
+}
+
+#[test]
+fn debug_is_bounded() {
+    use pcap_file::pcap::RawPcapPacket;
+    use pcap_file::pcapng::blocks::enhanced_packet::EnhancedPacketBlock;
+
+    let data = vec![0xAB_u8; 65536];
+    let packet = PcapPacket::new(Duration::ZERO, 65536, &data);
+    let raw = RawPcapPacket { ts_sec: 0, ts_frac: 0, incl_len: 65536, orig_len: 65536, data: Cow::Borrowed(&data) };
+    let mut block = EnhancedPacketBlock::default();
+    block.data = Cow::Borrowed(&data);
+
+    for debug in [format!("{packet:?}"), format!("{raw:?}"), format!("{block:?}")] {
+        assert!(debug.len() < 500, "{debug}");
+        assert!(debug.contains(" (65536 bytes)"), "{debug}");
+    }
+    // Only the length is shown with the redact feature
+    let debug = format!("{:?}", PcapPacket::new(Duration::ZERO, 2, &[1, 2]));
+    match pcap_file::PAYLOAD_REDACTED {
+        false => assert_eq!(debug, "PcapPacket { timestamp: 0ns, orig_len: 2, data: [1, 2] }"),
+        true => assert_eq!(debug, "PcapPacket { timestamp: 0ns, orig_len: 2, data: <redacted> (2 bytes) }"),
+    }
+
+    // The in-memory readers and writers hold the whole capture, only the type of the I/O object is shown
+    use pcap_file::pcap::{MultiCapture, PcapBufReader, RawPcapReader, RawPcapWriter};
+    use pcap_file::pcapng::{PcapNgReader, PcapNgWriter};
+    use pcap_file::pcapng::blocks::interface_description::InterfaceDescriptionBlock;
+
+    let header = PcapHeader { snaplen: 65536, ..Default::default() };
+    let mut writer = PcapWriter::with_header(Vec::new(), header).unwrap();
+    writer.write_packet(&packet).unwrap();
+    let writer_debug = format!("{writer:?}");
+    let pcap = writer.into_writer();
+
+    let mut reader = PcapReader::new(&pcap[..]).unwrap();
+    let reader_debug = format!("{reader:?}");
+    reader.next_packet().unwrap().unwrap();
+
+    let mut raw_writer = RawPcapWriter::new(Vec::new(), pcap[..24].try_into().unwrap()).unwrap();
+    raw_writer.write_record(pcap[24..40].try_into().unwrap(), &pcap[40..]).unwrap();
+
+    let mut pcapng_writer = PcapNgWriter::new(Vec::new()).unwrap();
+    pcapng_writer.write_pcapng_block(InterfaceDescriptionBlock::new(DataLink::ETHERNET, 0)).unwrap();
+    pcapng_writer.write_packet(0, &packet, None).unwrap();
+    let pcapng_writer_debug = format!("{pcapng_writer:?}");
+    let pcapng = pcapng_writer.into_inner();
+
+    let debugs = [
+        writer_debug,
+        reader_debug,
+        format!("{reader:?}"),
+        format!("{:?}", PcapBufReader::new(&pcap[..]).unwrap()),
+        format!("{:?}", RawPcapReader::new(&pcap[..]).unwrap()),
+        format!("{raw_writer:?}"),
+        format!("{:?}", MultiCapture::new(vec![PcapReader::new(&pcap[..]).unwrap()])),
+        format!("{:?}", PcapReader::new(&pcap[..]).unwrap().remap_macs(|mac| mac)),
+        pcapng_writer_debug,
+        format!("{:?}", PcapNgReader::new(&pcapng[..]).unwrap()),
+    ];
+    for debug in debugs {
+        assert!(debug.len() < 1000, "{debug}");
+    }
+    #[cfg(feature = "bytes")]
+    {
+        let debug = format!("{:?}", pcap_file::pcap::PcapBytesReader::new(&pcap[..]).unwrap());
+        assert!(debug.len() < 1000, "{debug}");
+    }
+}
+
+#[test]
+fn rotating_writer() {
+    use pcap_file::pcap::{RotatingPcapWriter, RotationPolicy};
+
+    let pid = std::process::id();
+    let path = move |policy: &str, idx: usize| std::env::temp_dir().join(format!("pcap_file_rotating_{pid}_{policy}_{idx}.pcap"));
+    let read_ts = |path| {
+        let mut reader = PcapReader::new(std::fs::File::open(&path).unwrap()).unwrap();
+        let mut ts = vec![];
+        while let Some(packet) = reader.next_packet() {
+            ts.push(packet.unwrap().timestamp.as_secs());
+        }
+        std::fs::remove_file(path).unwrap();
+        ts
+    };
+
+    // Periods of 60s aligned on the epoch, an older packet stays in the current file
+    let create = |idx| std::fs::File::create(path("secs", idx)).unwrap();
+    let mut writer = RotatingPcapWriter::new(PcapHeader::default(), RotationPolicy::EverySeconds(60), create).unwrap();
+    for secs in [50, 59, 60, 30, 119, 300] {
+        writer.write_packet(&PcapPacket::new(Duration::from_secs(secs), 10, &[0; 10])).unwrap();
+    }
+    assert_eq!(writer.file_index(), 2);
+    drop(writer);
+    let files: Vec<_> = (0..3).map(|idx| read_ts(path("secs", idx))).collect();
+    assert_eq!(files, [vec![50, 59], vec![60, 30, 119], vec![300]]);
+
+    // 24 + 2 * 26 bytes per file, a bigger packet gets its own file
+    let create = |idx| std::fs::File::create(path("bytes", idx)).unwrap();
+    let mut writer = RotatingPcapWriter::new(PcapHeader::default(), RotationPolicy::EveryBytes(76), create).unwrap();
+    let too_long = PcapPacket::new(Duration::ZERO, 70_000, &[0; 70_000]);
+    assert!(writer.write_packet(&too_long).is_err());
+    for (secs, len) in [(0, 10), (1, 10), (2, 10), (3, 100), (4, 10)] {
+        writer.write_packet(&PcapPacket::new(Duration::from_secs(secs), len, &vec![0; len as usize])).unwrap();
+    }
+    assert_eq!((writer.file_index(), writer.file_len()), (3, 24 + 16 + 10));
+    drop(writer);
+    let files: Vec<_> = (0..4).map(|idx| read_ts(path("bytes", idx))).collect();
+    assert_eq!(files, [vec![0, 1], vec![2], vec![3], vec![4]]);
+
+    // Each new writer is configured by the hook, and a new file can be started by the caller
+    let create = |idx| std::fs::File::create(path("configure", idx)).unwrap();
+    let mut writer = RotatingPcapWriter::new(PcapHeader::default(), RotationPolicy::EverySeconds(60), create).unwrap();
+    assert!(writer.set_configure(|writer| writer.set_fixed_caplen(Some(0x40000))).is_err());
+    writer
+        .set_configure(|writer| {
+            writer.set_collect_stats(true);
+            writer.set_fixed_caplen(Some(4))
+        })
+        .unwrap();
+    writer.start_new_file().unwrap();
+    for secs in [0, 1, 60] {
+        writer.write_packet(&PcapPacket::new(Duration::from_secs(secs), 10, &[0; 10])).unwrap();
+    }
+    writer.start_new_file().unwrap();
+    writer.write_packet(&PcapPacket::new(Duration::from_secs(61), 10, &[0; 10])).unwrap();
+    assert_eq!(writer.file_index(), 2);
+    let nb_packets: Vec<_> = writer.finished_stats().iter().map(|stats| stats.unwrap().nb_packets).collect();
+    assert_eq!(nb_packets, [2, 1]);
+    assert_eq!(writer.get_ref().unwrap().stats().unwrap().nb_bytes, 4);
+    drop(writer);
+    for idx in 0..3 {
+        let mut reader = PcapReader::new(std::fs::File::open(path("configure", idx)).unwrap()).unwrap();
+        while let Some(packet) = reader.next_packet() {
+            assert_eq!(packet.unwrap().data.len(), 4);
+        }
+    }
+    let files: Vec<_> = (0..3).map(|idx| read_ts(path("configure", idx))).collect();
+    assert_eq!(files, [vec![0, 1], vec![60], vec![61]]);
+
+    // A new file which can't be started once the previous one is finished is started again by the next packet
+    let mut writer = RotatingPcapWriter::new(PcapHeader::default(), RotationPolicy::EverySeconds(60), |_| Vec::new()).unwrap();
+    let mut nb_calls = 0;
+    writer
+        .set_configure(move |_| {
+            nb_calls += 1;
+            match nb_calls {
+                2 => Err(PcapError::InvalidConfiguration("configure failed")),
+                _ => Ok(()),
+            }
+        })
+        .unwrap();
+    writer.write_packet(&PcapPacket::new(Duration::from_secs(0), 10, &[0; 10])).unwrap();
+    assert!(writer.write_packet(&PcapPacket::new(Duration::from_secs(60), 10, &[0; 10])).is_err());
+    assert!(writer.get_ref().is_none());
+    assert_eq!((writer.file_index(), writer.finished_stats().len()), (1, 1));
+    writer.write_packet(&PcapPacket::new(Duration::from_secs(60), 10, &[0; 10])).unwrap();
+    assert_eq!(writer.file_index(), 1);
+    assert_eq!(writer.into_writer().unwrap().len(), 24 + 16 + 10);
+
+    let never = RotatingPcapWriter::new(PcapHeader::default(), RotationPolicy::EveryBytes(0), |_| Vec::new());
+    assert!(matches!(never, Err(PcapError::InvalidConfiguration(_))));
+}
+
+#[test]
+fn is_time_ordered() {
+    let pcap = sample_pcap(3);
+    assert!(PcapReader::new(&pcap[..]).unwrap().is_time_ordered().unwrap());
+    assert!(PcapReader::new(&pcap[..pcap.len() - 1]).unwrap().is_time_ordered().is_err());
+
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    for (secs, nanos) in [(1, 0), (1, 0), (2, 5000), (2, 4000), (3, 0)] {
+        pcap_writer.write_packet(&PcapPacket::new(Duration::new(secs, nanos), 1, &[0])).unwrap();
+    }
+    let pcap = pcap_writer.into_writer();
+
+    // The scan stops after the first inversion
+    let mut pcap_reader = PcapReader::new(&pcap[..]).unwrap();
+    assert!(!pcap_reader.is_time_ordered().unwrap());
+    assert_eq!(pcap_reader.next_packet().unwrap().unwrap().timestamp, Duration::new(3, 0));
+}
+
+#[test]
+fn concatenated() {
+    use pcap_file::pcap::{ConcatenatedPcaps, SectionBoundary};
+    use pcap_file::Endianness;
+
+    let pcap = |endianness, ts_resolution, datalink| {
+        let header = PcapHeader { endianness, ts_resolution, datalink, ..Default::default() };
+        let mut pcap_writer = PcapWriter::with_header(Vec::new(), header).unwrap();
+        pcap_writer.write_packet(&PcapPacket::new(Duration::new(1, 1000), 2, &[1, 2])).unwrap();
+        pcap_writer.write_packet(&PcapPacket::new(Duration::new(2, 2000), 2, &[3, 4])).unwrap();
+        (pcap_writer.into_writer(), header)
+    };
+    let (big, _) = pcap(Endianness::Big, TsResolution::MicroSecond, DataLink::ETHERNET);
+    let (little, little_header) = pcap(Endianness::Little, TsResolution::NanoSecond, DataLink::ETHERNET);
+    let (raw_ip, raw_ip_header) = pcap(Endianness::Little, TsResolution::MicroSecond, DataLink::RAW);
+
+    let glued = [&big[..], &little, &raw_ip].concat();
+
+    let mut pcap_reader = PcapReader::new(&glued[..]).unwrap();
+    pcap_reader.next_packet().unwrap().unwrap();
+    pcap_reader.next_packet().unwrap().unwrap();
+    assert!(matches!(pcap_reader.next_packet(), Some(Err(PcapError::EmbeddedGlobalHeader))));
+
+    let mut pcap_reader = PcapReader::new(&glued[..]).unwrap().with_concatenated(ConcatenatedPcaps::SameDatalink);
+    for _ in 0..4 {
+        assert_eq!(pcap_reader.next_packet().unwrap().unwrap().data.len(), 2);
+    }
+    assert!(matches!(pcap_reader.next_packet(), Some(Err(PcapError::InvalidField(_)))));
+
+    let mut pcap_reader = PcapReader::new(&glued[..]).unwrap().with_concatenated(ConcatenatedPcaps::AnyDatalink);
+    let mut timestamps = vec![];
+    while let Some(packet) = pcap_reader.next_packet() {
+        timestamps.push(packet.unwrap().timestamp);
+    }
+    assert_eq!(timestamps, [Duration::new(1, 1000), Duration::new(2, 2000)].repeat(3));
+    let boundaries =
+        [SectionBoundary { nb_packets_before: 2, header: little_header }, SectionBoundary { nb_packets_before: 4, header: raw_ip_header }];
+    assert_eq!(pcap_reader.section_boundaries(), boundaries);
+    assert_eq!(pcap_reader.header().datalink, DataLink::RAW);
+
+    // The header-only scans adopt the headers too
+    let mut pcap_reader = PcapReader::new(&glued[..]).unwrap().with_concatenated(ConcatenatedPcaps::AnyDatalink);
+    assert_eq!(pcap_reader.capture_stats().unwrap().nb_packets, 6);
+
+    // A packet whose timestamp looks like a global header is read as a packet, even when it is short and last
+    for (endianness, ts_resolution, timestamp) in [
+        (Endianness::Big, TsResolution::NanoSecond, Duration::new(0xD4C3B2A1, 0x02000000)),
+        (Endianness::Little, TsResolution::MicroSecond, Duration::new(0xA1B2C3D4, 2000)),
+    ] {
+        let header = PcapHeader { endianness, ts_resolution, ..Default::default() };
+        let mut pcap_writer = PcapWriter::with_header(Vec::new(), header).unwrap();
+        pcap_writer.write_packet(&PcapPacket::new(timestamp, 4, &[1, 2, 3, 4])).unwrap();
+        let data = pcap_writer.into_writer();
+        assert_eq!(PcapHeader::from_slice(&[&data[24..], &[0; 4]].concat()).map(|(_, header)| header.version_major).ok(), Some(2));
+
+        let mut pcap_reader = PcapReader::new(&data[..]).unwrap();
+        assert_eq!(pcap_reader.next_packet().unwrap().unwrap().timestamp, timestamp);
+        assert!(pcap_reader.next_packet().is_none());
+        let pcap_reader = PcapReader::new(&data[..]).unwrap().with_concatenated(ConcatenatedPcaps::AnyDatalink);
+        assert_eq!(pcap_reader.rate_series(Duration::from_secs(1), 1).unwrap().len(), 1);
+        assert!(PcapReader::new(&data[..]).unwrap().is_time_ordered().unwrap());
+    }
+}
+
+#[test]
+fn concatenated_endianness() {
+    use pcap_file::pcap::ConcatenatedPcaps;
+
+    let pcap = |endianness, ts_resolution| {
+        let header = PcapHeader { endianness, ts_resolution, ..Default::default() };
+        let mut pcap_writer = PcapWriter::with_header(Vec::new(), header).unwrap();
+        pcap_writer.write_packet(&PcapPacket::new(Duration::new(1, 5000), 3, &[1, 2, 3])).unwrap();
+        pcap_writer.into_writer()
+    };
+    let glued = [pcap(Endianness::Little, TsResolution::NanoSecond), pcap(Endianness::Big, TsResolution::MicroSecond)].concat();
+
+    // Each section is decoded with its own byte order and resolution, the raw fields included
+    let mut pcap_reader = PcapReader::new(&glued[..]).unwrap().with_concatenated(ConcatenatedPcaps::AnyDatalink);
+    let packet = pcap_reader.next_raw_packet().unwrap().unwrap();
+    assert_eq!((packet.ts_sec, packet.ts_frac, packet.incl_len), (1, 5000, 3));
+    assert_eq!(pcap_reader.header().endianness, Endianness::Little);
+
+    let packet = pcap_reader.next_raw_packet().unwrap().unwrap();
+    assert_eq!((packet.ts_sec, packet.ts_frac, packet.incl_len), (1, 5, 3));
+    assert_eq!(pcap_reader.header().endianness, Endianness::Big);
+    assert_eq!(pcap_reader.section_boundaries()[0].header.ts_resolution, TsResolution::MicroSecond);
+    assert!(pcap_reader.next_raw_packet().is_none());
+
+    let mut pcap_reader = PcapReader::new(&glued[..]).unwrap().with_concatenated(ConcatenatedPcaps::AnyDatalink);
+    let timestamps: Vec<_> = std::iter::from_fn(|| pcap_reader.next_packet().map(|p| p.unwrap().timestamp)).collect();
+    assert_eq!(timestamps, [Duration::new(1, 5000); 2]);
+}
+
+#[test]
+fn packet_header_with_timestamp() {
+    let header = pcap_file::pcap::PcapPacketHeader { ts_sec: 0, ts_frac: 0, incl_len: 4, orig_len: 8 };
+
+    let micro = header.with_timestamp(10, 999_999, TsResolution::MicroSecond).unwrap();
+    assert_eq!((micro.ts_sec, micro.ts_frac, micro.incl_len, micro.orig_len), (10, 999_999, 4, 8));
+    assert!(matches!(header.with_timestamp(10, 1_000_000, TsResolution::MicroSecond), Err(PcapError::InvalidField(_))));
+
+    let nano = header.with_timestamp(10, 999_999_999, TsResolution::NanoSecond).unwrap();
+    assert_eq!((nano.ts_sec, nano.ts_frac), (10, 999_999_999));
+    assert!(matches!(header.with_timestamp(10, 1_000_000_000, TsResolution::NanoSecond), Err(PcapError::InvalidField(_))));
+}
+
+#[test]
+fn header_validate() {
+    // Every combination of the fields checked by the writers, with a few other fields varying along
+    for version_major in [0, 1, 2, 3, u16::MAX] {
+        for snaplen in [0, 1, 65535, u32::MAX] {
+            for (i, datalink) in [DataLink::ETHERNET, DataLink::RAW, DataLink::Unknown(0xFFFF)].into_iter().enumerate() {
+                for ts_resolution in [TsResolution::MicroSecond, TsResolution::NanoSecond] {
+                    let header = PcapHeader {
+                        version_major,
+                        version_minor: i as u16 * 3,
+                        snaplen,
+                        datalink,
+                        ts_resolution,
+                        endianness: if i % 2 == 0 { Endianness::Big } else { Endianness::Little },
+                        ..Default::default()
+                    };
+
+                    let validated = header.validate();
+                    let written = PcapWriter::with_header(Vec::new(), header);
+                    assert_eq!(validated.is_ok(), written.is_ok(), "{header:?}");
+                    assert_eq!(validated.is_ok(), version_major == 2 && snaplen != 0);
+                }
+            }
+        }
+    }
+
+    // Pseudo-random headers: validate agrees with the writer, and the pcap written can be read back
+    let mut state = 0x9E37_79B9_7F4A_7C15_u64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    for _ in 0..10_000 {
+        let (a, b) = (next(), next());
+        let header = PcapHeader {
+            version_major: [2, a as u16][a as usize >> 63],
+            version_minor: (a >> 16) as u16,
+            ts_correction: (a >> 32) as i32,
+            ts_accuracy: b as u32,
+            snaplen: [0, 1, b as u32 >> 8][(b >> 62) as usize % 3],
+            datalink: DataLink::from([b as u32 & 0x1FF, (b as u32 & 0x1FF).swap_bytes(), (b >> 32) as u32][(b >> 61) as usize % 3]),
+            ts_resolution: [TsResolution::MicroSecond, TsResolution::NanoSecond][(b >> 60) as usize & 1],
+            endianness: [Endianness::Big, Endianness::Little][(b >> 59) as usize & 1],
+        };
+
+        let written = PcapWriter::with_header(Vec::new(), header);
+        assert_eq!(header.validate().is_ok(), written.is_ok(), "{header:?}");
+        if let Ok(writer) = written {
+            let pcap = writer.into_writer();
+            assert_eq!(PcapReader::new(&pcap[..]).map(|reader| reader.header()).ok(), Some(header), "{header:?}");
+        }
+    }
+}
+
+#[test]
+fn multi_capture() {
+    use pcap_file::pcap::{MultiCapture, SourceId};
+
+    let pcap = |timestamps: &[u64]| {
+        let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+        for &ms in timestamps {
+            pcap_writer.write_packet(&PcapPacket::new(Duration::from_millis(ms), 1, &[0])).unwrap();
+        }
+        pcap_writer.into_writer()
+    };
+    let read_all = |pcaps: &[&Vec<u8>]| {
+        let readers = pcaps.iter().map(|pcap| PcapReader::new(&pcap[..]).unwrap()).collect();
+        let mut capture = MultiCapture::new(readers);
+        let mut packets = vec![];
+        while let Some(packet) = capture.next_packet() {
+            let (SourceId(source), packet) = packet.unwrap();
+            packets.push((source, packet.timestamp.as_millis() as u64));
+        }
+        packets
+    };
+
+    let (a, b) = (pcap(&[1, 2]), pcap(&[5, 6]));
+    assert_eq!(read_all(&[&a, &b]), [(0, 1), (0, 2), (1, 5), (1, 6)]);
+    assert_eq!(read_all(&[&b, &a]), [(1, 1), (1, 2), (0, 5), (0, 6)]);
+
+    let (a, b) = (pcap(&[1, 3, 5]), pcap(&[2, 3, 4, 9]));
+    assert_eq!(read_all(&[&a, &b]), [(0, 1), (1, 2), (0, 3), (1, 3), (1, 4), (0, 5), (1, 9)]);
+
+    // The packets with the same timestamp are returned in the order of the sources
+    let a = pcap(&[1, 2]);
+    assert_eq!(read_all(&[&a, &a, &a]), [(0, 1), (1, 1), (2, 1), (0, 2), (1, 2), (2, 2)]);
+
+    // A source in error is finished, the other ones are still read
+    let (a, b) = (pcap(&[1, 4]), pcap(&[2, 3]));
+    let truncated = &b[..b.len() - 1];
+    let mut capture = MultiCapture::new(vec![PcapReader::new(&a[..]).unwrap(), PcapReader::new(truncated).unwrap()]);
+    assert_eq!(capture.next_packet().unwrap().unwrap().0, SourceId(0));
+    assert_eq!(capture.next_packet().unwrap().unwrap().0, SourceId(1));
+    assert!(capture.next_packet().unwrap().is_err());
+    assert!(capture.is_finished(SourceId(1)) && !capture.is_finished(SourceId(0)));
+    assert_eq!(capture.progress(SourceId(1)).nb_packets, 1);
+    assert_eq!(capture.next_packet().unwrap().unwrap().0, SourceId(0));
+    assert!(capture.next_packet().is_none());
+    assert_eq!(capture.progress(SourceId(0)).max_timestamp, Some(Duration::from_millis(4)));
+}
+
+#[test]
+fn try_for_each() {
+    let data = sample_pcap(5);
+
+    let mut total = 0;
+    let res = PcapReader::new(&data[..]).unwrap().try_for_each(|packet| {
+        total += packet.data.len();
+        Ok(())
+    });
+    assert!(res.is_ok());
+    assert_eq!(total, 50);
+
+    // Stops at the first error of the closure or of the reader
+    let mut nb = 0;
+    let res = PcapReader::new(&data[..]).unwrap().try_for_each(|packet| {
+        nb += 1;
+        if packet.data[0] == 2 { Err(PcapError::InvalidField("stop")) } else { Ok(()) }
+    });
+    assert!(matches!(res, Err(PcapError::InvalidField("stop"))));
+    assert_eq!(nb, 3);
+
+    let truncated = &data[..data.len() - 1];
+    assert!(PcapReader::new(truncated).unwrap().try_for_each(|_| Ok(())).is_err());
+}
+
+#[test]
+fn write_packet_owned() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use pcap_file::pcap::Keep;
+
+    // Records the address of the data given to the transform, and of the data once modified
+    let seen = Arc::new(AtomicUsize::new(0));
+    let modified = Arc::new(AtomicUsize::new(0));
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    let (seen_by_transform, modified_by_transform) = (seen.clone(), modified.clone());
+    pcap_writer.set_transform(move |_, data| {
+        seen_by_transform.store(data.as_ptr() as usize, Ordering::SeqCst);
+        data.to_mut()[0] = 0xFF;
+        modified_by_transform.store(data.as_ptr() as usize, Ordering::SeqCst);
+        Ok(Keep::Yes)
+    });
+
+    let data = vec![1_u8, 2, 3];
+    let ptr = data.as_ptr() as usize;
+    pcap_writer.write_packet_owned(PcapPacket::new_owned(Duration::from_secs(1), 3, data)).unwrap();
+    assert_eq!(seen.load(Ordering::SeqCst), ptr);
+
+    // The borrowed packets are copied when they are modified, into the buffer of the transform: here the owned data
+    // of the previous packet
+    let data = [4_u8, 5, 6];
+    pcap_writer.write_packet_owned(PcapPacket::new(Duration::from_secs(2), 3, &data)).unwrap();
+    assert_eq!(seen.load(Ordering::SeqCst), data.as_ptr() as usize);
+    assert_eq!(modified.load(Ordering::SeqCst), ptr);
+
+    let pcap = pcap_writer.into_writer();
+    let mut pcap_reader = PcapReader::new(&pcap[..]).unwrap();
+    assert_eq!(&pcap_reader.next_packet().unwrap().unwrap().data[..], [0xFF, 2, 3]);
+    assert_eq!(&pcap_reader.next_packet().unwrap().unwrap().data[..], [0xFF, 5, 6]);
+    assert_eq!(data, [4, 5, 6]);
+}
+
+#[test]
+fn orig_len_histogram() {
+    // Packets snapped to 4 bytes whatever their original length
+    let header = PcapHeader { snaplen: 4, ..Default::default() };
+    let mut pcap_writer = PcapWriter::with_header(Vec::new(), header).unwrap();
+    for orig_len in [60, 1500, 60, 4, 1500, 60] {
+        pcap_writer.write_packet(&PcapPacket::new(Duration::ZERO, orig_len, &[0; 4])).unwrap();
+    }
+    let data = pcap_writer.into_writer();
+
+    let histogram = PcapReader::new(&data[..]).unwrap().orig_len_histogram().unwrap();
+    assert_eq!(histogram.into_iter().collect::<Vec<_>>(), [(4, 1), (60, 3), (1500, 2)]);
+
+    assert!(PcapReader::new(&data[..data.len() - 1]).unwrap().orig_len_histogram().is_err());
+}
+
+#[test]
+fn network_data() {
+    let frame: Vec<u8> = (0..20).collect();
+    let packet = PcapPacket::new(Duration::ZERO, 20, &frame);
+
+    assert_eq!(packet.network_data(DataLink::ETHERNET), Some(&frame[14..]));
+    assert_eq!(packet.network_data(DataLink::LINUX_SLL2), Some(&[][..]));
+    assert_eq!(packet.network_data(DataLink::RAW), Some(&frame[..]));
+    assert_eq!(packet.network_data(DataLink::IEEE802_11_RADIOTAP), None);
+    assert_eq!(PcapPacket::new(Duration::ZERO, 20, &frame[..10]).network_data(DataLink::ETHERNET), None);
+}
+
+#[test]
+fn packet_from_slice() {
+    let header = PcapHeader { ts_resolution: TsResolution::NanoSecond, endianness: Endianness::Little, snaplen: 8, ..Default::default() };
+    let record = |incl_len: u32, data: &[u8]| {
+        [&10_u32.to_le_bytes()[..], &123_456_789_u32.to_le_bytes(), &incl_len.to_le_bytes(), &9_u32.to_le_bytes(), data].concat()
+    };
+
+    // The timestamp is read in nanoseconds and little endian, the rest of the slice is returned
+    let data = [record(4, &[1, 2, 3, 4]), vec![0xFF]].concat();
+    let (rem, packet) = PcapPacket::from_slice(&data, &header).unwrap();
+    assert_eq!(packet.timestamp, Duration::new(10, 123_456_789));
+    assert_eq!((packet.orig_len, &packet.data[..], rem), (9, &[1, 2, 3, 4][..], &[0xFF][..]));
+
+    // incl_len > snaplen
+    let data = record(9, &[0; 9]);
+    assert!(matches!(PcapPacket::from_slice(&data, &header), Err(PcapError::InvalidField(_))));
+}
+
+#[cfg(feature = "bytes")]
+#[test]
+fn packet_into_bytes() {
+    // A borrowed payload is copied
+    let data = [1, 2, 3, 4];
+    let bytes = PcapPacket::new(Duration::ZERO, 4, &data).into_bytes();
+    assert_eq!(bytes[..], data);
+    assert_ne!(bytes.as_ptr(), data.as_ptr());
+
+    // An owned payload is moved without copy
+    let data = vec![5, 6, 7, 8];
+    let ptr = data.as_ptr();
+    let bytes = PcapPacket::new_owned(Duration::ZERO, 4, data).into_bytes();
+    assert_eq!(bytes[..], [5, 6, 7, 8]);
+    assert_eq!(bytes.as_ptr(), ptr);
+}
+
+#[test]
+fn unix_nanos() {
+    use pcap_file::pcap::RawPcapPacket;
+
+    let max_secs = u32::MAX as u128 * 1_000_000_000;
+    let packet = PcapPacket::new(Duration::new(u32::MAX as u64, 999_999_999), 1, &[0]);
+    assert_eq!(packet.unix_nanos(), max_secs + 999_999_999);
+
+    let raw = |ts_sec, ts_frac| RawPcapPacket { ts_sec, ts_frac, incl_len: 1, orig_len: 1, data: Cow::Borrowed(&[0]) };
+    assert_eq!(raw(u32::MAX, 999_999).unix_nanos(TsResolution::MicroSecond), max_secs + 999_999_000);
+    assert_eq!(raw(u32::MAX, 999_999_999).unix_nanos(TsResolution::NanoSecond), max_secs + 999_999_999);
+
+    // The micro and nano encodings of the same instant, which is also the one of the converted packet
+    let micro = raw(u32::MAX, 123_456);
+    let nano = raw(u32::MAX, 123_456_000);
+    assert_eq!(micro.unix_nanos(TsResolution::MicroSecond), nano.unix_nanos(TsResolution::NanoSecond));
+    let packet = micro.clone().try_into_pcap_packet(TsResolution::MicroSecond, 0xFFFF).unwrap();
+    assert_eq!(packet.unix_nanos(), micro.unix_nanos(TsResolution::MicroSecond));
+}
+
+#[cfg(feature = "etherparse")]
+#[test]
+fn parse_headers() {
+    use etherparse::{PacketBuilder, TransportSlice};
+
+    let builder = PacketBuilder::ethernet2([1; 6], [2; 6]).single_vlan(100.try_into().unwrap());
+    let mut ethernet = vec![];
+    builder.ipv4([10, 0, 0, 1], [10, 0, 0, 2], 64).udp(1234, 53).write(&mut ethernet, &[1, 2, 3]).unwrap();
+
+    // The link-layer header is skipped, the VLAN tag of Ethernet included
+    let ip = ethernet[18..].to_vec();
+    let null = [&2_u32.to_le_bytes()[..], &ip].concat();
+    for (datalink, frame) in [(DataLink::ETHERNET, &ethernet), (DataLink::RAW, &ip), (DataLink::NULL, &null)] {
+        let packet = PcapPacket::new(Duration::ZERO, frame.len() as u32, frame);
+        let headers = packet.parse_headers(datalink).unwrap().unwrap();
+        let Some(TransportSlice::Udp(udp)) = headers.transport else { panic!("{datalink:?}") };
+        assert_eq!((udp.source_port(), udp.destination_port(), udp.payload()), (1234, 53, &[1, 2, 3][..]));
+    }
+
+    assert!(PcapPacket::new(Duration::ZERO, 4, &ethernet[..4]).parse_headers(DataLink::ETHERNET).unwrap().is_err());
+    assert!(PcapPacket::new(Duration::ZERO, 4, &ethernet[..4]).parse_headers(DataLink::LINUX_SLL2).is_none());
+    assert!(PcapPacket::new(Duration::ZERO, 4, &ethernet[..4]).parse_headers(DataLink::IEEE802_11_RADIOTAP).is_none());
+}
+
+#[test]
+fn raw_records() {
+    use pcap_file::pcap::{RawPcapReader, RawPcapWriter};
+
+    // A big endian pcap with an invalid timestamp and an incl_len bigger than the snaplen and the orig_len
+    let mut pcap = sample_pcap(3);
+    let header = PcapHeader { endianness: Endianness::Big, snaplen: 8, ..Default::default() };
+    let mut raw_pcap = vec![];
+    header.write_to(&mut raw_pcap).unwrap();
+    raw_pcap.extend_from_slice(&[0, 0, 0, 1, 0xFF, 0xFF, 0xFF, 0xFF, 0, 0, 0, 10, 0, 0, 0, 2]);
+    raw_pcap.extend_from_slice(&[7; 10]);
+
+    for pcap in [&pcap, &raw_pcap] {
+        let mut reader = RawPcapReader::new(&pcap[..]).unwrap();
+        let mut writer = RawPcapWriter::new(Vec::new(), *reader.raw_header_bytes()).unwrap();
+        while let Some(record) = reader.next_record() {
+            let (header, data) = record.unwrap();
+            assert_eq!(writer.write_record(&header, data).unwrap(), 16 + data.len());
+        }
+        assert_eq!(&writer.into_writer(), pcap);
+    }
+
+    let mut reader = RawPcapReader::new(&raw_pcap[..]).unwrap();
+    assert_eq!(reader.endianness(), Endianness::Big);
+    assert_eq!(reader.next_record().unwrap().unwrap().1, [7; 10]);
+    assert!(reader.next_record().is_none());
+
+    // The cap on incl_len and the truncated records
+    let mut reader = RawPcapReader::new(&pcap[..]).unwrap().with_max_incl_len(9);
+    assert!(matches!(reader.next_record(), Some(Err(PcapError::InvalidField(_)))));
+    for len in [pcap.len() - 1, pcap.len() - 12] {
+        let mut reader = RawPcapReader::new(&pcap[..len]).unwrap();
+        assert!(reader.next_record().unwrap().is_ok());
+        assert!(reader.next_record().unwrap().is_ok());
+        assert!(matches!(reader.next_record(), Some(Err(PcapError::TruncatedPacket))));
+    }
+
+    // The buffer grows for the records bigger than it when the cap allows them
+    let mut writer = PcapWriter::with_header(Vec::new(), PcapHeader { snaplen: 10_000_000, ..Default::default() }).unwrap();
+    writer.write_packet(&PcapPacket::new(Duration::ZERO, 9_000_000, &vec![7; 9_000_000])).unwrap();
+    let big_pcap = writer.into_writer();
+    assert!(matches!(RawPcapReader::new(&big_pcap[..]).unwrap().next_record(), Some(Err(PcapError::InvalidField(_)))));
+    let mut reader = RawPcapReader::new(&big_pcap[..]).unwrap().with_max_incl_len(10_000_000);
+    assert_eq!(reader.next_record().unwrap().unwrap().1.len(), 9_000_000);
+    assert!(reader.next_record().is_none());
+
+    // The incl_len must be the length of the data
+    let mut writer = RawPcapWriter::new(Vec::new(), *RawPcapReader::new(&pcap[..]).unwrap().raw_header_bytes()).unwrap();
+    let mut header = [0_u8; 16];
+    header[8] = 3;
+    assert!(writer.write_record(&header, &[1, 2, 3]).is_ok());
+    assert!(writer.write_record(&header, &[1, 2]).is_err());
+
+    pcap[..4].copy_from_slice(&[0; 4]);
+    assert!(RawPcapReader::new(&pcap[..]).is_err());
+}
+
+#[test]
+fn truncation_policy() {
+    use std::io::BufReader;
+
+    use pcap_file::pcap::{ChunkedPcapDecoder, MalformedPcapBuilder, MalformedRecord, PcapBufReader, TruncationPolicy};
+
+    let data = sample_pcap(3);
+    let two_packets = MalformedPcapBuilder::new(PcapHeader::default())
+        .record(MalformedRecord::new(0, 0, &[0; 10]))
+        .record(MalformedRecord::new(1, 0, &[1; 10]));
+    let last = MalformedRecord::new(2, 0, &[2; 10]);
+    let cut_data = two_packets.clone().record(last.clone().with_short_data(6)).into_bytes();
+    let cut_header = two_packets.clone().truncate_at(two_packets.offset() + 8).record(last).into_bytes();
+    let (cut_data, cut_header) = (&cut_data[..], &cut_header[..]);
+
+    let read_all = |pcap: &[u8], policy| {
+        let mut pcap_reader = PcapReader::new(pcap).unwrap().with_truncation_policy(policy);
+        let mut packets = vec![];
+        while let Some(packet) = pcap_reader.next_packet() {
+            match packet {
+                Ok(packet) => packets.push((packet.orig_len, packet.data.into_owned(), pcap_reader.is_partial())),
+                Err(e) => {
+                    assert!(e.is_eof_like());
+                    return Err(packets);
+                },
+            }
+        }
+        Ok(packets)
+    };
+
+    let whole = |i| (10, vec![i; 10], false);
+    for cut in [cut_data, cut_header] {
+        assert_eq!(read_all(cut, TruncationPolicy::Error), Err(vec![whole(0), whole(1)]));
+        assert_eq!(read_all(cut, TruncationPolicy::StopClean), Ok(vec![whole(0), whole(1)]));
+    }
+    assert_eq!(read_all(cut_data, TruncationPolicy::YieldPartial), Ok(vec![whole(0), whole(1), (10, vec![2; 6], true)]));
+    assert_eq!(read_all(cut_header, TruncationPolicy::YieldPartial), Ok(vec![whole(0), whole(1)]));
+    assert_eq!(read_all(&data, TruncationPolicy::YieldPartial), Ok(vec![whole(0), whole(1), whole(2)]));
+
+    let mut pcap_reader = PcapReader::new(cut_data).unwrap().with_truncation_policy(TruncationPolicy::YieldPartial);
+    let nb_raw = std::iter::from_fn(|| pcap_reader.next_raw_packet().map(|packet| packet.unwrap().data.len())).collect::<Vec<_>>();
+    assert_eq!(nb_raw, [10, 10, 6]);
+
+    // The adapters and the indexed lookups follow the policy
+    let pcap_reader = PcapReader::new(cut_data).unwrap().with_truncation_policy(TruncationPolicy::StopClean);
+    let mut dedup = pcap_reader.dedup_by_key(|packet| packet.data[0]);
+    assert!(dedup.next_packet().unwrap().is_ok());
+    assert!(dedup.next_packet().unwrap().is_ok());
+    assert!(dedup.next_packet().is_none());
+
+    for (policy, nb_packets) in [(TruncationPolicy::StopClean, 2), (TruncationPolicy::YieldPartial, 3)] {
+        let mut pcap_reader = PcapReader::new(Cursor::new(cut_data)).unwrap().with_truncation_policy(policy);
+        let index = PcapIndex::build(&mut pcap_reader, 2).unwrap();
+        assert_eq!(index.nb_packets(), nb_packets);
+
+        let last = pcap_reader.nth_packet(2, Some(&index)).unwrap().map(|packet| packet.data.len());
+        assert_eq!(last, (nb_packets == 3).then_some(6));
+        assert_eq!(pcap_reader.nth_packet(2, None).unwrap().map(|packet| packet.data.len()), last);
+
+        // So do the methods reading only the packet headers
+        for cut in [cut_data, cut_header] {
+            let stats = PcapReader::new(cut).unwrap().with_truncation_policy(policy).capture_stats().unwrap();
+            let partial = policy == TruncationPolicy::YieldPartial && cut == cut_data;
+            assert_eq!(stats.nb_packets, 2 + partial as u64);
+        }
+        assert!(PcapReader::new(cut_data).unwrap().capture_stats().unwrap_err().is_eof_like());
+
+        let mut pcap_reader = PcapReader::new(cut_data).unwrap().with_truncation_policy(policy);
+        let mut payloads = vec![];
+        while let Some(header) = pcap_reader.read_header().unwrap() {
+            payloads.push((pcap_reader.read_payload(&header).unwrap().len(), pcap_reader.is_partial()));
+        }
+        assert_eq!(payloads, [(10, false), (10, false), (6, true)][..nb_packets as usize]);
+    }
+
+    // And the other readers
+    let buf_read_all = |pcap: &[u8], policy| {
+        let mut pcap_reader = PcapBufReader::new(BufReader::with_capacity(16, pcap)).unwrap().with_truncation_policy(policy);
+        let mut packets = vec![];
+        while let Some(packet) = pcap_reader.next_packet() {
+            match packet {
+                Ok(packet) => packets.push((packet.orig_len, packet.data.into_owned(), pcap_reader.is_partial())),
+                Err(e) => {
+                    assert!(e.is_eof_like());
+                    return Err(packets);
+                },
+            }
+        }
+        Ok(packets)
+    };
+    let decode_all = |pcap: &[u8], policy| {
+        let mut decoder = ChunkedPcapDecoder::new().with_truncation_policy(policy);
+        let mut packets = vec![];
+        decoder.feed(pcap);
+        while let Some(packet) = decoder.poll_end() {
+            match packet {
+                Ok(packet) => packets.push((packet.orig_len, packet.data.into_owned(), decoder.is_partial())),
+                Err(e) => {
+                    assert!(e.is_eof_like());
+                    return Err(packets);
+                },
+            }
+        }
+        Ok(packets)
+    };
+    for pcap in [cut_data, cut_header, &data] {
+        for policy in [TruncationPolicy::Error, TruncationPolicy::StopClean, TruncationPolicy::YieldPartial] {
+            assert_eq!(buf_read_all(pcap, policy), read_all(pcap, policy));
+            assert_eq!(decode_all(pcap, policy), read_all(pcap, policy));
+        }
+    }
+
+    #[cfg(feature = "bytes")]
+    for pcap in [cut_data, cut_header, &data] {
+        for policy in [TruncationPolicy::Error, TruncationPolicy::StopClean, TruncationPolicy::YieldPartial] {
+            let mut pcap_reader = pcap_file::pcap::PcapBytesReader::new(pcap).unwrap().with_truncation_policy(policy);
+            let mut packets = vec![];
+            let read = loop {
+                match pcap_reader.next_packet() {
+                    Some(Ok(packet)) => packets.push((packet.orig_len, packet.data.to_vec(), pcap_reader.is_partial())),
+                    Some(Err(e)) => {
+                        assert!(e.is_eof_like());
+                        break Err(packets);
+                    },
+                    None => break Ok(packets),
+                }
+            };
+            assert_eq!(read, read_all(pcap, policy));
+        }
+    }
+}
+
+#[test]
+fn length_mismatch() {
+    use pcap_file::pcap::RawPcapPacket;
+
+    let raw = |incl_len, data: &'static [u8]| RawPcapPacket { ts_sec: 1, ts_frac: 0, incl_len, orig_len: 10, data: Cow::Borrowed(data) };
+
+    // Rejected by default, nothing is written
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    let err = pcap_writer.write_raw_packet(&raw(3, &[1, 2])).unwrap_err();
+    assert!(matches!(err, PcapError::LengthMismatch { incl_len: 3, data_len: 2 }));
+    assert_eq!(pcap_writer.get_ref().len(), 24);
+
+    // Written as given when trusting incl_len, the extra data being dropped
+    assert_eq!(pcap_writer.write_raw_packet_unchecked(&raw(2, &[1, 2, 3])).unwrap(), 18);
+    assert_eq!(pcap_writer.write_raw_packet_unchecked(&raw(20, &[4, 5])).unwrap_err().code(), "PCAP_LENGTH_MISMATCH");
+    assert_eq!(pcap_writer.write_raw_packet_unchecked(&raw(12, &[6; 12])).unwrap(), 28);
+    let data = pcap_writer.into_writer();
+    assert_eq!(data.len(), 24 + 18 + 28);
+
+    let mut pcap_reader = PcapReader::new(&data[..]).unwrap();
+    assert_eq!(pcap_reader.next_packet().unwrap().unwrap().data[..], [1, 2]);
+    // incl_len > orig_len
+    assert!(pcap_reader.next_packet().unwrap().is_err());
+}
+
+#[test]
+fn pad_to() {
+    use std::io::BufReader;
+
+    use pcap_file::pcap::{ChunkedPcapDecoder, PcapBufReader, PcapPacketHeader, PcapParser, RawPcapReader};
+
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    assert!(pcap_writer.pad_to(0).is_err());
+    assert!(pcap_writer.pad_to(1 << 20).is_err());
+    pcap_writer.write_packet(&PcapPacket::new(Duration::new(1, 0), 10, &[1; 10])).unwrap();
+    pcap_writer.write_packet(&PcapPacket::new(Duration::new(2, 0), 10, &[2; 10])).unwrap();
+    assert_eq!(pcap_writer.pad_to(4096).unwrap(), 4096 - 24 - 2 * 26);
+    assert_eq!(pcap_writer.pad_to(4096).unwrap(), 0);
+    let data = pcap_writer.into_writer();
+    assert_eq!(data.len(), 4096);
+
+    // Skipped when ignored
+    let mut pcap_reader = PcapReader::new(&data[..]).unwrap().with_ignore_padding(true);
+    assert_eq!(pcap_reader.next_packet().unwrap().unwrap().data[..], [1; 10]);
+    assert_eq!(pcap_reader.next_packet().unwrap().unwrap().data[..], [2; 10]);
+    assert!(pcap_reader.next_packet().is_none());
+
+    let mut pcap_reader = PcapReader::new(&data[..]).unwrap();
+    pcap_reader.next_packet().unwrap().unwrap();
+    pcap_reader.next_packet().unwrap().unwrap();
+    assert!(pcap_reader.next_packet().unwrap().is_err());
+    let mut pcap_reader = PcapReader::new(&data[..]).unwrap();
+    let header = (0..3).map(|_| pcap_reader.read_header().unwrap().unwrap()).last().unwrap();
+    assert!(header.is_padding());
+    assert_eq!(header, PcapPacketHeader::padding(4096 - 24 - 2 * 26 - 16));
+
+    // A gap shorter than a record header takes another block
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    pcap_writer.write_packet(&PcapPacket::new(Duration::new(1, 0), 10, &[1; 10])).unwrap();
+    assert_eq!(pcap_writer.pad_to(16).unwrap(), 30);
+    pcap_writer.write_packet(&PcapPacket::new(Duration::new(2, 0), 10, &[2; 10])).unwrap();
+    assert_eq!(pcap_writer.pad_to(4).unwrap(), 18);
+    let data = pcap_writer.into_writer();
+    assert_eq!(data.len(), 24 + 26 + 30 + 26 + 18);
+
+    let pcap_reader = PcapReader::new(&data[..]).unwrap().with_ignore_padding(true);
+    let nb_packets = pcap_reader.fold_packets(0, |nb, _| nb + 1).unwrap();
+    assert_eq!(nb_packets, 2);
+
+    // The other readers skip them too, with the parser, wherever the buffers split them
+    let expected = [vec![1; 10], vec![2; 10]];
+    for capacity in [1, 16, 30, 50, data.len()] {
+        let mut pcap_reader = PcapBufReader::new(BufReader::with_capacity(capacity, &data[..])).unwrap().with_ignore_padding(true);
+        let read: Vec<_> = std::iter::from_fn(|| pcap_reader.next_packet().map(|packet| packet.unwrap().data.into_owned())).collect();
+        assert_eq!(read, expected, "capacity {capacity}");
+    }
+
+    for chunk_len in 1..=data.len() {
+        let mut decoder = ChunkedPcapDecoder::new().with_ignore_padding(true);
+        let mut read = vec![];
+        for chunk in data.chunks(chunk_len) {
+            decoder.feed(chunk);
+            while let Some(packet) = decoder.poll() {
+                read.push(packet.unwrap().data.into_owned());
+            }
+        }
+        assert_eq!(read, expected, "chunk_len {chunk_len}");
+        assert!(decoder.poll_end().is_none());
+    }
+
+    let mut raw_reader = RawPcapReader::new(&data[..]).unwrap().with_ignore_padding(true);
+    let read: Vec<_> = std::iter::from_fn(|| raw_reader.next_record().map(|record| record.unwrap().1.to_vec())).collect();
+    assert_eq!(read, expected);
+
+    let (rem, mut parser) = PcapParser::new(&data[..]).unwrap();
+    parser.set_ignore_padding(true);
+    let (rem, packet) = parser.next_packet(rem).unwrap();
+    assert_eq!(packet.data[..], expected[0]);
+    let (rem, packet) = parser.next_packet(rem).unwrap();
+    assert_eq!(packet.data[..], expected[1]);
+    assert!(matches!(parser.next_packet(rem), Err(PcapError::IncompleteBuffer)));
+
+    #[cfg(feature = "bytes")]
+    {
+        let mut pcap_reader = pcap_file::pcap::PcapBytesReader::with_capacity(&data[..], 64).unwrap().with_ignore_padding(true);
+        let read: Vec<_> = std::iter::from_fn(|| pcap_reader.next_packet().map(|packet| packet.unwrap().data.to_vec())).collect();
+        assert_eq!(read, expected);
+    }
+}
+
+#[test]
+fn zero_read_retry() {
+    use std::collections::VecDeque;
+    use std::io::Read;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    use pcap_file::ZeroReadRetry;
+
+    /// Returns the chunks one per read, an empty chunk being a read returning no data
+    struct FlakyReader(VecDeque<Vec<u8>>);
+
+    impl Read for FlakyReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let chunk = self.0.pop_front().unwrap_or_default();
+            buf[..chunk.len()].copy_from_slice(&chunk);
+            Ok(chunk.len())
+        }
+    }
+
+    // Two zero reads in the first packet, then a clean end after the second packet
+    let pcap = sample_pcap(2);
+    let flaky = || FlakyReader(VecDeque::from([pcap[..30].to_vec(), vec![], vec![], pcap[30..].to_vec()]));
+
+    let mut pcap_reader = PcapReader::new(flaky()).unwrap();
+    let err = pcap_reader.next_packet().unwrap().unwrap_err();
+    assert!(matches!(&err, PcapError::TruncatedPacket));
+
+    let mut pcap_reader = PcapReader::new(flaky()).unwrap().with_zero_read_retry(ZeroReadRetry::new(1));
+    let err = pcap_reader.next_packet().unwrap().unwrap_err();
+    assert!(matches!(err, PcapError::UnexpectedEofMidPacket(1)));
+    assert!(err.is_eof_like());
+
+    let retries = Arc::new(AtomicU32::new(0));
+    let counter = retries.clone();
+    let retry = ZeroReadRetry::new(3).with_backoff(move |retry| {
+        assert_eq!(counter.fetch_add(1, Ordering::Relaxed) + 1, retry);
+    });
+    let mut pcap_reader = PcapReader::new(flaky()).unwrap().with_zero_read_retry(retry);
+    assert_eq!(pcap_reader.next_packet().unwrap().unwrap().data[..], [0; 10]);
+    assert_eq!(pcap_reader.next_packet().unwrap().unwrap().data[..], [1; 10]);
+    assert!(pcap_reader.next_packet().is_none());
+    assert_eq!(retries.load(Ordering::Relaxed), 2);
+}
+
+#[test]
+fn malformed_builder() {
+    use pcap_file::pcap::{ConcatenatedPcaps, MalformedPcapBuilder, MalformedRecord};
+
+    let header = PcapHeader { endianness: Endianness::Little, ..Default::default() };
+    let builder = MalformedPcapBuilder::new(header).record(MalformedRecord::new(1, 0, &[1, 2]));
+    assert_eq!(builder.offset(), 24 + 18);
+
+    // The operators compose on the same record
+    let data = builder
+        .clone()
+        .record(MalformedRecord::new(2, 7, &[3, 4, 5]).with_incl_len(1).with_ts_frac(1_000_000).with_swapped_endianness())
+        .into_bytes();
+    assert_eq!(data[..24], hex::decode("d4c3b2a1020004000000000000000000ffff000001000000").unwrap()[..]);
+    assert_eq!(data[42..], hex::decode("00000002000f42400000000100000003030405").unwrap()[..]);
+
+    // A duplicated global header is only accepted by the tolerant mode
+    let data = builder.clone().duplicate_global_header().record(MalformedRecord::new(2, 0, &[3, 4])).into_bytes();
+    let mut pcap_reader = PcapReader::new(&data[..]).unwrap();
+    pcap_reader.next_packet().unwrap().unwrap();
+    assert!(matches!(pcap_reader.next_packet(), Some(Err(PcapError::EmbeddedGlobalHeader))));
+
+    let mut pcap_reader = PcapReader::new(&data[..]).unwrap().with_concatenated(ConcatenatedPcaps::SameDatalink);
+    let timestamps: Vec<_> = std::iter::from_fn(|| pcap_reader.next_packet().map(|p| p.unwrap().timestamp)).collect();
+    assert_eq!(timestamps, [Duration::from_secs(1), Duration::from_secs(2)]);
+
+    // The truncation applies to the whole pcap, whenever it is set
+    let data = builder.clone().truncate_at(30).record(MalformedRecord::new(2, 0, &[3, 4])).into_bytes();
+    assert_eq!(data.len(), 30);
+    assert_eq!(builder.truncate_at(1000).into_bytes().len(), 42);
+}
+
+#[test]
+fn consumed_bytes() {
+    use pcap_file::pcap::{MalformedPcapBuilder, MalformedRecord, TruncationPolicy};
+
+    let data = MalformedPcapBuilder::new(PcapHeader::default())
+        .record(MalformedRecord::new(0, 0, &[1; 100]))
+        .record(MalformedRecord::new(1, 0, &[2; 4]))
+        .record(MalformedRecord::new(2, 0, &[3; 10]).with_short_data(6))
+        .into_bytes();
+
+    // The data limit doesn't change the bytes occupied, a partial packet only occupies what is left
+    let mut pcap_reader = PcapReader::new(&data[..]).unwrap().with_data_limit(8).with_truncation_policy(TruncationPolicy::YieldPartial);
+    let mut consumed = vec![];
+    while let Some(packet) = pcap_reader.next_packet() {
+        packet.unwrap();
+        consumed.push(pcap_reader.consumed_bytes());
+    }
+    assert_eq!(consumed, [116, 20, 22]);
+    assert_eq!(24 + consumed.iter().sum::<u64>(), data.len() as u64);
+
+    let mut pcap_reader = PcapReader::new(&data[..]).unwrap();
+    assert_eq!(pcap_reader.consumed_bytes(), 0);
+    pcap_reader.read_header().unwrap().unwrap();
+    assert_eq!(pcap_reader.consumed_bytes(), 116);
+    pcap_reader.next_raw_packet().unwrap().unwrap();
+    assert_eq!(pcap_reader.consumed_bytes(), 20);
+}
+
+#[test]
+fn create_atomic() {
+    use pcap_file::pcap::{RotatingPcapWriter, RotationPolicy};
+    use pcap_file::AtomicFile;
+
+    let dir = std::env::temp_dir().join(format!("pcap_file_create_atomic_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir(&dir).unwrap();
+    let path = dir.join("capture.pcap");
+    let tmp_path = dir.join(format!("capture.pcap.tmp-{}", std::process::id()));
+    let packet = PcapPacket::new(Duration::from_secs(1), 4, &[1, 2, 3, 4]);
+
+    // Only renamed into place once finished
+    let mut pcap_writer = PcapWriter::create_atomic(&path, PcapHeader::default()).unwrap();
+    pcap_writer.write_packet(&packet).unwrap();
+    assert_eq!(pcap_writer.get_ref().tmp_path(), tmp_path);
+    assert!(tmp_path.exists() && !path.exists());
+    pcap_writer.finish().unwrap();
+    assert!(path.exists() && !tmp_path.exists());
+    assert_eq!(PcapReader::from_path(&path).unwrap().next_packet().unwrap().unwrap().data, packet.data);
+
+    // Dropped or panicking before being finished: the previous file is kept and the temporary file is removed
+    let mut pcap_writer = PcapWriter::create_atomic(&path, PcapHeader::default()).unwrap();
+    pcap_writer.write_packet(&PcapPacket::new(Duration::from_secs(2), 1, &[9])).unwrap();
+    drop(pcap_writer);
+    assert!(!tmp_path.exists());
+
+    let crashed = std::panic::catch_unwind(|| {
+        let mut pcap_writer = PcapWriter::create_atomic(&path, PcapHeader::default()).unwrap();
+        pcap_writer.write_packet(&packet).unwrap();
+        panic!("crashed job");
+    });
+    assert!(crashed.is_err() && !tmp_path.exists());
+    assert_eq!(std::fs::read(&path).unwrap().len(), 24 + 16 + 4);
+
+    // The rename fails after the data are synced: both paths are reported, and the temporary file is kept to recover it
+    let dir_path = dir.join("dir.pcap");
+    let dir_tmp_path = dir.join(format!("dir.pcap.tmp-{}", std::process::id()));
+    std::fs::create_dir(&dir_path).unwrap();
+    std::fs::write(dir_path.join("file"), b"not empty").unwrap();
+    let err = PcapWriter::create_atomic(&dir_path, PcapHeader::default()).unwrap().finish().unwrap_err();
+    assert_eq!(err.code(), "PCAP_RENAME_FAILED");
+    assert!(matches!(&err, PcapError::RenameFailed { from, to, .. } if **from == dir_tmp_path && **to == dir_path));
+    assert!(err.to_string().contains(&*dir_path.to_string_lossy()));
+    assert_eq!(std::fs::read(&dir_tmp_path).unwrap().len(), 24);
+    std::fs::remove_file(&dir_tmp_path).unwrap();
+
+    // Each rotated file is renamed into place before the next one is started
+    let rotated = |idx: usize| dir.join(format!("rotated_{idx}.pcap"));
+    let make_writer = |idx| {
+        assert!(idx == 0 || rotated(idx - 1).exists());
+        AtomicFile::create(rotated(idx)).unwrap()
+    };
+    let mut writer = RotatingPcapWriter::new_atomic(PcapHeader::default(), RotationPolicy::EverySeconds(60), make_writer).unwrap();
+    for secs in [1, 61, 121] {
+        writer.write_packet(&PcapPacket::new(Duration::from_secs(secs), 4, &[1, 2, 3, 4])).unwrap();
+    }
+    assert!(rotated(0).exists() && rotated(1).exists() && !rotated(2).exists());
+    writer.finish().unwrap();
+    assert!(rotated(2).exists());
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 5);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn rate_series() {
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    for millis in [500, 1200, 1700, 4100, 3900] {
+        pcap_writer.write_packet(&PcapPacket::new(Duration::from_millis(millis), 1, &[0])).unwrap();
+    }
+    let data = pcap_writer.into_writer();
+
+    // A point per window, the empty ones included, the older packet is counted in the current window
+    let series = PcapReader::new(&data[..]).unwrap().rate_series(Duration::from_secs(1), 5).unwrap();
+    let secs: Vec<_> = series.iter().map(|(start, nb_packets)| (start.as_secs(), *nb_packets)).collect();
+    assert_eq!(secs, [(0, 1), (1, 2), (2, 0), (3, 0), (4, 2)]);
+    let bounded = PcapReader::new(&data[..]).unwrap().rate_series(Duration::from_secs(1), 4);
+    assert!(matches!(bounded, Err(PcapError::InvalidConfiguration(_))));
+
+    // Aligned on the epoch, not on the first packet
+    let series = PcapReader::new(&data[..]).unwrap().rate_series(Duration::from_millis(1500), 5).unwrap();
+    assert_eq!(series, [(Duration::ZERO, 2), (Duration::from_millis(1500), 1), (Duration::from_secs(3), 2)]);
+
+    // A long silence with a short window is stopped by the bound before the empty windows are allocated
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    for secs in [0, u32::MAX as u64] {
+        pcap_writer.write_packet(&PcapPacket::new(Duration::from_secs(secs), 1, &[0])).unwrap();
+    }
+    let silence = pcap_writer.into_writer();
+    let series = PcapReader::new(&silence[..]).unwrap().rate_series(Duration::from_millis(1), 1000);
+    assert!(matches!(series, Err(PcapError::InvalidConfiguration(_))));
+
+    // Shifted by the epoch offset
+    let series = PcapReader::new(&data[..]).unwrap().with_epoch_offset(10).rate_series(Duration::from_secs(1), 5).unwrap();
+    assert_eq!(series[0], (Duration::from_secs(10), 1));
+    let shifted = PcapReader::new(&data[..]).unwrap().with_epoch_offset(-1).rate_series(Duration::from_secs(1), 5);
+    assert!(matches!(shifted, Err(PcapError::InvalidField(_))));
+    let reader = PcapReader::new(&data[..]).unwrap().with_epoch_offset(-1).with_epoch_shift_policy(EpochShiftPolicy::Clamp);
+    let series = reader.rate_series(Duration::from_secs(1), 5).unwrap();
+    assert_eq!(series, [(Duration::ZERO, 3), (Duration::from_secs(1), 0), (Duration::from_secs(2), 0), (Duration::from_secs(3), 2)]);
+
+    assert!(PcapReader::new(&sample_pcap(0)[..]).unwrap().rate_series(Duration::from_secs(1), 0).unwrap().is_empty());
+    let zero = PcapReader::new(&data[..]).unwrap().rate_series(Duration::ZERO, 5);
+    assert!(matches!(zero, Err(PcapError::InvalidConfiguration(_))));
+}
+
+#[test]
+fn progress() {
+    use pcap_file::pcap::ConcatenatedPcaps;
+
+    let data = sample_pcap(7);
+
+    let mut calls = vec![];
+    let mut progress = PcapReader::new(&data[..]).unwrap().on_progress(3, |nb_packets, nb_bytes| calls.push((nb_packets, nb_bytes)));
+    while let Some(pkt) = progress.next_packet() {
+        pkt.unwrap();
+    }
+    drop(progress);
+
+    assert_eq!(calls, vec![(3, 24 + 3 * 26), (6, 24 + 6 * 26)]);
+
+    // The whole records are counted whatever the data limit, with the global headers adopted
+    let glued = [sample_pcap(2), sample_pcap(1)].concat();
+    let pcap_reader = PcapReader::new(&glued[..]).unwrap().with_data_limit(2).with_concatenated(ConcatenatedPcaps::AnyDatalink);
+    let mut calls = vec![];
+    let mut progress = pcap_reader.on_progress(1, |nb_packets, nb_bytes| calls.push((nb_packets, nb_bytes)));
+    while let Some(pkt) = progress.next_packet() {
+        assert_eq!(pkt.unwrap().data.len(), 2);
+    }
+    drop(progress);
+
+    assert_eq!(calls, vec![(1, 24 + 26), (2, 24 + 2 * 26), (3, 2 * 24 + 3 * 26)]);
+    assert_eq!(calls[2].1, glued.len() as u64);
+}
+
+#[test]
+fn index_lookups() {
+    let data = sample_pcap(7);
+    let mut reader = PcapReader::new(Cursor::new(&data[..])).unwrap();
+
+    for stride in [1, 3, 7, 10] {
+        let index = PcapIndex::build(&mut reader, stride).unwrap();
+        assert_eq!(index.nb_packets(), 7);
+
+        for packet_no in 0..7 {
+            let offset = 24 + packet_no * 26;
+            assert_eq!(index.offset_of(&mut reader, packet_no).unwrap(), Some(offset));
+            assert_eq!(reader.next_packet().unwrap().unwrap().data[0], packet_no as u8);
+            assert_eq!(index.packet_at_offset(&mut reader, offset).unwrap(), Some(packet_no));
+        }
+        assert_eq!(index.offset_of(&mut reader, 7).unwrap(), None);
+        assert_eq!(index.packet_at_offset(&mut reader, 24 + 7 * 26).unwrap(), None);
+        assert!(matches!(index.packet_at_offset(&mut reader, 60), Err(PcapError::NotARecordBoundary(60))));
+        assert!(matches!(index.packet_at_offset(&mut reader, 10), Err(PcapError::NotARecordBoundary(10))));
+    }
+
+    let index = PcapIndex::build(&mut reader, 2).unwrap();
+    let mut other_writer = PcapWriter::new(Vec::new()).unwrap();
+    other_writer.write_packet(&PcapPacket::new(Duration::ZERO, 4, &[1, 2, 3, 4])).unwrap();
+    let other = other_writer.into_writer();
+    let mut other_reader = PcapReader::new(Cursor::new(&other[..])).unwrap();
+    assert!(matches!(index.offset_of(&mut other_reader, 0), Err(PcapError::IndexMismatch)));
+}
+
+#[test]
+fn header_from_fields() {
+    let header = PcapHeader::from_fields(0xD4C3B2A1, 2, 4, 0, 0, 262144, 1).unwrap();
+
+    // Header written by tcpdump for a little endian ethernet capture
+    let tcpdump = hex::decode("d4c3b2a10200040000000000000000000000040001000000").unwrap();
+    let mut written = vec![];
+    header.write_to(&mut written).unwrap();
+    assert_eq!(PcapHeader::from_slice(&tcpdump).unwrap().1, header);
+    assert_eq!(PcapHeader::from_slice(&written).unwrap().1, header);
+
+    assert!(PcapHeader::from_fields(0x12345678, 2, 4, 0, 0, 65535, 1).is_err());
+    assert!(PcapHeader::from_fields(0xA1B2C3D4, 1, 4, 0, 0, 65535, 1).is_err());
+}
+
+#[test]
+fn unsupported_variants() {
+    let variants = [
+        ("a1b2cd34", "Kuznetzov modified pcap"),
+        ("34cdb2a1", "Kuznetzov modified pcap"),
+        ("1c0001ac", "IXIA hardware pcap"),
+        ("ac01001c", "IXIA hardware pcap"),
+        ("1c0001ab", "IXIA software pcap"),
+        ("ab01001c", "IXIA software pcap"),
+        ("a12b3c4d", "Navtel pcap"),
+        ("4d3c2ba1", "Navtel pcap"),
+        ("0a0d0d0a", "pcapng file"),
+    ];
+
+    for (magic, name) in variants {
+        let data = hex::decode(format!("{magic}0002000400000000000000000000000000000001")).unwrap();
+        match PcapHeader::from_slice(&data) {
+            Err(PcapError::UnsupportedVariant(variant)) => assert_eq!(variant, name),
+            res => panic!("Unexpected result for {magic}: {res:?}"),
+        }
+    }
+
+    let data = hex::decode("deadbeef0002000400000000000000000000000000000001").unwrap();
+    assert!(matches!(PcapHeader::from_slice(&data), Err(PcapError::BadMagic)));
+}
+
+#[test]
+fn swapped_snaplen_datalink() {
+    // Little endian header whose snaplen (65535) and datalink (Ethernet) are written in big endian
+    let mut data = hex::decode("d4c3b2a10200040000000000000000000000ffff00000001").unwrap();
+    data.extend_from_slice(&sample_pcap(1)[24..]);
+
+    assert!(matches!(PcapHeader::from_slice(&data), Err(PcapError::SuspectedQuirk(PcapQuirk::SwappedSnaplenDatalink))));
+    assert!(matches!(PcapReader::new(&data[..]), Err(PcapError::SuspectedQuirk(PcapQuirk::SwappedSnaplenDatalink))));
+
+    let mut reader = PcapReader::with_quirks(&data[..], &[PcapQuirk::SwappedSnaplenDatalink]).unwrap();
+    assert_eq!(reader.fixed_quirks(), &[PcapQuirk::SwappedSnaplenDatalink]);
+    assert_eq!(reader.header().snaplen, 0xFFFF);
+    assert_eq!(reader.header().datalink, DataLink::ETHERNET);
+    assert_eq!(reader.next_packet().unwrap().unwrap().data[..], [0; 10]);
+
+    // A well formed pcap has nothing to fix
+    let sample = sample_pcap(1);
+    let reader = PcapReader::with_quirks(&sample[..], &[PcapQuirk::SwappedSnaplenDatalink]).unwrap();
+    assert!(reader.fixed_quirks().is_empty());
+}
+
+#[test]
+fn sample() {
+    let data = sample_pcap(8);
+
+    let mut sample = PcapReader::new(&data[..]).unwrap().sample(3);
+    let mut sampled = vec![];
+    while let Some(pkt) = sample.next_packet() {
+        sampled.push(pkt.unwrap().data[0]);
+    }
+
+    assert_eq!(sampled, vec![0, 3, 6]);
+}
+
+#[test]
+fn enumerate_packets() {
+    let data = sample_pcap(3);
+
+    let mut enumerate = PcapReader::new(&data[..]).unwrap().enumerate_packets().starting_at(1);
+    let mut enumerated = vec![];
+    while let Some((index, pkt)) = enumerate.next_packet() {
+        enumerated.push((index, pkt.unwrap().data[0]));
+    }
+    assert_eq!(enumerated, [(1, 0), (2, 1), (3, 2)]);
+
+    // The errors don't increment the index
+    let mut enumerate = PcapReader::new(&data[..data.len() - 1]).unwrap().enumerate_packets();
+    assert_eq!(enumerate.next_packet().unwrap().0, 0);
+    assert_eq!(enumerate.next_packet().unwrap().0, 1);
+    for _ in 0..2 {
+        let (index, pkt) = enumerate.next_packet().unwrap();
+        assert_eq!(index, 2);
+        assert!(pkt.is_err());
+    }
+}
+
+#[test]
+fn handoff_after_last_packet() {
+    use std::io::Read;
+
+    use pcap_file::pcapng::PcapNgReader;
+    use pcap_file::pcapng::PcapNgWriter;
+    use pcap_file::Endianness;
+
+    // A pcap followed by a pcapng
+    let mut data = sample_pcap(3);
+    let pcapng = PcapNgWriter::with_endianness(Vec::new(), Endianness::Little).unwrap().into_inner();
+    data.extend_from_slice(&pcapng);
+
+    // Seekable reader
+    let mut pcap_reader = PcapReader::new(Cursor::new(&data[..])).unwrap();
+    for _ in 0..3 {
+        pcap_reader.next_packet().unwrap().unwrap();
+    }
+    assert!(pcap_reader.next_packet().unwrap().is_err());
+
+    let cursor = pcap_reader.into_rewound_reader().unwrap();
+    assert_eq!(cursor.position(), 24 + 3 * 26);
+    PcapNgReader::new(cursor).unwrap();
+
+    // Non seekable reader
+    let mut pcap_reader = PcapReader::new(&data[..]).unwrap();
+    for _ in 0..3 {
+        pcap_reader.next_packet().unwrap().unwrap();
+    }
+    assert!(pcap_reader.next_packet().unwrap().is_err());
+
+    let (reader, read_ahead) = pcap_reader.into_parts();
+    assert_eq!(read_ahead, pcapng);
+    PcapNgReader::new(read_ahead.as_slice().chain(reader)).unwrap();
+}
+
+#[test]
+fn reservoir_sample() {
+    let data = sample_pcap(100);
+    let sample_of = |k, seed| -> Vec<u8> {
+        let sample = PcapReader::new(&data[..]).unwrap().reservoir_sample(k, seed).unwrap();
+        sample.iter().map(|pkt| pkt.data[0]).collect()
+    };
+
+    let sample = sample_of(10, 42);
+    assert_eq!(sample.len(), 10);
+    assert!(sample.windows(2).all(|w| w[0] < w[1]));
+    assert_eq!(sample, sample_of(10, 42));
+    assert_ne!(sample, sample_of(10, 43));
+
+    assert_eq!(sample_of(200, 42), (0..100).collect::<Vec<u8>>());
+}
+
+#[test]
+fn nth_packet() {
+    let data = sample_pcap(10);
+    let mut reader = PcapReader::new(Cursor::new(&data[..])).unwrap();
+    let index = PcapIndex::build(&mut reader, 4).unwrap();
+
+    for idx in [None, Some(&index)] {
+        assert_eq!(reader.nth_packet(7, idx).unwrap().unwrap().data[0], 7);
+        assert_eq!(reader.next_packet().unwrap().unwrap().data[0], 8);
+        assert_eq!(reader.nth_packet(0, idx).unwrap().unwrap().data[0], 0);
+
+        assert!(reader.nth_packet(10, idx).unwrap().is_none());
+        assert!(reader.nth_packet(1000, idx).unwrap().is_none());
+        assert!(reader.next_packet().is_none());
+    }
+
+    // The pcap is rewound under its first global header
+    use pcap_file::pcap::ConcatenatedPcaps;
+
+    let header = PcapHeader { endianness: Endianness::Big, ts_resolution: TsResolution::NanoSecond, ..Default::default() };
+    let mut pcap_writer = PcapWriter::with_header(Vec::new(), header).unwrap();
+    pcap_writer.write_packet(&PcapPacket::new(Duration::new(1, 5), 1, &[10])).unwrap();
+    let glued = [&data[..], &pcap_writer.into_writer()[..]].concat();
+
+    let mut reader = PcapReader::new(Cursor::new(&glued[..])).unwrap().with_concatenated(ConcatenatedPcaps::AnyDatalink);
+    assert_eq!(reader.nth_packet(10, None).unwrap().unwrap().timestamp, Duration::new(1, 5));
+    assert_eq!(reader.section_boundaries().len(), 1);
+    let index = PcapIndex::build(&mut reader, 4).unwrap();
+    assert_eq!((index.nb_packets(), reader.header().endianness), (11, Endianness::Big));
+    let packet = reader.nth_packet(1, None).unwrap().unwrap();
+    assert_eq!((packet.timestamp, packet.data[0]), (Duration::new(1, 0), 1));
+    assert_eq!(reader.header().endianness, Endianness::Little);
+    assert!(reader.section_boundaries().is_empty());
+}
+
+#[test]
+fn data_limit() {
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    pcap_writer.write_packet(&PcapPacket::new(Duration::ZERO, 1500, &[1; 100])).unwrap();
+    pcap_writer.write_packet(&PcapPacket::new(Duration::ZERO, 4, &[2; 4])).unwrap();
+    let data = pcap_writer.into_writer();
+
+    let mut pcap_reader = PcapReader::new(&data[..]).unwrap().with_data_limit(8);
+    let pkt = pcap_reader.next_packet().unwrap().unwrap();
+    assert_eq!((pkt.orig_len, &pkt.data[..]), (1500, &[1; 8][..]));
+    assert!(pcap_reader.is_data_truncated());
+
+    let pkt = pcap_reader.next_raw_packet().unwrap().unwrap();
+    assert_eq!((pkt.incl_len, pkt.orig_len, &pkt.data[..]), (4, 4, &[2; 4][..]));
+    assert!(!pcap_reader.is_data_truncated());
+
+    // Composes with the other ways of reading
+    let pcap_reader = PcapReader::new(&data[..]).unwrap().with_data_limit(2);
+    let sample = pcap_reader.reservoir_sample(2, 0).unwrap();
+    assert_eq!(&sample[0].data[..], &[1, 1]);
+    assert_eq!(&sample[1].data[..], &[2, 2]);
+}
+
+#[test]
+fn path_constructors() {
+    let path = std::env::temp_dir().join(format!("pcap_file_path_constructors_{}.pcap", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let header = PcapHeader { ts_resolution: TsResolution::NanoSecond, endianness: pcap_file::Endianness::Little, ..Default::default() };
+    let mut pcap_writer = PcapWriter::create(&path, header).unwrap();
+    pcap_writer.write_packet(&PcapPacket::new(Duration::new(1, 1), 1, &[1])).unwrap();
+    pcap_writer.flush().unwrap();
+    drop(pcap_writer);
+
+    let mut pcap_writer = PcapWriter::append_path(&path).unwrap();
+    assert_eq!(pcap_writer.ts_resolution(), TsResolution::NanoSecond);
+    pcap_writer.write_packet(&PcapPacket::new(Duration::new(2, 2), 1, &[2])).unwrap();
+    pcap_writer.flush().unwrap();
+    drop(pcap_writer);
+
+    let mut pcap_reader = PcapReader::from_path(&path).unwrap();
+    assert_eq!(pcap_reader.header(), header);
+    assert_eq!(pcap_reader.next_packet().unwrap().unwrap().timestamp, Duration::new(1, 1));
+    assert_eq!(pcap_reader.next_packet().unwrap().unwrap().timestamp, Duration::new(2, 2));
+    assert!(pcap_reader.next_packet().is_none());
+
+    std::fs::write(&path, b"not a pcap file, definitely not").unwrap();
+    let err = PcapReader::from_path(&path).unwrap_err();
+    assert!(err.to_string().contains(&*path.to_string_lossy()));
+    assert!(matches!(err, PcapError::WithPath { source, .. } if matches!(*source, PcapError::BadMagic)));
+    assert!(matches!(PcapWriter::append_path(&path), Err(PcapError::WithPath { .. })));
+
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(PcapReader::from_path(&path), Err(PcapError::WithPath { .. })));
+}
+
+#[test]
+fn autoflush() {
+    use std::io::BufWriter;
+
+    let mut pcap_writer = PcapWriter::new(BufWriter::new(Vec::new())).unwrap();
+    pcap_writer.write_packet(&PcapPacket::new(Duration::ZERO, 1, &[1])).unwrap();
+    assert!(pcap_writer.get_ref().get_ref().is_empty());
+
+    pcap_writer.set_autoflush(true);
+    pcap_writer.write_packet(&PcapPacket::new(Duration::ZERO, 1, &[2])).unwrap();
+    assert_eq!(pcap_writer.get_ref().get_ref().len(), 24 + 2 * 17);
+}
+
+#[test]
+fn canonicalize() {
+    use pcap_file::pcap::{canonicalize, CanonicalForm};
+
+    // Same packets, written in a different order with different parameters
+    let packets = [
+        PcapPacket::new(Duration::new(1, 5000), 10, &[1, 2]),
+        PcapPacket::new(Duration::new(1, 5000), 2, &[1, 1]),
+        PcapPacket::new(Duration::new(0, 0), 3, &[3; 3]),
+    ];
+
+    let header = PcapHeader { endianness: pcap_file::Endianness::Big, ..Default::default() };
+    let mut pcap_writer = PcapWriter::with_header(Vec::new(), header).unwrap();
+    for packet in &packets {
+        pcap_writer.write_packet(packet).unwrap();
+    }
+    let pcap_a = pcap_writer.into_writer();
+
+    let header = PcapHeader {
+        endianness: pcap_file::Endianness::Little,
+        ts_resolution: TsResolution::NanoSecond,
+        snaplen: 100,
+        ..Default::default()
+    };
+    let mut pcap_writer = PcapWriter::with_header(Vec::new(), header).unwrap();
+    for packet in packets.iter().rev() {
+        pcap_writer.write_packet(packet).unwrap();
+    }
+    let pcap_b = pcap_writer.into_writer();
+
+    let canonical_a = canonicalize(&mut PcapReader::new(&pcap_a[..]).unwrap(), Vec::new(), CanonicalForm::default())
+        .unwrap()
+        .into_writer();
+    let canonical_b = canonicalize(&mut PcapReader::new(&pcap_b[..]).unwrap(), Vec::new(), CanonicalForm::default())
+        .unwrap()
+        .into_writer();
+    assert_eq!(canonical_a, canonical_b);
+
+    let mut pcap_reader = PcapReader::new(&canonical_a[..]).unwrap();
+    assert_eq!(pcap_reader.header().snaplen, 262144);
+    assert_eq!(&pcap_reader.next_packet().unwrap().unwrap().data[..], &[3; 3]);
+    assert_eq!(&pcap_reader.next_packet().unwrap().unwrap().data[..], &[1, 1]);
+    assert_eq!(&pcap_reader.next_packet().unwrap().unwrap().data[..], &[1, 2]);
+}
+
+#[test]
+fn header_then_payload() {
+    let data = sample_pcap(4);
+    let mut pcap_reader = PcapReader::new(&data[..]).unwrap();
+
+    let header = pcap_reader.read_header().unwrap().unwrap();
+    assert_eq!((header.ts_sec, header.incl_len, header.orig_len), (0, 10, 10));
+    assert_eq!(pcap_reader.read_payload(&header).unwrap(), vec![0; 10]);
+
+    let header = pcap_reader.read_header().unwrap().unwrap();
+    assert_eq!(header.ts_sec, 1);
+    pcap_reader.skip_payload(&header).unwrap();
+    assert!(pcap_reader.skip_payload(&header).is_err());
+
+    // The payload not read is skipped
+    let header = pcap_reader.read_header().unwrap().unwrap();
+    assert_eq!(header.ts_sec, 2);
+    assert_eq!(pcap_reader.next_packet().unwrap().unwrap().data[0], 3);
+
+    assert!(pcap_reader.read_header().unwrap().is_none());
+}
+
+#[test]
+fn easy() {
+    use std::ops::ControlFlow;
+
+    use pcap_file::easy::{self, FileInfo, PacketOwned, MAX_PACKET_DATA};
+
+    let path = std::env::temp_dir().join(format!("pcap_file_easy_{}.pcap", std::process::id()));
+
+    let info = FileInfo { snaplen: u32::MAX, ..Default::default() };
+    let packets: Vec<_> = (0..4_u8).map(|i| PacketOwned::new_owned(Duration::new(i as u64, 0), 3, vec![i; 3])).collect();
+    let big = PacketOwned::new_owned(Duration::new(4, 0), 300_000, vec![4; MAX_PACKET_DATA]);
+    easy::write_all(&path, &info, &[packets.clone(), vec![big]].concat()).unwrap();
+
+    let (read_info, read) = easy::read_all(&path).unwrap();
+    assert_eq!(read_info, info);
+    assert_eq!(read.len(), 5);
+    assert_eq!(read[..4].iter().map(|p| &p.data).collect::<Vec<_>>(), packets.iter().map(|p| &p.data).collect::<Vec<_>>());
+    assert_eq!((read[4].orig_len, read[4].data.len()), (300_000, MAX_PACKET_DATA));
+
+    // The packets longer than the limit aren't silently truncated
+    let too_big = PacketOwned::new_owned(Duration::new(5, 0), 300_000, vec![5; MAX_PACKET_DATA + 1]);
+    easy::write_all(&path, &info, &[packets.clone(), vec![too_big]].concat()).unwrap();
+    match easy::read_all(&path) {
+        Err(PcapError::WithPath { source, .. }) => assert!(matches!(*source, PcapError::InvalidField(_))),
+        res => panic!("Unexpected result: {:?}", res.map(|(_, read)| read.len())),
+    }
+
+    let mut seen = vec![];
+    easy::for_each_packet(&path, |packet| {
+        seen.push(packet.data[0]);
+        if seen.len() == 2 { ControlFlow::Break(()) } else { ControlFlow::Continue(()) }
+    })
+    .unwrap();
+    assert_eq!(seen, [0, 1]);
+
+    let too_long = PacketOwned::new_owned(Duration::ZERO, 10, vec![0; 10]);
+    let small = FileInfo { snaplen: 5, ..Default::default() };
+    assert!(matches!(easy::write_all(&path, &small, &[too_long]), Err(PcapError::WithPath { .. })));
+
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(easy::read_all(&path), Err(PcapError::WithPath { .. })));
+}
+
+#[test]
+fn frames_to_pcap() {
+    use pcap_file::easy::frames_to_pcap;
+    use pcap_file::DataLink;
+
+    let frames = vec![vec![1_u8; 60], vec![2_u8; 100_000]];
+    let mut data = Vec::new();
+    frames_to_pcap(&frames, DataLink::IEEE802_11, &mut data).unwrap();
+
+    let mut pcap_reader = PcapReader::new(&data[..]).unwrap();
+    assert_eq!((pcap_reader.header().datalink, pcap_reader.header().snaplen), (DataLink::IEEE802_11, 100_000));
+    for (i, frame) in frames.iter().enumerate() {
+        let packet = pcap_reader.next_packet().unwrap().unwrap();
+        assert_eq!(packet.timestamp, Duration::from_micros(i as u64));
+        assert_eq!((packet.orig_len, &packet.data[..]), (frame.len() as u32, &frame[..]));
+    }
+    assert!(pcap_reader.next_packet().is_none());
+}
+
+#[test]
+fn merge() {
+    use pcap_file::pcap::{merge, ClockCorrection, NegativeTimestamp};
+
+    let pcap = |timestamps: &[u64], id: u8| {
+        let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+        for &ms in timestamps {
+            pcap_writer.write_packet(&PcapPacket::new(Duration::from_millis(ms), 1, &[id])).unwrap();
+        }
+        pcap_writer.into_writer()
+    };
+    // The clock of the host B is 37ms ahead and loses 1ms/s
+    let host_a = pcap(&[100, 1100, 2100], 0);
+    let host_b = pcap(&[130, 1130, 2130], 1);
+
+    let inputs = vec![
+        (PcapReader::new(&host_a[..]).unwrap(), ClockCorrection::default()),
+        (PcapReader::new(&host_b[..]).unwrap(), ClockCorrection::linear(-37_000_000, 1_000_000)),
+    ];
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    let stats = merge(inputs, &mut pcap_writer, NegativeTimestamp::Error).unwrap();
+    assert_eq!(stats.nb_packets, 6);
+    assert_eq!(stats.inputs[1].min_timestamp, Some(Duration::from_millis(93)));
+    assert_eq!(stats.inputs[1].max_timestamp, Some(Duration::from_millis(2095)));
+
+    let data = pcap_writer.into_writer();
+    let mut pcap_reader = PcapReader::new(&data[..]).unwrap();
+    let mut merged = vec![];
+    while let Some(packet) = pcap_reader.next_packet() {
+        let packet = packet.unwrap();
+        merged.push((packet.timestamp.as_millis(), packet.data[0]));
+    }
+    assert_eq!(merged, [(93, 1), (100, 0), (1094, 1), (1100, 0), (2095, 1), (2100, 0)]);
+
+    // Timestamps corrected before the epoch
+    let inputs = || vec![(PcapReader::new(&host_a[..]).unwrap(), ClockCorrection::offset(-1_000_000_000))];
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    assert!(merge(inputs(), &mut pcap_writer, NegativeTimestamp::Error).is_err());
+    let stats = merge(inputs(), &mut pcap_writer, NegativeTimestamp::Clamp).unwrap();
+    assert_eq!((stats.inputs[0].nb_clamped, stats.inputs[0].min_timestamp), (1, Some(Duration::ZERO)));
+}
+
+#[test]
+fn dedup_by_key() {
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    for (i, flow) in [1_u8, 2, 1, 3, 2, 1].into_iter().enumerate() {
+        pcap_writer.write_packet(&PcapPacket::new(Duration::new(i as u64, 0), 2, &[flow, i as u8])).unwrap();
+    }
+    let data = pcap_writer.into_writer();
+
+    let mut dedup = PcapReader::new(&data[..]).unwrap().dedup_by_key(|packet| packet.data[0]);
+    let mut kept = vec![];
+    while let Some(packet) = dedup.next_packet() {
+        kept.push(packet.unwrap().data.to_vec());
+    }
+
+    assert_eq!(kept, [[1, 0], [2, 1], [3, 3]]);
+    assert_eq!(dedup.nb_keys(), 3);
+}
+
+#[test]
+fn remap_macs() {
+    let frame = [[1_u8; 6], [2; 6], [3; 6]].concat();
+    let data = frames_pcap(DataLink::ETHERNET, &[&frame, &frame[..13], &[&[2_u8; 12][..], &[8, 0]].concat()]);
+
+    let mut remap = PcapReader::new(&data[..]).unwrap().remap_macs(|mac| [mac[0] + 10; 6]);
+    let packet = remap.next_packet().unwrap().unwrap();
+    assert!(matches!(packet.data, Cow::Owned(_)));
+    assert_eq!(packet.data[..], [[11_u8; 6], [12; 6], [3; 6]].concat());
+    assert_eq!(remap.next_packet().unwrap().unwrap().data[..], [&[12_u8; 12][..], &[8, 0]].concat());
+    assert!(remap.next_packet().is_none());
+    assert_eq!(remap.nb_skipped(), 1);
+
+    // The other datalinks are returned as read
+    let data = frames_pcap(DataLink::RAW, &[&frame[..13]]);
+
+    let mut remap = PcapReader::new(&data[..]).unwrap().remap_macs(|_| [0; 6]);
+    assert_eq!(remap.next_packet().unwrap().unwrap().data[..], frame[..13]);
+}
+
+#[test]
+fn remap_ips() {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    let ipv4 = crate::ipv4_header(Ipv4Addr::new(192, 168, 0, 1), Ipv4Addr::new(192, 168, 0, 199));
+    let data = frames_pcap(DataLink::RAW, &[&ipv4, &ipv4[..16], &[0; 4]]);
+
+    let mut remap = PcapReader::new(&data[..]).unwrap().remap_ips(|_| IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)));
+    let packet = remap.next_packet().unwrap().unwrap();
+    assert_eq!(packet.data[..], crate::ipv4_header(Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 1)));
+
+    // The cut IP packet is skipped, the other ones are returned as read
+    let packet = remap.next_packet().unwrap().unwrap();
+    assert!(matches!(packet.data, Cow::Borrowed([0, 0, 0, 0])));
+    assert!(remap.next_packet().is_none());
+    assert_eq!(remap.nb_skipped(), 1);
+}
+
+/// Metadata-only capture: the snaplen of 14 only keeps the Ethernet headers.
+fn metadata_only_pcap() -> Vec<u8> {
+    let header = PcapHeader { snaplen: 14, ..Default::default() };
+    let mut pcap_writer = PcapWriter::with_header(Vec::new(), header).unwrap();
+    for i in 0..4_u8 {
+        let frame = vec![i; 60 + i as usize * 100];
+        pcap_writer.write_packet(&PcapPacket::new(Duration::new(i as u64, 0), frame.len() as u32, &frame[..14])).unwrap();
+    }
+
+    pcap_writer.into_writer()
+}
+
+#[test]
+fn metadata_only() {
+    use pcap_file::pcap::{canonicalize, CanonicalForm};
+
+    let data = metadata_only_pcap();
+
+    let mut pcap_reader = PcapReader::new(&data[..]).unwrap();
+    assert_eq!(pcap_reader.header().snaplen, 14);
+    let mut nb_packets = 0;
+    while let Some(packet) = pcap_reader.next_packet() {
+        let packet = packet.unwrap();
+        assert!(packet.is_truncated());
+        assert_eq!(packet.data.len(), 14);
+        nb_packets += 1;
+    }
+    assert_eq!(nb_packets, 4);
+
+    // The canonical form keeps the snapped packets, even with a smaller snaplen
+    let form = CanonicalForm { snaplen: 6, ..Default::default() };
+    let out = canonicalize(&mut PcapReader::new(&data[..]).unwrap(), Vec::new(), form).unwrap().into_writer();
+    let mut pcap_reader = PcapReader::new(&out[..]).unwrap();
+    assert_eq!(pcap_reader.header().snaplen, 6);
+    let packet = pcap_reader.next_packet().unwrap().unwrap();
+    assert_eq!((packet.data.len(), packet.orig_len), (6, 60));
+
+    // The writer still rejects data longer than the snaplen
+    let mut pcap_writer = PcapWriter::with_header(Vec::new(), PcapHeader { snaplen: 14, ..Default::default() }).unwrap();
+    assert!(pcap_writer.write_packet(&PcapPacket::new(Duration::ZERO, 60, &[0; 15])).is_err());
+    assert!(!PcapPacket::new(Duration::ZERO, 14, &[0; 14]).is_truncated());
+}
+
+#[test]
+fn dedup_by_key_bounded() {
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    for (i, flow) in [1_u8, 2, 1, 3, 2, 1, 1].into_iter().enumerate() {
+        pcap_writer.write_packet(&PcapPacket::new(Duration::new(i as u64, 0), 2, &[flow, i as u8])).unwrap();
+    }
+    let data = pcap_writer.into_writer();
+
+    // Only the 2 most recently seen keys are remembered: 3 evicts 2, then 2 evicts 1
+    let mut dedup = PcapReader::new(&data[..]).unwrap().dedup_by_key_bounded(|packet| packet.data[0], 2);
+    let mut kept = vec![];
+    while let Some(packet) = dedup.next_packet() {
+        kept.push(packet.unwrap().data.to_vec());
+    }
+
+    assert_eq!(kept, [[1, 0], [2, 1], [3, 3], [2, 4], [1, 5]]);
+    assert_eq!(dedup.nb_keys(), 2);
+}
+
+#[test]
+fn write_to_slice() {
+    use byteorder_slice::LittleEndian;
+
+    let header = PcapHeader { endianness: pcap_file::Endianness::Little, ..Default::default() };
+    let packet = PcapPacket::new(Duration::new(1, 2000), 10, &[7; 10]);
+
+    let mut buf = [0_u8; 50];
+    let header_len = header.write_to_slice(&mut buf).unwrap();
+    let packet_len = packet.write_to_slice::<LittleEndian>(&mut buf[header_len..], header.ts_resolution, header.snaplen).unwrap();
+    assert_eq!((header_len, packet_len), (24, 26));
+
+    let mut pcap_writer = PcapWriter::with_header(Vec::new(), header).unwrap();
+    pcap_writer.write_packet(&packet).unwrap();
+    assert_eq!(&buf[..], &pcap_writer.into_writer()[..]);
+
+    // All the serialization paths give the same record
+    let record = &buf[header_len..];
+    assert_eq!(record.len(), pcap_file::pcap::RECORD_HEADER_LEN + 10);
+    let mut out = vec![];
+    packet.write_to::<_, LittleEndian>(&mut out, header.ts_resolution, header.snaplen).unwrap();
+    assert_eq!(out, record);
+    let mut out = vec![];
+    PcapReader::new(&buf[..]).unwrap().next_raw_packet().unwrap().unwrap().write_to::<_, LittleEndian>(&mut out).unwrap();
+    assert_eq!(out, record);
+    let (_, state) = pcap_file::pcap::PcapWriterState::start(header).unwrap();
+    let mut out = vec![];
+    state.serialize_packet(&packet, &mut out).unwrap();
+    assert_eq!(out, record);
+
+    let mut small = [0_u8; 25];
+    assert!(matches!(header.write_to_slice(&mut small[..23]), Err(PcapError::IncompleteBuffer)));
+    assert!(matches!(packet.write_to_slice::<LittleEndian>(&mut small, header.ts_resolution, header.snaplen), Err(PcapError::IncompleteBuffer)));
+    assert_eq!(small, [0; 25]);
+}
+
+#[test]
+fn writer_transform() {
+    use pcap_file::pcap::{zero_ethernet_addresses, Keep, RawPcapPacket};
+    use pcap_file::DataLink;
+
+    let frame: Vec<u8> = (0..20).collect();
+
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    pcap_writer.set_transform(zero_ethernet_addresses(DataLink::ETHERNET));
+    pcap_writer.write_packet(&PcapPacket::new(Duration::new(1, 0), 20, &frame)).unwrap();
+    pcap_writer.write_packet(&PcapPacket::new(Duration::new(2, 0), 20, &frame[..5])).unwrap();
+
+    // Keep only the headers of the even packets, drop the odd ones
+    pcap_writer.set_transform(|header, data| {
+        data.to_mut().truncate(14);
+        Ok(if header.ts_sec % 2 == 0 { Keep::Yes } else { Keep::No })
+    });
+    let raw = RawPcapPacket { ts_sec: 4, ts_frac: 0, incl_len: 20, orig_len: 20, data: Cow::Borrowed(&frame[..]) };
+    assert_eq!(pcap_writer.write_raw_packet(&raw).unwrap(), 30);
+    assert_eq!(pcap_writer.write_packet(&PcapPacket::new(Duration::new(5, 0), 20, &frame)).unwrap(), 0);
+
+    // The packets returned by the transform are validated
+    pcap_writer.set_transform(|_, data| {
+        data.to_mut().resize(70_000, 0);
+        Ok(Keep::Yes)
+    });
+    assert!(pcap_writer.write_packet(&PcapPacket::new(Duration::new(6, 0), 20, &frame)).is_err());
+    pcap_writer.remove_transform();
+    pcap_writer.write_packet(&PcapPacket::new(Duration::new(7, 0), 20, &frame)).unwrap();
+
+    let data = pcap_writer.into_writer();
+    let mut pcap_reader = PcapReader::new(&data[..]).unwrap();
+    let mut packets = vec![];
+    while let Some(packet) = pcap_reader.next_packet() {
+        let packet = packet.unwrap();
+        packets.push((packet.timestamp.as_secs(), packet.orig_len, packet.data.into_owned()));
+    }
+
+    assert_eq!(packets, [
+        (1, 20, [&[0; 12][..], &frame[12..]].concat()),
+        (2, 20, vec![0; 5]),
+        (4, 20, frame[..14].to_vec()),
+        (7, 20, frame.clone()),
+    ]);
+}
+
+#[test]
+fn transform_copies_on_write() {
+    use pcap_file::pcap::{zero_ethernet_addresses, Keep};
+    use pcap_file::DataLink;
+
+    let frame: Vec<u8> = (0..20).collect();
+
+    // The data left intact are not copied
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    pcap_writer.set_transform(|_, data| Ok(if data.is_borrowed() { Keep::Yes } else { Keep::No }));
+    assert_eq!(pcap_writer.write_packet(&PcapPacket::new(Duration::ZERO, 20, &frame)).unwrap(), 36);
+
+    // Only the Ethernet frames are anonymized
+    let mut zero_raw_ip = zero_ethernet_addresses(DataLink::RAW);
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    pcap_writer.set_transform(move |header, data| {
+        zero_raw_ip(header, data)?;
+        Ok(if data.is_borrowed() { Keep::Yes } else { Keep::No })
+    });
+    assert_eq!(pcap_writer.write_packet(&PcapPacket::new(Duration::ZERO, 20, &frame)).unwrap(), 36);
+}
+
+#[test]
+fn copy_and_convert() {
+    use pcap_file::pcap::{convert_pcapng, copy_packets, zero_ethernet_addresses};
+    use pcap_file::pcapng::blocks::interface_description::InterfaceDescriptionBlock;
+    use pcap_file::pcapng::{PcapNgReader, PcapNgWriter};
+    use pcap_file::DataLink;
+
+    let frame: Vec<u8> = (0..20).collect();
+    let anonymized = [&[0; 12][..], &frame[12..]].concat();
+
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    pcap_writer.write_packet(&PcapPacket::new(Duration::new(1, 0), 20, &frame)).unwrap();
+    pcap_writer.write_packet(&PcapPacket::new(Duration::new(2, 0), 30, &frame)).unwrap();
+    let pcap = pcap_writer.into_writer();
+
+    let mut pcapng_writer = PcapNgWriter::new(Vec::new()).unwrap();
+    pcapng_writer.write_pcapng_block(InterfaceDescriptionBlock::new(DataLink::ETHERNET, 0xFFFF)).unwrap();
+    pcapng_writer.write_packet(0, &PcapPacket::new(Duration::new(1, 0), 20, &frame), None).unwrap();
+    pcapng_writer.write_packet(0, &PcapPacket::new(Duration::new(2, 0), 30, &frame), Some("comment")).unwrap();
+    let pcapng = pcapng_writer.into_inner();
+
+    let read_all = |pcap: &[u8]| {
+        let mut pcap_reader = PcapReader::new(pcap).unwrap();
+        let mut packets = vec![];
+        while let Some(packet) = pcap_reader.next_packet() {
+            let packet = packet.unwrap();
+            packets.push((packet.timestamp.as_secs(), packet.orig_len, packet.data.into_owned()));
+        }
+        packets
+    };
+    let expected = [(1, 20, anonymized.clone()), (2, 30, anonymized.clone())];
+
+    // Both go through the transform
+    let mut copied = PcapWriter::new(Vec::new()).unwrap();
+    copied.set_transform(zero_ethernet_addresses(DataLink::ETHERNET));
+    assert_eq!(copy_packets(&mut PcapReader::new(&pcap[..]).unwrap(), &mut copied).unwrap(), 2);
+    assert_eq!(read_all(&copied.into_writer()), expected);
+
+    let mut converted = PcapWriter::new(Vec::new()).unwrap();
+    converted.set_transform(zero_ethernet_addresses(DataLink::ETHERNET));
+    assert_eq!(convert_pcapng(&mut PcapNgReader::new(&pcapng[..]).unwrap(), &mut converted).unwrap(), 2);
+    assert_eq!(read_all(&converted.into_writer()), expected);
+
+    let header = PcapHeader { datalink: DataLink::RAW, ..Default::default() };
+    let mut raw_ip = PcapWriter::with_header(Vec::new(), header).unwrap();
+    let res = convert_pcapng(&mut PcapNgReader::new(&pcapng[..]).unwrap(), &mut raw_ip);
+    assert!(matches!(res, Err(PcapError::InvalidConfiguration(_))));
+}
+
+#[test]
+fn group_by_timestamp() {
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    for (i, ts) in [1_u64, 1, 2, 3, 3, 3, 1].into_iter().enumerate() {
+        pcap_writer.write_packet(&PcapPacket::new(Duration::new(ts, 0), 1, &[i as u8])).unwrap();
+    }
+    let data = pcap_writer.into_writer();
+
+    let mut groups = PcapReader::new(&data[..]).unwrap().group_by_timestamp();
+    let mut result = vec![];
+    while let Some(group) = groups.next_group() {
+        let (ts, packets) = group.unwrap();
+        result.push((ts.as_secs(), packets.iter().map(|p| p.data[0]).collect::<Vec<_>>()));
+    }
+
+    assert_eq!(result, [(1, vec![0, 1]), (2, vec![2]), (3, vec![3, 4, 5]), (1, vec![6])]);
+}
+
+#[test]
+fn check() {
+    use pcap_file::pcap::{check, CheckOptions, MalformedPcapBuilder, MalformedRecord, Severity};
+
+    let data = MalformedPcapBuilder::new(PcapHeader::default())
+        .record(MalformedRecord::new(2, 0, &[0; 4]))
+        .record(MalformedRecord::new(1, 0, &[0; 4]).with_orig_len(2))
+        .record(MalformedRecord::new(3, 0, &[0; 4]).with_ts_frac(2_000_000))
+        .record(MalformedRecord::new(4, 0, &[0; 10]).with_short_data(5))
+        .into_bytes();
+
+    let report = check(&data[..], CheckOptions::default());
+    let findings: Vec<_> = report.findings.iter().map(|f| (f.offset, f.severity, f.code)).collect();
+    assert_eq!(findings, [
+        (44, Severity::Error, "PCAP_INCL_LEN_GT_ORIG_LEN"),
+        (44, Severity::Warning, "PCAP_TIME_NOT_ORDERED"),
+        (64, Severity::Error, "PCAP_INVALID_TS_FRAC"),
+        (84, Severity::Error, "PCAP_TRUNCATED_PACKET"),
+    ]);
+    assert_eq!((report.nb_errors, report.nb_warnings, report.nb_packets, report.nb_bytes), (3, 1, 4, 84));
+    assert!(!report.is_ok() && !report.is_capped());
+
+    let report = check(&data[..], CheckOptions { max_findings: 1, check_time_order: false, ..Default::default() });
+    assert_eq!((report.findings.len(), report.nb_errors, report.nb_warnings), (1, 3, 0));
+    assert!(report.is_capped());
+
+    let report = check(&b"not a pcap, definitely not"[..], CheckOptions::default());
+    assert_eq!((report.findings[0].code, report.nb_packets), ("PCAP_BAD_MAGIC", 0));
+
+    assert!(check(&sample_pcap(3)[..], CheckOptions::default()).is_ok());
+}
+
+#[test]
+fn check_resync() {
+    use pcap_file::pcap::{check, CheckOptions, Severity};
+
+    // Garbage between the first and the second packet
+    let pcap = sample_pcap(3);
+    let data = [&pcap[..50], &[0xFF; 7], &pcap[50..]].concat();
+
+    let report = check(&data[..], CheckOptions::default());
+    assert!(report.findings.iter().all(|f| f.offset == 50));
+    assert!(report.findings.iter().any(|f| f.severity == Severity::Error));
+    let last = report.findings.last().unwrap();
+    assert_eq!((last.severity, last.code), (Severity::Warning, "PCAP_RESYNCHRONIZED"));
+    assert_eq!(last.message, "7 bytes were skipped to find the next packet");
+    assert_eq!((report.nb_packets, report.nb_bytes), (3, data.len() as u64));
+
+    // Without resynchronisation, the check stops at the garbage
+    let report = check(&data[..], CheckOptions { max_resync_skip: 0, ..Default::default() });
+    assert_eq!((report.nb_packets, report.nb_bytes, report.nb_warnings), (1, 50, 0));
+
+    // Garbage at the end
+    let data = [&pcap[..], &[0xFF; 20]].concat();
+    let report = check(&data[..], CheckOptions::default());
+    assert_eq!((report.nb_packets, report.nb_bytes, report.nb_warnings), (3, pcap.len() as u64, 0));
+    assert!(!report.is_ok());
+}
+
+#[test]
+fn fold_packets() {
+    let data = sample_pcap(5);
+
+    let (total, max_ts) = PcapReader::new(&data[..])
+        .unwrap()
+        .fold_packets((0, Duration::ZERO), |(total, max_ts), packet| (total + packet.data.len(), max_ts.max(packet.timestamp)))
+        .unwrap();
+    assert_eq!((total, max_ts), (50, Duration::from_secs(4)));
+
+    let truncated = &data[..data.len() - 1];
+    assert!(PcapReader::new(truncated).unwrap().fold_packets(0, |nb, _| nb + 1).is_err());
+}
+
+#[test]
+fn buf_reader() {
+    use std::io::BufReader;
+
+    use pcap_file::pcap::PcapBufReader;
+
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    for i in 0..20_u8 {
+        let data = vec![i; i as usize * 3];
+        pcap_writer.write_packet(&PcapPacket::new(Duration::new(i as u64, 0), data.len() as u32, &data)).unwrap();
+    }
+    let data = pcap_writer.into_writer();
+
+    let mut expected = vec![];
+    let mut pcap_reader = PcapReader::new(&data[..]).unwrap();
+    while let Some(packet) = pcap_reader.next_packet() {
+        expected.push(packet.unwrap().into_owned());
+    }
+
+    // Tiny buffers split the headers and the data of the packets at every possible position
+    for capacity in (1..100).chain([data.len()]) {
+        let mut pcap_reader = PcapBufReader::new(BufReader::with_capacity(capacity, &data[..])).unwrap();
+        let mut nb_borrowed = 0;
+        for packet in &expected {
+            let read = pcap_reader.next_packet().unwrap().unwrap();
+            assert_eq!((read.timestamp, read.orig_len, &read.data[..]), (packet.timestamp, packet.orig_len, &packet.data[..]));
+            nb_borrowed += matches!(read.data, Cow::Borrowed(_)) as usize;
+        }
+        assert!(pcap_reader.next_packet().is_none(), "capacity {capacity}");
+
+        if capacity == data.len() {
+            assert_eq!(nb_borrowed, expected.len());
+        }
+    }
+
+    // Truncated in the middle of a packet
+    let mut pcap_reader = PcapBufReader::new(BufReader::with_capacity(16, &data[..data.len() - 1])).unwrap();
+    let mut last = None;
+    while let Some(packet) = pcap_reader.next_packet() {
+        last = Some(packet.map(drop));
+    }
+    assert!(matches!(last, Some(Err(PcapError::TruncatedPacket))));
+}
+
+#[test]
+fn epoch_offset() {
+    // Device counting the seconds from 2000-01-01
+    const Y2K: i64 = 946684800;
+
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    pcap_writer.set_epoch_offset(Y2K);
+    pcap_writer.write_packet(&PcapPacket::new(Duration::new(Y2K as u64 + 10, 5000), 1, &[1])).unwrap();
+    assert!(pcap_writer.write_packet(&PcapPacket::new(Duration::new(10, 0), 1, &[2])).is_err(), "Before the device epoch");
+    let data = pcap_writer.into_writer();
+
+    // The raw fields are left intact
+    let mut pcap_reader = PcapReader::new(&data[..]).unwrap().with_epoch_offset(Y2K);
+    assert_eq!(pcap_reader.next_raw_packet().unwrap().unwrap().ts_sec, 10);
+
+    let mut pcap_reader = PcapReader::new(&data[..]).unwrap().with_epoch_offset(Y2K);
+    assert_eq!(pcap_reader.next_packet().unwrap().unwrap().timestamp, Duration::new(Y2K as u64 + 10, 5000));
+    assert!(pcap_reader.next_packet().is_none());
+
+    // Negative timestamps are errors, which don't block the reader
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    pcap_writer.write_packet(&PcapPacket::new(Duration::new(10, 0), 1, &[1])).unwrap();
+    pcap_writer.write_packet(&PcapPacket::new(Duration::new(20, 0), 1, &[2])).unwrap();
+    let data = pcap_writer.into_writer();
+
+    let mut pcap_reader = PcapReader::new(&data[..]).unwrap().with_epoch_offset(-15);
+    assert!(matches!(pcap_reader.next_packet(), Some(Err(PcapError::InvalidField(_)))));
+    assert_eq!(pcap_reader.next_packet().unwrap().unwrap().timestamp, Duration::from_secs(5));
+    assert!(pcap_reader.next_packet().is_none());
+
+    let mut pcap_reader = PcapReader::new(&data[..]).unwrap().with_epoch_offset(-15).dedup_by_key(|p| p.data[0]);
+    assert!(matches!(pcap_reader.next_packet(), Some(Err(PcapError::InvalidField(_)))));
+    assert_eq!(pcap_reader.next_packet().unwrap().unwrap().timestamp, Duration::from_secs(5));
+
+    // Or are clamped to the epoch
+    let mut pcap_reader = PcapReader::new(&data[..]).unwrap().with_epoch_offset(-15).with_epoch_shift_policy(EpochShiftPolicy::Clamp);
+    assert_eq!(pcap_reader.next_packet().unwrap().unwrap().timestamp, Duration::ZERO);
+
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    pcap_writer.set_epoch_offset(Y2K);
+    pcap_writer.set_epoch_shift_policy(EpochShiftPolicy::Clamp);
+    pcap_writer.write_packet(&PcapPacket::new(Duration::new(10, 0), 1, &[2])).unwrap();
+    let data = pcap_writer.into_writer();
+    assert_eq!(PcapReader::new(&data[..]).unwrap().next_raw_packet().unwrap().unwrap().ts_sec, 0);
+}
+
+#[test]
+fn chunked_decoder() {
+    use pcap_file::pcap::ChunkedPcapDecoder;
+
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    for i in 0..10_u8 {
+        let data = vec![i; i as usize * 5];
+        pcap_writer.write_packet(&PcapPacket::new(Duration::new(i as u64, 0), data.len() as u32, &data)).unwrap();
+    }
+    let data = pcap_writer.into_writer();
+
+    // Chunks splitting the global header, the packet headers and the data at every possible position
+    for chunk_len in 1..=data.len() {
+        let mut decoder = ChunkedPcapDecoder::new();
+        let mut packets = vec![];
+        let mut nb_borrowed = 0;
+        for chunk in data.chunks(chunk_len) {
+            decoder.feed(chunk);
+            while let Some(packet) = decoder.poll() {
+                let packet = packet.unwrap();
+                nb_borrowed += matches!(packet.data, Cow::Borrowed(_)) as usize;
+                packets.push((packet.timestamp.as_secs() as u8, packet.data.into_owned()));
+            }
+        }
+
+        let expected: Vec<_> = (0..10_u8).map(|i| (i, vec![i; i as usize * 5])).collect();
+        assert_eq!(packets, expected, "chunk_len {chunk_len}");
+        assert_eq!(decoder.buffered_len(), 0);
+        if chunk_len == data.len() {
+            assert_eq!(nb_borrowed, 10);
+        }
+    }
+
+    // The errors are returned again
+    let mut decoder = ChunkedPcapDecoder::new();
+    decoder.feed(b"not a pcap, definitely not");
+    assert!(matches!(decoder.poll(), Some(Err(PcapError::BadMagic))));
+    assert!(matches!(decoder.poll(), Some(Err(PcapError::BadMagic))));
+    assert!(decoder.header().is_none());
+}
+
+#[test]
+fn write_auto() {
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    assert!(pcap_writer.set_timestamp_step(Duration::from_nanos(1500)).is_err(), "Not a multiple of a microsecond");
+    assert!(pcap_writer.set_timestamp_step(Duration::ZERO).is_err());
+
+    pcap_writer.write_auto(&[0]).unwrap();
+    pcap_writer.set_timestamp_start(Duration::from_secs(100));
+    pcap_writer.set_timestamp_step(Duration::from_millis(10)).unwrap();
+    pcap_writer.write_auto(&[1]).unwrap();
+    pcap_writer.write_auto(&[2, 2]).unwrap();
+    let data = pcap_writer.into_writer();
+
+    let mut pcap_reader = PcapReader::new(&data[..]).unwrap();
+    let expected = [(Duration::ZERO, 1), (Duration::from_secs(100), 1), (Duration::from_millis(100_010), 2)];
+    for (timestamp, orig_len) in expected {
+        let packet = pcap_reader.next_packet().unwrap().unwrap();
+        assert_eq!((packet.timestamp, packet.orig_len), (timestamp, orig_len));
+    }
+    assert!(pcap_reader.next_packet().is_none());
+}
+
+#[test]
+fn writer_construction_errors() {
+    use std::error::Error;
+    use std::io::{ErrorKind, Write};
+
+    #[derive(Debug)]
+    struct FailingWriter;
+
+    impl Write for FailingWriter {
+        fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::new(ErrorKind::PermissionDenied, "read only"))
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    // Invalid headers are caller bugs and nothing is written
+    let mut sink = Vec::new();
+    let header = PcapHeader { snaplen: 0, ..Default::default() };
+    let err = PcapWriter::with_header(&mut sink, header).unwrap_err();
+    assert!(matches!(err, PcapError::InvalidConfiguration(_)));
+    assert!(sink.is_empty());
+
+    let header = PcapHeader { version_major: 3, ..Default::default() };
+    assert_eq!(PcapWriter::with_header(&mut sink, header).unwrap_err().code(), "PCAP_INVALID_CONFIGURATION");
+    assert!(sink.is_empty());
+
+    // The IO errors are kept intact
+    let err = PcapWriter::new(FailingWriter).unwrap_err();
+    assert!(matches!(err, PcapError::IoError(_)));
+    let source = err.source().unwrap().downcast_ref::<std::io::Error>().unwrap();
+    assert_eq!(source.kind(), ErrorKind::PermissionDenied);
+    assert_eq!(source.to_string(), "read only");
+}
+
+#[cfg(feature = "sha2")]
+#[test]
+fn content_hash() {
+    use pcap_file::Endianness;
+
+    // Same packets with another endianness, timestamp resolution and snaplen
+    let header = PcapHeader {
+        endianness: Endianness::Big,
+        ts_resolution: TsResolution::NanoSecond,
+        snaplen: 100,
+        ..Default::default()
+    };
+    let mut pcap_writer = PcapWriter::with_header(Vec::new(), header).unwrap();
+    for i in 0..5_u8 {
+        pcap_writer.write_packet(&PcapPacket::new(Duration::new(i as u64, 0), 10, &[i; 10])).unwrap();
+    }
+    let other = pcap_writer.into_writer();
+
+    let hash = PcapReader::new(&sample_pcap(5)[..]).unwrap().content_hash().unwrap();
+    assert_eq!(PcapReader::new(&other[..]).unwrap().content_hash().unwrap(), hash);
+    assert_ne!(PcapReader::new(&sample_pcap(4)[..]).unwrap().content_hash().unwrap(), hash);
+
+    // SHA-256 of nothing
+    let empty = PcapReader::new(&sample_pcap(0)[..]).unwrap().content_hash().unwrap();
+    assert_eq!(hex::encode(empty), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+}
+
+#[test]
+fn split_on_gap() {
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    for ts in [0, 1, 2, 10, 11, 30, 5] {
+        pcap_writer.write_packet(&PcapPacket::new(Duration::from_secs(ts), 1, &[ts as u8])).unwrap();
+    }
+    let data = pcap_writer.into_writer();
+
+    let groups = |absolute| {
+        let mut split = PcapReader::new(&data[..]).unwrap().split_on_gap(Duration::from_secs(5)).absolute_difference(absolute);
+        let mut groups = vec![];
+        while let Some(group) = split.next_group() {
+            groups.push(group.unwrap().iter().map(|packet| packet.data[0]).collect::<Vec<_>>());
+        }
+        groups
+    };
+    assert_eq!(groups(false), [vec![0, 1, 2], vec![10, 11], vec![30, 5]]);
+    assert_eq!(groups(true), [vec![0, 1, 2], vec![10, 11], vec![30], vec![5]]);
+
+    let mut split = PcapReader::new(&data[..]).unwrap().split_on_gap(Duration::from_secs(5));
+    let mut starts = vec![];
+    while let Some(packet) = split.next_packet() {
+        starts.push(packet.unwrap().0);
+    }
+    assert_eq!(starts, [true, false, false, true, false, true, false]);
+}
+
+#[test]
+fn fixed_caplen() {
+    use pcap_file::pcap::RawPcapPacket;
+
+    let header = PcapHeader { snaplen: 100, ..Default::default() };
+    assert!(matches!(PcapWriter::with_fixed_caplen(Vec::new(), header, 101), Err(PcapError::InvalidConfiguration(_))));
+
+    let mut pcap_writer = PcapWriter::with_fixed_caplen(Vec::new(), header, 8).unwrap();
+    assert_eq!(pcap_writer.write_packet(&PcapPacket::new(Duration::ZERO, 4, &[1, 2, 3, 4])).unwrap(), 24);
+    pcap_writer.write_packet(&PcapPacket::new(Duration::ZERO, 20, &[5; 10])).unwrap();
+    let raw = RawPcapPacket { ts_sec: 0, ts_frac: 0, incl_len: 2, orig_len: 2, data: Cow::Borrowed(&[6, 6]) };
+    pcap_writer.write_raw_packet(&raw).unwrap();
+    let data = pcap_writer.into_writer();
+    assert_eq!(data.len(), 24 + 3 * 24);
+
+    let mut pcap_reader = PcapReader::new(&data[..]).unwrap();
+    let expected: [(u32, &[u8]); 3] = [(4, &[1, 2, 3, 4, 0, 0, 0, 0]), (20, &[5; 8]), (2, &[6, 6, 0, 0, 0, 0, 0, 0])];
+    for (orig_len, expected_data) in expected {
+        let raw = pcap_reader.next_raw_packet().unwrap().unwrap();
+        assert_eq!((raw.incl_len, raw.orig_len, &raw.data[..]), (8, orig_len, expected_data));
+    }
+}
+
+#[test]
+fn write_timestamp_units() {
+    for ts_resolution in [TsResolution::MicroSecond, TsResolution::NanoSecond] {
+        let header = PcapHeader { ts_resolution, ..Default::default() };
+        let mut pcap_writer = PcapWriter::with_header(Vec::new(), header).unwrap();
+
+        // The fraction of write_data is in the resolution of the writer, the other methods don't depend on it
+        let frac = match ts_resolution {
+            TsResolution::MicroSecond => 250_000,
+            TsResolution::NanoSecond => 250_000_000,
+        };
+        pcap_writer.write_data(1, frac, [0]).unwrap();
+        pcap_writer.write_micros(1, 250_000, [1]).unwrap();
+        pcap_writer.write_nanos(1, 250_000_000, [2]).unwrap();
+        pcap_writer.write_at(Duration::from_millis(1250), [3]).unwrap();
+
+        assert!(pcap_writer.write_data(1, frac * 4, [0]).is_err());
+        assert!(pcap_writer.write_micros(1, 1_000_000, [0]).is_err());
+        assert!(pcap_writer.write_nanos(1, 1_000_000_000, [0]).is_err());
+
+        // Precision below the resolution is truncated
+        pcap_writer.write_nanos(1, 1, [4]).unwrap();
+
+        let data = pcap_writer.into_writer();
+        let mut pcap_reader = PcapReader::new(&data[..]).unwrap();
+        for _ in 0..4 {
+            assert_eq!(pcap_reader.next_packet().unwrap().unwrap().timestamp, Duration::from_millis(1250));
+        }
+        let expected = match ts_resolution {
+            TsResolution::MicroSecond => Duration::from_secs(1),
+            TsResolution::NanoSecond => Duration::new(1, 1),
+        };
+        assert_eq!(pcap_reader.next_packet().unwrap().unwrap().timestamp, expected);
+        assert!(pcap_reader.next_packet().is_none());
+    }
+}
+
+#[test]
+fn strip_fcs() {
+    use pcap_file::pcap::strip_fcs;
+
+    let frame: Vec<u8> = (0..64).collect();
+
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    pcap_writer.set_transform(strip_fcs(4));
+    pcap_writer.write_packet(&PcapPacket::new(Duration::ZERO, 64, &frame)).unwrap();
+    // Truncated in the middle of the FCS, or before it
+    pcap_writer.write_packet(&PcapPacket::new(Duration::ZERO, 64, &frame[..62])).unwrap();
+    pcap_writer.write_packet(&PcapPacket::new(Duration::ZERO, 64, &frame[..20])).unwrap();
+    pcap_writer.write_packet(&PcapPacket::new(Duration::ZERO, 2, &frame[..2])).unwrap();
+
+    let data = pcap_writer.into_writer();
+    let mut pcap_reader = PcapReader::new(&data[..]).unwrap();
+    let mut lens = vec![];
+    while let Some(packet) = pcap_reader.next_packet() {
+        let packet = packet.unwrap();
+        assert_eq!(packet.data[..], frame[..packet.data.len()]);
+        lens.push((packet.data.len(), packet.orig_len));
+    }
+    assert_eq!(lens, [(60, 60), (60, 60), (20, 60), (0, 0)]);
+}
+
+#[test]
+fn snaplen_violations() {
+    use pcap_file::pcap::RawPcapPacket;
+
+    let header = PcapHeader { snaplen: 4, ..Default::default() };
+    let mut pcap_writer = PcapWriter::with_header(Vec::new(), header).unwrap();
+    for len in [4_u32, 6, 2, 5] {
+        let data = vec![0; len as usize];
+        let raw = RawPcapPacket { ts_sec: 0, ts_frac: 0, incl_len: len, orig_len: len, data: Cow::Owned(data) };
+        pcap_writer.write_raw_packet(&raw).unwrap();
+    }
+    let data = pcap_writer.into_writer();
+
+    let mut pcap_reader = PcapReader::new(&data[..]).unwrap();
+    assert_eq!(pcap_reader.snaplen_violations().unwrap(), [(24 + 20, 6), (24 + 20 + 22 + 18, 5)]);
+
+    // Truncated capture
+    let mut pcap_reader = PcapReader::new(&data[..data.len() - 1]).unwrap();
+    assert!(pcap_reader.snaplen_violations().is_err());
+}
+
+#[test]
+fn capture_stats() {
+    use pcap_file::pcap::{CaptureStats, RawPcapPacket};
+
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    assert!(pcap_writer.stats().is_none());
+    pcap_writer.set_collect_stats(true);
+
+    pcap_writer.write_packet(&PcapPacket::new(Duration::new(1, 5000), 4, &[1, 2, 3, 4])).unwrap();
+    pcap_writer.write_packet(&PcapPacket::new(Duration::new(3, 0), 100, &[5; 10])).unwrap();
+    let raw = RawPcapPacket { ts_sec: 2, ts_frac: 7, incl_len: 2, orig_len: 2, data: Cow::Borrowed(&[6, 6]) };
+    pcap_writer.write_raw_packet(&raw).unwrap();
+
+    let expected = CaptureStats {
+        nb_packets: 3,
+        nb_bytes: 16,
+        nb_orig_bytes: 106,
+        nb_truncated: 1,
+        first_timestamp: Some(Duration::new(1, 5000)),
+        last_timestamp: Some(Duration::new(2, 7000)),
+    };
+    assert_eq!(pcap_writer.stats(), Some(&expected));
+
+    let data = pcap_writer.into_writer();
+    assert_eq!(PcapReader::new(&data[..]).unwrap().capture_stats().unwrap(), expected);
+}
+
+#[test]
+fn map_err() {
+    #[derive(Debug)]
+    enum AppError {
+        Pcap(&'static str),
+    }
+
+    fn count_packets(data: &[u8]) -> Result<usize, AppError> {
+        let mut packets = PcapReader::new(data).unwrap().map_err(|e| AppError::Pcap(e.code()));
+        let mut nb_packets = 0;
+        while let Some(packet) = packets.next_packet() {
+            packet?;
+            nb_packets += 1;
+        }
+        Ok(nb_packets)
+    }
+
+    let data = sample_pcap(3);
+    assert_eq!(count_packets(&data).unwrap(), 3);
+    assert!(matches!(count_packets(&data[..data.len() - 1]), Err(AppError::Pcap("PCAP_TRUNCATED_PACKET"))));
+}
@@ -88,3 +88,603 @@ fn writer() {
         }
     }
 }
+
+#[test]
+fn write_packet_with_comment() {
+    use std::borrow::Cow;
+    use std::time::Duration;
+
+    use pcap_file::pcap::PcapPacket;
+    use pcap_file::pcapng::blocks::enhanced_packet::EnhancedPacketOption;
+    use pcap_file::pcapng::blocks::interface_description::InterfaceDescriptionBlock;
+    use pcap_file::DataLink;
+
+    let data = [0xAB_u8; 5];
+    let packet = PcapPacket::new(Duration::new(10, 0), 5, &data);
+
+    let mut pcapng_writer = PcapNgWriter::new(Vec::new()).unwrap();
+    pcapng_writer.write_pcapng_block(InterfaceDescriptionBlock::new(DataLink::ETHERNET, 0xFFFF)).unwrap();
+    pcapng_writer.write_packet(0, &packet, Some("suspicious")).unwrap();
+    pcapng_writer.write_packet(0, &packet, None).unwrap();
+
+    let out = pcapng_writer.into_inner();
+    let mut pcapng_reader = PcapNgReader::new(&out[..]).unwrap();
+    pcapng_reader.next_block().unwrap().unwrap();
+
+    let commented = pcapng_reader.next_block().unwrap().unwrap().into_enhanced_packet().unwrap();
+    assert_eq!(commented.options, vec![EnhancedPacketOption::Comment(Cow::Borrowed("suspicious"))]);
+    assert_eq!(&commented.data[..], &data[..]);
+
+    let plain = pcapng_reader.next_block().unwrap().unwrap().into_enhanced_packet().unwrap();
+    assert!(plain.options.is_empty());
+    assert_eq!(plain.timestamp, Duration::new(10, 0));
+}
+
+#[test]
+fn option_length_bounded_by_block() {
+    use byteorder_slice::LittleEndian;
+    use pcap_file::pcapng::blocks::interface_description::InterfaceDescriptionBlock;
+    use pcap_file::pcapng::PcapNgBlock;
+    use pcap_file::PcapError;
+
+    // if_name option claiming 0xFFFF bytes in a 16 bytes block body
+    let body = [1, 0, 0, 0, 0xFF, 0xFF, 0, 0, 2, 0, 0xFF, 0xFF, b'e', b't', b'h', b'0'];
+
+    match InterfaceDescriptionBlock::from_slice::<LittleEndian>(&body) {
+        Err(PcapError::InvalidOption { code: 2, offset: 0, .. }) => {},
+        res => panic!("Unexpected result: {res:?}"),
+    }
+}
+
+#[test]
+fn write_simple_packets() {
+    use std::time::Duration;
+
+    use pcap_file::pcap::PcapPacket;
+    use pcap_file::pcapng::blocks::interface_description::InterfaceDescriptionBlock;
+    use pcap_file::DataLink;
+
+    let data = [0xCD_u8; 30];
+
+    let mut pcapng_writer = PcapNgWriter::new(Vec::new()).unwrap();
+    let packet = PcapPacket::new(Duration::ZERO, 30, &data);
+    assert!(pcapng_writer.write_simple_packet(&packet).is_err(), "No interface 0");
+
+    pcapng_writer.write_pcapng_block(InterfaceDescriptionBlock::new(DataLink::ETHERNET, 20)).unwrap();
+    assert!(pcapng_writer.write_simple_packet(&packet).is_err(), "Longer than the snaplen");
+
+    pcapng_writer.write_simple_packet(&PcapPacket::new(Duration::ZERO, 30, &data[..20])).unwrap();
+    pcapng_writer.write_packet(0, &PcapPacket::new(Duration::new(1, 0), 30, &data[..20]), None).unwrap();
+    pcapng_writer.write_simple_packet(&PcapPacket::new(Duration::ZERO, 7, &data[..7])).unwrap();
+    assert!(pcapng_writer.write_simple_packet(&PcapPacket::new(Duration::ZERO, 30, &data[..7])).is_err(), "Truncated below the snaplen");
+
+    let out = pcapng_writer.into_inner();
+    let mut pcapng_reader = PcapNgReader::new(&out[..]).unwrap();
+    pcapng_reader.next_block().unwrap().unwrap();
+
+    let spb = pcapng_reader.next_block().unwrap().unwrap().into_simple_packet().unwrap();
+    assert_eq!((spb.original_len, &spb.data[..]), (30, &data[..20]));
+
+    pcapng_reader.next_block().unwrap().unwrap().into_enhanced_packet().unwrap();
+
+    let spb = pcapng_reader.next_block().unwrap().unwrap().into_simple_packet().unwrap();
+    assert_eq!((spb.original_len, &spb.data[..]), (7, &data[..7]));
+    assert!(pcapng_reader.next_block().is_none());
+}
+
+#[test]
+fn keepalives() {
+    use std::time::Duration;
+
+    use pcap_file::pcap::PcapPacket;
+    use pcap_file::pcapng::blocks::interface_description::InterfaceDescriptionBlock;
+    use pcap_file::pcapng::{Block, KeepaliveBlock};
+    use pcap_file::DataLink;
+
+    let data = [0x42_u8; 9];
+    let packets: Vec<_> = (0..3).map(|i| PcapPacket::new(Duration::new(i, 0), 9, &data)).collect();
+
+    let mut pcapng_writer = PcapNgWriter::new(Vec::new()).unwrap();
+    assert!(pcapng_writer.keepalive().is_err(), "No interface 0");
+
+    pcapng_writer.write_pcapng_block(InterfaceDescriptionBlock::new(DataLink::ETHERNET, 0xFFFF)).unwrap();
+    pcapng_writer.keepalive().unwrap();
+    pcapng_writer.write_packet(0, &packets[0], None).unwrap();
+    pcapng_writer.set_keepalive_block(KeepaliveBlock::Custom(32473));
+    pcapng_writer.keepalive().unwrap();
+    pcapng_writer.write_packet(0, &packets[1], None).unwrap();
+    pcapng_writer.keepalive().unwrap();
+    pcapng_writer.write_packet(0, &packets[2], None).unwrap();
+    assert_eq!(pcapng_writer.keepalive_count(), 3);
+
+    let out = pcapng_writer.into_inner();
+    let mut pcapng_reader = PcapNgReader::new(&out[..]).unwrap();
+
+    let mut read_packets = vec![];
+    let mut nb_blocks = 0;
+    while let Some(block) = pcapng_reader.next_block() {
+        if let Block::EnhancedPacket(epb) = block.unwrap() {
+            read_packets.push((epb.timestamp, epb.data.into_owned()));
+        }
+        nb_blocks += 1;
+    }
+
+    assert_eq!(nb_blocks, 7);
+    let expected: Vec<_> = packets.iter().map(|p| (p.timestamp, p.data.to_vec())).collect();
+    assert_eq!(read_packets, expected);
+}
+
+#[test]
+fn interface_filter() {
+    use std::borrow::Cow;
+
+    use pcap_file::pcapng::blocks::interface_description::{InterfaceDescriptionBlock, InterfaceDescriptionOption};
+    use pcap_file::DataLink;
+
+    let mut idb = InterfaceDescriptionBlock::new(DataLink::ETHERNET, 0xFFFF);
+    idb.options.push(InterfaceDescriptionOption::IfFilter(Cow::Borrowed(b"\x00tcp port 80")));
+
+    let mut pcapng_writer = PcapNgWriter::new(Vec::new()).unwrap();
+    pcapng_writer.write_pcapng_block(idb.clone()).unwrap();
+
+    let out = pcapng_writer.into_inner();
+    let mut pcapng_reader = PcapNgReader::new(&out[..]).unwrap();
+    pcapng_reader.next_block().unwrap().unwrap();
+    assert_eq!(pcapng_reader.interfaces()[0].filter().as_deref(), Some("tcp port 80"));
+
+    // BPF bytecode isn't a filter string
+    idb.options = vec![InterfaceDescriptionOption::IfFilter(Cow::Borrowed(&[1, 0, 0, 0, 0]))];
+    assert_eq!(idb.filter(), None);
+    assert_eq!(InterfaceDescriptionBlock::new(DataLink::ETHERNET, 0xFFFF).filter(), None);
+}
+
+#[test]
+fn fcs_len() {
+    use std::time::Duration;
+
+    use pcap_file::pcap::PcapPacket;
+    use pcap_file::pcapng::blocks::enhanced_packet::EnhancedPacketOption;
+    use pcap_file::pcapng::blocks::interface_description::{InterfaceDescriptionBlock, InterfaceDescriptionOption};
+    use pcap_file::pcapng::Block;
+    use pcap_file::DataLink;
+
+    let frame: Vec<u8> = (0..64).collect();
+    let mut fcs_idb = InterfaceDescriptionBlock::new(DataLink::ETHERNET, 0xFFFF);
+    fcs_idb.options.push(InterfaceDescriptionOption::IfFcsLen(4));
+
+    let mut pcapng_writer = PcapNgWriter::new(Vec::new()).unwrap();
+    pcapng_writer.write_pcapng_block(InterfaceDescriptionBlock::new(DataLink::ETHERNET, 0xFFFF)).unwrap();
+    pcapng_writer.write_pcapng_block(fcs_idb).unwrap();
+    pcapng_writer.write_packet(0, &PcapPacket::new(Duration::ZERO, 64, &frame), None).unwrap();
+    pcapng_writer.write_packet(1, &PcapPacket::new(Duration::ZERO, 64, &frame), None).unwrap();
+    pcapng_writer.write_packet(1, &PcapPacket::new(Duration::ZERO, 64, &frame[..62]), None).unwrap();
+
+    let out = pcapng_writer.into_inner();
+    let mut pcapng_reader = PcapNgReader::new(&out[..]).unwrap();
+    let mut fcs_lens = vec![];
+    let mut epbs = vec![];
+    while let Some(block) = pcapng_reader.next_block() {
+        if let Block::EnhancedPacket(epb) = block.unwrap().into_owned() {
+            fcs_lens.push(epb.fcs_len(&pcapng_reader.interfaces()[epb.interface_id as usize]));
+            epbs.push(epb);
+        }
+    }
+    assert_eq!(fcs_lens, [None, Some(4), Some(4)]);
+
+    epbs[1].strip_fcs(4);
+    assert_eq!((epbs[1].original_len, &epbs[1].data[..]), (60, &frame[..60]));
+
+    // The flags override the interface, only the FCS bytes still captured are removed
+    let interface = &pcapng_reader.interfaces()[1];
+    let epb = &mut epbs[2];
+    epb.options.push(EnhancedPacketOption::Flags(2 << 5 | 1));
+    assert_eq!(epb.fcs_len(interface), Some(2));
+    epb.strip_fcs(2);
+    assert_eq!((epb.original_len, epb.data.len()), (62, 62));
+    assert_eq!(epb.options, [EnhancedPacketOption::Flags(1)]);
+    assert_eq!(epb.fcs_len(interface), Some(4));
+}
+
+#[test]
+fn name_resolver() {
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    use pcap_file::pcapng::blocks::interface_description::InterfaceDescriptionBlock;
+    use pcap_file::pcapng::NameResolver;
+    use pcap_file::DataLink;
+
+    let mut resolver = NameResolver::new();
+    resolver.insert_v4(Ipv4Addr::new(10, 0, 0, 1), vec!["gateway.lan".to_owned(), "router.lan".to_owned()]);
+    resolver.insert_v6(Ipv6Addr::LOCALHOST, vec!["localhost".to_owned()]);
+
+    let mut pcapng_writer = PcapNgWriter::new(Vec::new()).unwrap();
+    pcapng_writer.write_pcapng_block(InterfaceDescriptionBlock::new(DataLink::ETHERNET, 0xFFFF)).unwrap();
+    pcapng_writer.write_name_resolver(&resolver).unwrap();
+    let out = pcapng_writer.into_inner();
+
+    // The names are only known once their block is read
+    let mut pcapng_reader = PcapNgReader::new(&out[..]).unwrap();
+    pcapng_reader.next_block().unwrap().unwrap();
+    assert!(pcapng_reader.resolver().is_empty());
+    pcapng_reader.next_block().unwrap().unwrap();
+
+    let read = pcapng_reader.resolver();
+    assert_eq!(read, &resolver);
+    assert_eq!(read.lookup_v4(Ipv4Addr::new(10, 0, 0, 1)), Some("gateway.lan"));
+    assert_eq!(read.names_v4(Ipv4Addr::new(10, 0, 0, 1)).len(), 2);
+    assert_eq!(read.lookup_v6(Ipv6Addr::LOCALHOST), Some("localhost"));
+    assert_eq!(read.lookup_v4(Ipv4Addr::new(10, 0, 0, 2)), None);
+
+    assert_eq!(NameResolver::from_pcapng(&out[..]).unwrap(), resolver);
+
+    // The raw blocks aren't decoded
+    let mut pcapng_reader = PcapNgReader::new(&out[..]).unwrap();
+    while let Some(block) = pcapng_reader.next_raw_block() {
+        block.unwrap();
+    }
+    assert!(pcapng_reader.resolver().is_empty());
+
+    // The names are scoped to their section, except for from_pcapng
+    let second_section = PcapNgWriter::new(Vec::new()).unwrap().into_inner();
+    let data = [&out[..], &second_section].concat();
+    let mut pcapng_reader = PcapNgReader::new(&data[..]).unwrap();
+    while let Some(block) = pcapng_reader.next_block() {
+        block.unwrap();
+    }
+    assert!(pcapng_reader.resolver().is_empty());
+    assert_eq!(NameResolver::from_pcapng(&data[..]).unwrap(), resolver);
+
+    // The number of addresses is bounded
+    let mut bounded = NameResolver::with_max_len(1);
+    assert!(bounded.insert_v4(Ipv4Addr::new(10, 0, 0, 1), vec!["a".to_owned()]));
+    assert!(!bounded.insert_v4(Ipv4Addr::new(10, 0, 0, 2), vec!["b".to_owned()]));
+    assert!(bounded.insert_v4(Ipv4Addr::new(10, 0, 0, 1), vec!["c".to_owned()]));
+    assert_eq!((bounded.len(), bounded.lookup_v4(Ipv4Addr::new(10, 0, 0, 1))), (1, Some("c")));
+}
+
+#[test]
+fn sections() {
+    use std::time::Duration;
+
+    use pcap_file::pcap::PcapPacket;
+    use pcap_file::pcapng::blocks::interface_description::InterfaceDescriptionBlock;
+    use pcap_file::pcapng::{process_sections, section_ranges, Block};
+    use pcap_file::{DataLink, Endianness};
+
+    // A whole section header inside a packet must not be taken for a section start
+    let fake_section = PcapNgWriter::new(Vec::new()).unwrap().into_inner();
+
+    let section = |endianness, nb_packets| {
+        let mut pcapng_writer = PcapNgWriter::with_endianness(Vec::new(), endianness).unwrap();
+        pcapng_writer.write_pcapng_block(InterfaceDescriptionBlock::new(DataLink::ETHERNET, 0xFFFF)).unwrap();
+        for _ in 0..nb_packets {
+            let packet = PcapPacket::new(Duration::ZERO, fake_section.len() as u32, &fake_section);
+            pcapng_writer.write_packet(0, &packet, None).unwrap();
+        }
+        pcapng_writer.into_inner()
+    };
+
+    let sections = [section(Endianness::Little, 1), section(Endianness::Big, 2), section(Endianness::Little, 0)];
+    let data = sections.concat();
+
+    let ranges = section_ranges(&data).unwrap();
+    assert_eq!(ranges.iter().map(|range| range.len()).collect::<Vec<_>>(), sections.iter().map(Vec::len).collect::<Vec<_>>());
+
+    let nb_packets = process_sections(&data, 2, |_, section| {
+        let mut pcapng_reader = PcapNgReader::new(section).unwrap();
+        let mut nb_packets = 0;
+        while let Some(block) = pcapng_reader.next_block() {
+            nb_packets += matches!(block.unwrap(), Block::EnhancedPacket(_)) as usize;
+        }
+        nb_packets
+    })
+    .unwrap();
+    assert_eq!(nb_packets, [1, 2, 0]);
+
+    assert!(section_ranges(&data[4..]).is_err());
+}
+
+#[test]
+fn epb_flags() {
+    use pcap_file::pcapng::blocks::enhanced_packet::{EnhancedPacketBlock, EnhancedPacketOption, EpbFlags, PacketDirection, ReceptionType};
+    use pcap_file::pcapng::blocks::interface_description::InterfaceDescriptionBlock;
+    use pcap_file::pcapng::Block;
+    use pcap_file::DataLink;
+
+    let mut pcapng_writer = PcapNgWriter::new(Vec::new()).unwrap();
+    pcapng_writer.write_pcapng_block(InterfaceDescriptionBlock::new(DataLink::ETHERNET, 0xFFFF)).unwrap();
+    let mut epb = EnhancedPacketBlock::default();
+    epb.data = vec![0; 4].into();
+    pcapng_writer.write_pcapng_block(epb.clone()).unwrap();
+    // Outbound multicast, 4 bytes of FCS, checksums offloaded, CRC error
+    epb.options = vec![EnhancedPacketOption::Flags(0b10 | 0b010 << 2 | 4 << 5 | 1 << 9 | 1 << 24)];
+    pcapng_writer.write_pcapng_block(epb).unwrap();
+
+    let out = pcapng_writer.into_inner();
+    let mut pcapng_reader = PcapNgReader::new(&out[..]).unwrap();
+    let mut flags = vec![];
+    while let Some(block) = pcapng_reader.next_block() {
+        if let Block::EnhancedPacket(epb) = block.unwrap() {
+            flags.push(epb.flags());
+        }
+    }
+    assert_eq!(flags[0], None);
+
+    let flags = flags[1].unwrap();
+    assert_eq!(flags.direction(), Some(PacketDirection::Outbound));
+    assert_eq!(flags.reception_type(), Some(ReceptionType::Multicast));
+    assert_eq!(flags.fcs_len(), Some(4));
+    assert!(flags.checksum_not_ready() && !flags.checksum_valid() && !flags.tcp_segmentation_offloaded());
+    assert!(flags.crc_error());
+    assert_eq!(flags.link_layer_errors(), 1 << 8);
+
+    let flags = EpbFlags(0b01 | 1 << 10);
+    assert_eq!((flags.direction(), flags.reception_type(), flags.fcs_len()), (Some(PacketDirection::Inbound), None, None));
+    assert!(flags.checksum_valid() && !flags.crc_error());
+}
+
+#[test]
+fn section_length_backfill() {
+    use std::io::Cursor;
+    use std::time::Duration;
+
+    use pcap_file::pcap::PcapPacket;
+    use pcap_file::pcapng::blocks::interface_description::InterfaceDescriptionBlock;
+    use pcap_file::pcapng::blocks::section_header::SectionHeaderBlock;
+    use pcap_file::pcapng::{section_ranges, Block};
+    use pcap_file::{DataLink, Endianness};
+
+    let write = |backfill: bool| {
+        let mut pcapng_writer = PcapNgWriter::with_endianness(Cursor::new(Vec::new()), Endianness::Big).unwrap();
+        if backfill {
+            pcapng_writer = pcapng_writer.with_section_length_backfill().unwrap();
+        }
+        for (i, (endianness, nb_packets)) in [(Endianness::Big, 2), (Endianness::Little, 1), (Endianness::Big, 0)].into_iter().enumerate() {
+            if i > 0 {
+                pcapng_writer.write_pcapng_block(SectionHeaderBlock { endianness, ..Default::default() }).unwrap();
+            }
+            pcapng_writer.write_pcapng_block(InterfaceDescriptionBlock::new(DataLink::ETHERNET, 0xFFFF)).unwrap();
+            for i in 0..nb_packets {
+                pcapng_writer.write_packet(0, &PcapPacket::new(Duration::ZERO, 3, &[i; 3]), None).unwrap();
+            }
+        }
+        pcapng_writer.finish().unwrap().into_inner()
+    };
+
+    let (plain, backfilled) = (write(false), write(true));
+    let ranges = section_ranges(&plain).unwrap();
+    assert_eq!(ranges.len(), 3);
+    assert_eq!(section_ranges(&backfilled).unwrap(), ranges);
+
+    // The SectionHeaderBlocks without options are 28 bytes long
+    let mut section_lengths = vec![];
+    let mut pcapng_reader = PcapNgReader::new(&backfilled[..]).unwrap();
+    section_lengths.push(pcapng_reader.section().section_length);
+    while let Some(block) = pcapng_reader.next_block() {
+        if let Block::SectionHeader(section) = block.unwrap() {
+            section_lengths.push(section.section_length);
+        }
+    }
+    assert_eq!(section_lengths, ranges.iter().map(|range| range.len() as i64 - 28).collect::<Vec<_>>());
+
+    // A wrong length is ignored
+    let mut lying = backfilled.clone();
+    lying[16..24].copy_from_slice(&12_i64.to_be_bytes());
+    assert_eq!(section_ranges(&lying).unwrap(), ranges);
+    lying[16..24].copy_from_slice(&i64::MAX.to_be_bytes());
+    assert_eq!(section_ranges(&lying).unwrap(), ranges);
+}
+
+#[test]
+fn blocks_before_first_interface() {
+    use std::time::Duration;
+
+    use byteorder_slice::LittleEndian;
+    use pcap_file::pcap::PcapPacket;
+    use pcap_file::pcapng::blocks::interface_description::InterfaceDescriptionBlock;
+    use pcap_file::pcapng::blocks::interface_statistics::InterfaceStatisticsBlock;
+    use pcap_file::pcapng::{Block, EarlyBlockPolicy, RawBlock};
+    use pcap_file::{DataLink, Endianness, PcapError};
+
+    // Section header, interface, statistics and 3 packets, split into blocks to reorder them
+    let mut pcapng_writer = PcapNgWriter::with_endianness(Vec::new(), Endianness::Little).unwrap();
+    pcapng_writer.write_pcapng_block(InterfaceDescriptionBlock::new(DataLink::ETHERNET, 0xFFFF)).unwrap();
+    pcapng_writer.write_pcapng_block(InterfaceStatisticsBlock { interface_id: 0, timestamp: 0, options: vec![] }).unwrap();
+    for i in 1..=3 {
+        pcapng_writer.write_packet(0, &PcapPacket::new(Duration::from_secs(i as u64), 1, &[i]), None).unwrap();
+    }
+    let data = pcapng_writer.into_inner();
+    let mut blocks = vec![];
+    let mut src = &data[..];
+    while !src.is_empty() {
+        let (rem, _) = RawBlock::from_slice::<LittleEndian>(src).unwrap();
+        blocks.push(&src[..src.len() - rem.len()]);
+        src = rem;
+    }
+    let [shb, idb, isb, epb1, epb2, epb3] = blocks[..] else { panic!() };
+
+    let read_all = |pcapng: &[u8], policy| {
+        let mut pcapng_reader = PcapNgReader::new(pcapng).unwrap().with_early_block_policy(policy);
+        let mut blocks = vec![];
+        while let Some(block) = pcapng_reader.next_block() {
+            match block {
+                Ok(Block::EnhancedPacket(epb)) => {
+                    assert_eq!(epb.timestamp, Duration::from_secs(epb.data[0] as u64));
+                    blocks.push(epb.data[0]);
+                },
+                Ok(Block::InterfaceDescription(_)) => blocks.push(100),
+                Ok(Block::InterfaceStatistics(_)) => blocks.push(101),
+                Ok(Block::SectionHeader(_)) => blocks.push(102),
+                Ok(block) => panic!("{block:?}"),
+                Err(PcapError::BlockBeforeInterface { block_type, offset }) => return Err((blocks, block_type, offset)),
+                Err(e) => panic!("{e}"),
+            }
+        }
+        Ok((blocks, pcapng_reader.nb_dropped_early_blocks()))
+    };
+
+    let packets_first = [shb, epb1, isb, epb2, idb, epb3].concat();
+    let statistics_first = [shb, isb, epb1, idb, epb2, epb3].concat();
+    let defer = EarlyBlockPolicy::Defer { max_bytes: 1024 };
+
+    assert_eq!(read_all(&packets_first, EarlyBlockPolicy::Strict), Err((vec![], 6, 28)));
+    assert_eq!(read_all(&packets_first, defer), Ok((vec![101, 100, 1, 2, 3], 0)));
+    assert_eq!(read_all(&packets_first, EarlyBlockPolicy::Drop), Ok((vec![101, 100, 3], 2)));
+
+    // The statistics are returned as they come, like before the policies
+    let offset = (shb.len() + isb.len()) as u64;
+    assert_eq!(read_all(&statistics_first, EarlyBlockPolicy::Strict), Err((vec![101], 6, offset)));
+    assert_eq!(read_all(&statistics_first, defer), Ok((vec![101, 100, 1, 2, 3], 0)));
+    assert_eq!(read_all(&statistics_first, EarlyBlockPolicy::Drop), Ok((vec![101, 100, 2, 3], 1)));
+
+    // The deferred blocks are bounded, the blocks exceeding the bound are dropped and the reading goes on
+    let small = EarlyBlockPolicy::Defer { max_bytes: epb1.len() };
+    assert_eq!(read_all(&packets_first, small), Ok((vec![101, 100, 1, 3], 1)));
+    let tiny = EarlyBlockPolicy::Defer { max_bytes: 0 };
+    assert_eq!(read_all(&packets_first, tiny), read_all(&packets_first, EarlyBlockPolicy::Drop));
+
+    // The blocks still deferred at the end of their section are dropped
+    let without_interface = [&packets_first[..], shb, epb1].concat();
+    assert_eq!(read_all(&without_interface, defer), Ok((vec![101, 100, 1, 2, 3, 102], 1)));
+    let without_interface = [shb, epb1, &statistics_first[..]].concat();
+    assert_eq!(read_all(&without_interface, defer), Ok((vec![102, 101, 100, 1, 2, 3], 1)));
+
+    // The spec ordering is unaffected
+    assert_eq!(read_all(&data, EarlyBlockPolicy::Strict), Ok((vec![100, 101, 1, 2, 3], 0)));
+}
+
+#[test]
+fn pad_to() {
+    use std::time::Duration;
+
+    use pcap_file::pcap::PcapPacket;
+    use pcap_file::pcapng::blocks::interface_description::InterfaceDescriptionBlock;
+    use pcap_file::pcapng::Block;
+    use pcap_file::DataLink;
+
+    let mut pcapng_writer = PcapNgWriter::new(Vec::new()).unwrap();
+    assert!(pcapng_writer.pad_to(0).is_err());
+    assert!(pcapng_writer.pad_to(4094).is_err());
+    pcapng_writer.write_pcapng_block(InterfaceDescriptionBlock::new(DataLink::ETHERNET, 0xFFFF)).unwrap();
+    pcapng_writer.write_packet(0, &PcapPacket::new(Duration::new(1, 0), 10, &[1; 10]), None).unwrap();
+    let len = pcapng_writer.get_ref().len();
+    assert_eq!(pcapng_writer.pad_to(4096).unwrap(), 4096 - len);
+    assert_eq!(pcapng_writer.pad_to(4096).unwrap(), 0);
+    // A gap shorter than a custom block takes another block
+    pcapng_writer.write_packet(0, &PcapPacket::new(Duration::new(2, 0), 10, &[2; 10]), None).unwrap();
+    let len = pcapng_writer.get_ref().len();
+    assert_eq!(len % 4, 0);
+    assert_eq!(pcapng_writer.pad_to(8).unwrap() + len, (len + 16).next_multiple_of(8));
+    let out = pcapng_writer.into_inner();
+
+    let mut pcapng_reader = PcapNgReader::new(&out[..]).unwrap();
+    let mut packets = vec![];
+    while let Some(block) = pcapng_reader.next_block() {
+        match block.unwrap() {
+            Block::EnhancedPacket(epb) => packets.push(epb.data.into_owned()),
+            Block::Unknown(block) => assert_eq!(block.type_, 0x40000BAD),
+            _ => {},
+        }
+    }
+    assert_eq!(packets, [[1; 10], [2; 10]]);
+}
+
+#[test]
+fn read_interfaces_only() {
+    use std::borrow::Cow;
+    use std::time::Duration;
+
+    use pcap_file::pcap::PcapPacket;
+    use pcap_file::pcapng::blocks::interface_description::{InterfaceDescriptionBlock, InterfaceDescriptionOption, TsResolution};
+    use pcap_file::pcapng::InterfaceInfo;
+    use pcap_file::DataLink;
+
+    let interface = |linktype, name: Option<&'static str>, ts_resol: Option<u8>| {
+        let mut idb = InterfaceDescriptionBlock::new(linktype, 0xFFFF);
+        idb.options.extend(name.map(|name| InterfaceDescriptionOption::IfName(Cow::Borrowed(name))));
+        idb.options.extend(ts_resol.map(InterfaceDescriptionOption::IfTsResol));
+        idb
+    };
+
+    let mut pcapng_writer = PcapNgWriter::new(Vec::new()).unwrap();
+    pcapng_writer.write_pcapng_block(interface(DataLink::ETHERNET, Some("eth0"), None)).unwrap();
+    pcapng_writer.write_pcapng_block(interface(DataLink::IEEE802_11, Some("wlan0"), Some(9))).unwrap();
+    pcapng_writer.write_pcapng_block(interface(DataLink::USB_LINUX_MMAPPED, None, Some(3))).unwrap();
+    pcapng_writer.write_packet(0, &PcapPacket::new(Duration::ZERO, 4, &[1, 2, 3, 4]), None).unwrap();
+    // Not returned, after the first packet
+    pcapng_writer.write_pcapng_block(interface(DataLink::RAW, Some("tun0"), None)).unwrap();
+    let data = pcapng_writer.into_inner();
+
+    let info = |linktype, name: Option<&str>, ts_resolution| InterfaceInfo {
+        linktype,
+        snaplen: 0xFFFF,
+        name: name.map(str::to_string),
+        ts_resolution,
+    };
+    let expected = [
+        info(DataLink::ETHERNET, Some("eth0"), TsResolution::MICRO),
+        info(DataLink::IEEE802_11, Some("wlan0"), TsResolution::NANO),
+        info(DataLink::USB_LINUX_MMAPPED, None, TsResolution::MILLI),
+    ];
+    assert_eq!(PcapNgReader::read_interfaces_only(&data[..]).unwrap(), expected);
+
+    // Without packets, and without interfaces
+    let data = PcapNgWriter::new(Vec::new()).unwrap().into_inner();
+    assert!(PcapNgReader::read_interfaces_only(&data[..]).unwrap().is_empty());
+    assert!(PcapNgReader::read_interfaces_only(&data[..4]).is_err());
+}
+
+#[test]
+fn consumed_bytes() {
+    use std::time::Duration;
+
+    use pcap_file::pcap::PcapPacket;
+    use pcap_file::pcapng::blocks::interface_description::InterfaceDescriptionBlock;
+    use pcap_file::DataLink;
+
+    let mut pcapng_writer = PcapNgWriter::new(Vec::new()).unwrap();
+    let shb_len = pcapng_writer.get_ref().len() as u64;
+    pcapng_writer.write_pcapng_block(InterfaceDescriptionBlock::new(DataLink::ETHERNET, 0xFFFF)).unwrap();
+    pcapng_writer.write_packet(0, &PcapPacket::new(Duration::ZERO, 5, &[1; 5]), None).unwrap();
+    pcapng_writer.write_packet(0, &PcapPacket::new(Duration::ZERO, 5, &[2; 5]), Some("odd")).unwrap();
+    let out = pcapng_writer.into_inner();
+
+    let mut pcapng_reader = PcapNgReader::new(&out[..]).unwrap();
+    let mut consumed = vec![];
+    while let Some(block) = pcapng_reader.next_block() {
+        block.unwrap();
+        consumed.push(pcapng_reader.consumed_bytes());
+    }
+
+    // The data of the first packet are padded to 8 bytes
+    assert_eq!(consumed[1], 28 + 8 + 4);
+    assert!(consumed[2] > consumed[1]);
+    assert_eq!(shb_len + consumed.iter().sum::<u64>(), out.len() as u64);
+}
+
+#[test]
+fn max_options_per_block() {
+    use std::borrow::Cow;
+
+    use pcap_file::pcapng::blocks::enhanced_packet::{EnhancedPacketBlock, EnhancedPacketOption};
+    use pcap_file::pcapng::blocks::interface_description::InterfaceDescriptionBlock;
+    use pcap_file::{DataLink, PcapError};
+
+    let mut pcapng_writer = PcapNgWriter::new(Vec::new()).unwrap();
+    pcapng_writer.write_pcapng_block(InterfaceDescriptionBlock::new(DataLink::ETHERNET, 0xFFFF)).unwrap();
+    let mut epb = EnhancedPacketBlock::default();
+    epb.options = vec![EnhancedPacketOption::Comment(Cow::Borrowed("odd")); 3];
+    pcapng_writer.write_pcapng_block(epb).unwrap();
+    let out = pcapng_writer.into_inner();
+
+    let mut pcapng_reader = PcapNgReader::new(&out[..]).unwrap().with_max_options_per_block(3);
+    pcapng_reader.next_block().unwrap().unwrap();
+    assert_eq!(pcapng_reader.next_block().unwrap().unwrap().into_enhanced_packet().unwrap().options.len(), 3);
+
+    let mut pcapng_reader = PcapNgReader::new(&out[..]).unwrap().with_max_options_per_block(2);
+    pcapng_reader.next_block().unwrap().unwrap();
+    match pcapng_reader.next_block().unwrap() {
+        Err(PcapError::InvalidOption { code: 1, .. }) => {},
+        res => panic!("Unexpected result: {res:?}"),
+    }
+}
@@ -41,3 +41,497 @@ fn timestamp_resolution() {
         i += 1;
     }
 }
+
+
+/// Test that every error variant has its own stable code.
+#[test]
+fn error_codes() {
+    use std::collections::HashSet;
+    use std::io::{Error, ErrorKind};
+    use std::path::Path;
+
+    use pcap_file::pcap::PcapQuirk;
+    use pcap_file::PcapError;
+
+    let invalid_utf8 = vec![0xFF];
+    let errors = [
+        PcapError::IncompleteBuffer,
+        PcapError::IoError(Error::from(ErrorKind::UnexpectedEof)),
+        PcapError::UnexpectedEofMidPacket(3),
+        PcapError::TruncatedPacket,
+        PcapError::BadMagic,
+        PcapError::InvalidField("field"),
+        PcapError::InvalidConfiguration("configuration"),
+        PcapError::Utf8Error(std::str::from_utf8(&invalid_utf8).unwrap_err()),
+        PcapError::FromUtf8Error(String::from_utf8(invalid_utf8.clone()).unwrap_err()),
+        PcapError::UnsupportedVariant("variant"),
+        PcapError::SuspectedQuirk(PcapQuirk::SwappedSnaplenDatalink),
+        PcapError::SuspiciousEndianness,
+        PcapError::IndexMismatch,
+        PcapError::EmbeddedGlobalHeader,
+        PcapError::NotARecordBoundary(0),
+        PcapError::LengthMismatch { incl_len: 10, data_len: 2 },
+        PcapError::InvalidInterfaceId(0),
+        PcapError::BlockBeforeInterface { block_type: 6, offset: 28 },
+        PcapError::InvalidTsResolution(0),
+        PcapError::InvalidOption { code: 0, offset: 0, reason: "reason" },
+        PcapError::RenameFailed { from: Path::new("a.tmp").into(), to: Path::new("a").into(), source: Error::from(ErrorKind::NotFound) },
+        PcapError::TimestampTooBig,
+    ];
+
+    let codes: HashSet<_> = errors.iter().map(PcapError::code).collect();
+    assert_eq!(codes.len(), errors.len());
+    assert!(codes.iter().all(|code| code.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')));
+    assert!(codes.iter().all(|code| code.starts_with("PCAP_") || code.starts_with("PCAPNG_")));
+    assert_eq!(PcapError::SuspiciousEndianness.code(), "PCAP_SUSPICIOUS_ENDIANNESS");
+
+    let with_path = PcapError::WithPath { path: "test.pcap".into(), source: Box::new(PcapError::IncompleteBuffer) };
+    assert_eq!(with_path.code(), "PCAP_INCOMPLETE_BUFFER");
+    assert!(with_path.is_recoverable() && with_path.is_eof_like());
+
+    assert!(errors[1..4].iter().all(|e| e.is_eof_like() && !e.is_recoverable()));
+    assert!(PcapError::IoError(Error::from(ErrorKind::Interrupted)).is_recoverable());
+    assert!(errors[4..].iter().all(|e| !e.is_recoverable() && !e.is_eof_like()));
+
+    // The errors returned when reading a bad or truncated pcap
+    let err = pcap_file::pcap::PcapReader::new(&b"not a pcap, definitely not"[..]).unwrap_err();
+    assert_eq!(err.code(), "PCAP_BAD_MAGIC");
+
+    let mut writer = pcap_file::pcap::PcapWriter::new(Vec::new()).unwrap();
+    writer.write_packet(&pcap_file::pcap::PcapPacket::new(std::time::Duration::ZERO, 4, &[1, 2, 3, 4])).unwrap();
+    let pcap = writer.into_writer();
+    let mut reader = pcap_file::pcap::PcapReader::new(&pcap[..pcap.len() - 1]).unwrap();
+    assert_eq!(reader.next_packet().unwrap().unwrap_err().code(), "PCAP_TRUNCATED_PACKET");
+}
+
+
+/// Test that the core types can be sent to other threads, and shared when they are read only.
+#[test]
+fn send_sync() {
+    use std::fs::File;
+    use std::io::BufWriter;
+
+    use pcap_file::pcap::{PcapBufReader, PcapHeader, PcapPacket, PcapPacketHeader, PcapParser, PcapReader, PcapWriter, RawPcapPacket};
+    use pcap_file::pcapng::{Block, PcapNgParser, PcapNgWriter};
+    use pcap_file::PcapError;
+
+    fn assert_send<T: Send>() {}
+    fn assert_sync<T: Sync>() {}
+
+    assert_send::<PcapPacket<'static>>();
+    assert_sync::<PcapPacket<'static>>();
+    assert_send::<RawPcapPacket<'static>>();
+    assert_sync::<RawPcapPacket<'static>>();
+    assert_send::<PcapHeader>();
+    assert_sync::<PcapHeader>();
+    assert_send::<PcapPacketHeader>();
+    assert_sync::<PcapPacketHeader>();
+    // Not Sync because of the interior mutability of EnhancedPacketBlock::set_write_ts_resolution
+    assert_send::<Block<'static>>();
+    assert_send::<PcapError>();
+    assert_sync::<PcapError>();
+
+    assert_send::<PcapParser>();
+    assert_sync::<PcapParser>();
+    assert_send::<PcapNgParser>();
+    assert_sync::<PcapNgParser>();
+
+    assert_send::<PcapReader<File>>();
+    assert_send::<PcapBufReader<std::io::BufReader<File>>>();
+    assert_send::<PcapNgReader<File>>();
+    assert_send::<PcapWriter<BufWriter<File>>>();
+    assert_send::<PcapNgWriter<BufWriter<File>>>();
+}
+
+#[test]
+fn datalink_names() {
+    use pcap_file::DataLink;
+
+    assert_eq!(DataLink::ETHERNET.name(), Some("EN10MB"));
+    assert_eq!(DataLink::LINUX_SLL2.name(), Some("LINUX_SLL2"));
+    assert_eq!(DataLink::Unknown(60000).name(), None);
+
+    for name in ["EN10MB", "en10mb", "DLT_EN10MB", "ETHERNET", "linktype_ethernet", "1"] {
+        assert_eq!(DataLink::from_name(name), Some(DataLink::ETHERNET), "{name}");
+    }
+    assert_eq!(DataLink::from_name("C_HDLC"), Some(DataLink::C_HDLC));
+    assert_eq!(DataLink::from_name("NOT_A_LINK"), None);
+
+    assert_eq!((DataLink::ETHERNET.header_len(), DataLink::RAW.header_len()), (Some(14), Some(0)));
+    assert_eq!((DataLink::IEEE802_11_RADIOTAP.header_len(), DataLink::Unknown(60000).header_len()), (None, None));
+
+    // Every known name round-trips
+    for code in 0..300 {
+        let link = DataLink::from(code);
+        if let Some(name) = link.name() {
+            assert_eq!(DataLink::from_name(name), Some(link), "{name}");
+        }
+    }
+}
+
+#[test]
+fn unknown_datalink_round_trip() {
+    use std::io::Cursor;
+
+    use pcap_file::pcap::{merge, ClockCorrection, NegativeTimestamp, PcapHeader, PcapPacket, PcapReader, PcapWriter};
+    use pcap_file::pcapng::blocks::interface_description::InterfaceDescriptionBlock;
+    use pcap_file::pcapng::{Block, PcapNgWriter};
+    use pcap_file::{DataLink, Endianness, PcapError};
+
+    let pcap = |datalink, endianness| {
+        let mut pcap_writer = PcapWriter::with_header(Vec::new(), PcapHeader { datalink, endianness, ..Default::default() }).unwrap();
+        pcap_writer.write_packet(&PcapPacket::new(Duration::ZERO, 2, &[1, 2])).unwrap();
+        pcap_writer.into_writer()
+    };
+    let pcapng = |datalink, endianness| {
+        let mut pcapng_writer = PcapNgWriter::with_endianness(Vec::new(), endianness)?;
+        pcapng_writer.write_pcapng_block(InterfaceDescriptionBlock::new(datalink, 0xFFFF))?;
+        Ok::<_, PcapError>(pcapng_writer.into_inner())
+    };
+
+    // Random codes, plus the edges of the 16 bits of a pcapng and the ethernet code byte swapped
+    let mut state = 0x2545F4914F6CDD1D_u64;
+    let mut codes = vec![0, 65000, 65535, 65536, 0x0100_0000, u32::MAX];
+    codes.extend((0..500).map(|_| {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state >> (state % 32)) as u32
+    }));
+
+    for code in codes {
+        let datalink = DataLink::from(code);
+        assert_eq!(u32::from(datalink), code);
+        assert_eq!(DataLink::from_name(&code.to_string()), Some(datalink));
+
+        for endianness in [Endianness::Big, Endianness::Little] {
+            // A pcap keeps the whole code, and is written back byte for byte, except the codes looking like a known
+            // code byte swapped, which the reader takes for a quirk, so the writer rejects them
+            let header = PcapHeader { datalink, endianness, ..Default::default() };
+            if code > 0xFFFF && !matches!(DataLink::from(code.swap_bytes()), DataLink::Unknown(_)) {
+                assert!(matches!(header.validate(), Err(PcapError::InvalidConfiguration(_))), "{code}");
+                assert!(matches!(PcapWriter::with_header(Vec::new(), header), Err(PcapError::InvalidConfiguration(_))), "{code}");
+            }
+            else {
+                let data = pcap(datalink, endianness);
+                let header = PcapReader::new(&data[..]).unwrap_or_else(|e| panic!("{code}: {e}")).header();
+                assert_eq!(header.datalink, datalink);
+                assert_eq!(pcap(header.datalink, header.endianness), data);
+            }
+
+            // A pcapng only keeps 16 bits, the bigger codes are rejected instead of being truncated
+            match pcapng(datalink, endianness) {
+                Ok(data) => {
+                    let mut pcapng_reader = PcapNgReader::new(&data[..]).unwrap();
+                    let Some(Ok(Block::InterfaceDescription(idb))) = pcapng_reader.next_block() else { panic!("{code}") };
+                    assert_eq!(idb.linktype, datalink);
+                    assert_eq!(pcapng(idb.linktype, endianness).unwrap(), data);
+                },
+                Err(e) => {
+                    assert!(code > u16::MAX as u32, "{code}: {e}");
+                    assert!(matches!(e, PcapError::InvalidField(_)));
+                },
+            }
+        }
+    }
+
+    // Two pcaps with the same unknown datalink can be merged
+    let unknown = DataLink::Unknown(65000);
+    let inputs = vec![
+        (PcapReader::new(Cursor::new(pcap(unknown, Endianness::Big))).unwrap(), ClockCorrection::default()),
+        (PcapReader::new(Cursor::new(pcap(unknown, Endianness::Little))).unwrap(), ClockCorrection::default()),
+    ];
+    let mut pcap_writer = PcapWriter::with_header(Vec::new(), PcapHeader { datalink: unknown, ..Default::default() }).unwrap();
+    assert_eq!(merge(inputs, &mut pcap_writer, NegativeTimestamp::Error).unwrap().nb_packets, 2);
+    assert_eq!(PcapReader::new(&pcap_writer.into_writer()[..]).unwrap().header().datalink, unknown);
+
+    // A passthrough conversion pcap -> pcapng -> pcap keeps the code of an Unknown built by hand, even a known one,
+    // which is read back as the variant of the code
+    for unknown in [DataLink::Unknown(199), DataLink::Unknown(65000)] {
+        let data = pcap(unknown, Endianness::Little);
+        let mut pcap_reader = PcapReader::new(&data[..]).unwrap();
+        let header = pcap_reader.header();
+        assert_eq!(header.datalink, unknown.normalize());
+
+        let mut pcapng_writer = PcapNgWriter::with_endianness(Vec::new(), Endianness::Little).unwrap();
+        pcapng_writer.write_pcapng_block(InterfaceDescriptionBlock::new(header.datalink, header.snaplen)).unwrap();
+        while let Some(packet) = pcap_reader.next_packet() {
+            pcapng_writer.write_packet(0, &packet.unwrap(), None).unwrap();
+        }
+        let pcapng_data = pcapng_writer.into_inner();
+
+        let mut pcapng_reader = PcapNgReader::new(&pcapng_data[..]).unwrap();
+        let Some(Ok(Block::InterfaceDescription(idb))) = pcapng_reader.next_block() else { panic!("{unknown:?}") };
+        assert_eq!(idb.linktype, unknown.normalize());
+        let header = PcapHeader { datalink: idb.linktype, snaplen: idb.snaplen, endianness: Endianness::Little, ..Default::default() };
+        let mut pcap_writer = PcapWriter::with_header(Vec::new(), header).unwrap();
+        while let Some(block) = pcapng_reader.next_block() {
+            let Block::EnhancedPacket(epb) = block.unwrap() else { continue };
+            pcap_writer.write_packet(&PcapPacket::new(epb.timestamp, epb.original_len, &epb.data)).unwrap();
+        }
+        assert_eq!(pcap_writer.into_writer(), data);
+    }
+
+    // The writers normalize the data links they are given, the other ones are kept as is
+    assert_ne!(DataLink::Unknown(199), DataLink::IPMB_KONTRON);
+    assert_eq!(DataLink::Unknown(199).normalize(), DataLink::IPMB_KONTRON);
+    assert_eq!(DataLink::Unknown(65000).normalize(), DataLink::Unknown(65000));
+    assert_eq!(DataLink::Unknown(1).header_len(), None);
+    assert_eq!(DataLink::Unknown(1).normalize().header_len(), Some(14));
+    assert_eq!(InterfaceDescriptionBlock::new(DataLink::Unknown(199), 0).linktype, DataLink::IPMB_KONTRON);
+    let header = PcapHeader { datalink: DataLink::Unknown(199), ..Default::default() };
+    assert_eq!(PcapWriter::with_header(Vec::new(), header).unwrap().state().header().datalink, DataLink::IPMB_KONTRON);
+    let idb = InterfaceDescriptionBlock { linktype: DataLink::Unknown(199), snaplen: 0, options: vec![] };
+    let mut pcapng_writer = PcapNgWriter::new(Vec::new()).unwrap();
+    pcapng_writer.write_pcapng_block(idb).unwrap();
+    assert_eq!(pcapng_writer.interfaces()[0].linktype, DataLink::IPMB_KONTRON);
+}
+
+/// Snapshots of the serialized reports: a change of their output is a breaking change.
+#[cfg(feature = "serde")]
+#[test]
+fn serde_snapshots() {
+    use pcap_file::pcap::{
+        check, detect_timezone_shift, CaptureStats, CheckOptions, MergeInputStats, MergeStats, PcapHeader, PcapPacket, PcapReader,
+        PcapWriter, TimezoneOptions,
+    };
+
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    pcap_writer.write_packet(&PcapPacket::new(Duration::new(1, 500), 60, &[0; 20])).unwrap();
+    pcap_writer.write_packet(&PcapPacket::new(Duration::new(0, 0), 20, &[0; 20])).unwrap();
+    let data = pcap_writer.into_writer();
+
+    let header = PcapReader::new(&data[..]).unwrap().header();
+    assert_eq!(
+        serde_json::to_string(&header).unwrap(),
+        r#"{"version_major":2,"version_minor":4,"ts_correction":0,"ts_accuracy":0,"snaplen":65535,"datalink":1,"#.to_owned()
+            + r#""ts_resolution":"microsecond","endianness":"little"}"#
+    );
+
+    let stats = PcapReader::new(&data[..]).unwrap().capture_stats().unwrap();
+    assert_eq!(
+        serde_json::to_string(&stats).unwrap(),
+        r#"{"nb_packets":2,"nb_bytes":40,"nb_orig_bytes":80,"nb_truncated":1,"first_timestamp":1000000000,"last_timestamp":0}"#
+    );
+    assert_eq!(
+        serde_json::to_string(&CaptureStats::default()).unwrap(),
+        r#"{"nb_packets":0,"nb_bytes":0,"nb_orig_bytes":0,"nb_truncated":0,"first_timestamp":null,"last_timestamp":null}"#
+    );
+
+    let report = check(&data[..], CheckOptions::default());
+    assert_eq!(
+        serde_json::to_string(&report).unwrap(),
+        r#"{"findings":[{"offset":60,"severity":"warning","code":"PCAP_TIME_NOT_ORDERED","#.to_owned()
+            + r#""message":"The timestamp is before the one of the previous packet"}],"#
+            + r#""nb_errors":0,"nb_warnings":1,"nb_packets":2,"nb_bytes":96}"#
+    );
+
+    let mut pcap_writer = PcapWriter::with_header(Vec::new(), PcapHeader { ts_correction: 3600, ..Default::default() }).unwrap();
+    pcap_writer.write_packet(&PcapPacket::new(Duration::ZERO, 1, &[0])).unwrap();
+    let local = pcap_writer.into_writer();
+    let shift = detect_timezone_shift(&local[..], &TimezoneOptions::default()).unwrap().unwrap();
+    assert_eq!(serde_json::to_string(&shift).unwrap(), r#"{"offset_secs":3600,"reason":"ts_correction","offset":0}"#);
+
+    let input = MergeInputStats {
+        nb_packets: 2,
+        min_timestamp: Some(Duration::new(3, 7)),
+        max_timestamp: Some(Duration::from_secs(4)),
+        nb_clamped: 0,
+    };
+    let stats = MergeStats { nb_packets: 2, inputs: vec![input] };
+    assert_eq!(
+        serde_json::to_string(&stats).unwrap(),
+        r#"{"nb_packets":2,"inputs":[{"nb_packets":2,"min_timestamp":3000000007,"max_timestamp":4000000000,"nb_clamped":0}]}"#
+    );
+
+    let header = PcapHeader { datalink: pcap_file::DataLink::Unknown(65000), ..Default::default() };
+    assert!(serde_json::to_string(&header).unwrap().contains(r#""datalink":65000"#));
+}
+
+/// Returns an IPv4 header without options nor payload, with a valid checksum.
+fn ipv4_header(src: std::net::Ipv4Addr, dst: std::net::Ipv4Addr) -> Vec<u8> {
+    let mut header = [&[0x45, 0, 0, 20, 0, 0, 0x40, 0, 0x40, 0x11, 0, 0][..], &src.octets(), &dst.octets()].concat();
+    let checksum = !ones_complement_sum(&header);
+    header[10..12].copy_from_slice(&checksum.to_be_bytes());
+
+    header
+}
+
+/// Returns whether the checksum of an IPv4 header is valid.
+fn is_ipv4_checksum_valid(header: &[u8]) -> bool {
+    ones_complement_sum(header) == 0xFFFF
+}
+
+/// Returns the 16-bit one's complement sum of the data, whose length is even.
+fn ones_complement_sum(data: &[u8]) -> u16 {
+    let sum: u32 = data.chunks(2).map(|word| u32::from(u16::from_be_bytes([word[0], word[1]]))).sum();
+    let sum = (sum & 0xFFFF) + (sum >> 16);
+
+    ((sum & 0xFFFF) + (sum >> 16)) as u16
+}
+
+#[test]
+fn remap_ip_addresses() {
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+    use pcap_file::{remap_ip_addresses, DataLink};
+
+    let (a, b) = (Ipv4Addr::new(192, 168, 0, 1), Ipv4Addr::new(192, 168, 0, 199));
+    let ipv4 = ipv4_header(a, b);
+    assert!(is_ipv4_checksum_valid(&ipv4));
+
+    // Behind an Ethernet header with a VLAN tag
+    let mut frame = [&[0xAA; 12][..], &[0x81, 0x00, 0x00, 0x01, 0x08, 0x00], &ipv4].concat();
+    let swap = |ip| IpAddr::V4(if ip == IpAddr::V4(a) { b } else { a });
+    assert!(remap_ip_addresses(DataLink::ETHERNET, &mut frame, swap).unwrap());
+    assert_eq!(frame[18..], ipv4_header(b, a));
+
+    let mut packet = ipv4.clone();
+    assert!(remap_ip_addresses(DataLink::RAW, &mut packet, |_| IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))).unwrap());
+    assert_eq!(packet, ipv4_header(Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 1)));
+    assert!(is_ipv4_checksum_valid(&packet));
+
+    let mut ipv6 = [&[0x60, 0, 0, 0, 0, 0, 0x3B, 0x40][..], &Ipv6Addr::LOCALHOST.octets(), &[0; 16]].concat();
+    let to_v6 = |_| IpAddr::V6(Ipv6Addr::new(0x2001, 0xDB8, 0, 0, 0, 0, 0, 1));
+    assert!(remap_ip_addresses(DataLink::IPV6, &mut ipv6, to_v6).unwrap());
+    assert_eq!(ipv6[8..24], ipv6[24..40]);
+    assert_eq!(ipv6[8..10], [0x20, 0x01]);
+
+    // Not IP, cut, or mapped to another version: the data are unchanged
+    let mut arp = [&[0xAA; 12][..], &[0x08, 0x06], &[0; 28]].concat();
+    assert!(!remap_ip_addresses(DataLink::ETHERNET, &mut arp, to_v6).unwrap());
+    assert!(!remap_ip_addresses(DataLink::USB_LINUX_MMAPPED, &mut packet.clone(), to_v6).unwrap());
+    assert!(remap_ip_addresses(DataLink::RAW, &mut ipv4.clone()[..19], swap).is_err());
+    let mut packet = ipv4.clone();
+    assert!(remap_ip_addresses(DataLink::RAW, &mut packet, to_v6).is_err());
+    assert_eq!(packet, ipv4);
+}
+
+#[cfg(feature = "sha2")]
+#[test]
+fn prefix_preserving_anonymizer() {
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    use pcap_file::PrefixPreservingAnonymizer;
+
+    let anonymizer = PrefixPreservingAnonymizer::new([7; 32]);
+    let common_prefix = |a: u128, b: u128| (a ^ b).leading_zeros();
+
+    let ips = [Ipv4Addr::new(10, 1, 2, 3), Ipv4Addr::new(10, 1, 2, 200), Ipv4Addr::new(10, 1, 128, 3), Ipv4Addr::new(192, 168, 0, 1)];
+    for a in ips {
+        for b in ips {
+            let (anon_a, anon_b) = (anonymizer.anonymize_ipv4(a), anonymizer.anonymize_ipv4(b));
+            let prefix = common_prefix(u32::from(a).into(), u32::from(b).into());
+            assert_eq!(common_prefix(u32::from(anon_a).into(), u32::from(anon_b).into()), prefix);
+        }
+    }
+    assert_ne!(anonymizer.anonymize_ipv4(ips[0]), ips[0]);
+    assert_eq!(anonymizer.anonymize_ipv4(ips[0]), PrefixPreservingAnonymizer::new([7; 32]).anonymize_ipv4(ips[0]));
+    assert_ne!(anonymizer.anonymize_ipv4(ips[0]), PrefixPreservingAnonymizer::new([8; 32]).anonymize_ipv4(ips[0]));
+
+    let (a, b) = (Ipv6Addr::new(0x2001, 0xDB8, 1, 0, 0, 0, 0, 1), Ipv6Addr::new(0x2001, 0xDB8, 2, 0, 0, 0, 0, 1));
+    let (anon_a, anon_b) = (anonymizer.anonymize_ipv6(a), anonymizer.anonymize_ipv6(b));
+    assert_eq!(common_prefix(anon_a.into(), anon_b.into()), common_prefix(a.into(), b.into()));
+}
+
+/// Test that no formatted output contains the packet data with the redact feature.
+#[cfg(feature = "redact")]
+#[test]
+fn redaction() {
+    use std::borrow::Cow;
+
+    use pcap_file::pcap::{
+        check, CheckOptions, MalformedPcapBuilder, MalformedRecord, PcapHeader, PcapPacket, PcapReader, PcapWriter, RawPcapReader,
+    };
+    use pcap_file::pcapng::blocks::simple_packet::SimplePacketBlock;
+    use pcap_file::pcapng::PcapNgWriter;
+    use pcap_file::DataLink;
+
+    const MARKER: &[u8] = b"MARKER-7f3a";
+    let mut outputs = vec![];
+    let mut push = |debug: String, display: Option<String>| outputs.extend([Some(debug), display].into_iter().flatten());
+
+    // Valid packets, then a record whose data can't be parsed
+    let builder = MalformedPcapBuilder::new(PcapHeader::default())
+        .record(MalformedRecord::new(1, 0, MARKER))
+        .record(MalformedRecord::new(2, 3_000_000, MARKER))
+        .record(MalformedRecord::new(3, 0, MARKER).with_incl_len(1_000_000_000));
+    push(format!("{builder:?}"), None);
+    push(format!("{:?}", MalformedRecord::new(1, 0, MARKER)), None);
+    let pcap = builder.into_bytes();
+
+    let mut pcap_reader = PcapReader::new(&pcap[..]).unwrap();
+    while let Some(packet) = pcap_reader.next_packet() {
+        match packet {
+            Ok(packet) => push(format!("{packet:?}"), None),
+            Err(e) => {
+                push(format!("{e:?}"), Some(e.to_string()));
+                break;
+            },
+        }
+    }
+
+    // The readers and the writers over in-memory captures, before and after reading
+    let mut pcap_reader = PcapReader::new(&pcap[..]).unwrap();
+    push(format!("{pcap_reader:?}"), None);
+    pcap_reader.next_packet().unwrap().unwrap();
+    push(format!("{pcap_reader:?}"), None);
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    pcap_writer.write_packet(&PcapPacket::new(Duration::ZERO, 11, MARKER)).unwrap();
+    push(format!("{pcap_writer:?}"), None);
+
+    let mut raw_reader = RawPcapReader::new(&pcap[..]).unwrap();
+    push(format!("{raw_reader:?}"), None);
+    while let Some(record) = raw_reader.next_record() {
+        match record {
+            // The data are a plain slice, formatted by the caller
+            Ok((header, _)) => push(format!("{header:?}"), None),
+            Err(e) => {
+                push(format!("{e:?}"), Some(e.to_string()));
+                break;
+            },
+        }
+    }
+
+    let report = check(&pcap[..], CheckOptions::default());
+    assert!(!report.is_ok());
+    push(format!("{report:?}"), None);
+    for finding in &report.findings {
+        push(format!("{finding:?}"), Some(finding.message.clone()));
+    }
+
+    // The marker where a header is expected
+    let garbage = [MARKER, MARKER, MARKER].concat();
+    let e = PcapReader::new(&garbage[..]).unwrap_err();
+    push(format!("{e:?}"), Some(e.to_string()));
+    let e = PcapNgReader::new(&garbage[..]).unwrap_err();
+    push(format!("{e:?}"), Some(e.to_string()));
+
+    // The blocks of a pcapng
+    let mut pcapng_writer = PcapNgWriter::new(Vec::new()).unwrap();
+    let idb = pcap_file::pcapng::blocks::interface_description::InterfaceDescriptionBlock::new(DataLink::ETHERNET, 0xFFFF);
+    pcapng_writer.write_pcapng_block(idb).unwrap();
+    pcapng_writer.write_packet(0, &PcapPacket::new(Duration::ZERO, 11, MARKER), None).unwrap();
+    push(format!("{pcapng_writer:?}"), None);
+    let pcapng = pcapng_writer.into_inner();
+
+    let mut pcapng_reader = PcapNgReader::new(&pcapng[..]).unwrap();
+    push(format!("{pcapng_reader:?}"), None);
+    while let Some(block) = pcapng_reader.next_block() {
+        push(format!("{:?}", block.unwrap()), None);
+    }
+    push(format!("{pcapng_reader:?}"), None);
+    let mut pcapng_reader = PcapNgReader::new(&pcapng[..]).unwrap();
+    while let Some(block) = pcapng_reader.next_raw_block() {
+        push(format!("{:?}", block.unwrap()), None);
+    }
+    push(format!("{:?}", SimplePacketBlock { original_len: 11, data: Cow::Borrowed(MARKER) }), None);
+
+    const _: () = assert!(pcap_file::PAYLOAD_REDACTED);
+    assert!(outputs.iter().any(|output| output.contains("<redacted> (11 bytes)")));
+    for output in outputs {
+        for needle in ["MARKER", "77, 65, 82, 75", "4d41524b", "4D41524B"] {
+            assert!(!output.contains(needle), "{output}");
+        }
+    }
+}